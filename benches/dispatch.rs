@@ -0,0 +1,32 @@
+// Compares the dispatch strategies of the machine.
+// MIT license.
+
+use std::io;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use brainfuck::{Dispatch, Machine, OptLevel, Program};
+use brainfuck::optimize::optimize;
+
+/// Three nested loops of 255 iterations each, about 50 million instructions without optimizations.
+const NESTED: &str = "-[>-[>-[-]<-]<-]";
+
+fn dispatch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dispatch");
+    group.sample_size(10);
+    for level in [OptLevel::O0, OptLevel::O1] {
+        let program = optimize(&Program::parse(NESTED).expect("the program is valid"), level);
+        for (name, dispatch) in [("match", Dispatch::Match), ("threaded", Dispatch::Threaded)] {
+            group.bench_with_input(BenchmarkId::new(name, format!("O{}", level)), &program, |b, p| {
+                b.iter(|| {
+                    let mut machine = Machine::builder().dispatch(dispatch).io(io::empty(), io::sink()).build();
+                    machine.run(p).expect("the program runs");
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+// Benchmarks of the optimizer and the interpreter on classic programs.
+// MIT license.
+
+use std::hint::black_box;
+use std::io::{self, Cursor};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use brainfuck::{BfError, Machine, OptLevel, Program};
+use brainfuck::optimize::optimize;
+
+/// A program of the `programs` directory, with the input it is given and, for ones that never end, the number of
+/// bytes it prints before it is stopped.
+struct Bench {
+    name: &'static str,
+    source: &'static str,
+    input: &'static [u8],
+    max_output: Option<u64>,
+}
+
+/// The programs, each running for up to a couple seconds.
+const PROGRAMS: &[Bench] = &[
+    Bench { name: "mandelbrot", source: include_str!("../programs/mandelbrot.b"), input: b"", max_output: None },
+    Bench { name: "golden", source: include_str!("../programs/golden.b"), input: b"", max_output: Some(800) },
+    Bench { name: "random", source: include_str!("../programs/random.b"), input: b"", max_output: Some(400) },
+    Bench { name: "fib", source: include_str!("../programs/fib.b"), input: b"", max_output: Some(1_000_000) },
+];
+
+/// Parses the programs.
+fn programs() -> Vec<(&'static Bench, Program)> {
+    PROGRAMS.iter().map(|bench| (bench, Program::parse(bench.source).expect("the program is valid"))).collect()
+}
+
+fn optimizer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("optimize");
+    for (bench, program) in programs() {
+        for level in [OptLevel::O1, OptLevel::O2, OptLevel::O3] {
+            group.bench_with_input(BenchmarkId::new(bench.name, format!("O{}", level)), &program, |b, p| {
+                b.iter(|| optimize(black_box(p), level))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn interpreter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("run");
+    // The programs take seconds to run.
+    group.sample_size(10);
+    for (bench, program) in programs() {
+        let program = optimize(&program, OptLevel::default());
+        group.bench_with_input(BenchmarkId::from_parameter(bench.name), &program, |b, p| {
+            b.iter(|| {
+                let mut machine = Machine::builder().max_output(bench.max_output).io(Cursor::new(bench.input), io::sink()).build();
+                match machine.run(p) {
+                    Ok(()) | Err(BfError::OutputLimit(_)) => (),
+                    Err(e) => panic!("{} failed: {}", bench.name, e),
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, optimizer, interpreter);
+criterion_main!(benches);
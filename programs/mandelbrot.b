@@ -0,0 +1,251 @@
+[mandelbrot.b -- display the Mandelbrot set
+from -2 to 0.5 and from -1.2i to 1.2i, in fixed point with a scale of 30,
+up to 32 iterations per character]
+
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-]++++++++++++++++++++++++++++++++++++>>
+>[-]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-]++++++++++++++++++++++++++++
++++++++++[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[-]>>>[-]++++++
+++++++++++++++++++++++++++++++++++++++++++++++++++++++>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-]+++++++++++++++++++++++++++++++++++++++++++++++++
+++++++++++++++++++++++++++[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<[-]>>>[-]>>>[-]>>>[-]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>[-]++++++++++++++++++++++++++++++++>>>[-]<<<<<<[-]+[<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<[-]<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<
+<<<<<<<<<+>>>]<<<[->>>+<<<]>>>>>>[->>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<
+<<<<+>>>>>>]<<<<<<[->>>>>>+<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-]<<<<<<<<<<<<<
+<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>>>>]<<<<<<<
+<<[->>>>>>>>>+<<<<<<<<<]>>>>>>>>>>>>[->>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<+>>>>>>>>>>>>]<<<<<<<<<<<<[->>>>>>>>>>>>+<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>
+>>>>>>>>[->>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<+>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>
+>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>[-]+>>>>>>>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>
++<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<
+[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>
++<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<
+[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>
++<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<
+[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>
++<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<
+[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>
++<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<
+[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>
++<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<
+[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>
++<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<
+[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>
++<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<
+[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>
++<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<
+[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>
++<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<
+[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>
++<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<
+[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>
++<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<
+[-]>>>>>>>->]<<>+<[->-]>[<<<<<<<[-]>>>>>>>->]<<[-]<<<<<<<<<<<<<<<<<<<<<[->>>>>>>
+>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
++<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+[-]+>>>>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>
+->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->
+]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<
+<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>
++<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<
+[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[-
+>-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-
+]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>
+[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<
+<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<
+<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[
+-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]
+>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>
+>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>
+->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->
+]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<
+<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>
++<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<
+[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[-
+>-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<[-]<<<[
+-<<<+>>>]<<+<[>-]>[<<<<<<<<<<<<<[-]<<<<<<[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>
+>>>+>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>
+>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<+>>>+>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>
+>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<]<<<<<<<<<<<<<<<[-]++++++++
++++++++>>>>>>>>>[->>>[-<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>+<<<]>>>[-<<<+>>>]<<<<<
+<<<<<<<<<<<<<[->>>->+<[>-]>[<++++++++++++++++++++++++++++++>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<>->]
+<<<<<]>>>>>>>>>>>>]>>>[-]<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>[-]<<<<<<[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>+>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<+>>>+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]<<<<<<<<<<<<<<
+<[-]+++++++++++++++>>>>>>>>>[->>>[-<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>+<<<]>>>[-<
+<<+>>>]<<<<<<<<<<<<<<<<<<[->>>->+<[>-]>[<++++++++++++++++++++++++++++++>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<>->]<<<<<]>>>>>>>>>>>>]>>>[-]<<<<<<<<<<<<[-]>>>>>>>>>>>>[-]>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>+>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+<<<]>>>[
+-<<<+>>>]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-]+>>>>+<[->-]>[<<<<[-
+]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>
+>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>
+>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>-
+>]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]
+<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<
+>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+
+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[
+->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->
+-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]
+>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[
+<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<
+<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<
+[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-
+]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>
+>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>
+>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>-
+>]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]
+<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<
+>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+
+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[
+->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->
+-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]
+>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[
+<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<
+<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<
+[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-
+]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>
+>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>
+>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>-
+>]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]
+<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<
+>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+
+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[
+->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->
+-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]
+>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[
+<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<
+<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<
+[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-]>>>>->]<<>+<[->-]>[<<<<[-
+]>>>>->]<<[-]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[-]>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<[>-]>[<<<<<<<<<<[-]<<<<<<<<<<<<[-<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>+>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<
+<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>+>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<]<<<<<<<<<<<<<<<[-]++++++++>>>>>>>>>[->>>[-<<<<<<<<<<<<
+<<<+>>>>>>>>>>>>>>>>>>+<<<]>>>[-<<<+>>>]<<<<<<<<<<<<<<<<<<[->>>->+<[>-]>[<++++++
++++++++++>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<>->]<<<<<]>>>>>>>>>>>>]>>>[-]<<<<<<<<<<<<[
+-]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-]<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<+<[>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<>-]>[<>->]<<>>>>>>>+<[>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<[->-]>[<+>->
+]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<>-]>[<>->]<<<<<<<<<<<[-]>>>[-]>>>[-]>>>[-]>>>>>
+>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]
+>>>[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+<[->-]>[<>>>+<<<>->]<<
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<[-<<<<<<<<<<<<<<<<<<<<<+>>>>>>+>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<[->>>>>
+>>>>>>>>>>+<<<<<<<<<<<<<<<]<<<<<<[->>>>>>>>>>>>>+<[->-]>[<<<<+>>>>->]<<<<<<<<<<<
+<<<]>>>>>>>>>>>>>>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>+>>>>>>>>>>>>>>>>>>
+]<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<]<<<<<<[->>>>>>>>>>+<[
+->-]>[<>>>+<<<>->]<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+
+<[<<<[-<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>>>>>>>>>>]>>>>-]>[<<<<[-<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]>>>>->]<<[-]<<<<<<<<<
+<<<<<<<<<<<<[-<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>+>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<
+<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<]<<<<<<[->>>>>>>>>>>>>>
+>>>>>+<[->-]>[<<<<+>>>>->]<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>+>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<
+<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<]<<<<<<[->>>>>>>>>>>>>>
+>>+<[->-]>[<>>>+<<<>->]<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>->]<<[-<<<+>>>]<<<<<<<<<<<<<<<[-]>>>[-]>>>[-]>>>>>>>->]<<>>>>>>>
+>>>>>>>>->>>+<<+<[>-]>[<<<<[-]>>>>->]<<<<<<<<<<<<<<<<+<[>>>>>>>>>>>>[-]<<<<<<<<<
+<<<[-]>-]>[<>->]<<>>>>>>>>>>>>]>>>>>>>>>[-]++++++++++++++++++++++++++++++++<<<<<
+<<<<<<<<<<<<<[-]+>>>>>>>>>>>>>>>->+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->
+]<<<<<<<<<<<<<<<<<[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>
+>+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>++++++++++++++<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>
+->]<<<<<<<<<<<<<<<<<[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>
+>>>+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>--<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<<<<<<
+<<<<<<<<<<[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>+>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>++++++++++++++<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<<<<
+<<<<<<<<<<<<[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>+>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<
+<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<<<<<<<<<<<<<<<<[-<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]<<<<<<[-
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>--------
+--------<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<[->-]>[
+<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>
+>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<<<<<<<<<<<<<<<<[-<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>+>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<]<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
++<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>
+>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<
+<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>
+>>>>->]<<<<<<<<<<<<<<<<<[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
++>>>>>>+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>-----<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]
+<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-
+]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]
+>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>
+>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<<<<<<<<<<<<<<<<[-<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>>>>>>+>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[-
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<]<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>--<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-
+]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]
+>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>
+>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<
+<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]
+<<>+<[->-]>[<<<<<<<<<<<<<<<<[-]>>>>>>>>>>>>>>>>->]<<>+<[->-]>[<<<<<<<<<<<<<<<<[-
+]>>>>>>>>>>>>>>>>->]<<<<<<<<<<<<<<<<<[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<+>>>>>>+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]<<<<<<[->>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>++++++++++++++++++++++
++++++++<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<]
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>[-]<<<<<<<<
+<<<<<<<[-]>>>>>>>>>>>>>>>>>>.<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+<[-
+>-]>[<<<<+>>>>->]<<>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<
+[-]++++++++++.<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+<[->-]>[<>>>+<<<>->]<
+<>+<[->-]>[<>>>+<<<>->]<<>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]
@@ -0,0 +1,11 @@
+[rot13.b -- apply ROT13 to the input
+Adapted from the example in the Wikipedia article about Brainfuck to stop at a zero end of input]
+
+,[
+    [>>++++[>++++++++<-]<+<-[>+>+>-[>>>]<[[>+<-]>>+>]<<<<<-]]
+    >>>[-]+>--[-[<->+++[-]]]<[
+        ++++++++++++<[>-[>+>>]>[+[<+>-]>+>>]<<<<<-]
+        >>[<+>-]>[-[-<<[-]>>]<<[<<->>-]>>]<<[<<+>>-]
+    ]
+    <[-]<.[-]<,
+]
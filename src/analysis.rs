@@ -0,0 +1,180 @@
+// Static analysis of programs.
+// MIT license.
+
+use std::fmt;
+
+use crate::program::{Op, Program};
+
+/// What is known about a loop of a program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopInfo {
+    /// Index of the opening bracket.
+    pub open: usize,
+    /// Index of the closing bracket.
+    pub close: usize,
+    /// Whether every iteration leaves the pointer where it started.
+    pub balanced: bool,
+    /// How much every iteration changes the cell the loop tests, if it is always the same.
+    pub delta: Option<i32>,
+    /// Whether the loop contains no other loops.
+    pub innermost: bool,
+    /// Whether the loop does input or output.
+    pub io: bool,
+    /// Whether the loop reads input.
+    pub input: bool,
+}
+
+/// Whether a loop terminates once entered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// The loop always terminates.
+    Always,
+    /// The loop never terminates once entered, since it does not change the cell it tests.
+    Never,
+    /// The loop only terminates if the cell it tests is a multiple of the given power of two when entering it.
+    IfMultipleOf(u32),
+    /// Nothing is known.
+    Unknown,
+}
+
+impl LoopInfo {
+    /// Whether the loop is innermost, balanced, does no input or output, and counts its cell down or up by one.
+    /// Those loops run a number of times known when entering them, and can be replaced by straight code.
+    pub fn is_simple(&self) -> bool {
+        self.innermost && self.balanced && !self.io && matches!(self.delta, Some(1) | Some(-1))
+    }
+
+    /// Whether the loop terminates, for innermost balanced loops without input, which change the cell they test by
+    /// the same amount on every iteration. Cells wrap around, so odd amounts always reach zero.
+    pub fn termination(&self) -> Termination {
+        match self.delta {
+            _ if self.input => Termination::Unknown,
+            Some(0) => Termination::Never,
+            Some(delta) if delta % 2 != 0 => Termination::Always,
+            Some(delta) => Termination::IfMultipleOf(1 << delta.trailing_zeros()),
+            None => Termination::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for LoopInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", if self.balanced { "balanced" } else { "unbalanced" })?;
+        if let Some(delta) = self.delta {
+            write!(f, ", delta {}", delta)?;
+        }
+        if self.innermost {
+            write!(f, ", innermost")?;
+        }
+        if self.io {
+            write!(f, ", io")?;
+        }
+        if self.is_simple() {
+            write!(f, ", simple")?;
+        }
+        Ok(())
+    }
+}
+
+/// A loop being analyzed.
+struct Frame {
+    open: usize,
+    /// Pointer position relative to the start of the iteration, if known.
+    offset: Option<isize>,
+    delta: Option<i32>,
+    innermost: bool,
+    io: bool,
+    input: bool,
+}
+
+impl Frame {
+    /// Records a change of the cell at the given offset that is not a known addition.
+    fn clobber(&mut self, offset: isize) {
+        if self.offset.map(|o| o + offset) == Some(0) {
+            self.delta = None;
+        }
+    }
+}
+
+/// Classifies all the loops of the program, in the order of their closing brackets.
+pub fn loops(program: &Program) -> Vec<LoopInfo> {
+    let mut loops = vec![];
+    let mut stack: Vec<Frame> = vec![];
+    for (i, op) in program.ops.iter().enumerate() {
+        let Some(frame) = stack.last_mut() else {
+            if let Op::Open(_) = op {
+                stack.push(Frame { open: i, offset: Some(0), delta: Some(0), innermost: true, io: false, input: false });
+            }
+            continue;
+        };
+        match *op {
+            Op::Move(n) => frame.offset = frame.offset.map(|o| o + n),
+            Op::Add(n) => if frame.offset == Some(0) {
+                frame.delta = frame.delta.map(|d| d.wrapping_add(n));
+            },
+            Op::AddAt { offset, value } => if frame.offset.map(|o| o + offset) == Some(0) {
+                frame.delta = frame.delta.map(|d| d.wrapping_add(value));
+            },
+            Op::Out | Op::OutByte(_) | Op::Debug | Op::Channel | Op::Send => frame.io = true,
+            Op::In | Op::Receive => {
+                frame.io = true;
+                frame.input = true;
+                frame.clobber(0);
+            },
+            Op::Clock => {
+                frame.io = true;
+                frame.clobber(0);
+            },
+            Op::File(_) => {
+                frame.io = true;
+                frame.clobber(0);
+                frame.clobber(1);
+            },
+            Op::Clear => frame.clobber(0),
+            Op::MulAdd { offset, .. } => frame.clobber(offset),
+            Op::Fill { offset, len, .. } => (0..len as isize).for_each(|o| frame.clobber(offset + o)),
+            Op::Scan(_) => frame.offset = None,
+            // The new thread goes on one cell further.
+            Op::Fork => {
+                frame.io = true;
+                frame.clobber(0);
+                frame.offset = None;
+            },
+            Op::Open(_) => {
+                frame.innermost = false;
+                stack.push(Frame { open: i, offset: Some(0), delta: Some(0), innermost: true, io: false, input: false });
+            },
+            Op::Close(_) => {
+                let frame = stack.pop().expect("balanced brackets");
+                let balanced = frame.offset == Some(0);
+                let info = LoopInfo {
+                    open: frame.open,
+                    close: i,
+                    balanced,
+                    delta: frame.delta.filter(|_| balanced && frame.innermost),
+                    innermost: frame.innermost,
+                    io: frame.io,
+                    input: frame.input,
+                };
+                if let Some(parent) = stack.last_mut() {
+                    // The nested loop can change any cell it reaches, and leaves the pointer in an unknown position
+                    // unless it is balanced.
+                    parent.delta = None;
+                    parent.io |= info.io;
+                    parent.input |= info.input;
+                    if !balanced {
+                        parent.offset = None;
+                    }
+                }
+                loops.push(info);
+            },
+        }
+    }
+    loops
+}
+
+/// Whether the loop opening at the given index never runs, because it is at the start of the program or right after
+/// another loop, where the current cell is zero. Those loops are often used for comments.
+pub fn is_dead(program: &Program, open: usize) -> bool {
+    open == 0 || matches!(program.ops[open - 1], Op::Close(_))
+}
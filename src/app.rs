@@ -0,0 +1,1364 @@
+// The full command line interface, with every subcommand.
+// MIT license.
+
+use std::{io, fs};
+use std::cell::RefCell;
+use io::{BufRead, prelude::*};
+use std::error::Error;
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use clap::{CommandFactory, Parser, ValueEnum};
+
+use brainfuck::{BfError, CellWidth, Dialect, Eof, Machine, MachineBuilder, OptLevel, Program, Snapshot, Span, Tape};
+use brainfuck::asm;
+use brainfuck::ast;
+use brainfuck::cfg;
+use brainfuck::convert;
+use brainfuck::analysis::{self, Termination};
+use brainfuck::bytecode;
+use brainfuck::decompile::decompile;
+use brainfuck::explain::explain;
+use brainfuck::equiv::{self, Outcome};
+use brainfuck::format::format;
+use brainfuck::fuzz::{self, Problem};
+use brainfuck::golf;
+use brainfuck::codegen;
+use brainfuck::generate::{self, Rng};
+use brainfuck::machine::{BitMachine, DEFAULT_MEM_SIZE};
+use brainfuck::optimize::{precompute, PassReport, Pipeline, Target, PRECOMPUTE_STEPS};
+use brainfuck::profile::Profile;
+use brainfuck::program;
+use brainfuck::stats::{self, Stats};
+use brainfuck::symexec::{self, Goal, Limits};
+use brainfuck::transpile::{self, Language, Settings};
+
+use crate::{bundle, cache, cast, cli, config, console, diagnostic, examples, logger, repl, sanitize, signal, teach, tee, tutorial};
+#[cfg(feature = "server")]
+use crate::{jupyter, lsp, serve};
+use cli::{AsmArgs, AstFormat, BundleArgs, CacheCommand, ChannelStream, CLIArgs, Command, CompileArgs, ConvertArgs, EquivArgs, ErrorFormat, ExamplesCommand, FuzzArgs, GenRandomArgs, GenTextArgs, GolfArgs, IoMode, MachineArgs, OptArgs, PipeArgs, RunArgs, SourceArgs, StatsArgs, StatsFormat, SymexecArgs};
+#[cfg(feature = "server")]
+use cli::{KernelArgs, ServeArgs};
+use cast::Cast;
+use config::Config;
+use diagnostic::Located;
+use logger::ColorChoice;
+use sanitize::Sanitize;
+use tee::{Capture, Tee};
+
+pub fn main() {
+    // Bundled executables run their program, and take no arguments.
+    match bundle::embedded() {
+        Ok(Some(bytecode)) => {
+            logger::init(0, false, ColorChoice::default());
+            if let Err(e) = run_bundle(&bytecode) {
+                if diagnostic::is_broken_pipe(&*e) {
+                    return;
+                }
+                let _ = io::stdout().flush();
+                log::error!("{}", e);
+                process::exit(1);
+            }
+            return;
+        },
+        Ok(None) => (),
+        Err(e) => log::debug!("Can not check for a bundled program: {}", e),
+    }
+
+    let args = CLIArgs::parse();
+    let global = &args.global;
+    logger::init(global.verbose, global.quiet, global.color.unwrap_or_default());
+
+    if let Err(e) = run(&args) {
+        // Whoever reads the output does not want more of it, which is no failure.
+        if diagnostic::is_broken_pipe(&*e) {
+            log::debug!("Output closed: {}", e);
+            return;
+        }
+        let _ = io::stdout().flush();
+        match global.error_format {
+            ErrorFormat::Text => log::error!("{}", e),
+            ErrorFormat::Json => eprintln!("{}", diagnostic::json(&*e)),
+        }
+        if let Some(BfError::Interrupted) = diagnostic::bf_error(&*e) {
+            process::exit(signal::INTERRUPTED_STATUS);
+        }
+        process::exit(1);
+    }
+}
+
+/// Runs the command given in the arguments.
+fn run(args: &CLIArgs) -> Result<(), Box<dyn Error>> {
+    let config = Config::load(args.global.config.as_deref())?;
+    if args.global.color.is_none() {
+        if let Some(color) = config.color.as_deref() {
+            logger::set_color(ColorChoice::from_str(color, true)?);
+        }
+    }
+
+    match &args.command {
+        None => run_program(&args.run, &config, args.run.debug),
+        Some(Command::Run(run)) => run_program(run, &config, run.debug),
+        Some(Command::Debug(run)) => run_program(run, &config, true),
+        Some(Command::Check(source)) => check(source),
+        Some(Command::Lint(source)) => lint(source),
+        Some(Command::Fmt(source)) => {
+            write!(io::stdout(), "{}", format(&parse(source)?))?;
+            Ok(())
+        },
+        Some(Command::Compile(compile_args)) => compile(compile_args, &config),
+        Some(Command::Bundle(bundle_args)) => bundle(bundle_args, &config),
+        Some(Command::Asm(asm_args)) => asm(asm_args),
+        Some(Command::Convert(convert_args)) => convert(convert_args),
+        Some(Command::GenRandom(gen_args)) => gen_random(gen_args),
+        Some(Command::GenText(gen_args)) => gen_text(gen_args),
+        Some(Command::Const(const_args)) => {
+            writeln!(io::stdout(), "{}", codegen::emit_constant(const_args.value))?;
+            Ok(())
+        },
+        Some(Command::Fuzz(fuzz_args)) => fuzz(fuzz_args),
+        Some(Command::Golf(golf_args)) => golf(golf_args),
+        Some(Command::Explain(source)) => {
+            write!(io::stdout(), "{}", explain(&parse(source)?))?;
+            Ok(())
+        },
+        Some(Command::Decompile(decompile_args)) => {
+            let program = optimize_program(&parse(&decompile_args.source)?, &decompile_args.opt, &config, Target::default(), false)?;
+            write!(io::stdout(), "{}", decompile(&program))?;
+            Ok(())
+        },
+        Some(Command::Cfg(cfg_args)) => {
+            let program = optimize_program(&parse(&cfg_args.source)?, &cfg_args.opt, &config, Target::default(), false)?;
+            match &cfg_args.output {
+                Some(path) => fs::write(path, cfg::dot(&program))?,
+                None => write!(io::stdout(), "{}", cfg::dot(&program))?,
+            }
+            Ok(())
+        },
+        Some(Command::Equiv(equiv_args)) => equiv(equiv_args, &config),
+        Some(Command::Pipe(pipe_args)) => pipe(pipe_args, &config),
+        Some(Command::Symexec(symexec_args)) => symexec(symexec_args, &config),
+        Some(Command::Repl(repl_args)) => repl::run(repl_args, builder(&repl_args.machine, &config)?),
+        Some(Command::Tutorial(tutorial_args)) => tutorial::run(tutorial_args),
+        Some(Command::Stats(stats_args)) => stats(stats_args, &config),
+        #[cfg(feature = "server")]
+        Some(Command::Serve(serve_args)) => serve(serve_args, &config),
+        #[cfg(feature = "server")]
+        Some(Command::JupyterKernel(kernel_args)) => jupyter_kernel(kernel_args, &config),
+        #[cfg(feature = "server")]
+        Some(Command::Lsp) => Ok(lsp::serve(io::stdin().lock(), io::stdout().lock())?),
+        Some(Command::Cache { command: CacheCommand::Clear }) => {
+            let removed = cache::clear()?;
+            writeln!(io::stdout(), "Removed {} cached program{}", removed, if removed == 1 { "" } else { "s" })?;
+            Ok(())
+        },
+        Some(Command::Examples { command }) => run_example(command, &config),
+        Some(Command::Completions { shell }) => {
+            // Written in one go, since the generator panics on errors like a closed pipe.
+            let mut script = vec![];
+            clap_complete::generate(*shell, &mut CLIArgs::command(), "brainfuck", &mut script);
+            io::stdout().write_all(&script)?;
+            Ok(())
+        },
+    }
+}
+
+/// Resolves the machine settings. Command line flags and environment variables take precedence over the
+/// configuration file.
+fn settings(args: &MachineArgs, config: &Config) -> Result<Settings, Box<dyn Error>> {
+    let cell_width: CellWidth = match args.cell_width {
+        Some(w) => w,
+        None => config.cell_width.map(|w| w.to_string().parse()).transpose()?.unwrap_or_default(),
+    };
+    let eof: Eof = match args.eof {
+        Some(e) => e,
+        None => config.eof.as_deref().map(str::parse).transpose()?.unwrap_or_default(),
+    };
+    if config.mem_size == Some(0) {
+        return Err("The memory tape needs at least one cell, mem_size can not be 0 in the configuration file".into());
+    }
+    Ok(Settings {
+        mem_size: args
+            .mem_size
+            .or_else(|| args.tape_file.as_deref().and_then(Tape::cells_in))
+            .or(config.mem_size)
+            .unwrap_or(DEFAULT_MEM_SIZE),
+        cell_width,
+        eof,
+    })
+}
+
+/// The optimization target of the machine settings. A tape file keeps the cells of the previous runs, and
+/// --init-mem loads some, so the machine does not start fresh.
+fn target(args: &MachineArgs, config: &Config) -> Result<Target, Box<dyn Error>> {
+    let fresh = args.tape_file.is_none() && args.init_mem.is_none();
+    Ok(Target { fresh, ..settings(args, config)?.target() })
+}
+
+/// Resolves the optimization level. Debug mode steps through the instructions as written unless a level is given.
+fn opt_level(args: &OptArgs, config: &Config, debug: bool) -> Result<OptLevel, Box<dyn Error>> {
+    match (args.opt_level, config.opt_level) {
+        (Some(level), _) => Ok(level),
+        (None, Some(level)) => Ok(level.to_string().parse()?),
+        (None, None) if debug => Ok(OptLevel::O0),
+        (None, None) => Ok(OptLevel::default()),
+    }
+}
+
+/// Optimizes the program with the passes given in the arguments, or else those of the optimization level.
+fn optimize_program(program: &Program, args: &OptArgs, config: &Config, target: Target, debug: bool) -> Result<Program, Box<dyn Error>> {
+    let pipeline = match &args.passes {
+        Some(names) => Pipeline::from_names(names.iter().map(String::as_str))?,
+        None => {
+            let level = opt_level(args, config, debug)?;
+            log::debug!("Optimization level: {}", level);
+            Pipeline::for_level(level)
+        },
+    };
+    log::debug!("Optimization passes: {}", pipeline.names().join(", "));
+    let mut pipeline = pipeline.target(target);
+    let mut profile = None;
+    if let Some(path) = &args.profile_use {
+        let text = fs::read_to_string(path).map_err(|e| format!("Can not read {}: {}", path.display(), e))?;
+        let loaded: Profile = serde_json::from_str(&text).map_err(|e| format!("Invalid profile {}: {}", path.display(), e))?;
+        pipeline = pipeline.profile(&loaded);
+        profile = Some(loaded);
+    }
+    if args.opt_report {
+        let (optimized, reports) = pipeline.report(program, profile.as_ref());
+        opt_report(program, &optimized, &reports, profile.as_ref());
+        return Ok(optimized);
+    }
+    let (optimized, stats) = pipeline.run(program);
+    if args.opt_stats {
+        eprintln!("{:<12} {:>8}    {:<8} Time", "Pass", "Before", "After");
+        for pass in &stats {
+            eprintln!("{}", pass);
+        }
+    }
+    Ok(optimized)
+}
+
+/// Prints what every pass did to the standard error, and what they did together: the instructions they removed,
+/// and, with a profile, how many fewer would run.
+fn opt_report(program: &Program, optimized: &Program, reports: &[PassReport], profile: Option<&Profile>) {
+    let change = |before: u64, after: u64| (after as f64 - before as f64) * 100.0 / before.max(1) as f64;
+    eprint!("{:<12} {:>8}    {:<8} {:>10} {:>8}", "Pass", "Before", "After", "Eliminated", "Fused");
+    if profile.is_some() {
+        eprint!(" {:>14}    Run after", "Run before");
+    }
+    eprintln!();
+    for report in reports {
+        eprintln!("{}", report);
+    }
+    let (before, after) = (program.len() as u64, optimized.len() as u64);
+    eprint!("Total: {} -> {} instructions ({:+.1}%)", before, after, change(before, after));
+    match profile {
+        Some(profile) => {
+            let (before, after) = (profile.estimate(&program.instrs()), profile.estimate(&optimized.instrs()));
+            eprintln!(", an estimated {} -> {} run ({:+.1}%)", before, after, change(before, after));
+        },
+        None => eprintln!(", give a profile with --profile-use to estimate how many fewer run"),
+    }
+}
+
+/// Limits of sandboxed programs, unless the arguments give others. The output limit is that of the playground.
+const SANDBOX_MAX_STEPS: u64 = 10_000_000;
+const SANDBOX_TIMEOUT_MS: u64 = 5000;
+const SANDBOX_MAX_OUTPUT: u64 = 1 << 20;
+/// Largest tape of sandboxed programs, in cells, 4 MiB of memory.
+const SANDBOX_MAX_MEM: usize = 1 << 20;
+
+/// Applies the limits of the arguments, or those of the sandbox, to the machine.
+fn limit(mut builder: MachineBuilder, args: &RunArgs) -> MachineBuilder {
+    let sandbox = |value| Some(value).filter(|_| args.sandbox);
+    builder = builder.max_steps(args.max_steps.or(sandbox(SANDBOX_MAX_STEPS)));
+    if args.machine.max_output.is_none() && args.sandbox {
+        builder = builder.max_output(Some(SANDBOX_MAX_OUTPUT));
+    }
+    builder
+}
+
+/// The time limit of the arguments, or that of the sandbox, in milliseconds. WASI has no threads to stop the run,
+/// the runtime limits the time of sandboxed programs there.
+fn timeout(args: &RunArgs) -> Option<u64> {
+    args.timeout.or(Some(SANDBOX_TIMEOUT_MS).filter(|_| args.sandbox && !cfg!(target_os = "wasi")))
+}
+
+/// The standard output, sanitized if asked to.
+fn program_output(args: &RunArgs) -> Box<dyn Write> {
+    match args.sanitize_output {
+        Some(mode) => Box::new(Sanitize::new(console::stdout(), mode)),
+        None => console::stdout(),
+    }
+}
+
+/// How often files are checked for changes in watch mode.
+const WATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Runs the program every time its file or its input file changes, until interrupted. The last program parsed stays
+/// in memory: it is reused as long as the contents of the file are the same, and only looked up in the cache or
+/// optimized again when they change.
+fn watch(args: &RunArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+    let path = args.source.input.as_deref().expect("--watch requires a program file");
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut seen = None;
+    let mut program: Option<(String, Program)> = None;
+    log::info!("Watching {} for changes, press Ctrl-C to stop", path.display());
+    signal::install();
+    loop {
+        let current = (modified(path), args.input_file.as_deref().and_then(modified));
+        if seen == Some(current) {
+            if signal::INTERRUPTED.load(Ordering::Relaxed) {
+                return Err(BfError::Interrupted.into());
+            }
+            thread::sleep(WATCH_INTERVAL);
+            continue;
+        }
+        seen = Some(current);
+        let result = watch_run(path, args, config, &mut program);
+        match result {
+            // Ctrl-C stops watching as well.
+            Err(e) if matches!(diagnostic::bf_error(&*e), Some(BfError::Interrupted)) => return Err(e),
+            Err(e) => log::error!("{}", e),
+            Ok(()) => (),
+        }
+        eprintln!("[watching {}]", path.display());
+    }
+}
+
+/// Runs the program of the file once in watch mode. The program is the hash of the source code last parsed and the
+/// program optimized from it.
+fn watch_run(path: &Path, args: &RunArgs, config: &Config, program: &mut Option<(String, Program)>) -> Result<(), Box<dyn Error>> {
+    let code = fs::read_to_string(path).map_err(|e| format!("Can not read {}: {}", path.display(), e))?;
+    let hash = cache::key(&[code.as_bytes()]);
+    let program = match program {
+        Some((previous, program)) if *previous == hash => {
+            log::debug!("Program unchanged, running it again");
+            program
+        },
+        _ => &program.insert((hash, load_program(&code, args, config, false)?)).1,
+    };
+    let input: Box<dyn Read> = match &args.input_file {
+        Some(path) => Box::new(fs::File::open(path).map_err(|e| format!("Can not open {}: {}", path.display(), e))?),
+        None => Box::new(io::empty()),
+    };
+    let mut machine = limit(builder(&args.machine, config)?, args).io(input, program_output(args)).build();
+    let result = execute_within(&mut machine, program, timeout(args));
+    io::stdout().flush()?;
+    result
+}
+
+/// Parses and optimizes the program to run, or loads it from the cache if it was optimized with the same options
+/// before, then stores it there.
+fn load_program(code: &str, args: &RunArgs, config: &Config, debug: bool) -> Result<Program, Box<dyn Error>> {
+    let settings = settings(&args.machine, config)?;
+    let target = target(&args.machine, config)?;
+    // The statistics of the passes are only known when they run.
+    if args.no_cache || args.opt.opt_stats || args.opt.opt_report {
+        return optimize_program(&parse_source(code, &args.source)?, &args.opt, config, target, debug);
+    }
+    let passes = match &args.opt.passes {
+        Some(names) => names.join(","),
+        None => opt_level(&args.opt, config, debug)?.to_string(),
+    };
+    let profile = match &args.opt.profile_use {
+        Some(path) => fs::read(path).map_err(|e| format!("Can not read {}: {}", path.display(), e))?,
+        None => vec![],
+    };
+    let target_key = format!("{:?}", target);
+    let syntax = [args.source.extended as u8, args.source.fix_brackets as u8, args.source.dialect as u8];
+    let key = cache::key(&[code.as_bytes(), &syntax, passes.as_bytes(), &profile, target_key.as_bytes()]);
+    if let Some(program) = cache::load(&key) {
+        return Ok(program);
+    }
+    let program = optimize_program(&parse_source(code, &args.source)?, &args.opt, config, target, debug)?;
+    if let Err(e) = cache::store(&key, &program, &settings) {
+        log::warn!("Can not store the program in the cache: {}", e);
+    }
+    Ok(program)
+}
+
+/// Creates a machine builder with the resolved settings.
+fn builder(args: &MachineArgs, config: &Config) -> Result<MachineBuilder, Box<dyn Error>> {
+    let settings = settings(args, config)?;
+    let mut builder = Machine::builder()
+        .mem_size(settings.mem_size)
+        .cell_width(settings.cell_width)
+        .eof(settings.eof)
+        .max_loop_depth(args.max_loop_depth)
+        .max_output(args.max_output)
+        .tier_threshold(args.tier_threshold)
+        .time_slice(args.time_slice)
+        .fixed_time(args.fixed_time)
+        .allow_fs(args.allow_fs)
+        .io(console::stdin(), console::stdout())
+        .interrupt(&signal::INTERRUPTED)
+        .cell_format(args.cell_format.unwrap_or_default())
+        .debug_format(args.debug_format.unwrap_or_default());
+    if let Some(path) = &args.tape_file {
+        let tape = Tape::map(path, settings.mem_size).map_err(|e| format!("Can not map {}: {}", path.display(), e))?;
+        builder = builder.tape(tape);
+    }
+    if let Some(path) = &args.init_mem {
+        let bytes = fs::read(path).map_err(|e| format!("Can not read {}: {}", path.display(), e))?;
+        if args.init_offset.checked_add(bytes.len()).is_none_or(|end| end > settings.mem_size) {
+            let (len, offset, cells) = (bytes.len(), args.init_offset, settings.mem_size);
+            return Err(format!("The {} bytes of {} do not fit in the tape of {} cells from cell {}", len, path.display(), cells, offset).into());
+        }
+        builder = builder.preload(args.init_offset, bytes);
+    }
+    for (n, stream) in &args.channels {
+        let open_error = |path: &Path, e: io::Error| format!("Can not open {} for channel {}: {}", path.display(), n, e);
+        builder = match stream {
+            ChannelStream::Input(path) => {
+                let file = fs::File::open(path).map_err(|e| open_error(path, e))?;
+                builder.channel(*n, io::BufReader::new(file), NoOutput)
+            },
+            ChannelStream::Output(path) => {
+                let file = fs::File::create(path).map_err(|e| open_error(path, e))?;
+                builder.channel(*n, io::empty(), io::BufWriter::new(file))
+            },
+        };
+    }
+    if let Some(path) = &args.debug_output {
+        builder = builder.debug_output(io::BufWriter::new(fs::File::create(path)?));
+    }
+
+    #[cfg(feature = "signals")]
+    let builder = builder.dump_on(&signal::DUMP);
+    Ok(builder)
+}
+
+/// How a program of a pipe failed.
+enum StageError {
+    /// The next program stopped reading, which is not an error of this one.
+    BrokenPipe,
+    Interrupted,
+    Failed(String),
+}
+
+/// Runs the programs of the pipe at the same time, on a thread each, connected by pipes so that every one reads what
+/// the previous one prints as soon as it prints it. Fails with the error of the first program that failed, naming it.
+fn pipe(args: &PipeArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+    unshared(&args.machine, "pipe")?;
+    let target = target(&args.machine, config)?;
+    // Every program is valid before any of them runs.
+    let mut programs = vec![];
+    for (n, path) in args.programs.iter().enumerate() {
+        let source = fs::read_to_string(path).map_err(|e| format!("Can not read {}: {}", path.display(), e))?;
+        let program = Program::parse(&source).map_err(|e| format!("Stage {} ({}): {}", n + 1, path.display(), e))?;
+        programs.push(optimize_program(&program, &args.opt, config, target, false)?);
+    }
+    let mut inputs = vec![None];
+    let mut outputs = vec![];
+    for _ in 1..programs.len() {
+        let (reader, writer) = io::pipe()?;
+        inputs.push(Some(reader));
+        outputs.push(Some(writer));
+    }
+    outputs.push(None);
+    signal::install();
+    let results: Vec<Result<(), StageError>> = thread::scope(|scope| {
+        let stages: Vec<_> = programs
+            .iter()
+            .zip(inputs)
+            .zip(outputs)
+            .map(|((program, input), output)| {
+                scope.spawn(move || {
+                    run_stage(program, || {
+                        let input: Box<dyn Read> = match input {
+                            Some(reader) => Box::new(reader),
+                            None => console::stdin(),
+                        };
+                        let output: Box<dyn Write> = match output {
+                            Some(writer) => Box::new(io::BufWriter::new(writer)),
+                            None => console::stdout(),
+                        };
+                        Ok(builder(&args.machine, config)?.max_steps(args.max_steps).io(input, output))
+                    })
+                })
+            })
+            .collect();
+        stages.into_iter().map(|stage| stage.join().expect("stages do not panic")).collect()
+    });
+    first_failure(results, |n| format!("Stage {} ({})", n + 1, args.programs[n].display()))
+}
+
+/// Checks that the machine options do not name files, which the machines running at the same time would all use at
+/// once: they would share the tape mapped from --tape-file, and overwrite each other's channel and debug files.
+fn unshared(machine: &MachineArgs, command: &str) -> Result<(), Box<dyn Error>> {
+    if machine.tape_file.is_some() || !machine.channels.is_empty() || machine.init_mem.is_some() || machine.debug_output.is_some() {
+        return Err(format!(
+            "{} runs its programs at the same time, and can not be used with --tape-file, --channel, --init-mem or \
+            --debug-output, which every machine would share",
+            command
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Runs the two programs of --pair at the same time, on a thread each, connected to each other as peers. The first
+/// one reads the standard input.
+fn run_pair(paths: &[PathBuf], args: &RunArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+    unshared(&args.machine, "--pair")?;
+    if args.dump_mem_file.is_some() {
+        return Err("--pair runs two machines, and can not be used with --dump-mem-file".into());
+    }
+    let target = target(&args.machine, config)?;
+    let mut programs = vec![];
+    for path in paths {
+        let source = fs::read_to_string(path).map_err(|e| format!("Can not read {}: {}", path.display(), e))?;
+        let program = Program::parse_dialect(&source, Dialect::Pair, args.source.extended)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        programs.push(optimize_program(&program, &args.opt, config, target, false)?);
+    }
+    let (from_first, to_second) = io::pipe()?;
+    let (from_second, to_first) = io::pipe()?;
+    let peers = [(from_second, to_second), (from_first, to_first)];
+    signal::install();
+    let results: Vec<Result<(), StageError>> = thread::scope(|scope| {
+        let machines: Vec<_> = programs
+            .iter()
+            .zip(peers)
+            .enumerate()
+            .map(|(n, (program, (reader, writer)))| {
+                scope.spawn(move || {
+                    run_stage(program, || {
+                        let input: Box<dyn Read> = if n == 0 { console::stdin() } else { Box::new(io::empty()) };
+                        let builder = limit(builder(&args.machine, config)?, args).io(input, console::stdout());
+                        Ok(builder.peer(reader, writer))
+                    })
+                })
+            })
+            .collect();
+        machines.into_iter().map(|machine| machine.join().expect("machines do not panic")).collect()
+    });
+    first_failure(results, |n| paths[n].display().to_string())
+}
+
+/// The input of the dialects run on machines of their own, after checking that the arguments only ask to run the
+/// program, as they do not have the instructions the other options need.
+fn plain_input(args: &RunArgs, debug: bool) -> Result<Box<dyn Read>, Box<dyn Error>> {
+    let instructions = debug || args.debug_script.is_some() || args.dry_run || args.emit_ir || args.emit_ast.is_some();
+    let recorded = args.render_cast.is_some() || args.profile_out.is_some() || args.checkpoint_every.is_some() || args.teach;
+    let streams = args.lines || args.watch || args.bang_input || args.io.is_some() || args.tee.is_some();
+    if instructions || recorded || streams || args.resume.is_some() || args.machine.tape_file.is_some() {
+        return Err(format!(
+            "The {} dialect can not be used in debug mode, or with --dry-run, --emit-ir, --emit-ast, --render-cast, \
+            --profile-out, --checkpoint-every, --teach, --resume, --lines, --watch, --bang-input, --io, --tee or --tape-file",
+            args.source.dialect
+        )
+        .into());
+    }
+    Ok(match &args.input_file {
+        Some(path) => Box::new(fs::File::open(path).map_err(|e| format!("Can not open {}: {}", path.display(), e))?),
+        None => console::stdin(),
+    })
+}
+
+/// Number of cells of the tape of Smallfuck, unless --mem-size gives another.
+const SMALLFUCK_CELLS: usize = 32;
+
+/// Runs a program of the boolfuck or smallfuck dialect on a machine of bits, as parsed. The tape of Smallfuck, which
+/// has no output, is printed at the end.
+fn run_bits(args: &RunArgs, debug: bool) -> Result<(), Box<dyn Error>> {
+    let input = plain_input(args, debug)?;
+    if args.dump_mem_file.is_some() || args.machine.init_mem.is_some() {
+        return Err(format!("The cells of the {} dialect are bits, --dump-mem-file and --init-mem need bytes", args.source.dialect).into());
+    }
+    let program = parse(&args.source)?;
+    let max_output = args.machine.max_output.or(Some(SANDBOX_MAX_OUTPUT).filter(|_| args.sandbox));
+    let smallfuck = args.source.dialect == Dialect::Smallfuck;
+    let machine = match args.machine.mem_size {
+        _ if !smallfuck => BitMachine::new(),
+        Some(0) => return Err("The tape of Smallfuck needs at least one cell".into()),
+        cells => BitMachine::bounded(cells.unwrap_or(SMALLFUCK_CELLS)),
+    };
+    let mut machine = machine
+        .max_steps(args.max_steps.or(Some(SANDBOX_MAX_STEPS).filter(|_| args.sandbox)))
+        .max_output(max_output)
+        .interrupt(&signal::INTERRUPTED);
+    signal::install();
+    let run = || match machine.run(&program, input, program_output(args)) {
+        Ok(()) => Ok(()),
+        Err(BfError::Interrupted) => Err(BfError::Interrupted.into()),
+        Err(e) => Err(Located::run_error(e, &program, machine.instruction())),
+    };
+    match timeout(args) {
+        Some(ms) => match time_limited(ms, run) {
+            Err(e) if matches!(e.downcast_ref(), Some(BfError::TimeLimit(_))) => {
+                return Err(Located::run_error(BfError::TimeLimit(ms), &program, machine.instruction()));
+            },
+            result => result?,
+        },
+        None => run()?,
+    }
+    if smallfuck {
+        let tape: String = machine.cells().iter().map(|&bit| if bit { '1' } else { '0' }).collect();
+        writeln!(io::stdout(), "{}", tape)?;
+    }
+    Ok(())
+}
+
+/// Runs a program of the smbf dialect from the tape, with the options that do not need its instructions.
+fn run_self_modifying(args: &RunArgs, config: &Config, debug: bool) -> Result<(), Box<dyn Error>> {
+    let input = plain_input(args, debug)?;
+    let code = read_source(&args.source)?;
+    let mut machine = limit(builder(&args.machine, config)?, args).io(input, program_output(args)).build();
+    // Errors are at a position in the code as it was loaded.
+    let locate = |error: BfError, pos: usize| -> Box<dyn Error> {
+        let (line, column) = diagnostic::line_column(&code, pos);
+        Box::new(Located { error, span: Span::at(pos), line, column })
+    };
+    signal::install();
+    let mut run = || match machine.run_self_modifying(code.as_bytes()) {
+        Ok(()) => Ok(()),
+        Err(BfError::Interrupted) => Err(BfError::Interrupted.into()),
+        Err(e) => Err(locate(e, machine.instruction())),
+    };
+    let result = match timeout(args) {
+        Some(ms) => match time_limited(ms, run) {
+            Err(e) if matches!(e.downcast_ref(), Some(BfError::TimeLimit(_))) => {
+                Err(locate(BfError::TimeLimit(ms), machine.instruction()))
+            },
+            result => result,
+        },
+        None => run(),
+    };
+    if let (Some(path), Ok(())) = (&args.dump_mem_file, &result) {
+        let cell_width = settings(&args.machine, config)?.cell_width;
+        dump_memory(path, machine.memory(), cell_width, args.range)?;
+    }
+    result
+}
+
+/// Runs a program of a pipe or a pair on the machine of the builder, which is created on the thread of the program.
+fn run_stage(program: &Program, builder: impl FnOnce() -> Result<MachineBuilder, Box<dyn Error>>) -> Result<(), StageError> {
+    let mut machine = builder().map_err(|e| StageError::Failed(e.to_string()))?.build();
+    match machine.run(program) {
+        Ok(()) => Ok(()),
+        Err(BfError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => Err(StageError::BrokenPipe),
+        Err(BfError::Interrupted) => Err(StageError::Interrupted),
+        Err(e) => match program.spans.get(machine.instruction()) {
+            Some(span) => {
+                let (line, column) = program.location(span.start);
+                Err(StageError::Failed(format!("{} at {}:{}", e, line, column)))
+            },
+            None => Err(StageError::Failed(e.to_string())),
+        },
+    }
+}
+
+/// The error of the first program that failed, named by its index, reporting those of the others.
+fn first_failure(results: Vec<Result<(), StageError>>, name: impl Fn(usize) -> String) -> Result<(), Box<dyn Error>> {
+    let mut first = None;
+    for (n, result) in results.into_iter().enumerate() {
+        match result {
+            Err(StageError::Failed(e)) => {
+                let e = format!("{}: {}", name(n), e);
+                if first.is_some() {
+                    log::error!("{}", e);
+                } else {
+                    first = Some(e);
+                }
+            },
+            Err(StageError::Interrupted) => return Err(BfError::Interrupted.into()),
+            Err(StageError::BrokenPipe) | Ok(()) => (),
+        }
+    }
+    first.map_or(Ok(()), |e| Err(e.into()))
+}
+
+/// Opens the connection of --io, waiting for a client to connect when listening.
+fn connect(mode: &IoMode) -> Result<TcpStream, Box<dyn Error>> {
+    match mode {
+        IoMode::TcpListen(address) => {
+            let listener = TcpListener::bind(address).map_err(|e| format!("Can not listen on {}: {}", address, e))?;
+            log::info!("Waiting for a connection on {}", listener.local_addr()?);
+            let (stream, peer) = listener.accept()?;
+            log::info!("Connection from {}", peer);
+            Ok(stream)
+        },
+        IoMode::TcpConnect(address) => {
+            Ok(TcpStream::connect(address).map_err(|e| format!("Can not connect to {}: {}", address, e))?)
+        },
+    }
+}
+
+/// The output of a channel that only has an input, which can not be printed to.
+struct NoOutput;
+impl Write for NoOutput {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "the channel only has an input"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the program on the machine. When interrupted with Ctrl-C, prints where the program stopped first. Other
+/// errors are located at the instruction where the program stopped.
+fn execute(machine: &mut Machine, program: &Program) -> Result<(), Box<dyn Error>> {
+    signal::install();
+    match machine.run(program) {
+        Ok(()) => Ok(()),
+        Err(BfError::Interrupted) => {
+            eprint!("{}", machine.report(program));
+            Err(BfError::Interrupted.into())
+        },
+        Err(e) => Err(Located::run_error(e, program, machine.instruction())),
+    }
+}
+
+/// Set when the time limit of a run stops it.
+static TIMED_OUT: AtomicBool = AtomicBool::new(false);
+
+/// Runs the program like `execute`, stopping it once it runs for longer than the time limit, if there is one.
+fn execute_within(machine: &mut Machine, program: &Program, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+    let Some(ms) = timeout else { return execute(machine, program) };
+    match time_limited(ms, || execute(machine, program)) {
+        Err(e) if matches!(e.downcast_ref(), Some(BfError::TimeLimit(_))) => {
+            Err(Located::run_error(BfError::TimeLimit(ms), program, machine.instruction()))
+        },
+        result => result,
+    }
+}
+
+/// Calls `run`, stopping it like Ctrl-C once it runs for longer than the time limit. Fails with the time limit, not
+/// located, if it stopped the run.
+fn time_limited(ms: u64, run: impl FnOnce() -> Result<(), Box<dyn Error>>) -> Result<(), Box<dyn Error>> {
+    TIMED_OUT.store(false, Ordering::Relaxed);
+    // The run is stopped like with Ctrl-C, unless it ends first and closes the channel.
+    let (done, wait) = mpsc::channel::<()>();
+    let watchdog = thread::Builder::new()
+        .spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = wait.recv_timeout(Duration::from_millis(ms)) {
+                TIMED_OUT.store(true, Ordering::Relaxed);
+                signal::INTERRUPTED.store(true, Ordering::Relaxed);
+            }
+        })
+        .map_err(|e| format!("--timeout needs threads: {}", e))?;
+    let result = run();
+    drop(done);
+    watchdog.join().expect("the watchdog does not panic");
+    if TIMED_OUT.load(Ordering::Relaxed) {
+        signal::INTERRUPTED.store(false, Ordering::Relaxed);
+        if let Some(BfError::Interrupted) = result.as_ref().err().and_then(|e| diagnostic::bf_error(&**e)) {
+            return Err(BfError::TimeLimit(ms).into());
+        }
+    }
+    result
+}
+
+/// Returns the source code of the program, from the file, the inline code, or else the whole standard input.
+fn read_source(args: &SourceArgs) -> Result<String, Box<dyn Error>> {
+    if let Some(code) = args.eval.as_deref() {
+        log::info!("Interpreting: {}", code);
+        return Ok(code.to_string());
+    }
+    match args.input.as_deref() {
+        // The conventional "read from the standard input" path.
+        Some(path) if path == Path::new("-") => read_stdin(),
+        Some(path) => {
+            if !path.is_file() {
+                return Err(format!("Program file not found: {} (use -e to run inline code)", path.display()).into());
+            }
+            log::info!("Loading file: {}", path.display());
+            Ok(fs::read_to_string(path)?)
+        },
+        None => read_stdin(),
+    }
+}
+
+/// Reads the whole standard input, until the end of file.
+fn read_stdin() -> Result<String, Box<dyn Error>> {
+    log::info!("Reading program from the standard input");
+    let mut source = String::new();
+    io::stdin().read_to_string(&mut source)?;
+    Ok(source)
+}
+
+/// Reads and parses the program.
+fn parse(args: &SourceArgs) -> Result<Program, Box<dyn Error>> {
+    parse_source(&read_source(args)?, args)
+}
+
+/// Parses source code, in the syntax given in the arguments.
+fn parse_source(source: &str, args: &SourceArgs) -> Result<Program, Box<dyn Error>> {
+    if args.fix_brackets {
+        let (program, unmatched) = Program::parse_lenient(source, args.extended).map_err(|e| Located::parse_error(e, source))?;
+        for e in unmatched {
+            let (pos, fix) = match e {
+                BfError::UnmatchedClose(pos) => (pos, "Skipped the ']' without its '[' at"),
+                BfError::UnmatchedOpen(pos) => (pos, "Added a ']' at the end for the '[' at"),
+                _ => unreachable!("only brackets are unmatched"),
+            };
+            let (line, column) = diagnostic::line_column(source, pos);
+            log::warn!("{} {}:{}", fix, line, column);
+        }
+        return Ok(program);
+    }
+    Program::parse_dialect(source, args.dialect, args.extended).map_err(|e| Located::parse_error(e, source))
+}
+
+/// Writes the cells in the range, or all of them, to the file, in as many bytes as the cell width, in little endian
+/// order.
+fn dump_memory(path: &Path, memory: &[u32], cell_width: CellWidth, range: Option<(usize, Option<usize>)>) -> Result<(), Box<dyn Error>> {
+    let (start, end) = range.unwrap_or((0, None));
+    let end = end.unwrap_or(memory.len());
+    if end > memory.len() || start > end {
+        return Err(format!("The range {}..{} goes past the end of the tape of {} cells", start, end, memory.len()).into());
+    }
+    let bytes: Vec<u8> = match cell_width {
+        CellWidth::U8 => memory[start..end].iter().map(|&c| c as u8).collect(),
+        CellWidth::U16 => memory[start..end].iter().flat_map(|&c| (c as u16).to_le_bytes()).collect(),
+        CellWidth::U32 => memory[start..end].iter().flat_map(|&c| c.to_le_bytes()).collect(),
+    };
+    fs::write(path, bytes).map_err(|e| format!("Can not write {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// Saves the checkpoint next to the file, then replaces it, so that a crash while saving leaves the previous one.
+fn save_checkpoint(path: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    let partial = path.with_extension("partial");
+    fs::write(&partial, bytecode::encode_snapshot(snapshot))?;
+    fs::rename(&partial, path)?;
+    log::debug!("Checkpoint saved after {} steps", snapshot.steps);
+    Ok(())
+}
+
+/// Set every second when `--progress` is given, to print the progress of the running program.
+static PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// Runs a program.
+fn run_program(args: &RunArgs, config: &Config, debug: bool) -> Result<(), Box<dyn Error>> {
+    // Only the command line sets the limits of sandboxed programs.
+    let default_config = Config::default();
+    let config = if args.sandbox { &default_config } else { config };
+    if args.sandbox && debug {
+        return Err("--sandbox can not be used in debug mode".into());
+    }
+    if args.sandbox && args.io.is_some() {
+        return Err("--io can not be used with --sandbox, sandboxed programs can not use the network".into());
+    }
+    if args.sandbox && args.machine.allow_fs {
+        return Err("--allow-fs can not be used with --sandbox, sandboxed programs can not touch files".into());
+    }
+    let machine = &args.machine;
+    let files = [&args.tee, &machine.debug_output, &args.checkpoint_file, &machine.tape_file, &args.dump_mem_file, &args.render_cast, &args.profile_out];
+    if args.sandbox && (files.iter().any(|file| file.is_some()) || !machine.channels.is_empty()) {
+        return Err("--tee, --channel, --debug-output, --checkpoint-file, --tape-file, --dump-mem-file, --render-cast and \
+            --profile-out can not be used with --sandbox, sandboxed programs can not write files"
+            .into());
+    }
+    if args.sandbox && machine.mem_size.is_some_and(|cells| cells > SANDBOX_MAX_MEM) {
+        return Err(format!("--mem-size can be at most {} with --sandbox", SANDBOX_MAX_MEM).into());
+    }
+    if let Some(paths) = &args.pair {
+        return run_pair(paths, args, config);
+    }
+    match args.source.dialect {
+        Dialect::Smbf => return run_self_modifying(args, config, debug),
+        Dialect::Boolfuck | Dialect::Smallfuck => return run_bits(args, debug),
+        _ => (),
+    }
+    if args.watch {
+        if debug {
+            return Err("--watch can not be used in debug mode".into());
+        }
+        return watch(args, config);
+    }
+    let debug = debug || args.debug_script.is_some();
+    let mut builder = limit(builder(&args.machine, config)?, args).debug(debug);
+    if let Some(path) = &args.debug_script {
+        let script = fs::read_to_string(path).map_err(|e| format!("Can not read {}: {}", path.display(), e))?;
+        builder = builder.debug_script(script.lines().map(str::to_string).collect());
+    }
+    if args.teach {
+        // The narration comes before the pause, so it pauses itself.
+        let pause = Duration::from_millis(args.delay.unwrap_or(teach::DELAY));
+        builder = builder.observe(1, move |machine, program| teach::narrate(machine, program, pause));
+    } else if let Some(ms) = args.delay {
+        builder = builder.delay(Some(Duration::from_millis(ms))).delay_every(args.delay_every);
+    }
+    if args.progress {
+        builder = builder.progress(&PROGRESS);
+        thread::Builder::new()
+            .spawn(|| loop {
+                thread::sleep(Duration::from_secs(1));
+                PROGRESS.store(true, Ordering::Relaxed);
+            })
+            .map_err(|e| format!("--progress needs threads: {}", e))?;
+    }
+    // With --bang-input, the input comes with the program.
+    let mut source = None;
+    let mut socket = None;
+    let input: Box<dyn Read> = if args.bang_input {
+        let whole = read_source(&args.source)?;
+        let (code, input) = program::split_input(&whole);
+        log::debug!("Program input: {} bytes", input.len());
+        let input = io::Cursor::new(input.as_bytes().to_vec());
+        source = Some(code.to_string());
+        Box::new(input)
+    } else if let Some(path) = &args.input_file {
+        Box::new(fs::File::open(path).map_err(|e| format!("Can not open {}: {}", path.display(), e))?)
+    } else if let Some(mode) = &args.io {
+        let stream = connect(mode)?;
+        socket = Some(stream.try_clone()?);
+        Box::new(io::BufReader::new(stream))
+    } else {
+        console::stdin()
+    };
+    let mut output: Box<dyn Write> = match socket {
+        Some(stream) => Box::new(io::BufWriter::new(stream)),
+        None => program_output(args),
+    };
+    if let Some(path) = &args.tee {
+        let file = fs::File::create(path).map_err(|e| format!("Can not create {}: {}", path.display(), e))?;
+        output = Box::new(Tee(output, io::BufWriter::new(file)));
+    }
+    let mut cast = None;
+    if let Some(path) = &args.render_cast {
+        let screen = Capture::default();
+        output = Box::new(Tee(output, screen.clone()));
+        let interval = Duration::from_millis(args.cast_interval);
+        let recording = Cast::create(path, interval, screen).map_err(|e| format!("Can not create {}: {}", path.display(), e))?;
+        let recording = Rc::new(RefCell::new(recording));
+        let frames = Rc::clone(&recording);
+        builder = builder.observe(args.cast_every, move |machine, program| frames.borrow_mut().frame(machine, program));
+        cast = Some(recording);
+    }
+    // The number of times every instruction ran, for --profile-out.
+    let counts = Rc::new(RefCell::new(vec![]));
+    if args.profile_out.is_some() {
+        let observed = Rc::clone(&counts);
+        builder = builder.observe(1, move |machine, program| {
+            let mut counts = observed.borrow_mut();
+            counts.resize(program.len(), 0);
+            counts[machine.instruction()] += 1;
+            Ok(())
+        });
+    }
+    if let (Some(every), Some(path)) = (args.checkpoint_every, &args.checkpoint_file) {
+        let path = path.clone();
+        builder = builder.observe(every, move |machine, program| save_checkpoint(&path, &machine.snapshot(program)));
+    }
+    if let Some(path) = &args.resume {
+        let bytes = fs::read(path).map_err(|e| format!("Can not read {}: {}", path.display(), e))?;
+        let snapshot = bytecode::decode_snapshot(&bytes).map_err(|e| format!("Invalid checkpoint {}: {}", path.display(), e))?;
+        log::info!("Resuming after {} steps", snapshot.steps);
+        builder = builder.resume(snapshot);
+    }
+    builder = builder.io(input, output);
+    let mut machine = builder.build();
+    let target = target(&args.machine, config)?;
+    let parse = || match &source {
+        Some(code) => Ok(parse_source(code, &args.source)?),
+        None => parse(&args.source),
+    };
+
+    if args.dry_run {
+        let program = optimize_program(&parse()?, &args.opt, config, target, debug)?;
+        writeln!(io::stdout(), "{}", Stats::of(&program))?;
+        log::info!("Dry run, the program was not executed");
+    } else if let Some(AstFormat::Json) = args.emit_ast {
+        writeln!(io::stdout(), "{}", serde_json::to_string_pretty(&ast::tree(&parse()?))?)?;
+    } else if args.emit_ir {
+        write!(io::stdout(), "{}", optimize_program(&parse()?, &args.opt, config, target, debug)?.dump())?;
+    } else if args.source.is_given() || !args.lines {
+        let code = match source {
+            Some(code) => code,
+            None => read_source(&args.source)?,
+        };
+        let program = load_program(&code, args, config, debug || args.teach)?;
+        let result = execute_within(&mut machine, &program, timeout(args));
+        if let (Some(path), Err(e)) = (&args.checkpoint_file, &result) {
+            // The run stopped at an instruction it resumes at.
+            if let Some(BfError::Interrupted) = diagnostic::bf_error(&**e) {
+                save_checkpoint(path, &machine.snapshot(&program))?;
+                eprintln!("Checkpoint saved to {}, continue with --resume {}", path.display(), path.display());
+            }
+        }
+        if let Some(cast) = &cast {
+            // The last frame shows how the program ended.
+            let mut cast = cast.borrow_mut();
+            cast.frame(&machine, &program)?;
+            cast.finish()?;
+        }
+        if let (Some(path), Ok(())) = (&args.dump_mem_file, &result) {
+            let cell_width = settings(&args.machine, config)?.cell_width;
+            dump_memory(path, machine.memory(), cell_width, args.range)?;
+        }
+        if let Some(path) = &args.profile_out {
+            let mut counts = counts.borrow_mut();
+            counts.resize(program.len(), 0);
+            let profile = Profile::from_counts(&program, &counts);
+            fs::write(path, serde_json::to_string_pretty(&profile)?).map_err(|e| format!("Can not write {}: {}", path.display(), e))?;
+            log::info!("Profile of {} loops written to {}", profile.loops.len(), path.display());
+        }
+        result?;
+    } else {
+        // Read from standard input, line by line
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let l = line?;
+            log::debug!("Interpreting line: {}", l.as_str());
+            // The lines run one after the other on the same machine.
+            let target = Target { fresh: false, ..target };
+            let program = optimize_program(&parse_source(&l, &args.source)?, &args.opt, config, target, debug)?;
+            execute_within(&mut machine, &program, timeout(args))?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a program is valid.
+fn check(args: &SourceArgs) -> Result<(), Box<dyn Error>> {
+    let program = parse(args)?;
+    log::info!("Program is valid ({} instructions)", program.len());
+    Ok(())
+}
+
+/// Prints warnings about the loops of a program that may never terminate.
+fn lint(args: &SourceArgs) -> Result<(), Box<dyn Error>> {
+    let program = parse(args)?;
+    let mut loops = analysis::loops(&program);
+    loops.sort_by_key(|info| info.open);
+    let mut warnings = 0;
+    for info in loops.into_iter().filter(|info| !analysis::is_dead(&program, info.open)) {
+        let message = match info.termination() {
+            Termination::Never => "this loop never terminates once entered, it does not change the cell it tests".to_string(),
+            Termination::IfMultipleOf(n) => {
+                format!("this loop only terminates if the cell it tests is a multiple of {} when entering it", n)
+            },
+            Termination::Always | Termination::Unknown => continue,
+        };
+        let (line, column) = program.location(program.spans[info.open].start);
+        writeln!(io::stdout(), "{}:{}: warning: {}", line, column, message)?;
+        warnings += 1;
+    }
+    if warnings > 0 {
+        return Err(format!("Found {} warnings", warnings).into());
+    }
+    log::info!("No warnings");
+    Ok(())
+}
+
+/// Compiles a program to Rust.
+fn compile(args: &CompileArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+    let settings = settings(&args.machine, config)?;
+    let program = optimize_program(&parse(&args.source)?, &args.opt, config, settings.target(), false)?;
+    if program.ops.iter().any(|op| matches!(op, program::Op::Channel | program::Op::Send | program::Op::Receive | program::Op::Fork | program::Op::Clock | program::Op::File(_))) {
+        log::warn!("The instructions of the dialect are not translated, the program only has the standard input and output");
+    }
+    let code = if args.precompute {
+        let output = precompute(&program, &settings.target(), PRECOMPUTE_STEPS).ok_or(
+            "Can not precompute the program: it reads its input, uses '#', goes out of the memory or runs for too long",
+        )?;
+        log::info!("Precomputed {} bytes of output", output.len());
+        transpile::literal(&output, args.target)
+    } else {
+        transpile::translate(&program, &settings, args.target)
+    };
+    match &args.output {
+        Some(_) if args.standalone && args.target != Language::Rust => {
+            return Err("--standalone is only supported for Rust".into());
+        },
+        Some(dir) if args.standalone => {
+            fs::create_dir_all(dir.join("src"))?;
+            fs::write(dir.join("Cargo.toml"), transpile::cargo_manifest(&package_name(dir)))?;
+            fs::write(dir.join("src").join("main.rs"), code)?;
+            log::info!("Cargo project written to {}, build it with cargo build --release", dir.display());
+        },
+        Some(path) => fs::write(path, code)?,
+        None => write!(io::stdout(), "{}", code)?,
+    }
+    Ok(())
+}
+
+/// Creates an executable that runs the program.
+fn bundle(args: &BundleArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+    let settings = settings(&args.machine, config)?;
+    let program = optimize_program(&parse(&args.source)?, &args.opt, config, settings.target(), false)?;
+    bundle::write(&args.output, &bytecode::encode(&program, &settings))?;
+    log::info!("Executable written to {}", args.output.display());
+    Ok(())
+}
+
+/// Prints the statistics or the loop tree of a program, profiling it if asked.
+fn stats(args: &StatsArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+    let program = parse(&args.source)?;
+    if !args.loops {
+        let stats = Stats::of(&program);
+        match args.format {
+            StatsFormat::Text => writeln!(io::stdout(), "{}", stats)?,
+            StatsFormat::Json => {
+                let mut json = serde_json::to_value(&stats)?;
+                json["unbalanced_loops"] = stats.unbalanced_loops().into();
+                json["average_depth"] = stats.average_depth().into();
+                json["io_ratio"] = stats.io_ratio().into();
+                json["difficulty"] = stats.difficulty().into();
+                writeln!(io::stdout(), "{}", json)?;
+            },
+        }
+        return Ok(());
+    }
+    if !args.profile {
+        write!(io::stdout(), "{}", stats::loop_report(&program, None))?;
+        return Ok(());
+    }
+    // The program runs as written, so that every instruction is one of the source.
+    let counts = Rc::new(RefCell::new(vec![0u64; program.len()]));
+    let observed = Rc::clone(&counts);
+    let mut machine = builder(&args.machine, config)?
+        .observe(1, move |machine, _| {
+            observed.borrow_mut()[machine.instruction()] += 1;
+            Ok(())
+        })
+        .build();
+    execute(&mut machine, &program)?;
+    // Apart from the output of the program.
+    writeln!(io::stdout())?;
+    write!(io::stdout(), "{}", stats::loop_report(&program, Some(&counts.borrow())))?;
+    Ok(())
+}
+
+/// Serves the playground or the API.
+#[cfg(feature = "server")]
+fn serve(args: &ServeArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+    let limits = serve::Limits {
+        settings: settings(&args.machine, config)?,
+        max_steps: args.max_steps,
+        // Runs share the server, keep their output reasonable.
+        max_output: args.machine.max_output.unwrap_or(serve::DEFAULT_MAX_OUTPUT),
+        max_loop_depth: args.machine.max_loop_depth,
+        timeout: Duration::from_millis(args.timeout),
+    };
+    let workers = args.workers.unwrap_or_else(|| thread::available_parallelism().map_or(1, usize::from));
+    let options = serve::Options { playground: args.playground, api: args.api, workers, limits };
+    serve::serve(&format!("{}:{}", args.host, args.port), &options)?;
+    Ok(())
+}
+
+/// Runs as a Jupyter kernel, or installs it.
+#[cfg(feature = "server")]
+fn jupyter_kernel(args: &KernelArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+    let Some(connection_file) = &args.connection_file else {
+        let dir = jupyter::install()?;
+        writeln!(io::stdout(), "Installed the kernel in {}", dir.display())?;
+        return Ok(());
+    };
+    // The cells run one after the other on the same machine.
+    let target = Target { fresh: false, ..settings(&args.machine, config)?.target() };
+    let optimize = |program: &Program| optimize_program(program, &args.opt, config, target, false);
+    signal::install();
+    jupyter::run(connection_file, builder(&args.machine, config)?, &optimize)
+}
+
+/// Assembles a program of the macro language into Brainfuck.
+fn asm(args: &AsmArgs) -> Result<(), Box<dyn Error>> {
+    let code = asm::assemble(&read_source(&args.source)?)?;
+    match &args.output {
+        Some(path) => fs::write(path, code)?,
+        None => write!(io::stdout(), "{}", code)?,
+    }
+    Ok(())
+}
+
+/// Converts a program from one of the languages spelling Brainfuck differently to another.
+fn convert(args: &ConvertArgs) -> Result<(), Box<dyn Error>> {
+    let code = convert::convert(&read_source(&args.source)?, args.from, args.to)?;
+    match &args.output {
+        Some(path) => fs::write(path, code)?,
+        None => write!(io::stdout(), "{}", code)?,
+    }
+    Ok(())
+}
+
+/// The given seed, or one from the clock, shown with --verbose.
+fn seed(seed: Option<u64>) -> u64 {
+    let seed = seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64));
+    log::info!("Seed {}", seed);
+    seed
+}
+
+/// Writes a random program.
+fn gen_random(args: &GenRandomArgs) -> Result<(), Box<dyn Error>> {
+    let program = generate::random(args.size, &mut Rng::new(seed(args.seed)), args.terminating);
+    write_code(&program, &args.output)
+}
+
+/// Writes a program printing the text.
+fn gen_text(args: &GenTextArgs) -> Result<(), Box<dyn Error>> {
+    let text = match &args.text {
+        Some(text) => text.clone(),
+        None => io::read_to_string(io::stdin())?,
+    };
+    write_code(&codegen::emit_print_str(&text), &args.output)
+}
+
+/// Writes generated code to the file, or else to the standard output, in lines of 80 instructions.
+fn write_code(program: &str, output: &Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let mut code = String::new();
+    for line in program.as_bytes().chunks(80) {
+        code.push_str(std::str::from_utf8(line).expect("programs are ASCII"));
+        code.push('\n');
+    }
+    match output {
+        Some(path) => fs::write(path, code)?,
+        None => write!(io::stdout(), "{}", code)?,
+    }
+    Ok(())
+}
+
+/// Runs mutants of a program, and prints those which found a problem, with the source code of the mutant.
+fn fuzz(args: &FuzzArgs) -> Result<(), Box<dyn Error>> {
+    let path = &args.program;
+    let source = fs::read_to_string(path).map_err(|e| format!("Can not read {}: {}", path.display(), e))?;
+    // The panics are what is looked for, and reported below.
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| ()));
+    let report = fuzz::fuzz(&source, args.iterations, &mut Rng::new(seed(args.seed)), args.max_steps);
+    std::panic::set_hook(hook);
+    for finding in &report.findings {
+        let problem = match &finding.problem {
+            Problem::Panic(message) => format!("the interpreter panicked: {}", message),
+            Problem::Differs => "it does not do the same as the program".to_string(),
+        };
+        writeln!(io::stdout(), "Mutant ({}), at -O{} in the {} engine, on input \"{}\": {}", finding.mutation,
+            finding.opt_level, finding.engine, finding.input.escape_ascii(), problem)?;
+        writeln!(io::stdout(), "{}", finding.source.trim_end())?;
+    }
+    writeln!(io::stdout(), "Ran {} mutants: {} invalid, {} failed with an error, {} did not end, {} with a problem",
+        report.mutants, report.invalid, report.failed, report.timed_out, report.findings.len())?;
+    if !report.findings.is_empty() {
+        return Err(format!("Found {} mutants the interpreter does not handle", report.findings.len()).into());
+    }
+    Ok(())
+}
+
+/// Prints the pieces of a program that shorter code does the same as, and the bytes it saves.
+fn golf(args: &GolfArgs) -> Result<(), Box<dyn Error>> {
+    let source = read_source(&args.source)?;
+    let savings = golf::golf(&source, args.window as usize);
+    let width = savings.iter().map(|s| s.original.len()).max().unwrap_or(0);
+    for saving in &savings {
+        let location = format!("{}:{}", saving.line, saving.column);
+        writeln!(io::stdout(), "{:<8} {:<width$}  ->  {}", location, saving.original, saving.replacement, width = width)?;
+    }
+    let size = source.chars().filter(|c| "+-<>.,[]".contains(*c)).count();
+    let saved: usize = savings.iter().map(golf::Saving::saved).sum();
+    let percent = if size == 0 { 0.0 } else { 100.0 * saved as f64 / size as f64 };
+    let places = savings.len();
+    writeln!(io::stdout(), "Saves {} of {} bytes ({:.1}%), in {} place{}", saved, size, percent, places, if places == 1 { "" } else { "s" })?;
+    Ok(())
+}
+
+/// Checks that two programs behave the same.
+fn equiv(args: &EquivArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+    let settings = settings(&args.machine, config)?;
+    let load = |path: &Path| -> Result<Program, Box<dyn Error>> {
+        let source = SourceArgs { input: Some(path.to_path_buf()), eval: None, dialect: Dialect::Standard, extended: false, fix_brackets: false };
+        optimize_program(&parse(&source)?, &args.opt, config, settings.target(), false)
+    };
+    let (a, b) = (load(&args.a)?, load(&args.b)?);
+    let report = equiv::check(&a, &b, &settings, args.inputs, args.max_steps, args.output_only);
+    if report.timed_out > 0 {
+        log::warn!("{} of {} inputs hit the step limit and were skipped", report.timed_out, report.inputs);
+    }
+    let Some(counterexample) = report.counterexample else {
+        writeln!(io::stdout(), "The programs behave the same on {} inputs", report.inputs - report.timed_out)?;
+        return Ok(());
+    };
+    let describe = |path: &Path, outcome: &Outcome| -> io::Result<()> {
+        writeln!(io::stdout(), "{}: {}", path.display(), outcome.ending)?;
+        writeln!(io::stdout(), "    output: \"{}\"", outcome.output.escape_ascii())?;
+        if !args.output_only {
+            writeln!(io::stdout(), "    tape: {:?}", outcome.tape)?;
+        }
+        Ok(())
+    };
+    writeln!(io::stdout(), "Counterexample, with input \"{}\":", counterexample.input.escape_ascii())?;
+    describe(&args.a, &counterexample.a)?;
+    describe(&args.b, &counterexample.b)?;
+    Err("The programs are not equivalent".into())
+}
+
+/// Looks for an input reaching the goal given in the arguments.
+fn symexec(args: &SymexecArgs, config: &Config) -> Result<(), Box<dyn Error>> {
+    let settings = settings(&args.machine, config)?;
+    let program = optimize_program(&parse(&args.source)?, &args.opt, config, settings.target(), false)?;
+    let (goal, description) = match (args.reach, args.output) {
+        (Some((line, column)), _) => {
+            let index = program.instruction_at(line, column).ok_or(format!("No instruction at {}:{}", line, column))?;
+            (Goal::Reach(index), format!("reaches {}:{}", line, column))
+        },
+        (None, Some(byte)) => (Goal::Output(byte), format!("prints {} {:?}", byte, byte as char)),
+        (None, None) => unreachable!("clap requires a goal"),
+    };
+    let limits = Limits { max_depth: args.max_depth, max_steps: args.max_steps };
+    let exploration = symexec::explore(&program, &settings, goal, limits);
+    log::info!("Explored {} paths, {} cut at the limits", exploration.paths, exploration.cut);
+    match exploration.found {
+        Some(found) => writeln!(io::stdout(), "Input \"{}\" {} after {} instructions", found.input.escape_ascii(), description, found.steps)?,
+        None if exploration.cut > 0 => writeln!(io::stdout(), "No input {} within the limits, {} paths were cut", description, exploration.cut)?,
+        None => writeln!(io::stdout(), "No input {}", description)?,
+    }
+    Ok(())
+}
+
+/// Runs the program bundled in this executable.
+fn run_bundle(bytecode: &[u8]) -> Result<(), Box<dyn Error>> {
+    let (program, settings) = bytecode::decode(bytecode)?;
+    let mut machine = Machine::builder()
+        .mem_size(settings.mem_size)
+        .cell_width(settings.cell_width)
+        .eof(settings.eof)
+        .build();
+    machine.run(&program)?;
+    Ok(())
+}
+
+/// Returns a valid cargo package name from the name of the project directory.
+fn package_name(dir: &Path) -> String {
+    let name: String = dir
+        .canonicalize()
+        .ok()
+        .and_then(|d| d.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => name,
+        Some(_) => format!("bf-{}", name),
+        None => "program".to_string(),
+    }
+}
+
+/// Lists, shows or runs the bundled examples.
+fn run_example(command: &ExamplesCommand, config: &Config) -> Result<(), Box<dyn Error>> {
+    match command {
+        ExamplesCommand::List => {
+            for example in examples::EXAMPLES {
+                writeln!(io::stdout(), "{:<12} {}", example.name, example.description)?;
+            }
+        },
+        ExamplesCommand::Show { name } => write!(io::stdout(), "{}", examples::find(name)?.source)?,
+        ExamplesCommand::Run { name, machine, opt } => {
+            let program = Program::parse(examples::find(name)?.source)?;
+            let target = target(machine, config)?;
+            execute(&mut builder(machine, config)?.build(), &optimize_program(&program, opt, config, target, false)?)?;
+        },
+    }
+    Ok(())
+}
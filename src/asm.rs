@@ -0,0 +1,295 @@
+// Assembler of the BF macro language into plain Brainfuck.
+// MIT license.
+
+use std::collections::HashMap;
+
+use crate::error::BfError;
+
+/// A node of a parsed macro program.
+#[derive(Debug, Clone)]
+enum Node {
+    /// A Brainfuck command other than a bracket.
+    Command(char),
+    /// A loop, `[...]`.
+    Loop(Vec<Item>),
+    /// A group of items repeated together, `(...)`.
+    Group(Vec<Item>),
+    /// A use of the macro with the given name.
+    Call(String),
+    /// A move of the pointer to the named cell, `@name`.
+    Goto(String),
+}
+
+/// A node with its repetition count and source location.
+#[derive(Debug, Clone)]
+struct Item {
+    node: Node,
+    times: usize,
+    line: usize,
+    column: usize,
+}
+
+/// The definitions of a macro program.
+#[derive(Default)]
+struct Definitions {
+    macros: HashMap<String, Vec<Item>>,
+    cells: HashMap<String, isize>,
+}
+
+/// Reads the source code of a macro program.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: impl Into<String>) -> BfError {
+        BfError::Assembly(self.line, self.column, message.into())
+    }
+
+    /// Skips whitespace and comments, which run from `//` to the end of the line.
+    fn skip_blank(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.next();
+                },
+                Some('/') if self.chars.get(self.pos + 1) == Some(&'/') => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.next();
+                    }
+                },
+                _ => return,
+            }
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, BfError> {
+        let mut name = String::new();
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_alphanumeric() || *c == '_') {
+            name.push(c);
+            self.next();
+        }
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(self.error("expected a name"));
+        }
+        Ok(name)
+    }
+
+    fn number(&mut self) -> Result<usize, BfError> {
+        let mut digits = String::new();
+        while let Some(c) = self.peek().filter(char::is_ascii_digit) {
+            digits.push(c);
+            self.next();
+        }
+        digits.parse().map_err(|_| self.error("expected a number"))
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), BfError> {
+        self.skip_blank();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(self.error(format!("expected '{}', found '{}'", expected, c))),
+            None => Err(self.error(format!("expected '{}', found the end of the file", expected))),
+        }
+    }
+
+    /// Parses items up to the character closing the block opened at the current position, or the end of the file
+    /// if there is none. The definitions are only allowed at the top level, where they are stored in `defs`.
+    fn items(&mut self, closing: Option<char>, mut defs: Option<&mut Definitions>) -> Result<Vec<Item>, BfError> {
+        let opening = (self.line, self.column.saturating_sub(1));
+        let mut items = vec![];
+        loop {
+            self.skip_blank();
+            let (line, column) = (self.line, self.column);
+            let node = match self.peek() {
+                None if closing.is_none() => return Ok(items),
+                None => {
+                    let closing = closing.unwrap_or_default();
+                    return Err(BfError::Assembly(opening.0, opening.1, format!("missing '{}' for this block", closing)));
+                },
+                Some(c) if Some(c) == closing => {
+                    self.next();
+                    return Ok(items);
+                },
+                Some(c @ ('+' | '-' | '<' | '>' | '.' | ',' | '#')) => {
+                    self.next();
+                    Node::Command(c)
+                },
+                Some('[') => {
+                    self.next();
+                    Node::Loop(self.items(Some(']'), None)?)
+                },
+                Some('(') => {
+                    self.next();
+                    Node::Group(self.items(Some(')'), None)?)
+                },
+                Some('@') => {
+                    self.next();
+                    Node::Goto(self.identifier()?)
+                },
+                Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                    let name = self.identifier()?;
+                    match (name.as_str(), defs.as_deref_mut()) {
+                        ("cell", Some(defs)) => {
+                            self.cell(defs)?;
+                            continue;
+                        },
+                        ("macro", Some(defs)) => {
+                            self.definition(defs)?;
+                            continue;
+                        },
+                        ("cell" | "macro", None) => {
+                            return Err(BfError::Assembly(line, column, format!("'{}' is only allowed at the top level", name)));
+                        },
+                        _ => Node::Call(name),
+                    }
+                },
+                Some(c) => return Err(self.error(format!("unexpected '{}'", c))),
+            };
+            let times = if self.peek() == Some('{') {
+                if let Node::Goto(_) = node {
+                    return Err(self.error("a move to a cell can not be repeated"));
+                }
+                self.next();
+                let times = self.number()?;
+                self.expect('}')?;
+                times
+            } else {
+                1
+            };
+            items.push(Item { node, times, line, column });
+        }
+    }
+
+    /// Parses the rest of a cell declaration, `cell name = position`.
+    fn cell(&mut self, defs: &mut Definitions) -> Result<(), BfError> {
+        self.skip_blank();
+        let name = self.identifier()?;
+        self.expect('=')?;
+        self.skip_blank();
+        let position = self.number()?;
+        if defs.cells.insert(name.clone(), position as isize).is_some() {
+            return Err(self.error(format!("cell '{}' is already declared", name)));
+        }
+        Ok(())
+    }
+
+    /// Parses the rest of a macro definition, `macro name { body }`.
+    fn definition(&mut self, defs: &mut Definitions) -> Result<(), BfError> {
+        self.skip_blank();
+        let name = self.identifier()?;
+        if name == "cell" || name == "macro" {
+            return Err(self.error(format!("'{}' can not be the name of a macro", name)));
+        }
+        self.expect('{')?;
+        let body = self.items(Some('}'), None)?;
+        if defs.macros.insert(name.clone(), body).is_some() {
+            return Err(self.error(format!("macro '{}' is already defined", name)));
+        }
+        Ok(())
+    }
+}
+
+/// Writes the Brainfuck code of the items, keeping track of the pointer position for the moves to named cells.
+struct Emitter<'a> {
+    defs: &'a Definitions,
+    out: String,
+    /// Position of the pointer, if known.
+    pos: Option<isize>,
+    /// Macros being expanded, to catch recursive ones.
+    calls: Vec<&'a str>,
+    /// Number of moves to named cells so far.
+    gotos: usize,
+}
+
+impl<'a> Emitter<'a> {
+    fn emit(&mut self, items: &'a [Item]) -> Result<(), BfError> {
+        for item in items {
+            let error = |message: String| BfError::Assembly(item.line, item.column, message);
+            for _ in 0..item.times {
+                match &item.node {
+                    Node::Command(c) => {
+                        self.out.push(*c);
+                        match c {
+                            '>' => self.pos = self.pos.map(|p| p + 1),
+                            '<' => self.pos = self.pos.map(|p| p - 1),
+                            _ => (),
+                        }
+                    },
+                    Node::Loop(body) => {
+                        let (start, gotos) = (self.pos, self.gotos);
+                        self.out.push('[');
+                        self.emit(body)?;
+                        self.out.push(']');
+                        if self.pos != start {
+                            // Later iterations start elsewhere, so the positions of the first one are wrong.
+                            if self.gotos > gotos {
+                                return Err(error("a loop using named cells must end on the cell it started on".to_string()));
+                            }
+                            self.pos = None;
+                        }
+                    },
+                    Node::Group(body) => self.emit(body)?,
+                    Node::Call(name) => {
+                        let (name, body) = self.defs.macros.get_key_value(name).ok_or_else(|| error(format!("unknown macro '{}'", name)))?;
+                        if self.calls.contains(&name.as_str()) {
+                            return Err(error(format!("macro '{}' uses itself", name)));
+                        }
+                        self.calls.push(name);
+                        self.emit(body)?;
+                        self.calls.pop();
+                    },
+                    Node::Goto(name) => {
+                        let &target = self.defs.cells.get(name).ok_or_else(|| error(format!("unknown cell '{}'", name)))?;
+                        let pos = self.pos.ok_or_else(|| {
+                            error(format!("can not move to '{}', the pointer position is not known after a loop that does not end where it started", name))
+                        })?;
+                        let step = if target < pos { '<' } else { '>' };
+                        self.out.extend(std::iter::repeat_n(step, target.abs_diff(pos)));
+                        self.pos = Some(target);
+                        self.gotos += 1;
+                    },
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Assembles a program of the BF macro language into Brainfuck code.
+///
+/// The language is Brainfuck with a few additions:
+/// - `//` starts a comment, up to the end of the line. Any other text is an error.
+/// - `+{10}` repeats a command ten times. Loops, macros and groups of items in parentheses, like `(>+){3}`, can be
+///   repeated too.
+/// - `cell name = 3` names the cell at position 3, and `@name` moves the pointer to it. The pointer position is
+///   tracked from the start of the program, and is lost after loops that do not end where they started.
+/// - `macro name { ... }` defines a macro, and `name` expands to its body.
+pub fn assemble(source: &str) -> Result<String, BfError> {
+    let mut parser = Parser { chars: source.chars().collect(), pos: 0, line: 1, column: 1 };
+    let mut defs = Definitions::default();
+    let items = parser.items(None, Some(&mut defs))?;
+    let mut emitter = Emitter { defs: &defs, out: String::new(), pos: Some(0), calls: vec![], gotos: 0 };
+    emitter.emit(&items)?;
+    emitter.out.push('\n');
+    Ok(emitter.out)
+}
@@ -0,0 +1,61 @@
+// Syntax tree of programs, for tools working on their structure.
+// MIT license.
+
+use serde::Serialize;
+
+use crate::program::{Op, Program, Span};
+
+/// A node of the syntax tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Node {
+    /// The same instruction repeated, like `+++`.
+    Run {
+        /// The instruction, as written.
+        command: String,
+        count: usize,
+        span: Span,
+        /// Line and column, starting at 1, where the run starts.
+        line: usize,
+        column: usize,
+    },
+    /// A loop, with the nodes between its brackets.
+    Loop {
+        /// From the opening bracket to the closing one, included.
+        span: Span,
+        line: usize,
+        column: usize,
+        body: Vec<Node>,
+    },
+}
+
+/// Builds the syntax tree of the program: its loops and the runs of instructions between them.
+pub fn tree(program: &Program) -> Vec<Node> {
+    // The nodes of the loops being built, the whole program at the bottom.
+    let mut stack: Vec<Vec<Node>> = vec![vec![]];
+    for (i, (&op, &span)) in program.ops.iter().zip(&program.spans).enumerate() {
+        let (line, column) = program.location(span.start);
+        match op {
+            Op::Open(_) => stack.push(vec![]),
+            Op::Close(open) => {
+                let body = stack.pop().expect("balanced brackets");
+                let start = program.spans[open];
+                let (line, column) = program.location(start.start);
+                let node = Node::Loop { span: start.join(span), line, column, body };
+                stack.last_mut().expect("balanced brackets").push(node);
+            },
+            _ => {
+                let nodes = stack.last_mut().expect("balanced brackets");
+                let same = i > 0 && program.ops[i - 1] == op;
+                match nodes.last_mut() {
+                    Some(Node::Run { count, span: run, .. }) if same => {
+                        *count += 1;
+                        *run = run.join(span);
+                    },
+                    _ => nodes.push(Node::Run { command: op.to_string(), count: 1, span, line, column }),
+                }
+            },
+        }
+    }
+    stack.pop().expect("balanced brackets")
+}
@@ -0,0 +1,44 @@
+// Executables with a program appended to them.
+// MIT license.
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, prelude::*, SeekFrom};
+use std::path::Path;
+
+/// Bytes at the very end of a bundled executable.
+const MAGIC: &[u8; 8] = b"BFBUNDLE";
+
+/// Writes a copy of the running executable with the encoded program appended to it. The trailer after the program
+/// holds its length and `MAGIC`.
+pub fn write(path: &Path, bytecode: &[u8]) -> io::Result<()> {
+    fs::copy(env::current_exe()?, path)?;
+    let mut file = OpenOptions::new().append(true).open(path)?;
+    file.write_all(bytecode)?;
+    file.write_all(&(bytecode.len() as u64).to_le_bytes())?;
+    file.write_all(MAGIC)?;
+    Ok(())
+}
+
+/// Returns the encoded program appended to the running executable, if it is a bundle.
+pub fn embedded() -> io::Result<Option<Vec<u8>>> {
+    let mut file = File::open(env::current_exe()?)?;
+    let size = file.metadata()?.len();
+    if size < 16 {
+        return Ok(None);
+    }
+    let mut trailer = [0u8; 16];
+    file.seek(SeekFrom::End(-16))?;
+    file.read_exact(&mut trailer)?;
+    if &trailer[8..] != MAGIC {
+        return Ok(None);
+    }
+    let len = u64::from_le_bytes(trailer[..8].try_into().expect("8 bytes"));
+    if len > size - 16 {
+        return Ok(None);
+    }
+    let mut bytecode = vec![0u8; len as usize];
+    file.seek(SeekFrom::End(-16 - len as i64))?;
+    file.read_exact(&mut bytecode)?;
+    Ok(Some(bytecode))
+}
@@ -0,0 +1,434 @@
+// Binary encoding of programs.
+// MIT license.
+
+use crate::error::BfError;
+use crate::machine::{CellWidth, Eof, Snapshot};
+use crate::program::{FileOp, Op, Program, Span};
+use crate::transpile::Settings;
+
+/// Bytes every encoded program starts with, followed by the format version.
+const MAGIC: &[u8; 4] = b"BFBC";
+/// Version of the format.
+const VERSION: u8 = 1;
+
+/// Bytes every encoded snapshot starts with, followed by the format version.
+const SNAPSHOT_MAGIC: &[u8; 4] = b"BFST";
+
+/// Encodes the program and the machine settings it runs with. Source spans are not kept.
+pub fn encode(program: &Program, settings: &Settings) -> Vec<u8> {
+    let mut out = Vec::with_capacity(16 + program.len() * 9);
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.extend_from_slice(&(settings.mem_size as u64).to_le_bytes());
+    out.push(match settings.cell_width {
+        CellWidth::U8 => 8,
+        CellWidth::U16 => 16,
+        CellWidth::U32 => 32,
+    });
+    out.push(match settings.eof {
+        Eof::Zero => 0,
+        Eof::Unchanged => 1,
+        Eof::Max => 2,
+    });
+    out.extend_from_slice(&(program.len() as u64).to_le_bytes());
+    for op in &program.ops {
+        match *op {
+            Op::Move(n) => {
+                out.push(0);
+                out.extend_from_slice(&(n as i64).to_le_bytes());
+            },
+            Op::Add(n) => {
+                out.push(1);
+                out.extend_from_slice(&n.to_le_bytes());
+            },
+            Op::Out => out.push(2),
+            Op::In => out.push(3),
+            Op::Open(target) => {
+                out.push(4);
+                out.extend_from_slice(&(target as u64).to_le_bytes());
+            },
+            Op::Close(target) => {
+                out.push(5);
+                out.extend_from_slice(&(target as u64).to_le_bytes());
+            },
+            Op::Debug => out.push(6),
+            Op::Channel => out.push(13),
+            Op::Send => out.push(14),
+            Op::Receive => out.push(15),
+            Op::Fork => out.push(16),
+            Op::Clock => out.push(17),
+            Op::File(op) => {
+                out.push(18);
+                out.push(op.symbol() as u8);
+            },
+            Op::Clear => out.push(7),
+            Op::MulAdd { offset, factor } => {
+                out.push(8);
+                out.extend_from_slice(&(offset as i64).to_le_bytes());
+                out.extend_from_slice(&factor.to_le_bytes());
+            },
+            Op::Scan(n) => {
+                out.push(9);
+                out.extend_from_slice(&(n as i64).to_le_bytes());
+            },
+            Op::OutByte(b) => {
+                out.push(10);
+                out.push(b);
+            },
+            Op::AddAt { offset, value } => {
+                out.push(11);
+                out.extend_from_slice(&(offset as i64).to_le_bytes());
+                out.extend_from_slice(&value.to_le_bytes());
+            },
+            Op::Fill { offset, len, value } => {
+                out.push(12);
+                out.extend_from_slice(&(offset as i64).to_le_bytes());
+                out.extend_from_slice(&(len as u64).to_le_bytes());
+                out.extend_from_slice(&value.to_le_bytes());
+            },
+        }
+    }
+    out
+}
+
+/// Decodes a program encoded with `encode`, and its machine settings.
+pub fn decode(bytes: &[u8]) -> Result<(Program, Settings), BfError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.take(4)? != MAGIC {
+        return Err(BfError::InvalidBytecode("not a program".to_string()));
+    }
+    let version = reader.u8()?;
+    if version != VERSION {
+        return Err(BfError::InvalidBytecode(format!("unsupported version {}", version)));
+    }
+    let mem_size = reader.u64()? as usize;
+    let cell_width = match reader.u8()? {
+        8 => CellWidth::U8,
+        16 => CellWidth::U16,
+        32 => CellWidth::U32,
+        w => return Err(BfError::InvalidBytecode(format!("invalid cell width {}", w))),
+    };
+    let eof = match reader.u8()? {
+        0 => Eof::Zero,
+        1 => Eof::Unchanged,
+        2 => Eof::Max,
+        e => return Err(BfError::InvalidBytecode(format!("invalid end of input behavior {}", e))),
+    };
+    let len = reader.u64()? as usize;
+    let mut ops = Vec::with_capacity(len.min(bytes.len()));
+    for _ in 0..len {
+        let op = match reader.u8()? {
+            0 => Op::Move(reader.i64()? as isize),
+            1 => Op::Add(reader.i32()?),
+            2 => Op::Out,
+            3 => Op::In,
+            4 => Op::Open(reader.index(len)?),
+            5 => Op::Close(reader.index(len)?),
+            6 => Op::Debug,
+            7 => Op::Clear,
+            8 => Op::MulAdd { offset: reader.i64()? as isize, factor: reader.i32()? },
+            9 => Op::Scan(reader.i64()? as isize),
+            10 => Op::OutByte(reader.u8()?),
+            11 => Op::AddAt { offset: reader.i64()? as isize, value: reader.i32()? },
+            12 => Op::Fill { offset: reader.i64()? as isize, len: reader.u64()? as usize, value: reader.i32()? },
+            13 => Op::Channel,
+            14 => Op::Send,
+            15 => Op::Receive,
+            16 => Op::Fork,
+            17 => Op::Clock,
+            18 => Op::File(match reader.u8()? {
+                b'(' => FileOp::OpenRead,
+                b'{' => FileOp::OpenWrite,
+                b')' => FileOp::Close,
+                b';' => FileOp::Read,
+                b':' => FileOp::Write,
+                op => return Err(BfError::InvalidBytecode(format!("invalid file operation {}", op))),
+            }),
+            t => return Err(BfError::InvalidBytecode(format!("invalid instruction {}", t))),
+        };
+        ops.push(op);
+    }
+    if reader.pos != bytes.len() {
+        return Err(BfError::InvalidBytecode("trailing bytes".to_string()));
+    }
+    Ok((Program::from_ops(ops), Settings { mem_size, cell_width, eof }))
+}
+
+/// Encodes the source spans of the instructions of a program and the positions where its lines start, which
+/// `encode` leaves out.
+pub fn encode_source_map(program: &Program) -> Vec<u8> {
+    let mut out = Vec::with_capacity(16 + program.len() * 16 + program.lines.len() * 8);
+    out.extend_from_slice(&(program.spans.len() as u64).to_le_bytes());
+    for span in &program.spans {
+        out.extend_from_slice(&(span.start as u64).to_le_bytes());
+        out.extend_from_slice(&(span.end as u64).to_le_bytes());
+    }
+    out.extend_from_slice(&(program.lines.len() as u64).to_le_bytes());
+    for &line in &program.lines {
+        out.extend_from_slice(&(line as u64).to_le_bytes());
+    }
+    out
+}
+
+/// Adds a source map encoded with `encode_source_map` to a decoded program.
+pub fn decode_source_map(program: Program, bytes: &[u8]) -> Result<Program, BfError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let len = reader.u64()? as usize;
+    if len != program.len() {
+        return Err(BfError::InvalidBytecode(format!("source map of {} instructions for {}", len, program.len())));
+    }
+    let spans = (0..len)
+        .map(|_| Ok(Span { start: reader.u64()? as usize, end: reader.u64()? as usize }))
+        .collect::<Result<Vec<_>, BfError>>()?;
+    let lines = reader.u64()? as usize;
+    let lines = (0..lines).map(|_| Ok(reader.u64()? as usize)).collect::<Result<Vec<_>, BfError>>()?;
+    if reader.pos != bytes.len() {
+        return Err(BfError::InvalidBytecode("trailing bytes".to_string()));
+    }
+    Ok(Program { spans, lines, ..program })
+}
+
+/// Encodes the state of a run, to resume it later.
+pub fn encode_snapshot(snapshot: &Snapshot) -> Vec<u8> {
+    let mut out = Vec::with_capacity(64 + snapshot.memory.len() * 4 + snapshot.stack.len() * 8);
+    out.extend_from_slice(SNAPSHOT_MAGIC);
+    out.push(VERSION);
+    for value in [snapshot.program, snapshot.ptr as u64, snapshot.next as u64, snapshot.steps, snapshot.written, snapshot.read] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(&(snapshot.stack.len() as u64).to_le_bytes());
+    for &open in &snapshot.stack {
+        out.extend_from_slice(&(open as u64).to_le_bytes());
+    }
+    out.extend_from_slice(&(snapshot.memory.len() as u64).to_le_bytes());
+    for &cell in &snapshot.memory {
+        out.extend_from_slice(&cell.to_le_bytes());
+    }
+    out
+}
+
+/// Decodes a snapshot encoded with `encode_snapshot`. Whether it fits the program is checked when resuming it.
+pub fn decode_snapshot(bytes: &[u8]) -> Result<Snapshot, BfError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.take(4)? != SNAPSHOT_MAGIC {
+        return Err(BfError::InvalidBytecode("not a snapshot".to_string()));
+    }
+    let version = reader.u8()?;
+    if version != VERSION {
+        return Err(BfError::InvalidBytecode(format!("unsupported version {}", version)));
+    }
+    let program = reader.u64()?;
+    let ptr = reader.u64()? as usize;
+    let next = reader.u64()? as usize;
+    let (steps, written, read) = (reader.u64()?, reader.u64()?, reader.u64()?);
+    let depth = reader.u64()? as usize;
+    let stack = (0..depth).map(|_| Ok(reader.u64()? as usize)).collect::<Result<Vec<_>, BfError>>()?;
+    let len = reader.u64()? as usize;
+    let memory = reader.take(len.saturating_mul(4))?;
+    let memory = memory.chunks_exact(4).map(|c| u32::from_le_bytes(c.try_into().expect("4 bytes"))).collect();
+    if reader.pos != bytes.len() {
+        return Err(BfError::InvalidBytecode("trailing bytes".to_string()));
+    }
+    Ok(Snapshot { program, memory, ptr, next, stack, steps, written, read })
+}
+
+/// The 64-bit FNV-1a hash of the bytes, the same on every platform and with every build.
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// Reads the encoded values.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], BfError> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.bytes.len());
+        let slice = end.map(|end| &self.bytes[self.pos..end]).ok_or_else(|| BfError::InvalidBytecode("truncated".to_string()))?;
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, BfError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i32(&mut self) -> Result<i32, BfError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+
+    fn i64(&mut self) -> Result<i64, BfError> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes")))
+    }
+
+    fn u64(&mut self) -> Result<u64, BfError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes")))
+    }
+
+    /// A jump target, which must be an instruction of the program.
+    fn index(&mut self, len: usize) -> Result<usize, BfError> {
+        match self.u64()? as usize {
+            i if i < len => Ok(i),
+            i => Err(BfError::InvalidBytecode(format!("jump to {} out of the program", i))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A program with every instruction, with the extreme values of their operands, its brackets resolved.
+    fn every_op() -> Program {
+        let mut ops = vec![
+            Op::Move(-3),
+            Op::Move(isize::MAX),
+            Op::Add(-1),
+            Op::Add(i32::MAX),
+            Op::Out,
+            Op::In,
+            Op::Debug,
+            Op::Channel,
+            Op::Send,
+            Op::Receive,
+            Op::Fork,
+            Op::Clock,
+            Op::Clear,
+            Op::MulAdd { offset: -2, factor: i32::MIN },
+            Op::Scan(-1),
+            Op::Scan(4),
+            Op::OutByte(0),
+            Op::OutByte(u8::MAX),
+            Op::AddAt { offset: isize::MIN, value: 7 },
+            Op::Fill { offset: -1, len: 3, value: -1 },
+        ];
+        ops.extend([FileOp::OpenRead, FileOp::OpenWrite, FileOp::Close, FileOp::Read, FileOp::Write].map(Op::File));
+        ops.insert(0, Op::Open(ops.len() + 1));
+        ops.push(Op::Close(0));
+        Program::from_ops(ops)
+    }
+
+    /// The number of the variant of the instruction, so that a new one can not be left out of `every_op`.
+    fn variant(op: &Op) -> usize {
+        match op {
+            Op::Move(_) => 0,
+            Op::Add(_) => 1,
+            Op::Out => 2,
+            Op::In => 3,
+            Op::Open(_) => 4,
+            Op::Close(_) => 5,
+            Op::Debug => 6,
+            Op::Channel => 7,
+            Op::Send => 8,
+            Op::Receive => 9,
+            Op::Fork => 10,
+            Op::Clock => 11,
+            Op::File(_) => 12,
+            Op::Clear => 13,
+            Op::MulAdd { .. } => 14,
+            Op::Scan(_) => 15,
+            Op::OutByte(_) => 16,
+            Op::AddAt { .. } => 17,
+            Op::Fill { .. } => 18,
+        }
+    }
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            program: u64::MAX,
+            memory: vec![0, 1, u8::MAX as u32, u32::MAX, 0],
+            ptr: 3,
+            next: 7,
+            stack: vec![0, 4],
+            steps: 1 << 40,
+            written: 12,
+            read: 3,
+        }
+    }
+
+    fn invalid(result: Result<impl std::fmt::Debug, BfError>, message: &str) {
+        match result {
+            Err(BfError::InvalidBytecode(m)) => assert!(m.contains(message), "'{}' instead of '{}'", m, message),
+            other => panic!("{:?} instead of an error about '{}'", other, message),
+        }
+    }
+
+    #[test]
+    fn programs_round_trip() {
+        let program = every_op();
+        let mut variants: Vec<usize> = program.ops.iter().map(variant).collect();
+        variants.sort();
+        variants.dedup();
+        assert_eq!(variants, (0..=18).collect::<Vec<_>>());
+        for cell_width in [CellWidth::U8, CellWidth::U16, CellWidth::U32] {
+            for eof in [Eof::Zero, Eof::Unchanged, Eof::Max] {
+                for mem_size in [1, 30_000, 1 << 40] {
+                    let settings = Settings { mem_size, cell_width, eof };
+                    let (decoded, decoded_settings) = decode(&encode(&program, &settings)).unwrap();
+                    assert_eq!(decoded.ops, program.ops);
+                    assert_eq!(decoded_settings, settings);
+                }
+            }
+        }
+        let (empty, _) = decode(&encode(&Program::from_ops(vec![]), &Settings::default())).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn source_maps_round_trip() {
+        let program = Program::parse("+[->+<]\n>.\n").unwrap();
+        let (decoded, _) = decode(&encode(&program, &Settings::default())).unwrap();
+        let decoded = decode_source_map(decoded, &encode_source_map(&program)).unwrap();
+        assert_eq!((decoded.ops, decoded.spans, decoded.lines), (program.ops, program.spans, program.lines));
+    }
+
+    #[test]
+    fn snapshots_round_trip() {
+        assert_eq!(decode_snapshot(&encode_snapshot(&snapshot())).unwrap(), snapshot());
+        let empty = Snapshot { memory: vec![], stack: vec![], ..snapshot() };
+        assert_eq!(decode_snapshot(&encode_snapshot(&empty)).unwrap(), empty);
+    }
+
+    #[test]
+    fn refuses_truncated_input() {
+        let program = encode(&every_op(), &Settings::default());
+        for len in 0..program.len() {
+            assert!(decode(&program[..len]).is_err(), "{} bytes of {}", len, program.len());
+        }
+        invalid(decode(&program[..program.len() - 1]), "truncated");
+        let snapshot = encode_snapshot(&snapshot());
+        for len in 0..snapshot.len() {
+            assert!(decode_snapshot(&snapshot[..len]).is_err(), "{} bytes of {}", len, snapshot.len());
+        }
+        invalid(decode_snapshot(&snapshot[..snapshot.len() - 1]), "truncated");
+    }
+
+    #[test]
+    fn refuses_trailing_bytes() {
+        let mut program = encode(&every_op(), &Settings::default());
+        program.push(0);
+        invalid(decode(&program), "trailing bytes");
+        let mut snapshot = encode_snapshot(&snapshot());
+        snapshot.push(0);
+        invalid(decode_snapshot(&snapshot), "trailing bytes");
+    }
+
+    #[test]
+    fn refuses_jumps_out_of_the_program() {
+        let program = Program::from_ops(vec![Op::Open(2), Op::Close(0)]);
+        invalid(decode(&encode(&program, &Settings::default())), "jump to 2");
+        let program = Program::from_ops(vec![Op::Open(1), Op::Close(usize::MAX)]);
+        invalid(decode(&encode(&program, &Settings::default())), "out of the program");
+    }
+
+    #[test]
+    fn refuses_other_data() {
+        invalid(decode(&encode_snapshot(&snapshot())), "not a program");
+        invalid(decode_snapshot(&encode(&every_op(), &Settings::default())), "not a snapshot");
+        let mut program = encode(&every_op(), &Settings::default());
+        program[4] = VERSION + 1;
+        invalid(decode(&program), "unsupported version");
+    }
+}
@@ -0,0 +1,100 @@
+// Cache of optimized programs, keyed by their source and the options they were optimized with.
+// MIT license.
+
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use brainfuck::{bytecode, transpile::Settings, Program};
+
+/// Extension of cached programs.
+const EXTENSION: &str = "bfc";
+
+/// The directory of the cache: `brainfuck` in `$XDG_CACHE_HOME`, or else in `~/.cache`.
+pub fn dir() -> Option<PathBuf> {
+    let cache_home = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")));
+    cache_home.map(|dir| dir.join("brainfuck"))
+}
+
+/// The key of a program, from everything its optimized instructions depend on. It includes the version of the
+/// interpreter, since its instructions and passes change between versions.
+pub fn key(parts: &[&[u8]]) -> String {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    parts.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn path(key: &str) -> Option<PathBuf> {
+    Some(dir()?.join(key).with_extension(EXTENSION))
+}
+
+/// Returns the cached program, if there is one. Unreadable entries are ignored, they are replaced on the next store.
+pub fn load(key: &str) -> Option<Program> {
+    let path = path(key)?;
+    let bytes = fs::read(&path).ok()?;
+    let result = decode(&bytes);
+    match &result {
+        Some(_) => log::debug!("Program loaded from the cache: {}", path.display()),
+        None => log::warn!("Invalid cache entry: {}", path.display()),
+    }
+    result
+}
+
+/// Decodes an entry: the length of the encoded program, the program itself, then its source map.
+fn decode(bytes: &[u8]) -> Option<Program> {
+    let len = u64::from_le_bytes(bytes.get(..8)?.try_into().expect("8 bytes")) as usize;
+    let encoded = bytes.get(8..8usize.checked_add(len)?)?;
+    let (program, _) = bytecode::decode(encoded).ok()?;
+    bytecode::decode_source_map(program, &bytes[8 + len..]).ok()
+}
+
+/// Stores the optimized program. The entry is written next to its final path then renamed, so that concurrent runs
+/// never read half of it.
+pub fn store(key: &str, program: &Program, settings: &Settings) -> io::Result<()> {
+    let path = path(key).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no cache directory"))?;
+    fs::create_dir_all(path.parent().expect("entries are in the directory"))?;
+    let encoded = bytecode::encode(program, settings);
+    let mut bytes = (encoded.len() as u64).to_le_bytes().to_vec();
+    bytes.extend_from_slice(&encoded);
+    bytes.extend_from_slice(&bytecode::encode_source_map(program));
+    let partial = path.with_extension(format!("{}.{}", EXTENSION, writer()));
+    fs::write(&partial, bytes)?;
+    fs::rename(&partial, &path)?;
+    log::debug!("Program stored in the cache: {}", path.display());
+    Ok(())
+}
+
+/// Tells apart the processes writing entries. WASI has no process identifiers, the time is unique enough there.
+#[cfg(not(target_os = "wasi"))]
+fn writer() -> u32 {
+    std::process::id()
+}
+#[cfg(target_os = "wasi")]
+fn writer() -> u32 {
+    std::time::SystemTime::UNIX_EPOCH.elapsed().map_or(0, |d| d.subsec_nanos())
+}
+
+/// Removes every cached program. Returns how many there were.
+pub fn clear() -> io::Result<usize> {
+    let Some(dir) = dir() else { return Ok(0) };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut removed = 0;
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == EXTENSION) {
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
@@ -0,0 +1,141 @@
+// Recording of runs in the asciinema format.
+// MIT license.
+
+use std::fs::File;
+use std::io::{self, prelude::*, BufWriter};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use brainfuck::{Machine, Program};
+
+use crate::tee::Capture;
+
+/// Size of the terminal of the recordings.
+const WIDTH: usize = 80;
+const HEIGHT: usize = 24;
+
+/// Number of rows of cells in every frame.
+const TAPE_ROWS: usize = 4;
+
+/// Instructions longer than this are shown in the extended syntax, like `+*65`.
+const LONG_OP: usize = 8;
+
+/// Records frames of a run, with the instructions around the current one, the memory around the pointer and the
+/// last lines of the output, to an asciinema v2 file.
+pub struct Cast {
+    out: BufWriter<File>,
+    /// Time between frames.
+    interval: Duration,
+    /// Number of frames written so far.
+    frames: u32,
+    /// Number of steps at the last frame.
+    last: Option<u64>,
+    /// What the program printed.
+    screen: Capture,
+}
+
+impl Cast {
+    /// Creates the file and writes the header. The frames show what was written to the screen.
+    pub fn create(path: &Path, interval: Duration, screen: Capture) -> io::Result<Cast> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        writeln!(
+            out,
+            "{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}, \"title\": \"brainfuck\"}}",
+            WIDTH, HEIGHT, timestamp
+        )?;
+        Ok(Cast { out, interval, frames: 0, last: None, screen })
+    }
+
+    /// Records a frame with the state of the machine, unless it did not run since the last one.
+    pub fn frame(&mut self, machine: &Machine, program: &Program) -> io::Result<()> {
+        if self.last.replace(machine.steps()) == Some(machine.steps()) {
+            return Ok(());
+        }
+        let time = self.interval.as_secs_f64() * self.frames as f64;
+        self.frames += 1;
+        let text = render(machine, program, &self.screen.contents());
+        writeln!(self.out, "[{:.3}, \"o\", {}]", time, json_string(&text))
+    }
+
+    /// Writes the last frames to the file.
+    pub fn finish(&mut self) -> io::Result<()> {
+        log::info!("Recorded {} frames", self.frames);
+        self.out.flush()
+    }
+}
+
+/// Draws a frame, clearing the screen first.
+fn render(machine: &Machine, program: &Program, output: &[u8]) -> String {
+    let i = machine.instruction();
+    let mut lines = vec![];
+    if let Some(op) = program.ops.get(i) {
+        let (line, column) = program.location(program.spans[i].start);
+        lines.push(format!("Step {}, instruction {} ({}) at {}:{}", machine.steps(), i, op, line, column));
+        lines.push(String::new());
+        lines.push(code_line(program, i));
+        lines.push(String::new());
+    }
+    lines.extend(machine.hexdump_rows(TAPE_ROWS).lines().map(str::to_string));
+    lines.push(String::new());
+    lines.push("Output:".to_string());
+    let text = String::from_utf8_lossy(output);
+    let printed: Vec<&str> = text.lines().collect();
+    let room = HEIGHT.saturating_sub(lines.len());
+    for line in &printed[printed.len().saturating_sub(room)..] {
+        // Control characters of the program would break the frame.
+        lines.push(line.chars().map(|c| if c.is_control() { '?' } else { c }).take(WIDTH).collect());
+    }
+    format!("\x1b[H\x1b[2J{}", lines.join("\r\n"))
+}
+
+/// The instructions around the current one, which is highlighted, on a line of the recording.
+fn code_line(program: &Program, current: usize) -> String {
+    let text = |i: usize| {
+        let text = program.ops[i].to_string();
+        match text.chars().next() {
+            Some(c) if text.len() > LONG_OP && text.chars().all(|d| d == c) => format!("{}*{}", c, text.len()),
+            _ => text,
+        }
+    };
+    // Start far enough back to have the current instruction around the middle.
+    let mut start = current;
+    let mut width = 0;
+    while start > 0 && width + text(start - 1).len() <= WIDTH / 2 {
+        start -= 1;
+        width += text(start).len();
+    }
+    let mut line = String::new();
+    let mut width = 0;
+    for i in start..program.len() {
+        let text = text(i);
+        width += text.len();
+        if width > WIDTH {
+            break;
+        }
+        if i == current {
+            line.push_str(&format!("\x1b[7m{}\x1b[0m", text));
+        } else {
+            line.push_str(&text);
+        }
+    }
+    line
+}
+
+/// Quotes the text as a JSON string.
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,92 @@
+// Control-flow graphs of programs, in the Graphviz DOT language.
+// MIT license.
+
+use std::fmt::Write;
+
+use crate::program::{Op, Program};
+
+/// Instructions shown in a block, at most. The others are counted.
+const BLOCK_OPS: usize = 12;
+
+/// Writes the graph of a program.
+struct Graph<'a> {
+    program: &'a Program,
+    out: String,
+    nodes: usize,
+}
+
+impl Graph<'_> {
+    fn node(&mut self, label: &str, shape: &str) -> usize {
+        self.nodes += 1;
+        writeln!(self.out, "    n{} [label=\"{}\", shape={}];", self.nodes, escape(label), shape).unwrap();
+        self.nodes
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: Option<&str>, back: bool) {
+        let mut attributes: Vec<String> = label.map(|l| format!("label=\"{}\"", l)).into_iter().collect();
+        if back {
+            attributes.push("style=dashed".to_string());
+        }
+        let attributes = if attributes.is_empty() { String::new() } else { format!(" [{}]", attributes.join(", ")) };
+        writeln!(self.out, "    n{} -> n{}{};", from, to, attributes).unwrap();
+    }
+
+    /// Adds the basic block of the instructions, if there are any, after the node.
+    fn block(&mut self, start: usize, end: usize, from: (usize, Option<&'static str>)) -> (usize, Option<&'static str>) {
+        if start == end {
+            return from;
+        }
+        let ops = &self.program.ops[start..end];
+        let mut summary: Vec<String> = ops.iter().take(BLOCK_OPS).map(Op::to_string).collect();
+        if ops.len() > BLOCK_OPS {
+            summary.push(format!("... {} more", ops.len() - BLOCK_OPS));
+        }
+        let range = if end - start == 1 { format!("{}", start) } else { format!("{}-{}", start, end - 1) };
+        let label = format!("{}\n{}", range, summary.join(" "));
+        let node = self.node(&label, "box");
+        self.edge(from.0, node, from.1, false);
+        (node, None)
+    }
+
+    /// Adds the instructions from `start` to `end`, which contain whole loops, after the node and the label of the
+    /// edge leaving it. Returns the last node and the label of the edge leaving it.
+    fn code(&mut self, start: usize, end: usize, mut from: (usize, Option<&'static str>)) -> (usize, Option<&'static str>) {
+        let mut block = start;
+        let mut i = start;
+        while i < end {
+            let Op::Open(close) = self.program.ops[i] else {
+                i += 1;
+                continue;
+            };
+            from = self.block(block, i, from);
+            let (line, column) = self.program.location(self.program.spans[i].start);
+            let header = self.node(&format!("{}\nloop at {}:{}\ncell != 0?", i, line, column), "diamond");
+            self.edge(from.0, header, from.1, false);
+            let (last, label) = self.code(i + 1, close, (header, Some("yes")));
+            // The back-edge of the loop: the closing bracket tests the same cell as the opening one.
+            self.edge(last, header, label, true);
+            from = (header, Some("no"));
+            i = close + 1;
+            block = i;
+        }
+        self.block(block, end, from)
+    }
+}
+
+/// The control-flow graph of the program, in DOT. Basic blocks show their instructions, like `+++ > mul(1, 2)`, and
+/// every loop is a test of the current cell, with a dashed back-edge from the end of its body.
+pub fn dot(program: &Program) -> String {
+    let mut graph = Graph { program, out: String::new(), nodes: 0 };
+    graph.out.push_str("digraph program {\n    node [fontname=\"monospace\"];\n");
+    let start = graph.node("start", "oval");
+    let last = graph.code(0, program.len(), (start, None));
+    let end = graph.node("end", "oval");
+    graph.edge(last.0, end, last.1, false);
+    graph.out.push_str("}\n");
+    graph.out
+}
+
+/// Escapes a label, keeping line breaks.
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
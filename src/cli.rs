@@ -0,0 +1,915 @@
+// Command line arguments.
+// MIT license.
+
+use std::path::PathBuf;
+
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+use brainfuck::{CellFormat, CellWidth, DebugFormat, Dialect, Eof, OptLevel};
+use brainfuck::convert::Syntax;
+use brainfuck::equiv::Inputs;
+use brainfuck::machine::DEFAULT_TIME_SLICE;
+use brainfuck::transpile::Language;
+
+use crate::logger::ColorChoice;
+use crate::sanitize::SanitizeMode;
+
+#[derive(Parser)]
+#[command(name = "brainfuck")]
+#[command(author = "Toni Sagristà Sellés <me@tonisagrista.com>")]
+#[command(about = "Brainfuck interpreter written in Rust. Pass it a file to interpret, or the code with -e. If there are no arguments, it reads from the standard input.", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct CLIArgs {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Arguments of the default command, `run`.
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run a program. This is the default command.
+    Run(RunArgs),
+    /// Check that a program is valid without running it.
+    Check(SourceArgs),
+    /// Print a program with its loops indented and without comments.
+    Fmt(SourceArgs),
+    /// Warn about likely mistakes in a program, like loops that never terminate.
+    Lint(SourceArgs),
+    /// Compile a program to Rust source code.
+    Compile(CompileArgs),
+    /// Create an executable that runs a program, from a copy of this one.
+    Bundle(BundleArgs),
+    /// Assemble a program of the BF macro language into Brainfuck.
+    Asm(AsmArgs),
+    /// Convert a program between Brainfuck and the languages that only spell its instructions differently, like Ook!.
+    Convert(ConvertArgs),
+    /// Write a random program, with balanced brackets, to fuzz interpreters or to see what it does.
+    GenRandom(GenRandomArgs),
+    /// Write a short program printing a text.
+    GenText(GenTextArgs),
+    /// Print near-optimal code setting a cell to a value, on 8-bit cells.
+    Const(ConstArgs),
+    /// Run mutants of a program, and report those on which the interpreter panics instead of failing with an error.
+    Fuzz(FuzzArgs),
+    /// Look for shorter code doing the same as every short piece of a program, for code golf.
+    Golf(GolfArgs),
+    /// Print a program as pseudocode, with the common idioms recognized and annotated.
+    Decompile(DecompileArgs),
+    /// Print an annotated listing of a program, with what every piece of it does and the common idioms named, like
+    /// clearing, moving or copying a cell.
+    Explain(SourceArgs),
+    /// Write the control-flow graph of a program in the Graphviz DOT language, with its basic blocks and loops.
+    Cfg(CfgArgs),
+    /// Check that two programs behave the same on a set of inputs.
+    Equiv(EquivArgs),
+    /// Run programs at the same time, each one reading what the previous one prints, as it prints it.
+    Pipe(PipeArgs),
+    /// Look for an input that reaches an instruction or prints a byte, running the program with symbolic input.
+    /// Experimental.
+    Symexec(SymexecArgs),
+    /// Run a program in debug mode, pausing after every instruction.
+    Debug(RunArgs),
+    /// Interpret the lines typed in the standard input on a persistent machine.
+    Repl(ReplArgs),
+    /// Walk through lessons on the basics, checking the programs typed in on the machine they leave.
+    Tutorial(TutorialArgs),
+    /// Print statistics about a program.
+    Stats(StatsArgs),
+    /// Serve a web playground to write, run and step through programs in the browser, or an API to run them.
+    #[cfg(feature = "server")]
+    Serve(ServeArgs),
+    /// Run as a Jupyter kernel, interpreting every cell of a notebook on a persistent machine.
+    #[cfg(feature = "server")]
+    JupyterKernel(KernelArgs),
+    /// Run a language server for editors on the standard input and output, which reports unmatched brackets,
+    /// highlights matching ones, shows the depth of loops on hover and folds them.
+    #[cfg(feature = "server")]
+    Lsp,
+    /// Manage the cache of optimized programs, in ~/.cache/brainfuck.
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// List, show or run the bundled example programs.
+    Examples {
+        #[command(subcommand)]
+        command: ExamplesCommand,
+    },
+    /// Print the completion script for the given shell.
+    Completions {
+        /// The shell to generate completions for.
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Remove every cached program.
+    Clear,
+}
+
+#[derive(Subcommand)]
+pub enum ExamplesCommand {
+    /// List the examples.
+    List,
+    /// Print the source code of an example.
+    Show {
+        /// Name of the example.
+        name: String,
+    },
+    /// Run an example.
+    Run {
+        /// Name of the example.
+        name: String,
+
+        #[command(flatten)]
+        machine: Box<MachineArgs>,
+
+        #[command(flatten)]
+        opt: OptArgs,
+    },
+}
+
+#[derive(Args)]
+pub struct BundleArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub machine: MachineArgs,
+
+    #[command(flatten)]
+    pub opt: OptArgs,
+
+    /// Executable to create.
+    #[arg(short, long)]
+    pub output: PathBuf,
+}
+
+#[cfg(feature = "server")]
+#[derive(Args)]
+#[command(group(clap::ArgGroup::new("mode").required(true).multiple(true)))]
+pub struct ServeArgs {
+    /// Serve the playground page, at the root.
+    #[arg(long, group = "mode")]
+    pub playground: bool,
+
+    /// Serve the API, which runs the program of a POST to /run, like {"program": ",.", "input": "a", "limits":
+    /// {"max_steps": 1000}}, and answers with its output, number of steps and status.
+    #[arg(long, group = "mode")]
+    pub api: bool,
+
+    /// Port to listen on.
+    #[arg(long, default_value_t = 8080)]
+    pub port: u16,
+
+    /// Address to listen on. The default only accepts connections from this computer.
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: String,
+
+    #[command(flatten)]
+    pub machine: MachineArgs,
+
+    /// Maximum number of instructions of every run.
+    #[arg(long, default_value_t = 10_000_000)]
+    pub max_steps: u64,
+
+    /// Maximum time of every run, in milliseconds. Longer runs are stopped.
+    #[arg(long, value_name = "MS", default_value_t = 5000)]
+    pub timeout: u64,
+
+    /// Number of requests handled at the same time [default: the number of CPUs].
+    #[arg(long)]
+    pub workers: Option<usize>,
+}
+
+#[cfg(feature = "server")]
+#[derive(Args)]
+pub struct KernelArgs {
+    /// Connection file given by Jupyter, with the ports to listen on and the key to sign the messages with.
+    #[arg(short = 'f', long, value_name = "FILE", required_unless_present = "install")]
+    pub connection_file: Option<PathBuf>,
+
+    /// Install the kernel for this user, so that Jupyter lists it, instead of running it.
+    #[arg(long, conflicts_with = "connection_file")]
+    pub install: bool,
+
+    #[command(flatten)]
+    pub machine: MachineArgs,
+
+    #[command(flatten)]
+    pub opt: OptArgs,
+}
+
+#[derive(Args)]
+pub struct StatsArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    /// Format of the statistics.
+    #[arg(long, value_enum, default_value_t = StatsFormat::Text, conflicts_with = "loops")]
+    pub format: StatsFormat,
+
+    /// Print the loop tree instead, with the location, nesting depth and number of instructions of every loop.
+    #[arg(long)]
+    pub loops: bool,
+
+    /// Run the program first, with its input on the standard input, and add to the loop tree how many times every
+    /// loop was entered, how many iterations it ran and how many of the instructions run were in it.
+    #[arg(long, requires = "loops")]
+    pub profile: bool,
+
+    #[command(flatten)]
+    pub machine: MachineArgs,
+}
+
+#[derive(Args)]
+pub struct ReplArgs {
+    /// Do not show the panel with the cells around the pointer after every line.
+    #[arg(long)]
+    pub no_panel: bool,
+
+    /// Number of cells the panel shows.
+    #[arg(long, value_name = "CELLS", default_value_t = 9, value_parser = clap::value_parser!(u64).range(1..=64))]
+    pub panel_cells: u64,
+
+    /// Write every line entered to this file as it runs, with what it printed and the state after it, like
+    /// :save-session does.
+    #[arg(long, value_name = "FILE")]
+    pub transcript: Option<PathBuf>,
+
+    /// Run the lines of a session saved with :save-session or --transcript first, and warn about those that print
+    /// something else than they did.
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub machine: MachineArgs,
+}
+
+#[derive(Args)]
+pub struct AsmArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    /// Output file. If not given, the code is written to the standard output.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// Language of the program: bf, ook, blub or pikalang.
+    #[arg(long, default_value_t = Syntax::Brainfuck)]
+    pub from: Syntax,
+
+    /// Language to convert the program to: bf, ook, blub or pikalang.
+    #[arg(long, default_value_t = Syntax::Brainfuck)]
+    pub to: Syntax,
+
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    /// Output file. If not given, the code is written to the standard output.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct GenRandomArgs {
+    /// Number of instructions of the program.
+    #[arg(long, default_value_t = 100)]
+    pub size: usize,
+
+    /// Seed of the random numbers. The same seed gives the same program. If not given, one is picked, and shown with
+    /// --verbose.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Only write programs that end whatever their input, with loops that count down a cell, and that never move the
+    /// pointer left of the first cell.
+    #[arg(long)]
+    pub terminating: bool,
+
+    /// Output file. If not given, the code is written to the standard output.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct GenTextArgs {
+    /// Text to print. If not given, it is read from the standard input.
+    pub text: Option<String>,
+
+    /// Output file. If not given, the code is written to the standard output.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ConstArgs {
+    /// Value to set the cell to.
+    pub value: u8,
+}
+
+#[derive(Args)]
+pub struct FuzzArgs {
+    /// Program file to mutate.
+    pub program: PathBuf,
+
+    /// Number of mutants to run.
+    #[arg(long, default_value_t = 1000)]
+    pub iterations: usize,
+
+    /// Seed of the random mutations and inputs. If not given, one is picked, and shown with --verbose.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Maximum number of instructions every mutant executes.
+    #[arg(long, default_value_t = 100_000)]
+    pub max_steps: u64,
+}
+
+#[derive(Args)]
+pub struct GolfArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    /// Longest pieces to look for shorter code for, in instructions. The search takes much longer with every one
+    /// more.
+    #[arg(long, value_name = "INSTRUCTIONS", default_value_t = 8, value_parser = clap::value_parser!(u64).range(2..=12))]
+    pub window: u64,
+}
+
+#[derive(Args)]
+pub struct TutorialArgs {
+    /// Lesson to start from: 1 moves the pointer, 2 adds, 3 loops, 4 copies and 5 prints.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..=5))]
+    pub lesson: u64,
+}
+
+#[derive(Args)]
+pub struct DecompileArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub opt: OptArgs,
+}
+
+#[derive(Args)]
+pub struct CfgArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub opt: OptArgs,
+
+    /// Output file. If not given, the graph is written to the standard output.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct PipeArgs {
+    /// Program files, in order: the first one reads the standard input, and the last one prints to the standard
+    /// output.
+    #[arg(required = true)]
+    pub programs: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub machine: MachineArgs,
+
+    #[command(flatten)]
+    pub opt: OptArgs,
+
+    /// Stop a program once it runs this many instructions. The limits apply to every program on its own.
+    #[arg(long, value_name = "N")]
+    pub max_steps: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct EquivArgs {
+    /// First program file.
+    pub a: PathBuf,
+
+    /// Second program file.
+    pub b: PathBuf,
+
+    #[command(flatten)]
+    pub machine: MachineArgs,
+
+    #[command(flatten)]
+    pub opt: OptArgs,
+
+    /// Inputs to run the programs on: exhaustive:<LENGTH> for every input of up to that many bytes, or
+    /// random:<COUNT> for that many random inputs.
+    #[arg(long, default_value_t = Inputs::Exhaustive(1))]
+    pub inputs: Inputs,
+
+    /// Maximum number of instructions every run executes. Inputs on which a program runs longer are skipped.
+    #[arg(long, default_value_t = 1_000_000)]
+    pub max_steps: u64,
+
+    /// Only compare the outputs, not the final memory tapes.
+    #[arg(long)]
+    pub output_only: bool,
+}
+
+#[derive(Args)]
+pub struct SymexecArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub machine: MachineArgs,
+
+    #[command(flatten)]
+    pub opt: OptArgs,
+
+    /// Look for an input reaching the instruction at this line and column of the source code.
+    #[arg(long, value_name = "LINE:COL", value_parser = parse_location, required_unless_present = "output", conflicts_with = "output")]
+    pub reach: Option<(usize, usize)>,
+
+    /// Look for an input printing this byte, given as a number or a character.
+    #[arg(long, value_name = "BYTE", value_parser = parse_byte)]
+    pub output: Option<u8>,
+
+    /// Maximum number of branches on input-dependent cells per path.
+    #[arg(long, default_value_t = 16)]
+    pub max_depth: usize,
+
+    /// Maximum number of instructions per path.
+    #[arg(long, default_value_t = 100_000)]
+    pub max_steps: u64,
+}
+
+/// Parses a source location, `LINE:COL`.
+fn parse_location(s: &str) -> Result<(usize, usize), String> {
+    let invalid = || format!("invalid location '{}', expected LINE:COL", s);
+    let (line, column) = s.split_once(':').ok_or_else(invalid)?;
+    Ok((line.parse().map_err(|_| invalid())?, column.parse().map_err(|_| invalid())?))
+}
+
+/// Parses a range of cells, `START..END`, where both ends are optional.
+fn parse_range(s: &str) -> Result<(usize, Option<usize>), String> {
+    let invalid = || format!("invalid range '{}', expected START..END", s);
+    let (start, end) = s.split_once("..").ok_or_else(invalid)?;
+    let start = if start.is_empty() { 0 } else { start.parse().map_err(|_| invalid())? };
+    let end = if end.is_empty() { None } else { Some(end.parse().map_err(|_| invalid())?) };
+    match end {
+        Some(end) if end < start => Err(invalid()),
+        _ => Ok((start, end)),
+    }
+}
+
+/// Connections of --io.
+#[derive(Clone, Debug)]
+pub enum IoMode {
+    /// Wait for a client on the address.
+    TcpListen(String),
+    /// Connect to the server at the address.
+    TcpConnect(String),
+}
+
+/// Parses a connection, `tcp-listen:[HOST:]PORT` or `tcp-connect:HOST:PORT`.
+fn parse_io(s: &str) -> Result<IoMode, String> {
+    let invalid = || format!("invalid mode '{}', expected tcp-listen:PORT or tcp-connect:HOST:PORT", s);
+    let (mode, address) = s.split_once(':').ok_or_else(invalid)?;
+    let port = |address: &str| address.rsplit(':').next().is_some_and(|p| p.parse::<u16>().is_ok());
+    match mode {
+        "tcp-listen" if address.parse::<u16>().is_ok() => Ok(IoMode::TcpListen(format!("127.0.0.1:{}", address))),
+        "tcp-listen" if address.contains(':') && port(address) => Ok(IoMode::TcpListen(address.to_string())),
+        "tcp-connect" if address.contains(':') && port(address) => Ok(IoMode::TcpConnect(address.to_string())),
+        _ => Err(invalid()),
+    }
+}
+
+/// Where a channel of --channel reads or writes.
+#[derive(Clone, Debug)]
+pub enum ChannelStream {
+    Input(PathBuf),
+    Output(PathBuf),
+}
+
+/// Parses a channel, `N=FILE` for an output or `N=<FILE` for an input.
+fn parse_channel(s: &str) -> Result<(usize, ChannelStream), String> {
+    let (n, file) = s.split_once('=').ok_or_else(|| format!("invalid channel '{}', expected N=FILE or N=<FILE", s))?;
+    let n: usize = n.parse().map_err(|_| format!("invalid channel number '{}'", n))?;
+    if n == 0 {
+        return Err("channel 0 is the standard input and output".to_string());
+    }
+    match file.strip_prefix('<') {
+        Some(path) if !path.is_empty() => Ok((n, ChannelStream::Input(PathBuf::from(path)))),
+        None if !file.is_empty() => Ok((n, ChannelStream::Output(PathBuf::from(file)))),
+        _ => Err(format!("invalid channel '{}', expected N=FILE or N=<FILE", s)),
+    }
+}
+
+/// Parses a byte, given as a number or as a single ASCII character.
+fn parse_byte(s: &str) -> Result<u8, String> {
+    match s.parse() {
+        Ok(b) => Ok(b),
+        Err(_) if s.len() == 1 => Ok(s.as_bytes()[0]),
+        Err(_) => Err(format!("invalid byte '{}', expected a number from 0 to 255 or a character", s)),
+    }
+}
+
+/// Options shared by all commands.
+#[derive(Args)]
+pub struct GlobalArgs {
+    /// Increase diagnostics verbosity (-v for info, -vv for debug, -vvv for trace).
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Only report errors.
+    #[arg(short, long, conflicts_with = "verbose", global = true)]
+    pub quiet: bool,
+
+    /// Configuration file. By default, ./brainfuck.toml or ~/.config/brainfuck/config.toml are used if they exist.
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// When to use colors in diagnostics [default: auto].
+    #[arg(long, value_enum, env = "BF_COLOR", global = true)]
+    pub color: Option<ColorChoice>,
+
+    /// How to report errors: as text, or as a JSON object with a stable code, the message, the source span and a
+    /// hint, for editors and graders.
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = ErrorFormat::Text, global = true)]
+    pub error_format: ErrorFormat,
+}
+
+/// Formats of the errors.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// A line on the standard error.
+    Text,
+    /// A JSON object on a line of the standard error.
+    Json,
+}
+
+/// The program to work on.
+#[derive(Args)]
+pub struct SourceArgs {
+    /// Program file, or '-' to read the whole program from the standard input. If neither a file nor --eval are
+    /// given, the program is read from the standard input.
+    pub input: Option<PathBuf>,
+
+    /// Program code, given directly instead of in a file.
+    #[arg(short, long, value_name = "CODE", conflicts_with = "input")]
+    pub eval: Option<String>,
+
+    /// Accept the instructions of this dialect on top of those of Brainfuck: standard for none, channels for '~',
+    /// which makes '.' and ',' use the channel numbered by the current cell, given with --channel, pair for '!' and
+    /// '?', which send a byte to the peer of --pair and wait for one, brainfork for 'Y', which forks the thread, clock
+    /// for '@', which stores the time, fs for '(', '{', ';', ':' and ')', which open, read, write and close files
+    /// with --allow-fs, or smbf for self-modifying code run from the tape. boolfuck and smallfuck replace Brainfuck
+    /// with machines of bits: in boolfuck '+' flips the cell, ',' reads a bit and ';' prints one, and in smallfuck '*'
+    /// flips the cell and there is no input or output.
+    #[arg(long, value_name = "DIALECT", default_value_t, conflicts_with = "fix_brackets")]
+    pub dialect: Dialect,
+
+    /// Accept the extended syntax, where a command followed by '*' and a count is repeated that many times, like
+    /// '+*65' for 65 '+'.
+    #[arg(long)]
+    pub extended: bool,
+
+    /// Do not fail on unmatched brackets, like permissive interpreters: skip every ']' without its '[' and add the
+    /// missing ']' at the end, with a warning.
+    #[arg(long)]
+    pub fix_brackets: bool,
+}
+impl SourceArgs {
+    /// Whether the program is given in the arguments, as opposed to the standard input.
+    pub fn is_given(&self) -> bool {
+        self.input.is_some() || self.eval.is_some()
+    }
+}
+
+/// Configuration of the machine.
+#[derive(Args)]
+pub struct MachineArgs {
+    /// Number of cells in the memory tape [default: 40000, or 32 in the smallfuck dialect].
+    #[arg(long, env = "BF_MEM_SIZE", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub mem_size: Option<usize>,
+
+    /// Keep the memory tape in this file, mapped to memory, so that the cells survive the run and large tapes only
+    /// take memory for the pages in use. The file is created if missing, and resized to --mem-size if given [default:
+    /// the size of the file]. Only on Unix.
+    #[arg(long, value_name = "FILE")]
+    pub tape_file: Option<PathBuf>,
+
+    /// Load the bytes of this file in the cells before the program starts, one byte per cell, for the program to
+    /// work on without reading it through ','.
+    #[arg(long, value_name = "FILE")]
+    pub init_mem: Option<PathBuf>,
+
+    /// Cell the bytes of --init-mem are loaded from.
+    #[arg(long, value_name = "CELL", default_value_t = 0, requires = "init_mem")]
+    pub init_offset: usize,
+
+    /// Give the machine a channel, for the '~' instruction of the channels dialect: N=FILE writes what '.' prints on
+    /// channel N to the file, and N=<FILE reads ',' from it. Channel 0 is the standard input and output. Can be given
+    /// several times, and the files can be named pipes.
+    #[arg(long = "channel", value_name = "N=FILE", value_parser = parse_channel)]
+    pub channels: Vec<(usize, ChannelStream)>,
+
+    /// Width of the memory cells, in bits (8, 16 or 32) [default: 8].
+    #[arg(long, env = "BF_CELL_SIZE")]
+    pub cell_width: Option<CellWidth>,
+
+    /// What ',' stores at the end of the input (zero, unchanged or max) [default: zero].
+    #[arg(long, env = "BF_EOF")]
+    pub eof: Option<Eof>,
+
+    /// Maximum number of loops running inside each other. Programs nesting deeper fail when entering the loop past
+    /// the limit.
+    #[arg(long, env = "BF_MAX_LOOP_DEPTH")]
+    pub max_loop_depth: Option<usize>,
+
+    /// Maximum number of bytes a program prints. Programs printing more fail when printing the byte past the limit.
+    #[arg(long, value_name = "BYTES", env = "BF_MAX_OUTPUT")]
+    pub max_output: Option<u64>,
+
+    /// Compile the loops that ran this many iterations on the fly, optimized with every pass, instead of
+    /// interpreting them. Long programs run faster, while short ones start as fast as ever.
+    #[arg(long, value_name = "ITERATIONS", env = "BF_TIER_THRESHOLD")]
+    pub tier_threshold: Option<u64>,
+
+    /// Number of instructions every thread of a Brainfork program runs before the next one takes its turn. The
+    /// threads always take turns in the same order, so that the program prints the same thing on every run.
+    #[arg(long, value_name = "INSTRUCTIONS", default_value_t = DEFAULT_TIME_SLICE, value_parser = clap::value_parser!(u64).range(1..))]
+    pub time_slice: u64,
+
+    /// Make '@' in the clock dialect count the times it ran before instead of reading the time, so that the program
+    /// does the same thing on every run.
+    #[arg(long)]
+    pub fixed_time: bool,
+
+    /// Let the instructions of the fs dialect open files, create them and write them, with the permissions of this
+    /// user. Only for trusted programs.
+    #[arg(long)]
+    pub allow_fs: bool,
+
+    /// How cell values are shown in the states, reports and dumps: dec, hex, char, or all of them [default: dec].
+    #[arg(long, value_name = "FORMAT", env = "BF_CELL_FORMAT")]
+    pub cell_format: Option<CellFormat>,
+
+    /// What the '#' instruction prints: cell for the current cell only, window:<CELLS> for a hexdump of that many
+    /// cells around the pointer, or json for the whole state on one line [default: window:32].
+    #[arg(long, value_name = "FORMAT", env = "BF_DEBUG_FORMAT")]
+    pub debug_format: Option<DebugFormat>,
+
+    /// Write what the '#' instruction prints to this file instead of the standard error.
+    #[arg(long, value_name = "FILE")]
+    pub debug_output: Option<PathBuf>,
+}
+
+/// Optimization options.
+#[derive(Args)]
+pub struct OptArgs {
+    /// Optimization level: 0 for none, 1 to fuse instructions, 2 to also replace clear, copy and scan loops, 3 to
+    /// also reorder instructions between loops [default: 2, or 0 in debug mode].
+    #[arg(short = 'O', value_name = "LEVEL", env = "BF_OPT")]
+    pub opt_level: Option<OptLevel>,
+
+    /// Run exactly these optimization passes, in order, instead of the ones of the level. Available passes are
+    /// fuse, reorder, clear-loop, copy-loop and scan.
+    #[arg(long, value_name = "PASSES", value_delimiter = ',', conflicts_with = "opt_level")]
+    pub passes: Option<Vec<String>>,
+
+    /// Print what every optimization pass did to the standard error.
+    #[arg(long)]
+    pub opt_stats: bool,
+
+    /// Print a report of what every optimization pass did to the standard error: the instructions it eliminated and
+    /// those it fused, and, with --profile-use, the instructions run before and after it, estimated from the profile.
+    #[arg(long, conflicts_with = "opt_stats")]
+    pub opt_report: bool,
+
+    /// Optimize the loops where a run recorded with --profile-out spent its time with every pass, including those
+    /// of level 3.
+    #[arg(long, value_name = "FILE")]
+    pub profile_use: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub machine: MachineArgs,
+
+    #[command(flatten)]
+    pub opt: OptArgs,
+
+    /// Debug mode. Pause after every instruction and print the internal state.
+    #[arg(short, long)]
+    pub debug: bool,
+
+    /// Run the debugger commands in this file first, one per line, as if they were typed. Implies --debug.
+    #[arg(long, value_name = "FILE")]
+    pub debug_script: Option<PathBuf>,
+
+    /// When reading the program from the standard input, interpret every line as a separate program on the same
+    /// machine, instead of reading the whole input as one program.
+    #[arg(long)]
+    pub lines: bool,
+
+    /// Parse the program and report its statistics, without running it.
+    #[arg(long, conflicts_with = "lines")]
+    pub dry_run: bool,
+
+    /// Print the optimized instructions, with their jump targets and source locations, without running them.
+    #[arg(long, conflicts_with_all = ["lines", "dry_run"])]
+    pub emit_ir: bool,
+
+    /// Print the syntax tree of the program, its loops and runs of instructions with their source spans, in this
+    /// format, without running it.
+    #[arg(long, value_name = "FORMAT", value_enum, conflicts_with_all = ["lines", "dry_run", "emit_ir"])]
+    pub emit_ast: Option<AstFormat>,
+
+    /// Print the time, the number of instructions run so far, how many run per second and the current location to
+    /// the standard error, every second.
+    #[arg(long)]
+    pub progress: bool,
+
+    /// Also write the output of the program to this file.
+    #[arg(long, value_name = "FILE")]
+    pub tee: Option<PathBuf>,
+
+    /// Pause for this many milliseconds after every instruction, to follow the program in demonstrations.
+    #[arg(long, value_name = "MS")]
+    pub delay: Option<u64>,
+
+    /// Print what every instruction does, in plain English, to the standard error as it runs, pausing for --delay
+    /// milliseconds after every one, or 500. The program is not optimized, unless -O says otherwise, so that the
+    /// instructions are those of the source code.
+    #[arg(long, conflicts_with_all = ["debug", "debug_script", "lines", "watch", "render_cast", "profile_out", "checkpoint_every", "delay_every"])]
+    pub teach: bool,
+
+    /// Pause only once every this many instructions, with --delay.
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "delay")]
+    pub delay_every: u64,
+
+    /// Record the run in this file, as an asciinema cast: every frame shows the instructions around the current one,
+    /// the memory around the pointer and the output so far.
+    #[arg(long, value_name = "FILE", conflicts_with = "lines")]
+    pub render_cast: Option<PathBuf>,
+
+    /// Record a frame once every this many instructions, with --render-cast.
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "render_cast")]
+    pub cast_every: u64,
+
+    /// Time between the frames of the recording, in milliseconds, with --render-cast.
+    #[arg(long, value_name = "MS", default_value_t = 100, requires = "render_cast")]
+    pub cast_interval: u64,
+
+    /// Record how many times every loop was entered, how many iterations it ran and how many instructions ran in
+    /// it, in this JSON file, for --profile-use.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["lines", "render_cast"])]
+    pub profile_out: Option<PathBuf>,
+
+    /// Treat everything after the first '!' outside double quotes as the input of the program, instead of the
+    /// standard input, like in the files of many archives.
+    #[arg(long, conflicts_with = "lines")]
+    pub bang_input: bool,
+
+    /// Stop the program once it runs this many instructions.
+    #[arg(long, value_name = "N")]
+    pub max_steps: Option<u64>,
+
+    /// Stop the program once it runs for this many milliseconds.
+    #[arg(long, value_name = "MS")]
+    pub timeout: Option<u64>,
+
+    /// Run an untrusted program with conservative limits: stop it after 10 million instructions, 5 seconds or 1 MiB
+    /// of output, unless --max-steps, --timeout or --max-output say otherwise, and ignore the configuration file.
+    #[arg(long, conflicts_with_all = ["debug", "debug_script"])]
+    pub sandbox: bool,
+
+    /// Escape the bytes of the output that could control the terminal, like the escape sequences of colors and window
+    /// titles, or remove them with --sanitize-output=strip, to run untrusted programs. Files of --tee get the output
+    /// as is.
+    #[arg(long, value_name = "MODE", value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "escape")]
+    pub sanitize_output: Option<SanitizeMode>,
+
+    /// Read the input of the program from this file instead of the standard input.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["lines", "bang_input"])]
+    pub input_file: Option<PathBuf>,
+
+    /// Read ',' from and print '.' to a network connection instead of the standard streams: tcp-listen:PORT waits
+    /// for one on the port, of this computer only, or tcp-listen:HOST:PORT on the address, and tcp-connect:HOST:PORT
+    /// connects to a server. Not with --sandbox.
+    #[arg(long, value_name = "MODE", value_parser = parse_io, conflicts_with_all = ["lines", "bang_input", "input_file", "watch", "sanitize_output"])]
+    pub io: Option<IoMode>,
+
+    /// Run these two programs at the same time instead, in the pair dialect, where '!' sends the current cell to the
+    /// other program and '?' waits for a byte from it. The first one reads the standard input, and both print to the
+    /// standard output. Experimental.
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["A", "B"],
+        conflicts_with_all = ["input", "eval", "lines", "bang_input", "input_file", "io", "watch", "debug", "debug_script", "timeout", "sandbox", "dry_run", "emit_ir", "emit_ast", "render_cast", "profile_out", "checkpoint_every", "resume", "dump_mem_file", "tee"]
+    )]
+    pub pair: Option<Vec<PathBuf>>,
+
+    /// Save the state of the run to --checkpoint-file once every this many instructions, and when it is interrupted
+    /// with Ctrl-C, to continue it with --resume after a crash.
+    #[arg(
+        long,
+        value_name = "STEPS",
+        requires = "checkpoint_file",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with_all = ["debug", "debug_script", "lines", "render_cast", "profile_out", "channels"]
+    )]
+    pub checkpoint_every: Option<u64>,
+
+    /// File the checkpoints are saved to, each one replacing the previous one.
+    #[arg(long, value_name = "FILE", requires = "checkpoint_every")]
+    pub checkpoint_file: Option<PathBuf>,
+
+    /// Continue the run saved in this checkpoint instead of starting the program. The program, the machine options
+    /// and the input must be the same as in that run: the input it had read is skipped.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["debug", "debug_script", "lines", "bang_input", "channels"])]
+    pub resume: Option<PathBuf>,
+
+    /// Write the cells to this file once the program ends, as raw bytes, one per cell, or two or four in little
+    /// endian order for wider cells, for other tools to use them.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["lines", "watch"])]
+    pub dump_mem_file: Option<PathBuf>,
+
+    /// Only write these cells with --dump-mem-file, as START..END, with END excluded, START.. or ..END.
+    #[arg(long, value_name = "START..END", value_parser = parse_range, requires = "dump_mem_file")]
+    pub range: Option<(usize, Option<usize>)>,
+
+    /// Parse and optimize the program even if it is in the cache of optimized programs, and do not store it there.
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Run the program again, on a fresh machine, every time its file or the file of --input-file changes. The
+    /// program is only parsed and optimized again when its contents change.
+    #[arg(
+        long,
+        requires = "input",
+        conflicts_with_all = ["debug", "debug_script", "lines", "dry_run", "emit_ir", "emit_ast", "progress", "tee", "delay", "render_cast", "profile_out", "checkpoint_every", "resume"]
+    )]
+    pub watch: bool,
+}
+
+/// Formats of the statistics.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsFormat {
+    /// One statistic per line, for people.
+    Text,
+    /// A JSON object, for tools.
+    Json,
+}
+
+/// Formats of the syntax tree.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AstFormat {
+    /// A JSON array of nodes, runs like {"type": "run", "command": "+", "count": 3, ...} and loops like {"type":
+    /// "loop", "body": [...], ...}.
+    Json,
+}
+
+#[derive(Args)]
+pub struct CompileArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub machine: MachineArgs,
+
+    #[command(flatten)]
+    pub opt: OptArgs,
+
+    /// Output file. If not given, the code is written to the standard output.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Language to translate the program to (rust, js, python, go or java).
+    #[arg(long, default_value_t = Language::Rust)]
+    pub target: Language,
+
+    /// Write a complete cargo project in the output directory instead of a single file. Only for Rust.
+    #[arg(long, requires = "output")]
+    pub standalone: bool,
+
+    /// Run the program now and generate code that just prints its output. Only for programs that never read
+    /// their input.
+    #[arg(long)]
+    pub precompute: bool,
+}
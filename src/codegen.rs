@@ -0,0 +1,168 @@
+// Helpers writing short Brainfuck code, to assemble programs from Rust, for 8-bit cells.
+// MIT license.
+
+/// Largest step tried for the counters of the loops.
+const MAX_STEP: u8 = 8;
+
+/// Code moving the pointer by the given number of cells, to the right if it is positive.
+pub fn emit_move(offset: isize) -> String {
+    if offset < 0 { "<" } else { ">" }.repeat(offset.unsigned_abs())
+}
+
+/// Code clearing the cell at the pointer.
+pub fn emit_clear() -> &'static str {
+    "[-]"
+}
+
+/// A run of `+`, or of `-` if it is shorter, adding the given amount.
+fn run(delta: u8) -> String {
+    if delta <= 128 { "+".repeat(delta as usize) } else { "-".repeat(256 - delta as usize) }
+}
+
+/// Length of the run adding the given amount.
+fn len(delta: u8) -> usize {
+    (delta as usize).min(256 - delta as usize)
+}
+
+/// The counter of a loop: the value it starts at, and what every iteration adds to it until it is zero.
+#[derive(Debug, Clone, Copy)]
+struct Counter {
+    start: u8,
+    step: u8,
+}
+
+impl Counter {
+    /// Its code and the one of the body, with the counter on the cell right of the one the body adds to.
+    fn code(self, body: &str) -> String {
+        format!("{}[<{}>{}]", run(self.start), body, run(self.step))
+    }
+}
+
+/// How a piece of code adds an amount to a cell, before a run adding what it misses.
+#[derive(Debug, Clone, Copy)]
+enum Plan {
+    /// `>counter[<body>step]<`, adding the body once per iteration.
+    Loop(Counter, u8),
+    /// `>>counter[<counter[<body>step]>step]<<`, an inner loop adding the body once per iteration of both loops.
+    Nested(Counter, Counter, u8),
+}
+
+impl Plan {
+    fn code(self) -> String {
+        match self {
+            Plan::Loop(counter, body) => format!(">{}<", counter.code(&run(body))),
+            Plan::Nested(outer, inner, body) => format!(">>{}<<", outer.code(&inner.code(&run(body)))),
+        }
+    }
+}
+
+/// The cheapest loops adding every amount to a cell, with their length, found once for all the amounts.
+struct Adder {
+    loops: Vec<Option<(usize, Plan)>>,
+}
+
+impl Adder {
+    /// Tries every loop with a counter and a body of runs, and with `nested`, every loop of two of them. The loops
+    /// run a number of times that only matters mod 256, so the cheapest counter for every one is enough.
+    fn new(nested: bool) -> Adder {
+        let mut counters: Vec<Option<(usize, Counter)>> = vec![None; 256];
+        for start in 1..=255u8 {
+            for step in (1..=MAX_STEP).flat_map(|s| [s, s.wrapping_neg()]) {
+                // The counter may never get to zero, and the loop never end.
+                let Some(n) = (1..256usize).find(|&n| start.wrapping_add((n as u8).wrapping_mul(step)) == 0) else {
+                    continue;
+                };
+                let cost = len(start) + len(step);
+                if counters[n].is_none_or(|(c, _)| cost < c) {
+                    counters[n] = Some((cost, Counter { start, step }));
+                }
+            }
+        }
+        let mut loops: Vec<Option<(usize, Plan)>> = vec![None; 256];
+        // The body and the counters of the loops, with what they add.
+        let mut inner: Vec<Option<(usize, Counter, u8)>> = vec![None; 256];
+        for (n, counter) in counters.iter().enumerate() {
+            let Some((cost, counter)) = *counter else { continue };
+            for body in 1..=255u8 {
+                let amount = (n as u8).wrapping_mul(body) as usize;
+                let cost = cost + len(body);
+                if inner[amount].is_none_or(|(c, _, _)| cost < c) {
+                    inner[amount] = Some((cost, counter, body));
+                }
+            }
+        }
+        for (amount, plan) in inner.iter().enumerate() {
+            // `>`, `[<`, `>` and `]<` around the counter and the body.
+            loops[amount] = plan.map(|(cost, counter, body)| (cost + 6, Plan::Loop(counter, body)));
+        }
+        if nested {
+            for (n, outer) in counters.iter().enumerate() {
+                let Some((outer_cost, outer)) = *outer else { continue };
+                for (added, plan) in inner.iter().enumerate() {
+                    let Some((inner_cost, inner, body)) = *plan else { continue };
+                    let amount = (n * added) % 256;
+                    let cost = outer_cost + inner_cost + 12;
+                    if loops[amount].is_none_or(|(c, _)| cost < c) {
+                        loops[amount] = Some((cost, Plan::Nested(outer, inner, body)));
+                    }
+                }
+            }
+        }
+        Adder { loops }
+    }
+
+    /// The shortest code adding the amount: a run, or a loop and the run adding what it misses.
+    fn add(&self, delta: u8) -> String {
+        let best = self
+            .loops
+            .iter()
+            .enumerate()
+            .filter_map(|(amount, plan)| plan.map(|(cost, plan)| (cost + len(delta.wrapping_sub(amount as u8)), amount, plan)))
+            .min_by_key(|&(cost, _, _)| cost)
+            .filter(|&(cost, _, _)| cost < len(delta));
+        match best {
+            Some((_, amount, plan)) => plan.code() + &run(delta.wrapping_sub(amount as u8)),
+            None => run(delta),
+        }
+    }
+}
+
+/// Code adding the given amount to the cell at the pointer, with a run of `+` or `-`, or with a loop adding a
+/// multiple of it from the cell right of the pointer, whichever is shorter. That cell must be zero, and is left at
+/// zero. Cells wrap around, so that adding 255 is subtracting 1, and counters can too: `>++++[<+>++++]<++` adds 65,
+/// since the loop runs 63 times for its counter to wrap around to zero.
+pub fn emit_add(delta: i32) -> String {
+    Adder::new(false).add(delta as u8)
+}
+
+/// Code setting the cell at the pointer to the given value, whatever it was, like [`emit_add`] after a clear.
+pub fn emit_set_cell(value: u8) -> String {
+    format!("{}{}", emit_clear(), emit_add(value as i32))
+}
+
+/// Near-optimal code setting the cell at the pointer, which must be zero, to the given value. The search tries
+/// runs, and loops and pairs of nested loops whose counters step by up to 8 at a time, using that cells wrap around:
+/// `>-[<->---]<` is 171, minus a third of 255. The two cells right of the pointer must be zero, and are left at zero.
+pub fn emit_constant(value: u8) -> String {
+    Adder::new(true).add(value)
+}
+
+/// Code printing the bytes of the string with the cell at the pointer, going from one byte to the next by adding
+/// the difference, or by setting it again if that is shorter. It leaves the cell at the last byte, and needs the one
+/// right of it at zero, like [`emit_add`].
+pub fn emit_print_str(s: &str) -> String {
+    let adder = Adder::new(false);
+    let mut out = String::new();
+    let mut current = None;
+    for byte in s.bytes() {
+        let set = format!("{}{}", emit_clear(), adder.add(byte));
+        let code = match current {
+            Some(current) => Some(adder.add(byte.wrapping_sub(current))).filter(|add| add.len() <= set.len()).unwrap_or(set),
+            None => set,
+        };
+        out.push_str(&code);
+        out.push('.');
+        current = Some(byte);
+    }
+    out
+}
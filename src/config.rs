@@ -0,0 +1,55 @@
+// Configuration file support.
+// MIT license.
+
+use std::{env, fs};
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// Name of the configuration file looked up in the current directory.
+const LOCAL_CONFIG: &str = "brainfuck.toml";
+
+/// Default values loaded from a configuration file. Everything is optional, and command line flags take precedence.
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Number of cells in the memory tape.
+    pub mem_size: Option<usize>,
+    /// Width of the memory cells, in bits.
+    pub cell_width: Option<u32>,
+    /// What ',' stores at the end of the input.
+    pub eof: Option<String>,
+    /// Optimization level, from 0 to 3.
+    pub opt_level: Option<u8>,
+    /// When to use colors in diagnostics.
+    pub color: Option<String>,
+}
+
+impl Config {
+    /// Loads the configuration. If `path` is given, that file must exist. Otherwise, `./brainfuck.toml` is used
+    /// if it exists, then the user configuration file. If there is no file at all, all values are unset.
+    pub fn load(path: Option<&Path>) -> Result<Config, String> {
+        let path = match path {
+            Some(p) => p.to_path_buf(),
+            None => match Config::candidates().into_iter().find(|p| p.is_file()) {
+                Some(p) => p,
+                None => return Ok(Config::default()),
+            },
+        };
+        log::info!("Loading configuration: {}", path.display());
+        let contents = fs::read_to_string(&path).map_err(|e| format!("Can not read configuration file {}: {}", path.display(), e))?;
+        toml::from_str(&contents).map_err(|e| format!("Invalid configuration file {}: {}", path.display(), e))
+    }
+
+    /// Configuration files looked up when none is given, in order of preference.
+    fn candidates() -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::from(LOCAL_CONFIG)];
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+        if let Some(dir) = config_home {
+            paths.push(dir.join("brainfuck").join("config.toml"));
+        }
+        paths
+    }
+}
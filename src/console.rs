@@ -0,0 +1,137 @@
+// Standard input and output of programs, the same byte for byte on every platform.
+// MIT license.
+
+use std::io::{self, Read, Write};
+#[cfg(windows)]
+use std::io::IsTerminal;
+
+/// The standard input of programs. On the Windows console, the Enter key gives `\r\n`, which is read as the `\n`
+/// programs expect, like on other platforms.
+pub fn stdin() -> Box<dyn Read> {
+    #[cfg(windows)]
+    if io::stdin().is_terminal() {
+        return Box::new(windows::Crlf::new(io::stdin().lock()));
+    }
+    Box::new(io::stdin())
+}
+
+/// The standard output of programs. The Windows console only takes valid UTF-8 from the standard library, so
+/// programs printing any bytes write to it directly instead, in the UTF-8 code page.
+pub fn stdout() -> Box<dyn Write> {
+    #[cfg(windows)]
+    if io::stdout().is_terminal() {
+        return Box::new(io::LineWriter::new(windows::Console::new()));
+    }
+    Box::new(io::stdout())
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::ffi::c_void;
+    use std::io::{self, BufRead, Read, Write};
+    use std::mem;
+
+    type Handle = *mut c_void;
+
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+    const CP_UTF8: u32 = 65001;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> Handle;
+        fn GetConsoleOutputCP() -> u32;
+        fn SetConsoleOutputCP(code_page: u32) -> i32;
+        fn WriteFile(file: Handle, buffer: *const u8, len: u32, written: *mut u32, overlapped: *mut c_void) -> i32;
+    }
+
+    /// The console, written to without any translation in the UTF-8 code page, which it is set back from when
+    /// dropped.
+    pub struct Console {
+        handle: Handle,
+        code_page: u32,
+    }
+
+    impl Console {
+        pub fn new() -> Console {
+            // SAFETY: these functions have no preconditions, and failures only leave the code page as it was.
+            unsafe {
+                let code_page = GetConsoleOutputCP();
+                SetConsoleOutputCP(CP_UTF8);
+                Console { handle: GetStdHandle(STD_OUTPUT_HANDLE), code_page }
+            }
+        }
+    }
+
+    impl Write for Console {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let len = buf.len().min(u32::MAX as usize) as u32;
+            let mut written = 0;
+            // SAFETY: the buffer holds `len` bytes, and the write is synchronous.
+            if unsafe { WriteFile(self.handle, buf.as_ptr(), len, &mut written, std::ptr::null_mut()) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(written as usize)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Drop for Console {
+        fn drop(&mut self) {
+            // SAFETY: see `new`.
+            unsafe { SetConsoleOutputCP(self.code_page) };
+        }
+    }
+
+    /// Reads the console with every `\r\n` turned into `\n`.
+    pub struct Crlf<R: BufRead> {
+        inner: R,
+        /// Whether the last byte read was a `\r`, held back until the next one tells whether it ends a line.
+        cr: bool,
+    }
+
+    impl<R: BufRead> Crlf<R> {
+        pub fn new(inner: R) -> Crlf<R> {
+            Crlf { inner, cr: false }
+        }
+    }
+
+    impl<R: BufRead> Read for Crlf<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            loop {
+                let data = self.inner.fill_buf()?;
+                let mut read = 0;
+                if mem::take(&mut self.cr) && data.first() != Some(&b'\n') {
+                    buf[0] = b'\r';
+                    read = 1;
+                }
+                if data.is_empty() {
+                    return Ok(read);
+                }
+                let mut used = 0;
+                while read < buf.len() && used < data.len() {
+                    let b = data[used];
+                    used += 1;
+                    match (b, data.get(used)) {
+                        (b'\r', Some(b'\n')) => (),
+                        (b'\r', None) => self.cr = true,
+                        _ => {
+                            buf[read] = b;
+                            read += 1;
+                        },
+                    }
+                }
+                self.inner.consume(used);
+                // Only a held back `\r` was read, which is not the end of the input.
+                if read > 0 {
+                    return Ok(read);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+// Conversion of programs between Brainfuck and the languages that only spell its instructions differently.
+// MIT license.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::BfError;
+
+/// The eight instructions, in the order of the words of every language.
+const INSTRUCTIONS: [char; 8] = ['>', '<', '+', '-', '.', ',', '[', ']'];
+
+/// The marks after the words of Ook! and Blub, in pairs, for every instruction.
+const MARKS: [(char, char); 8] = [('.', '?'), ('?', '.'), ('.', '.'), ('!', '!'), ('!', '.'), ('.', '!'), ('!', '?'), ('?', '!')];
+
+/// The words of Pikalang, for every instruction.
+const PIKALANG: [&str; 8] = ["pipi", "pichu", "pi", "ka", "pikachu", "pikapi", "pika", "chu"];
+
+/// A language with the instructions of Brainfuck, spelled some other way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    Brainfuck,
+    /// Every instruction is a pair of `Ook.`, `Ook?` or `Ook!`.
+    Ook,
+    /// Like Ook!, with `Blub` instead.
+    Blub,
+    /// Every instruction is a word, like `pikachu` for `.`.
+    Pikalang,
+}
+
+impl Syntax {
+    /// The word of Ook! and Blub.
+    fn word(self) -> Option<&'static str> {
+        match self {
+            Syntax::Ook => Some("Ook"),
+            Syntax::Blub => Some("Blub"),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Syntax {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Syntax, String> {
+        match s {
+            "bf" | "brainfuck" => Ok(Syntax::Brainfuck),
+            "ook" => Ok(Syntax::Ook),
+            "blub" => Ok(Syntax::Blub),
+            "pikalang" => Ok(Syntax::Pikalang),
+            _ => Err(format!("unknown language '{}', expected bf, ook, blub or pikalang", s)),
+        }
+    }
+}
+
+impl fmt::Display for Syntax {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Syntax::Brainfuck => "bf",
+            Syntax::Ook => "ook",
+            Syntax::Blub => "blub",
+            Syntax::Pikalang => "pikalang",
+        })
+    }
+}
+
+/// An instruction read from a program, as its Brainfuck character, with the line it is on.
+struct Token {
+    instruction: char,
+    line: usize,
+}
+
+/// Converts the program from one language to the other. Comments are dropped, and every line of the program with
+/// instructions gives a line of the converted one.
+pub fn convert(source: &str, from: Syntax, to: Syntax) -> Result<String, BfError> {
+    let tokens = tokenize(source, from)?;
+    let mut out = String::new();
+    for line in tokens.chunk_by(|a, b| a.line == b.line) {
+        let words: Vec<String> = line.iter().map(|t| spell(t.instruction, to)).collect();
+        out.push_str(&words.join(if to == Syntax::Brainfuck { "" } else { " " }));
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Reads the instructions of a program. Anything that is not an instruction is a comment, but the words of Ook! and
+/// Blub must make pairs that are instructions.
+fn tokenize(source: &str, syntax: Syntax) -> Result<Vec<Token>, BfError> {
+    let mut tokens = vec![];
+    match syntax {
+        Syntax::Brainfuck => {
+            for (n, line) in source.lines().enumerate() {
+                tokens.extend(line.chars().filter(|c| INSTRUCTIONS.contains(c)).map(|c| Token { instruction: c, line: n + 1 }));
+            }
+        },
+        Syntax::Pikalang => {
+            for (n, line) in source.lines().enumerate() {
+                for word in line.split_whitespace() {
+                    if let Some(i) = PIKALANG.iter().position(|&w| w == word) {
+                        tokens.push(Token { instruction: INSTRUCTIONS[i], line: n + 1 });
+                    }
+                }
+            }
+        },
+        Syntax::Ook | Syntax::Blub => {
+            let word = syntax.word().expect("languages of pairs have a word");
+            // The first word of a pair not closed yet, with its mark.
+            let mut first: Option<(char, usize, usize)> = None;
+            for (n, line) in source.lines().enumerate() {
+                let mut chars = line.char_indices();
+                while let Some((at, _)) = chars.next() {
+                    let rest = &line[at..];
+                    let Some(mark) = rest.strip_prefix(word).and_then(|r| r.chars().next()).filter(|m| ".?!".contains(*m)) else {
+                        continue;
+                    };
+                    let column = line[..at].chars().count() + 1;
+                    match first.take() {
+                        None => first = Some((mark, n + 1, column)),
+                        Some((a, at_line, at_column)) => {
+                            let i = MARKS.iter().position(|&p| p == (a, mark)).ok_or_else(|| {
+                                BfError::Conversion(at_line, at_column, format!("'{0}{1} {0}{2}' is not an instruction", word, a, mark))
+                            })?;
+                            tokens.push(Token { instruction: INSTRUCTIONS[i], line: at_line });
+                        },
+                    }
+                    // Skip the rest of the word and its mark.
+                    for _ in 0..word.len() {
+                        chars.next();
+                    }
+                }
+            }
+            if let Some((mark, line, column)) = first {
+                return Err(BfError::Conversion(line, column, format!("'{}{}' is missing the other word of its pair", word, mark)));
+            }
+        },
+    }
+    Ok(tokens)
+}
+
+/// The instruction as it is written in the language.
+fn spell(instruction: char, syntax: Syntax) -> String {
+    let i = INSTRUCTIONS.iter().position(|&c| c == instruction).expect("tokens are instructions");
+    match syntax {
+        Syntax::Brainfuck => instruction.to_string(),
+        Syntax::Pikalang => PIKALANG[i].to_string(),
+        Syntax::Ook | Syntax::Blub => {
+            let word = syntax.word().expect("languages of pairs have a word");
+            format!("{0}{1} {0}{2}", word, MARKS[i].0, MARKS[i].1)
+        },
+    }
+}
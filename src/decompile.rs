@@ -0,0 +1,337 @@
+// Decompilation of programs to annotated pseudocode.
+// MIT license.
+
+use std::collections::HashMap;
+
+use crate::analysis::{self, LoopInfo};
+use crate::program::{FileOp, Op, Program};
+
+/// Number of spaces per block nesting level.
+const INDENT: usize = 4;
+
+/// A flag cell set to a known value before an `if`, and cleared by it. A loop on it right after the `if` is its
+/// `else`.
+#[derive(Clone, Copy)]
+struct Flag {
+    cell: isize,
+    value: i32,
+    /// The cell tested by the `if`.
+    tested: isize,
+}
+
+/// Writes the pseudocode of a program.
+struct Decompiler<'a> {
+    ops: &'a [Op],
+    /// The classification of every loop, at the index of its opening bracket.
+    loops: Vec<Option<LoopInfo>>,
+    out: String,
+    /// Whether the position of the pointer is known from the start of the program. Otherwise, positions are
+    /// relative to a variable `p`.
+    known: bool,
+    /// Position of the pointer, from the start of the program or relative to `p`.
+    pos: isize,
+    /// Cells with a known value, or with an unknown one, since the last loop.
+    values: HashMap<isize, Option<i32>>,
+    /// Whether the cells not in `values` are known to be zero, at the start of the program.
+    zeroed: bool,
+}
+
+impl Decompiler<'_> {
+    fn line(&mut self, depth: usize, code: &str, note: Option<String>) {
+        self.out.push_str(&" ".repeat(depth * INDENT));
+        self.out.push_str(code);
+        if let Some(note) = note {
+            self.out.push_str("  // ");
+            self.out.push_str(&note);
+        }
+        self.out.push('\n');
+    }
+
+    /// The cell at the given offset from the pointer.
+    fn cell(&self, offset: isize) -> String {
+        let at = self.pos + offset;
+        match (self.known, at) {
+            (true, _) => format!("cell[{}]", at),
+            (false, 0) => "cell[p]".to_string(),
+            (false, at) if at < 0 => format!("cell[p - {}]", at.unsigned_abs()),
+            (false, at) => format!("cell[p + {}]", at),
+        }
+    }
+
+    /// The value of the cell at the given position, if known.
+    fn value(&self, at: isize) -> Option<i32> {
+        match self.values.get(&at) {
+            Some(&value) => value,
+            None => Some(0).filter(|_| self.zeroed),
+        }
+    }
+
+    /// Forgets the values of all the cells, after code that may change any of them.
+    fn forget(&mut self) {
+        self.values.clear();
+        self.zeroed = false;
+    }
+
+    /// Makes `p` hold the pointer, before code that moves it by an amount only known at run time.
+    fn sync(&mut self, depth: usize) {
+        if self.known {
+            self.line(depth, &format!("p = {};", self.pos), None);
+        } else if self.pos < 0 {
+            self.line(depth, &format!("p -= {};", self.pos.unsigned_abs()), None);
+        } else if self.pos > 0 {
+            self.line(depth, &format!("p += {};", self.pos), None);
+        }
+        self.known = false;
+        self.pos = 0;
+        self.forget();
+    }
+
+    fn add(&mut self, depth: usize, offset: isize, value: i32) {
+        let cell = self.cell(offset);
+        let code = if value < 0 { format!("{} -= {};", cell, value.unsigned_abs()) } else { format!("{} += {};", cell, value) };
+        self.line(depth, &code, None);
+        let at = self.pos + offset;
+        self.values.insert(at, self.value(at).map(|v| v.wrapping_add(value)));
+    }
+
+    fn set(&mut self, offset: isize, value: Option<i32>) {
+        self.values.insert(self.pos + offset, value);
+    }
+
+    /// Offsets, relative to the loop, of all the cells the balanced loop at the given index writes to, at any depth.
+    fn writes(&self, open: usize, close: usize) -> Vec<isize> {
+        let mut writes = vec![];
+        // The nested loops are balanced too, so the moves can be followed in order.
+        let mut offset = 0;
+        for op in &self.ops[open + 1..close] {
+            match *op {
+                Op::Move(n) => offset += n,
+                Op::Add(_) | Op::Clear | Op::In => writes.push(offset),
+                Op::AddAt { offset: o, .. } | Op::MulAdd { offset: o, .. } => writes.push(offset + o),
+                Op::Fill { offset: o, len, .. } => writes.extend((0..len as isize).map(|i| offset + o + i)),
+                _ => (),
+            }
+        }
+        writes
+    }
+
+    /// Values, relative to the loop, of the cells written by the first iteration of the balanced loop at the given
+    /// index, if known at the end of it.
+    fn effect(&self, open: usize, close: usize) -> HashMap<isize, Option<i32>> {
+        let mut cells: HashMap<isize, Option<i32>> = HashMap::new();
+        let mut offset = 0;
+        let mut i = open + 1;
+        while i < close {
+            let value = |cells: &HashMap<isize, Option<i32>>, o: isize| match cells.get(&o) {
+                Some(&value) => value,
+                None if o == 0 => None,
+                None => self.value(self.pos + o),
+            };
+            match self.ops[i] {
+                Op::Move(n) => offset += n,
+                Op::Add(n) => {
+                    cells.insert(offset, value(&cells, offset).map(|v| v.wrapping_add(n)));
+                },
+                Op::AddAt { offset: o, value: n } => {
+                    cells.insert(offset + o, value(&cells, offset + o).map(|v| v.wrapping_add(n)));
+                },
+                Op::Clear => {
+                    cells.insert(offset, Some(0));
+                },
+                Op::Fill { offset: o, len, value } => cells.extend((0..len as isize).map(|j| (offset + o + j, Some(value)))),
+                Op::In => {
+                    cells.insert(offset, None);
+                },
+                Op::MulAdd { offset: o, .. } => {
+                    cells.insert(offset + o, None);
+                },
+                Op::Open(inner) => {
+                    cells.extend(self.writes(i, inner).into_iter().map(|w| (offset + w, None)));
+                    i = inner;
+                },
+                _ => (),
+            }
+            i += 1;
+        }
+        cells
+    }
+
+    /// Writes the instructions in the given range, which has balanced brackets.
+    fn block(&mut self, start: usize, end: usize, depth: usize) {
+        // Flags cleared by the previous `if`, if it was right before.
+        let mut flags: Vec<Flag> = vec![];
+        let mut i = start;
+        while i < end {
+            let op = self.ops[i];
+            if !matches!(op, Op::Move(_) | Op::Open(_)) {
+                flags.clear();
+            }
+            match op {
+                Op::Move(n) => self.pos += n,
+                Op::Add(n) => self.add(depth, 0, n),
+                Op::AddAt { offset, value } => self.add(depth, offset, value),
+                Op::Clear => {
+                    self.line(depth, &format!("{} = 0;", self.cell(0)), Some("clear".to_string()));
+                    self.set(0, Some(0));
+                },
+                Op::MulAdd { .. } => {
+                    let mut targets = vec![];
+                    while let Some(&Op::MulAdd { offset, factor }) = self.ops.get(i) {
+                        targets.push((offset, factor));
+                        i += 1;
+                    }
+                    let source = self.cell(0);
+                    let mut code: Vec<String> = targets
+                        .iter()
+                        .map(|&(offset, factor)| match factor {
+                            1 => format!("{} += {};", self.cell(offset), source),
+                            -1 => format!("{} -= {};", self.cell(offset), source),
+                            f if f < 0 => format!("{} -= {} * {};", self.cell(offset), f.unsigned_abs(), source),
+                            f => format!("{} += {} * {};", self.cell(offset), f, source),
+                        })
+                        .collect();
+                    let value = self.value(self.pos);
+                    for &(offset, factor) in &targets {
+                        let target = self.value(self.pos + offset);
+                        self.set(offset, target.zip(value).map(|(t, v)| t.wrapping_add(v.wrapping_mul(factor))));
+                    }
+                    // The copy loops are always followed by the clear of their cell.
+                    let note = if self.ops.get(i) == Some(&Op::Clear) {
+                        code.push(format!("{} = 0;", source));
+                        self.set(0, Some(0));
+                        match targets.as_slice() {
+                            [(_, 1)] => "move",
+                            targets if targets.iter().all(|&(_, f)| f == 1) => "copy",
+                            _ => "multiply",
+                        }
+                    } else {
+                        i -= 1;
+                        "multiply"
+                    };
+                    self.line(depth, &code.join(" "), Some(note.to_string()));
+                },
+                Op::Scan(n) => {
+                    self.sync(depth);
+                    let code = if n < 0 { format!("p -= {};", n.unsigned_abs()) } else { format!("p += {};", n) };
+                    let note = format!("find the next zero cell to the {}", if n < 0 { "left" } else { "right" });
+                    self.line(depth, &format!("while cell[p] {{ {} }}", code), Some(note));
+                },
+                Op::Fill { offset, len, value } => {
+                    let range = format!("cell[{}..{}]", self.pos + offset, self.pos + offset + len as isize);
+                    let range = if self.known { range } else { range.replacen('[', "[p + ", 1).replacen("..", "..p + ", 1) };
+                    self.line(depth, &format!("{} = {};", range, value), Some(format!("fill {} cells", len)));
+                    (0..len as isize).for_each(|o| self.set(offset + o, Some(value)));
+                },
+                Op::Out => {
+                    let note = self.value(self.pos).map(|v| v as u8).filter(|b| b.is_ascii_graphic() || *b == b' ');
+                    self.line(depth, &format!("print({});", self.cell(0)), note.map(|b| format!("{:?}", b as char)));
+                },
+                Op::OutByte(b) => self.line(depth, &format!("print({:?});", b as char), None),
+                Op::In => {
+                    self.line(depth, &format!("{} = read();", self.cell(0)), None);
+                    self.set(0, None);
+                },
+                Op::Debug => self.line(depth, "debug();", None),
+                Op::Channel => self.line(depth, &format!("channel({});", self.cell(0)), None),
+                Op::Send => self.line(depth, &format!("send({});", self.cell(0)), None),
+                Op::Receive => {
+                    self.line(depth, &format!("{} = receive();", self.cell(0)), None);
+                    self.set(0, None);
+                },
+                Op::Clock => {
+                    self.line(depth, &format!("{} = clock();", self.cell(0)), None);
+                    self.set(0, None);
+                },
+                Op::File(op) => {
+                    let (fd, data) = (self.cell(0), self.cell(1));
+                    match op {
+                        FileOp::OpenRead | FileOp::OpenWrite => {
+                            let mode = if op == FileOp::OpenRead { "r" } else { "w" };
+                            self.line(depth, &format!("{} = open({}.., {:?});", fd, data, mode), None);
+                            self.set(0, None);
+                        },
+                        FileOp::Close => {
+                            self.line(depth, &format!("close({}); {} = 0;", fd, fd), None);
+                            self.set(0, Some(0));
+                        },
+                        FileOp::Read => {
+                            self.line(depth, &format!("{} = read({});", data, fd), None);
+                            self.set(1, None);
+                        },
+                        FileOp::Write => self.line(depth, &format!("write({}, {});", fd, data), None),
+                    }
+                },
+                Op::Fork => {
+                    self.sync(depth);
+                    let note = "the new thread goes on one cell to the right, set to 1, and this one with the cell at 0";
+                    self.line(depth, "p = fork(p);", Some(note.to_string()));
+                },
+                Op::Open(close) => {
+                    let info = self.loops[i].expect("every loop is classified");
+                    let flag = flags.iter().find(|f| f.cell == self.pos).copied();
+                    flags.clear();
+                    // Loops that always end with their cell at zero run at most once.
+                    let effect = if info.balanced { self.effect(i, close) } else { HashMap::new() };
+                    let is_if = effect.get(&0) == Some(&Some(0));
+                    if is_if {
+                        for (&offset, _) in effect.iter().filter(|&(&o, &v)| o != 0 && v == Some(0)) {
+                            match self.value(self.pos + offset) {
+                                Some(value) if value as u8 != 0 => {
+                                    flags.push(Flag { cell: self.pos + offset, value, tested: self.pos });
+                                },
+                                _ => (),
+                            }
+                        }
+                    }
+                    if !info.balanced {
+                        self.sync(depth);
+                    }
+                    let cell = self.cell(0);
+                    // A loop on the flag that undoes its value runs once, when the `if` did not run.
+                    let (keyword, note) = match flag {
+                        Some(flag) if is_if || info.delta == Some(-flag.value) => {
+                            ("if", Some(format!("else of the if on {}", self.cell(flag.tested - self.pos))))
+                        },
+                        _ if is_if => ("if", Some("runs at most once".to_string())),
+                        _ => ("while", None),
+                    };
+                    self.line(depth, &format!("{} {} {{", keyword, cell), note);
+                    self.forget();
+                    let start = self.pos;
+                    self.block(i + 1, close, depth + 1);
+                    if info.balanced {
+                        self.pos = start;
+                    } else {
+                        // Every iteration starts where the previous one ended.
+                        self.sync(depth + 1);
+                    }
+                    self.forget();
+                    self.line(depth, "}", None);
+                    i = close;
+                },
+                Op::Close(_) => unreachable!("loops are written whole"),
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Decompiles the program to pseudocode, with the cells as an array `cell` and the common idioms annotated. The
+/// program is best optimized first, so that clear, copy and scan loops are recognized.
+pub fn decompile(program: &Program) -> String {
+    let mut loops = vec![None; program.len()];
+    for info in analysis::loops(program) {
+        loops[info.open] = Some(info);
+    }
+    let mut decompiler = Decompiler {
+        ops: &program.ops,
+        loops,
+        out: String::new(),
+        known: true,
+        pos: 0,
+        values: HashMap::new(),
+        zeroed: true,
+    };
+    decompiler.block(0, program.len(), 0);
+    decompiler.out
+}
@@ -0,0 +1,109 @@
+// Reporting of errors, as text or as JSON for tools.
+// MIT license.
+
+use std::error::Error;
+use std::{fmt, io};
+
+use serde_json::{json, Value};
+
+use brainfuck::{BfError, Program, Span};
+
+/// An error at a known place of the source code.
+#[derive(Debug)]
+pub struct Located {
+    pub error: BfError,
+    pub span: Span,
+    /// Line and column where the span starts, from 1.
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Located {
+    /// Locates a parse error in the source code it comes from. Errors without a position are left as they are.
+    pub fn parse_error(error: BfError, source: &str) -> Box<dyn Error> {
+        let Some(pos) = error.position() else { return error.into() };
+        let (line, column) = line_column(source, pos);
+        let span = match error {
+            BfError::RepeatTooLarge(span) => span,
+            _ => Span::at(pos),
+        };
+        Box::new(Located { error, span, line, column })
+    }
+
+    /// Locates an error of a run at the instruction where it stopped.
+    pub fn run_error(error: BfError, program: &Program, instruction: usize) -> Box<dyn Error> {
+        let Some(&span) = program.spans.get(instruction) else { return error.into() };
+        let (line, column) = program.location(span.start);
+        Box::new(Located { error, span, line, column })
+    }
+}
+
+/// The line and column of a position in source code, from 1.
+pub fn line_column(source: &str, pos: usize) -> (usize, usize) {
+    let before = &source.as_bytes()[..pos.min(source.len())];
+    let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+    (line, pos - before.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1) + 1)
+}
+
+impl fmt::Display for Located {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl Error for Located {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// The interpreter error of an error, if it is one, located or not.
+pub fn bf_error<'a>(e: &'a (dyn Error + 'static)) -> Option<&'a BfError> {
+    e.downcast_ref::<BfError>().or_else(|| e.downcast_ref::<Located>().map(|l| &l.error))
+}
+
+/// Whether the error comes from writing to a pipe closed by its reader, like `head` once it has the lines it needs.
+pub fn is_broken_pipe(e: &(dyn Error + 'static)) -> bool {
+    let io_error = match bf_error(e) {
+        Some(BfError::Io(e)) => Some(e),
+        _ => e.downcast_ref::<io::Error>(),
+    };
+    io_error.is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+/// What to do about an error, for the most common ones.
+fn hint(e: &BfError) -> Option<&'static str> {
+    match e {
+        BfError::UnmatchedOpen(_) => Some("Add a ']' to close the loop, or remove the '['."),
+        BfError::UnmatchedClose(_) => Some("Remove the ']', or add the '[' of its loop before it."),
+        BfError::Conversion(..) => Some("Check that the program is written in the language given with --from."),
+        BfError::RepeatTooLarge(_) => Some("Repeat the command a few times, or use a loop to add large amounts."),
+        BfError::InvalidBytecode(_) => Some("Create the file again with this version of the interpreter."),
+        BfError::PointerOverflow(ptr) if *ptr < 0 => Some("The pointer moved left of the first cell, check the '<' before."),
+        BfError::PointerOverflow(_) => Some("Give the machine more cells with --mem-size."),
+        BfError::LoopTooDeep(..) => Some("Raise the limit with --max-loop-depth."),
+        BfError::StepLimit(_) => Some("The program may never end, or raise the limit with --max-steps."),
+        BfError::OutputLimit(_) => Some("The program may never end, or raise the limit with --max-output."),
+        BfError::TimeLimit(_) => Some("The program may never end, or raise the limit with --timeout."),
+        BfError::UnknownChannel(_) => Some("Give the machine the channel with --channel N=FILE."),
+        BfError::NoPeer => Some("Run the program next to the one it talks to, with --pair."),
+        BfError::FilesDenied => Some("Let the program touch files with --allow-fs, if it can be trusted."),
+        BfError::BadFile(_) => Some("Use the descriptor that opening the file stored, and check that it is not zero."),
+        _ => None,
+    }
+}
+
+/// The error as a JSON object: its code, message and hint, and where it is in the source code when known. Errors
+/// that do not come from the interpreter, like a missing file, have no code.
+pub fn json(e: &(dyn Error + 'static)) -> Value {
+    let located = e.downcast_ref::<Located>();
+    let error = bf_error(e);
+    json!({
+        "code": error.map(BfError::code),
+        "message": e.to_string(),
+        "span": located.map(|l| l.span),
+        "line": located.map(|l| l.line),
+        "column": located.map(|l| l.column),
+        "hint": error.and_then(hint),
+    })
+}
@@ -0,0 +1,196 @@
+// Bounded equivalence checking of two programs.
+// MIT license.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::error::BfError;
+use crate::machine::Machine;
+use crate::program::Program;
+use crate::transpile::Settings;
+
+/// The inputs to run the programs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inputs {
+    /// Every input of up to the given number of bytes.
+    Exhaustive(usize),
+    /// The given number of random inputs, of up to 16 bytes.
+    Random(usize),
+}
+impl Inputs {
+    /// Generates the inputs, calling the function with every one of them until it returns `false`.
+    fn each(self, mut f: impl FnMut(&[u8]) -> bool) {
+        match self {
+            Inputs::Exhaustive(max_len) => {
+                for len in 0..=max_len {
+                    let mut input = vec![0u8; len];
+                    loop {
+                        if !f(&input) {
+                            return;
+                        }
+                        // Count up in base 256, from the last byte.
+                        match input.iter().rposition(|&b| b != u8::MAX) {
+                            Some(i) => {
+                                input[i] += 1;
+                                input[i + 1..].fill(0);
+                            },
+                            None => break,
+                        }
+                    }
+                }
+            },
+            Inputs::Random(count) => {
+                // A fixed seed, so that counterexamples can be reproduced.
+                let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+                let mut next = move || {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state
+                };
+                for _ in 0..count {
+                    let len = (next() % 17) as usize;
+                    let input: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+                    if !f(&input) {
+                        return;
+                    }
+                }
+            },
+        }
+    }
+}
+impl FromStr for Inputs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Inputs, String> {
+        let invalid = || format!("invalid inputs '{}', expected exhaustive:<LENGTH> or random:<COUNT>", s);
+        let (kind, n) = s.split_once(':').ok_or_else(invalid)?;
+        let n: usize = n.parse().map_err(|_| invalid())?;
+        match kind {
+            "exhaustive" => Ok(Inputs::Exhaustive(n)),
+            "random" => Ok(Inputs::Random(n)),
+            _ => Err(invalid()),
+        }
+    }
+}
+impl fmt::Display for Inputs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Inputs::Exhaustive(len) => write!(f, "exhaustive:{}", len),
+            Inputs::Random(count) => write!(f, "random:{}", count),
+        }
+    }
+}
+
+/// An output buffer that can still be read once given to a machine.
+#[derive(Clone, Default)]
+pub(crate) struct Buffer(pub(crate) Rc<RefCell<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// How one run of a program ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ending {
+    /// The program ended normally.
+    Finished,
+    /// The program failed, with the given message.
+    Failed(String),
+    /// The program did not end within the step limit.
+    TimedOut,
+}
+impl fmt::Display for Ending {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ending::Finished => write!(f, "finished"),
+            Ending::Failed(message) => write!(f, "failed: {}", message),
+            Ending::TimedOut => write!(f, "did not finish in time"),
+        }
+    }
+}
+
+/// The result of one run of a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub ending: Ending,
+    /// Everything the program printed.
+    pub output: Vec<u8>,
+    /// The memory tape at the end, without the trailing zero cells.
+    pub tape: Vec<u32>,
+}
+
+/// Runs the program on a fresh machine with the given input, for at most `max_steps` instructions.
+pub fn run(program: &Program, settings: &Settings, input: &[u8], max_steps: u64) -> Outcome {
+    let output = Buffer::default();
+    let mut machine = Machine::builder()
+        .mem_size(settings.mem_size)
+        .cell_width(settings.cell_width)
+        .eof(settings.eof)
+        .max_steps(Some(max_steps))
+        .io(io::Cursor::new(input.to_vec()), output.clone())
+        .build();
+    let ending = match machine.run(program) {
+        Ok(()) => Ending::Finished,
+        Err(BfError::StepLimit(_)) => Ending::TimedOut,
+        Err(e) => Ending::Failed(e.to_string()),
+    };
+    let memory = machine.memory();
+    let len = memory.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1);
+    let output = output.0.borrow().clone();
+    Outcome { ending, output, tape: memory[..len].to_vec() }
+}
+
+/// An input on which two programs behave differently.
+#[derive(Debug, Clone)]
+pub struct Counterexample {
+    pub input: Vec<u8>,
+    /// What the first program did.
+    pub a: Outcome,
+    /// What the second program did.
+    pub b: Outcome,
+}
+
+/// The result of comparing two programs.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// Number of inputs the programs ran on.
+    pub inputs: usize,
+    /// Number of inputs on which a program did not end within the step limit, which prove nothing.
+    pub timed_out: usize,
+    /// The first input on which the programs differ, if any.
+    pub counterexample: Option<Counterexample>,
+}
+
+/// Runs both programs on all the inputs, and compares how they end, their outputs and, unless `output_only` is
+/// set, their final tapes. Stops at the first difference.
+pub fn check(a: &Program, b: &Program, settings: &Settings, inputs: Inputs, max_steps: u64, output_only: bool) -> Report {
+    let mut report = Report::default();
+    inputs.each(|input| {
+        report.inputs += 1;
+        let outcome_a = run(a, settings, input, max_steps);
+        let outcome_b = run(b, settings, input, max_steps);
+        if outcome_a.ending == Ending::TimedOut || outcome_b.ending == Ending::TimedOut {
+            report.timed_out += 1;
+            return true;
+        }
+        let same = outcome_a.ending == outcome_b.ending
+            && outcome_a.output == outcome_b.output
+            && (output_only || outcome_a.tape == outcome_b.tape);
+        if !same {
+            report.counterexample = Some(Counterexample { input: input.to_vec(), a: outcome_a, b: outcome_b });
+        }
+        same
+    });
+    report
+}
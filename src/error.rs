@@ -0,0 +1,117 @@
+// Errors produced while parsing and running programs.
+// MIT license.
+
+use std::{fmt, io};
+
+use crate::program::{Span, MAX_REPEAT};
+
+/// Everything that can go wrong while parsing or running a program.
+#[derive(Debug)]
+pub enum BfError {
+    /// A `[` without its matching `]`, at the given source position.
+    UnmatchedOpen(usize),
+    /// A `]` without its matching `[`, at the given source position.
+    UnmatchedClose(usize),
+    /// The memory pointer left the tape. Holds the offending pointer value.
+    PointerOverflow(i64),
+    /// Reading input or writing output failed.
+    Io(io::Error),
+    /// An encoded program or snapshot could not be decoded, or does not fit, for the given reason.
+    InvalidBytecode(String),
+    /// Entering the loop at the given source position went past the given maximum number of nested loops.
+    LoopTooDeep(usize, usize),
+    /// The program did not end within the given number of instructions.
+    StepLimit(u64),
+    /// The program printed more than the given number of bytes.
+    OutputLimit(u64),
+    /// The program did not end within the given number of milliseconds.
+    TimeLimit(u64),
+    /// A macro program could not be assembled, at the given line and column, for the given reason.
+    Assembly(usize, usize, String),
+    /// A program could not be converted from another language, at the given line and column, for the given reason.
+    Conversion(usize, usize, String),
+    /// A count of the extended syntax, at the given span of the source code, is over the limit.
+    RepeatTooLarge(Span),
+    /// The program was stopped from the outside, through the interrupt flag of the machine.
+    Interrupted,
+    /// The program selected the given channel, which the machine does not have.
+    UnknownChannel(u32),
+    /// The program sent or received a byte, but the machine has no peer.
+    NoPeer,
+    /// The program ran a file instruction, but the machine may not touch files.
+    FilesDenied,
+    /// The program used the given file descriptor, which is not open, or not open for that.
+    BadFile(u32),
+}
+
+impl BfError {
+    /// A code identifying the kind of error, which does not change between versions, for tools: `BF00xx` for errors
+    /// in programs, `BF01xx` for errors while running them and `BF02xx` for errors of their input and output.
+    pub fn code(&self) -> &'static str {
+        match self {
+            BfError::UnmatchedOpen(_) => "BF0001",
+            BfError::UnmatchedClose(_) => "BF0002",
+            BfError::InvalidBytecode(_) => "BF0003",
+            BfError::Assembly(..) => "BF0004",
+            BfError::Conversion(..) => "BF0005",
+            BfError::RepeatTooLarge(_) => "BF0006",
+            BfError::PointerOverflow(ptr) if *ptr < 0 => "BF0101",
+            BfError::PointerOverflow(_) => "BF0102",
+            BfError::LoopTooDeep(..) => "BF0103",
+            BfError::StepLimit(_) => "BF0104",
+            BfError::OutputLimit(_) => "BF0105",
+            BfError::Interrupted => "BF0106",
+            BfError::TimeLimit(_) => "BF0107",
+            BfError::UnknownChannel(_) => "BF0108",
+            BfError::NoPeer => "BF0109",
+            BfError::FilesDenied => "BF0110",
+            BfError::BadFile(_) => "BF0111",
+            BfError::Io(_) => "BF0201",
+        }
+    }
+
+    /// The source position the error is at, for the errors that hold one.
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            BfError::UnmatchedOpen(pos) | BfError::UnmatchedClose(pos) | BfError::LoopTooDeep(_, pos) => Some(*pos),
+            BfError::RepeatTooLarge(span) => Some(span.start),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BfError::UnmatchedOpen(pos) => write!(f, "Matching bracket not found for '[' at position {}", pos),
+            BfError::UnmatchedClose(pos) => write!(f, "Matching bracket not found for ']' at position {}", pos),
+            BfError::PointerOverflow(ptr) => write!(f, "Memory overflow (pointer={})", ptr),
+            BfError::Io(e) => write!(f, "I/O error: {}", e),
+            BfError::InvalidBytecode(reason) => write!(f, "Invalid bytecode: {}", reason),
+            BfError::LoopTooDeep(max, pos) => {
+                write!(f, "Loop nesting limit of {} exceeded by the '[' at position {}", max, pos)
+            },
+            BfError::StepLimit(max) => write!(f, "Step limit reached, the program did not end within {} instructions", max),
+            BfError::OutputLimit(max) => write!(f, "Output limit reached, the program printed more than {} bytes", max),
+            BfError::TimeLimit(ms) => write!(f, "Time limit reached, the program did not end within {} ms", ms),
+            BfError::Assembly(line, column, reason) => write!(f, "Assembly error at {}:{}: {}", line, column, reason),
+            BfError::Conversion(line, column, reason) => write!(f, "Conversion error at {}:{}: {}", line, column, reason),
+            BfError::RepeatTooLarge(span) => {
+                write!(f, "Repetition count at position {} is over the limit of {}", span.start, MAX_REPEAT)
+            },
+            BfError::Interrupted => write!(f, "Interrupted"),
+            BfError::UnknownChannel(n) => write!(f, "Channel {} selected, but the machine has no such channel", n),
+            BfError::NoPeer => write!(f, "Message sent or received, but the machine has no peer"),
+            BfError::FilesDenied => write!(f, "File instruction run, but the machine may not touch files"),
+            BfError::BadFile(fd) => write!(f, "File {} used, but it is not open for that", fd),
+        }
+    }
+}
+
+impl std::error::Error for BfError {}
+
+impl From<io::Error> for BfError {
+    fn from(e: io::Error) -> BfError {
+        BfError::Io(e)
+    }
+}
@@ -0,0 +1,41 @@
+// Example programs bundled in the binary.
+// MIT license.
+
+/// A bundled example program.
+pub struct Example {
+    /// Name given to the `examples` command.
+    pub name: &'static str,
+    /// What the program does.
+    pub description: &'static str,
+    /// Source code.
+    pub source: &'static str,
+}
+
+/// All the bundled examples.
+pub const EXAMPLES: &[Example] = &[
+    Example { name: "hello", description: "Print Hello World!", source: include_str!("../programs/helloworld.b") },
+    Example { name: "rot13", description: "Apply ROT13 to the input", source: include_str!("../programs/rot13.b") },
+    Example {
+        name: "sierpinski",
+        description: "Display a Sierpinski triangle",
+        source: include_str!("../programs/sierpinski.b"),
+    },
+    Example {
+        name: "mandelbrot",
+        description: "Display the Mandelbrot set",
+        source: include_str!("../programs/mandelbrot.b"),
+    },
+    Example {
+        name: "xmastree",
+        description: "Print a Christmas tree as tall as the number in the input, given without a newline",
+        source: include_str!("../programs/xmastree.b"),
+    },
+];
+
+/// Returns the example with the given name.
+pub fn find(name: &str) -> Result<&'static Example, String> {
+    EXAMPLES.iter().find(|e| e.name == name).ok_or_else(|| {
+        let names: Vec<&str> = EXAMPLES.iter().map(|e| e.name).collect();
+        format!("Unknown example '{}', expected one of: {}", name, names.join(", "))
+    })
+}
@@ -0,0 +1,271 @@
+// Annotated listings of programs, with the common idioms recognized.
+// MIT license.
+
+use std::collections::BTreeMap;
+
+use crate::analysis::{self, LoopInfo};
+use crate::program::{Op, Program};
+
+/// Number of spaces per loop nesting level.
+const INDENT: usize = 2;
+
+/// A line of the listing: a piece of code and what it does.
+struct Line {
+    pos: usize,
+    depth: usize,
+    code: String,
+    label: String,
+}
+
+/// A loop that runs at most once, because it ends by clearing its cell, where it was and what its body does to the
+/// cells around it.
+struct If {
+    pos: isize,
+    deltas: BTreeMap<isize, i32>,
+}
+
+/// Writes the listing of a program.
+struct Explainer<'a> {
+    program: &'a Program,
+    /// The classification of every loop, at the index of its opening bracket.
+    loops: Vec<Option<LoopInfo>>,
+    lines: Vec<Line>,
+}
+
+/// A cell at the given offset from the pointer, in words.
+fn cell(offset: isize) -> String {
+    match offset {
+        0 => "this cell".to_string(),
+        1 => "the next cell".to_string(),
+        -1 => "the previous cell".to_string(),
+        o if o < 0 => format!("the cell {} to the left", o.unsigned_abs()),
+        o => format!("the cell {} to the right", o),
+    }
+}
+
+/// A list of things in words, like `a, b and c`.
+fn list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+impl Explainer<'_> {
+    fn ops(&self) -> &[Op] {
+        &self.program.ops
+    }
+
+    fn line(&mut self, start: usize, end: usize, depth: usize, label: String) {
+        let code = self.ops()[start..end].iter().map(Op::to_string).collect();
+        self.lines.push(Line { pos: self.program.spans[start].start, depth, code, label });
+    }
+
+    /// The moves and the additions of the code in the given range, if it has nothing else: where it leaves the
+    /// pointer, and what it adds to every cell, from where it starts.
+    fn straight(&self, start: usize, end: usize) -> Option<(isize, BTreeMap<isize, i32>)> {
+        let mut pos = 0;
+        let mut deltas = BTreeMap::new();
+        for op in &self.ops()[start..end] {
+            match *op {
+                Op::Move(n) => pos += n,
+                Op::Add(n) => *deltas.entry(pos).or_insert(0) += n,
+                _ => return None,
+            }
+        }
+        deltas.retain(|_, d| *d as u8 != 0);
+        Some((pos, deltas))
+    }
+
+    /// What the body of the loop at the given index adds to the cells around it, outside of the loops nested in it,
+    /// if it ends by clearing its cell and every loop in it leaves the pointer where it found it.
+    fn if_body(&self, open: usize, close: usize) -> Option<BTreeMap<isize, i32>> {
+        if !self.loops[open]?.balanced || close < open + 4 {
+            return None;
+        }
+        let clear = matches!(self.ops()[close - 3..close], [Op::Open(_), Op::Add(1 | -1), Op::Close(_)]);
+        let mut pos = 0;
+        let mut deltas = BTreeMap::new();
+        let mut i = open + 1;
+        while i < close - 3 {
+            match self.ops()[i] {
+                Op::Move(n) => pos += n,
+                Op::Add(n) => *deltas.entry(pos).or_insert(0) += n,
+                Op::Open(inner) if self.loops[i]?.balanced => i = inner,
+                Op::Open(_) => return None,
+                _ => (),
+            }
+            i += 1;
+        }
+        Some(deltas).filter(|_| clear && pos == 0)
+    }
+
+    /// Lists the instructions in the given range, which has balanced brackets.
+    fn block(&mut self, start: usize, end: usize, depth: usize) {
+        // Where the pointer is from the start of the block, while only moves and balanced loops change it.
+        let mut pos = Some(0isize);
+        // The `if` right before, which a loop on one of the cells it decrements may be the `else` of.
+        let mut last_if: Option<If> = None;
+        let mut i = start;
+        while i < end {
+            let op = self.ops()[i];
+            match op {
+                Op::Add(_) | Op::Move(_) => {
+                    let run = self.ops()[i..end].iter().take_while(|o| matches!(o, Op::Add(_) | Op::Move(_))).count();
+                    // A clear right before makes the additions to its cell a constant.
+                    let set = self.ops()[i..i + run].iter().take_while(|o| matches!(o, Op::Add(_))).count();
+                    let after_clear = set > 0
+                        && i > 0
+                        && matches!(self.ops()[i - 1], Op::Close(_))
+                        && self.lines.last().is_some_and(|l| l.label == "clear this cell");
+                    if after_clear {
+                        let (_, deltas) = self.straight(i, i + set).expect("only additions");
+                        let line = self.lines.last_mut().expect("the clear is listed");
+                        line.code.extend(self.program.ops[i..i + set].iter().map(Op::to_string));
+                        line.label = format!("set this cell to {}", deltas.get(&0).map_or(0, |&n| n as u8));
+                        i += set;
+                        continue;
+                    }
+                    let (moved, deltas) = self.straight(i, i + run).expect("only moves and additions");
+                    // Code that only changes the cell it moves to reads best in its order.
+                    let there = moved != 0 && deltas.len() == 1 && deltas.contains_key(&moved);
+                    let mut parts: Vec<String> = deltas
+                        .iter()
+                        .map(|(&o, &n)| (if there { 0 } else { o }, n))
+                        .map(|(o, n)| match (o, n < 0) {
+                            (0, false) => format!("add {}", n),
+                            (0, true) => format!("subtract {}", n.unsigned_abs()),
+                            (o, false) => format!("add {} to {}", n, cell(o)),
+                            (o, true) => format!("subtract {} from {}", n.unsigned_abs(), cell(o)),
+                        })
+                        .collect();
+                    let step = match moved {
+                        n if n < 0 => format!("move left {}", n.unsigned_abs()),
+                        n => format!("move right {}", n),
+                    };
+                    match moved {
+                        0 => (),
+                        _ if there => parts.insert(0, step),
+                        _ => parts.push(step),
+                    }
+                    let label = if parts.is_empty() { "does nothing".to_string() } else { list(&parts) };
+                    self.line(i, i + run, depth, label);
+                    pos = pos.map(|p| p + moved);
+                    if !deltas.is_empty() {
+                        last_if = None;
+                    }
+                    i += run;
+                    continue;
+                },
+                Op::Out => self.line(i, i + 1, depth, "print this cell".to_string()),
+                Op::In => self.line(i, i + 1, depth, "read a byte into this cell".to_string()),
+                Op::Debug => self.line(i, i + 1, depth, "show the memory, in debug mode".to_string()),
+                Op::Open(close) => {
+                    let else_of = match (&last_if, pos) {
+                        (Some(last), Some(pos)) if last.deltas.get(&(pos - last.pos)) == Some(&-1) => Some(last.pos - pos),
+                        _ => None,
+                    };
+                    last_if = None;
+                    let info = self.loops[i].expect("every loop is classified");
+                    let else_of = else_of.filter(|_| info.delta == Some(-1) || self.if_body(i, close).is_some());
+                    if let Some(label) = self.idiom(i, close).filter(|_| else_of.is_none()) {
+                        self.line(i, close + 1, depth, label);
+                    } else {
+                        let body = self.if_body(i, close);
+                        let label = match (else_of, &body) {
+                            (Some(tested), _) => {
+                                format!("else: runs once if {} was zero, and that loop did not run", cell(tested))
+                            },
+                            (_, Some(_)) => "if this cell is not zero: runs at most once, since it clears it".to_string(),
+                            _ => "loop while this cell is not zero".to_string(),
+                        };
+                        self.line(i, i + 1, depth, label);
+                        self.block(i + 1, close, depth + 1);
+                        self.line(close, close + 1, depth, String::new());
+                        if let (Some(deltas), Some(pos)) = (body, pos) {
+                            last_if = Some(If { pos, deltas });
+                        }
+                    }
+                    if !info.balanced {
+                        pos = None;
+                    }
+                    i = close + 1;
+                    continue;
+                },
+                Op::Close(_) => unreachable!("loops are listed whole"),
+                op => self.line(i, i + 1, depth, match op {
+                    Op::Fork => "fork a thread",
+                    Op::Clock => "store the time in this cell",
+                    Op::File(_) => "a file instruction",
+                    _ => "an instruction of the dialect",
+                }.to_string()),
+            }
+            if !matches!(op, Op::Move(_)) {
+                last_if = None;
+            }
+            i += 1;
+        }
+    }
+
+    /// Names the idiom the loop at the given index is, if it is one of the short loops of only moves and additions
+    /// that do one thing.
+    fn idiom(&self, open: usize, close: usize) -> Option<String> {
+        let (pos, mut deltas) = self.straight(open + 1, close)?;
+        if deltas.is_empty() && pos != 0 {
+            let side = if pos < 0 { "left" } else { "right" };
+            let step = if pos.abs() == 1 { String::new() } else { format!(", {} cells at a time", pos.abs()) };
+            return Some(format!("find the next zero cell to the {}{}", side, step));
+        }
+        let step = deltas.remove(&0)?;
+        if pos != 0 || (step != 1 && step != -1) {
+            return None;
+        }
+        if deltas.is_empty() {
+            return Some("clear this cell".to_string());
+        }
+        // Every iteration takes one from the cell, so that the others get what they add times its value.
+        let factors: Vec<(isize, i32)> = deltas.iter().map(|(&o, &d)| (o, if step == -1 { d } else { -d })).collect();
+        let cells: Vec<String> = factors.iter().map(|&(o, _)| cell(o)).collect();
+        Some(match factors.as_slice() {
+            [(_, 1)] => format!("move this cell to {}", cells[0]),
+            factors if factors.iter().all(|&(_, f)| f == 1) => format!("copy this cell to {}, clearing it", list(&cells)),
+            _ => {
+                let times: Vec<String> = factors
+                    .iter()
+                    .map(|&(o, f)| match f {
+                        1 => cell(o),
+                        -1 => format!("{} (subtracted)", cell(o)),
+                        f if f < 0 => format!("{} ({} times, subtracted)", cell(o), f.unsigned_abs()),
+                        f => format!("{} ({} times)", cell(o), f),
+                    })
+                    .collect();
+                format!("multiply this cell into {}, clearing it", list(&times))
+            },
+        })
+    }
+}
+
+/// Lists the program, a piece of code per line with what it does, and its loops indented. Runs of moves and
+/// additions are summed up, and the loops that are common idioms, like clearing a cell or moving it to another one,
+/// are on a line of their own. The program should not be optimized, so that the code is as it was written.
+pub fn explain(program: &Program) -> String {
+    let mut loops = vec![None; program.len()];
+    for info in analysis::loops(program) {
+        loops[info.open] = Some(info);
+    }
+    let mut explainer = Explainer { program, loops, lines: vec![] };
+    explainer.block(0, program.len(), 0);
+    let code = |line: &Line| " ".repeat(line.depth * INDENT) + &line.code;
+    // Long pieces of code, like those printing a constant, should not push all the labels to the right.
+    let width = explainer.lines.iter().map(|l| code(l).len()).filter(|&w| w <= 32).max().unwrap_or(0);
+    let mut out = String::new();
+    for line in &explainer.lines {
+        let (row, column) = program.location(line.pos);
+        let location = format!("{}:{}", row, column);
+        let text = format!("{:<8} {:<width$}  {}", location, code(line), line.label, width = width);
+        out.push_str(text.trim_end());
+        out.push('\n');
+    }
+    out
+}
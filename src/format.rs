@@ -0,0 +1,47 @@
+// Source code formatting.
+// MIT license.
+
+use crate::program::{Op, Program};
+
+/// Number of spaces per loop nesting level.
+const INDENT: usize = 4;
+
+/// Formats the given program. Every bracket goes on its own line, and the instructions in between are indented
+/// by their loop nesting level. Comments are not kept.
+pub fn format(program: &Program) -> String {
+    let mut out = String::new();
+    let mut depth: usize = 0;
+    // Whether the current line has instructions in it.
+    let mut open_line = false;
+
+    for op in &program.ops {
+        match op {
+            Op::Open(_) | Op::Close(_) => {
+                if open_line {
+                    out.push('\n');
+                    open_line = false;
+                }
+                if let Op::Close(_) = op {
+                    depth -= 1;
+                }
+                out.push_str(&" ".repeat(depth * INDENT));
+                out.push_str(&op.to_string());
+                out.push('\n');
+                if let Op::Open(_) = op {
+                    depth += 1;
+                }
+            },
+            _ => {
+                if !open_line {
+                    out.push_str(&" ".repeat(depth * INDENT));
+                    open_line = true;
+                }
+                out.push_str(&op.to_string());
+            }
+        }
+    }
+    if open_line {
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,221 @@
+// Fuzzing of the interpreter with mutants of a program.
+// MIT license.
+
+use std::fmt;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::equiv::Buffer;
+use crate::error::BfError;
+use crate::generate::Rng;
+use crate::machine::{Dispatch, Machine};
+use crate::optimize::{optimize, OptLevel};
+use crate::program::Program;
+
+/// Bytes a mutant may print before it is stopped.
+const MAX_OUTPUT: u64 = 1 << 16;
+
+/// The instructions mutations insert.
+const INSTRUCTIONS: &[u8] = b"+-<>.,[]";
+
+/// A change to a program. The first ones do not change what it does, the others most likely do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutation {
+    /// Insert `+-` or `-+`.
+    InsertNoop,
+    /// Insert a letter, a space or a new line, which are comments.
+    InsertComment,
+    /// Remove an instruction.
+    Delete,
+    /// Insert an instruction.
+    Insert,
+    /// Replace an instruction with another one.
+    Replace,
+    /// Swap two instructions next to each other.
+    Swap,
+    /// Copy a piece of the program somewhere else in it.
+    Duplicate,
+}
+
+const MUTATIONS: [Mutation; 7] = [
+    Mutation::InsertNoop,
+    Mutation::InsertComment,
+    Mutation::Delete,
+    Mutation::Insert,
+    Mutation::Replace,
+    Mutation::Swap,
+    Mutation::Duplicate,
+];
+
+impl Mutation {
+    /// Whether the mutant does the same as the program, apart from running more instructions.
+    pub fn preserves(self) -> bool {
+        matches!(self, Mutation::InsertNoop | Mutation::InsertComment)
+    }
+
+    /// Applies the mutation to the source code at random places.
+    fn apply(self, source: &[u8], rng: &mut Rng) -> Vec<u8> {
+        let mut mutant = source.to_vec();
+        let instructions: Vec<usize> = (0..source.len()).filter(|&i| INSTRUCTIONS.contains(&source[i])).collect();
+        let mut at = |len: usize| rng.below(len as u64 + 1) as usize;
+        let anywhere = at(source.len());
+        let instruction = if instructions.is_empty() { None } else { Some(instructions[at(instructions.len() - 1)]) };
+        match (self, instruction) {
+            (Mutation::InsertNoop, _) => {
+                let noop: &[u8] = if at(1) == 0 { b"+-" } else { b"-+" };
+                mutant.splice(anywhere..anywhere, noop.iter().copied());
+            },
+            (Mutation::InsertComment, _) => mutant.insert(anywhere, b"abcxyz \n"[at(7)]),
+            (Mutation::Insert, _) | (_, None) => mutant.insert(anywhere, INSTRUCTIONS[at(INSTRUCTIONS.len() - 1)]),
+            (Mutation::Delete, Some(i)) => {
+                mutant.remove(i);
+            },
+            (Mutation::Replace, Some(i)) => mutant[i] = INSTRUCTIONS[at(INSTRUCTIONS.len() - 1)],
+            (Mutation::Swap, Some(i)) => {
+                if let Some(&j) = instructions.iter().find(|&&j| j > i) {
+                    mutant.swap(i, j);
+                }
+            },
+            (Mutation::Duplicate, Some(i)) => {
+                let end = (i + 1 + at(7)).min(source.len());
+                mutant.splice(anywhere..anywhere, source[i..end].iter().copied());
+            },
+        }
+        mutant
+    }
+}
+
+impl fmt::Display for Mutation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Mutation::InsertNoop => "insert a no-op",
+            Mutation::InsertComment => "insert a comment",
+            Mutation::Delete => "delete an instruction",
+            Mutation::Insert => "insert an instruction",
+            Mutation::Replace => "replace an instruction",
+            Mutation::Swap => "swap two instructions",
+            Mutation::Duplicate => "duplicate a piece",
+        })
+    }
+}
+
+/// The ways the interpreter runs a program, which mutants are spread over.
+const ENGINES: [(&str, Dispatch, Option<u64>); 3] =
+    [("match", Dispatch::Match, None), ("threaded", Dispatch::Threaded, None), ("tiered", Dispatch::Threaded, Some(8))];
+
+const LEVELS: [OptLevel; 4] = [OptLevel::O0, OptLevel::O1, OptLevel::O2, OptLevel::O3];
+
+/// What went wrong with a mutant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The interpreter panicked, with the given message, instead of failing with an error.
+    Panic(String),
+    /// The mutant should do the same as the program, but it ended differently or printed something else.
+    Differs,
+}
+
+/// A mutant that found a problem, with how it ran.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub mutation: Mutation,
+    pub source: String,
+    pub input: Vec<u8>,
+    pub opt_level: OptLevel,
+    /// How the machine ran it: `match`, `threaded` or `tiered`.
+    pub engine: &'static str,
+    pub problem: Problem,
+}
+
+/// The result of fuzzing a program.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// Number of mutants run.
+    pub mutants: usize,
+    /// Mutants that did not parse, like those with unmatched brackets.
+    pub invalid: usize,
+    /// Mutants that failed with an error while running.
+    pub failed: usize,
+    /// Mutants that did not end within the step limit.
+    pub timed_out: usize,
+    pub findings: Vec<Finding>,
+}
+
+/// How a run ended, with the code of its error if it failed, and what it printed.
+type Run = (Result<(), &'static str>, Vec<u8>);
+
+/// Parses, optimizes and runs the source code, for at most `max_steps` instructions. Fails with the message of the
+/// panic if the interpreter panicked on the way.
+fn run(source: &[u8], input: &[u8], level: OptLevel, engine: usize, max_steps: u64) -> Result<Option<Run>, String> {
+    let (_, dispatch, tier_threshold) = ENGINES[engine];
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let Ok(program) = Program::parse(&String::from_utf8_lossy(source)) else {
+            return None;
+        };
+        let output = Buffer::default();
+        let mut machine = Machine::builder()
+            .dispatch(dispatch)
+            .tier_threshold(tier_threshold)
+            .max_steps(Some(max_steps))
+            .max_output(Some(MAX_OUTPUT))
+            .io(io::Cursor::new(input.to_vec()), output.clone())
+            .debug_output(io::sink())
+            .build();
+        let ended = machine.run(&optimize(&program, level)).map_err(|e| e.code());
+        let printed = output.0.borrow().clone();
+        Some((ended, printed))
+    }));
+    result.map_err(|payload| match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or("unknown panic", |m| m).to_string(),
+    })
+}
+
+/// Runs mutants of the program, every one with one mutation, on a random input and at a random optimization level
+/// and engine, and reports those on which the interpreter panics, and those which should do the same as the program
+/// but do not. Panics still go through the panic hook, which should be silenced not to print them.
+pub fn fuzz(source: &str, iterations: usize, rng: &mut Rng, max_steps: u64) -> Report {
+    let mut report = Report::default();
+    for _ in 0..iterations {
+        let mutation = MUTATIONS[rng.below(MUTATIONS.len() as u64) as usize];
+        let mutant = mutation.apply(source.as_bytes(), rng);
+        let len = rng.below(17) as usize;
+        let input: Vec<u8> = (0..len).map(|_| rng.next_u64() as u8).collect();
+        let level = LEVELS[rng.below(LEVELS.len() as u64) as usize];
+        let engine = rng.below(ENGINES.len() as u64) as usize;
+        report.mutants += 1;
+        let finding = |problem| Finding {
+            mutation,
+            source: String::from_utf8_lossy(&mutant).into_owned(),
+            input: input.clone(),
+            opt_level: level,
+            engine: ENGINES[engine].0,
+            problem,
+        };
+        let ran = match run(&mutant, &input, level, engine, max_steps) {
+            Ok(Some(ran)) => ran,
+            Ok(None) => {
+                report.invalid += 1;
+                continue;
+            },
+            Err(message) => {
+                report.findings.push(finding(Problem::Panic(message)));
+                continue;
+            },
+        };
+        // The unoptimized program, in the simplest engine, is the reference.
+        let timed_out = |run: &Run| run.0 == Err(BfError::StepLimit(max_steps).code());
+        if timed_out(&ran) {
+            report.timed_out += 1;
+        } else if ran.0.is_err() {
+            report.failed += 1;
+        }
+        if mutation.preserves() && !timed_out(&ran) {
+            if let Ok(Some(expected)) = run(source.as_bytes(), &input, OptLevel::O0, 0, max_steps) {
+                if !timed_out(&expected) && expected != ran {
+                    report.findings.push(finding(Problem::Differs));
+                }
+            }
+        }
+    }
+    report
+}
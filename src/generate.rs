@@ -0,0 +1,149 @@
+// Generation of random programs, which are always valid.
+// MIT license.
+
+/// A small pseudorandom number generator, xorshift64, which gives the same numbers for the same seed everywhere.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// A generator starting from the seed. Any seed works, zero too.
+    pub fn new(seed: u64) -> Rng {
+        // Xorshift gets stuck at zero, and similar seeds should not give similar numbers at first.
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number below `n`, which must not be zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+/// What the generator can do next, with how often it does it.
+#[derive(Clone, Copy, PartialEq)]
+enum Action {
+    Command(char),
+    Open,
+    Close,
+}
+
+const ACTIONS: [(Action, u64); 8] = [
+    (Action::Command('+'), 3),
+    (Action::Command('-'), 3),
+    (Action::Command('>'), 2),
+    (Action::Command('<'), 2),
+    (Action::Command('.'), 1),
+    (Action::Command(','), 1),
+    (Action::Open, 1),
+    (Action::Close, 1),
+];
+
+/// Loops a terminating program nests at most, so that it also ends soon: every loop runs up to 255 times with
+/// 8-bit cells, for every time the loop around it does.
+const TERMINATING_DEPTH: usize = 2;
+
+/// The state of a program being generated.
+struct Generator {
+    terminating: bool,
+    out: String,
+    /// Position of the pointer, from where it starts. Only known when loops leave the pointer where they found it.
+    pos: i64,
+    /// Positions of the loops not closed yet, from the outermost one.
+    loops: Vec<i64>,
+}
+
+impl Generator {
+    /// Instructions it takes to close every open loop from where the pointer is.
+    fn needed(&self) -> usize {
+        if !self.terminating {
+            return self.loops.len();
+        }
+        let mut pos = self.pos;
+        let mut needed = 0;
+        for &origin in self.loops.iter().rev() {
+            needed += pos.abs_diff(origin) as usize + 2;
+            pos = origin;
+        }
+        needed
+    }
+
+    /// Whether the action is allowed, and leaves enough instructions to close the loops after it.
+    fn allows(&self, action: Action, remaining: usize) -> bool {
+        let needed = self.needed();
+        let (cost, needed_after) = match action {
+            Action::Command(c) if self.terminating => {
+                match c {
+                    // The counters of the loops are only touched by the loops themselves.
+                    '+' | '-' | ',' if self.loops.contains(&self.pos) => return false,
+                    '<' if self.pos == 0 => return false,
+                    '<' | '>' => {
+                        let pos = if c == '<' { self.pos - 1 } else { self.pos + 1 };
+                        let Some(&origin) = self.loops.last() else { return true };
+                        (1, needed - self.pos.abs_diff(origin) as usize + pos.abs_diff(origin) as usize)
+                    },
+                    _ => (1, needed),
+                }
+            },
+            Action::Command(_) => (1, needed),
+            Action::Open if self.terminating && (self.loops.contains(&self.pos) || self.loops.len() == TERMINATING_DEPTH) => {
+                return false
+            },
+            Action::Open => (1, needed + if self.terminating { 2 } else { 1 }),
+            Action::Close if self.loops.last() != Some(&self.pos) && self.terminating => return false,
+            Action::Close if self.loops.is_empty() => return false,
+            Action::Close => (if self.terminating { 2 } else { 1 }, needed - if self.terminating { 2 } else { 1 }),
+        };
+        cost + needed_after <= remaining
+    }
+
+    fn apply(&mut self, action: Action) {
+        match action {
+            Action::Command(c) => {
+                self.out.push(c);
+                match c {
+                    '>' => self.pos += 1,
+                    '<' => self.pos -= 1,
+                    _ => (),
+                }
+            },
+            Action::Open => {
+                self.out.push('[');
+                self.loops.push(self.pos);
+            },
+            Action::Close => {
+                self.out.push_str(if self.terminating { "-]" } else { "]" });
+                self.loops.pop();
+            },
+        }
+    }
+}
+
+/// A random program of the given number of instructions, with balanced brackets. A terminating one ends whatever
+/// its input: every loop counts down a cell that nothing else in it touches and leaves the pointer where it found
+/// it, the pointer never moves left of where it starts, and loops are not nested deeper than two.
+pub fn random(size: usize, rng: &mut Rng, terminating: bool) -> String {
+    let mut generator = Generator { terminating, out: String::new(), pos: 0, loops: vec![] };
+    let mut remaining = size;
+    while remaining > 0 {
+        let allowed: Vec<_> = ACTIONS.iter().filter(|(a, _)| generator.allows(*a, remaining)).collect();
+        let total = allowed.iter().map(|(_, weight)| weight).sum();
+        let mut pick = rng.below(total);
+        let &&(action, _) = allowed
+            .iter()
+            .find(|(_, weight)| {
+                let found = pick < *weight;
+                pick = pick.saturating_sub(*weight);
+                found
+            })
+            .expect("the picked action is among the allowed ones");
+        generator.apply(action);
+        remaining -= if action == Action::Close && terminating { 2 } else { 1 };
+    }
+    generator.out
+}
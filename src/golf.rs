@@ -0,0 +1,213 @@
+// A superoptimizer for code golf, searching shorter code for every short piece of a program.
+// MIT license.
+
+use std::cmp::Reverse;
+
+use crate::generate::Rng;
+
+/// The instructions, in the order the search tries them.
+const INSTRUCTIONS: &[u8] = b"-+><.,[]";
+
+/// States every window and its candidates start from.
+const STATES: usize = 200;
+
+/// Instructions a window or a candidate may run from one state, so that loops that never end are stopped.
+const MAX_STEPS: usize = 2000;
+
+/// A piece of the program, and the shorter code doing the same.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Saving {
+    /// Line and column of the first instruction of the piece.
+    pub line: usize,
+    pub column: usize,
+    /// Instructions of the piece, without the comments between them.
+    pub original: String,
+    pub replacement: String,
+}
+
+impl Saving {
+    /// Bytes the replacement saves.
+    pub fn saved(&self) -> usize {
+        self.original.len() - self.replacement.len()
+    }
+}
+
+/// The memory around the pointer and the input a piece of code starts with.
+struct State {
+    tape: Vec<u8>,
+    input: Vec<u8>,
+}
+
+/// How a piece of code ended: where the pointer was, the tape, what it printed and how many bytes it read. `None`
+/// if the pointer left the tape of the state.
+#[derive(PartialEq)]
+struct Outcome(Option<(usize, Vec<u8>, Vec<u8>, usize)>);
+
+/// Runs the code from the state, with 8-bit cells and zero at the end of the input. `None` if it did not end.
+fn exec(code: &[u8], state: &State) -> Option<Outcome> {
+    let mut jumps = vec![0; code.len()];
+    let mut open = vec![];
+    for (i, &c) in code.iter().enumerate() {
+        match c {
+            b'[' => open.push(i),
+            b']' => {
+                let start = open.pop().expect("windows and candidates are balanced");
+                jumps[start] = i;
+                jumps[i] = start;
+            },
+            _ => (),
+        }
+    }
+    let mut tape = state.tape.clone();
+    let (mut ptr, mut pc, mut read, mut steps) = (tape.len() / 2, 0, 0, 0);
+    let mut output = vec![];
+    while pc < code.len() {
+        steps += 1;
+        if steps > MAX_STEPS {
+            return None;
+        }
+        match code[pc] {
+            b'+' => tape[ptr] = tape[ptr].wrapping_add(1),
+            b'-' => tape[ptr] = tape[ptr].wrapping_sub(1),
+            b'>' | b'<' => {
+                ptr = if code[pc] == b'>' { ptr + 1 } else { ptr.wrapping_sub(1) };
+                if ptr >= tape.len() {
+                    return Some(Outcome(None));
+                }
+            },
+            b'.' => output.push(tape[ptr]),
+            b',' => {
+                tape[ptr] = state.input.get(read).copied().unwrap_or(0);
+                read = (read + 1).min(state.input.len());
+            },
+            b'[' if tape[ptr] == 0 => pc = jumps[pc],
+            b']' if tape[ptr] != 0 => pc = jumps[pc],
+            _ => (),
+        }
+        pc += 1;
+    }
+    Some(Outcome(Some((ptr, tape, output, read))))
+}
+
+/// Random states for windows of the given length: cells with small values, or values next to the wrapping point,
+/// or any value, far enough on both sides for the pointer to move the whole window.
+fn states(window: usize) -> Vec<State> {
+    let mut rng = Rng::new(0);
+    let cells = 2 * window + 17;
+    let mut states = vec![State { tape: vec![0; cells], input: vec![] }];
+    while states.len() < STATES {
+        let mut value = || match rng.below(4) {
+            0 => 0,
+            1 => rng.below(4) as u8,
+            2 => 0u8.wrapping_sub(rng.below(3) as u8 + 1),
+            _ => rng.next_u64() as u8,
+        };
+        let tape = (0..cells).map(|_| value()).collect();
+        let input = (0..window).map(|_| value()).collect();
+        states.push(State { tape, input });
+    }
+    states
+}
+
+/// Looks for the shortest code doing the same as the window, shorter than it. Only candidates made of the kinds of
+/// instructions the window has are tried: code without output can not gain some, for instance. A window that does
+/// not end within the step limit from some state is not replaced, since nothing tells what it does from there.
+fn search(window: &[u8], states: &[State]) -> Option<Vec<u8>> {
+    let expected: Vec<Outcome> = states.iter().map(|s| exec(window, s)).collect::<Option<_>>()?;
+    let has = |c: &[u8]| c.iter().any(|c| window.contains(c));
+    let alphabet: Vec<u8> = INSTRUCTIONS
+        .iter()
+        .copied()
+        .filter(|&c| match c {
+            b'+' | b'-' | b'>' | b'<' => true,
+            b'[' | b']' => has(b"[]"),
+            _ => has(&[c]),
+        })
+        .collect();
+    // A candidate not ending within the step limit is rejected, like one ending differently.
+    let same = |candidate: &[u8]| states.iter().zip(&expected).all(|(state, outcome)| exec(candidate, state).as_ref() == Some(outcome));
+    (0..window.len()).find_map(|len| {
+        let mut candidate = Vec::with_capacity(len);
+        enumerate(&mut candidate, len, 0, &alphabet, &same)
+    })
+}
+
+/// Tries every candidate of the given length starting with the ones so far, with balanced brackets and without
+/// pieces that obviously do nothing, like `+-`, or never end, like `[]`.
+fn enumerate(candidate: &mut Vec<u8>, len: usize, depth: usize, alphabet: &[u8], same: &impl Fn(&[u8]) -> bool) -> Option<Vec<u8>> {
+    if candidate.len() == len {
+        return if depth == 0 && same(candidate) { Some(candidate.clone()) } else { None };
+    }
+    for &c in alphabet {
+        let wasted = matches!((candidate.last(), c), (Some(b'+'), b'-') | (Some(b'-'), b'+') | (Some(b'>'), b'<') | (Some(b'<'), b'>') | (Some(b'['), b']'));
+        let depth = match c {
+            b'[' => depth + 1,
+            b']' if depth == 0 => continue,
+            b']' => depth - 1,
+            _ => depth,
+        };
+        if wasted || depth > len - candidate.len() - 1 {
+            continue;
+        }
+        candidate.push(c);
+        let found = enumerate(candidate, len, depth, alphabet, same);
+        candidate.pop();
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+/// Whether the brackets of the code match.
+fn balanced(code: &[u8]) -> bool {
+    let mut depth = 0usize;
+    for &c in code {
+        match c {
+            b'[' => depth += 1,
+            b']' if depth == 0 => return false,
+            b']' => depth -= 1,
+            _ => (),
+        }
+    }
+    depth == 0
+}
+
+/// Looks for shorter code doing the same as every piece of up to `window` instructions of the program, with
+/// balanced brackets, from its start. Candidates are checked by running them and the piece from a couple hundred
+/// random tapes and inputs, with 8-bit cells, so they are very likely equivalent but not proven to be. Pieces that
+/// run out of steps from one of them are left as they are. The pieces that are replaced do not overlap.
+pub fn golf(source: &str, window: usize) -> Vec<Saving> {
+    let mut code = vec![];
+    let mut locations = vec![];
+    for (n, line) in source.lines().enumerate() {
+        for (column, c) in line.chars().enumerate() {
+            if c.is_ascii() && INSTRUCTIONS.contains(&(c as u8)) {
+                code.push(c as u8);
+                locations.push((n + 1, column + 1));
+            }
+        }
+    }
+    let states: Vec<Vec<State>> = (0..=window).map(states).collect();
+    let mut savings = vec![];
+    let mut i = 0;
+    while i < code.len() {
+        // The piece starting here whose replacement saves most, the shortest one of them.
+        let best = (2..=window.min(code.len() - i))
+            .filter(|&len| balanced(&code[i..i + len]))
+            .filter_map(|len| search(&code[i..i + len], &states[len]).map(|replacement| (len, replacement)))
+            .max_by_key(|(len, replacement)| (len - replacement.len(), Reverse(*len)));
+        match best {
+            Some((len, replacement)) => {
+                // What the replacement starts with like the piece is not part of the change.
+                let same = code[i..i + len].iter().zip(&replacement).take_while(|(a, b)| a == b).count();
+                let (line, column) = locations[i + same];
+                let text = |code: &[u8]| String::from_utf8_lossy(code).into_owned();
+                savings.push(Saving { line, column, original: text(&code[i + same..i + len]), replacement: text(&replacement[same..]) });
+                i += len;
+            },
+            None => i += 1,
+        }
+    }
+    savings
+}
@@ -0,0 +1,458 @@
+// A Jupyter kernel, running the cells of a notebook on a persistent machine.
+// MIT license.
+
+mod hmac;
+mod zmq;
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, prelude::*};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use brainfuck::{BfError, Machine, MachineBuilder, Program};
+
+use crate::signal;
+use zmq::Socket;
+
+/// Version of the messaging protocol.
+const PROTOCOL_VERSION: &str = "5.3";
+
+/// Separates the routing identities from the rest of a message.
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+/// Output is sent to the notebook at every line, or once this many bytes are waiting.
+const STREAM_BUFFER: usize = 4096;
+
+/// The connection file Jupyter gives to the kernel.
+#[derive(Deserialize)]
+struct ConnectionInfo {
+    transport: String,
+    ip: String,
+    shell_port: u16,
+    iopub_port: u16,
+    stdin_port: u16,
+    control_port: u16,
+    hb_port: u16,
+    #[serde(default)]
+    key: String,
+    #[serde(default)]
+    signature_scheme: String,
+}
+
+/// Optimizes the program of a cell.
+pub type Optimize<'a> = &'a dyn Fn(&Program) -> Result<Program, Box<dyn Error>>;
+
+/// Runs the kernel with the connection file, on a machine built from the builder, until the notebook shuts it down.
+pub fn run(connection_file: &Path, builder: MachineBuilder, optimize: Optimize) -> Result<(), Box<dyn Error>> {
+    let text = fs::read_to_string(connection_file)
+        .map_err(|e| format!("Can not read {}: {}", connection_file.display(), e))?;
+    let info: ConnectionInfo = serde_json::from_str(&text)?;
+    if info.transport != "tcp" {
+        return Err(format!("Unsupported transport '{}', only tcp is", info.transport).into());
+    }
+    if !info.key.is_empty() && info.signature_scheme != "hmac-sha256" {
+        return Err(format!("Unsupported signature scheme '{}', only hmac-sha256 is", info.signature_scheme).into());
+    }
+    let bind = |port: u16| TcpListener::bind((info.ip.as_str(), port));
+    let (requests, received) = mpsc::channel();
+    let iopub = Arc::new(Mutex::new(vec![]));
+    let stdin = Arc::new(Mutex::new(None));
+    forward(bind(info.shell_port)?, requests.clone());
+    forward(bind(info.control_port)?, requests);
+    subscribers(bind(info.iopub_port)?, Arc::clone(&iopub));
+    prompts(bind(info.stdin_port)?, Arc::clone(&stdin));
+    heartbeat(bind(info.hb_port)?);
+    log::info!("Kernel listening on {}", info.ip);
+
+    let session = Rc::new(Session::new(info.key.into_bytes(), iopub, stdin));
+    let machine = builder
+        .io(Input { session: Rc::clone(&session), buffer: VecDeque::new() }, Stream::new(&session, "stdout"))
+        .debug_output(Stream::new(&session, "stderr"))
+        .build();
+    let mut kernel = Kernel { session, machine, optimize, execution_count: 0 };
+    for (socket, frames) in received {
+        let Some(message) = kernel.session.parse(frames) else { continue };
+        match kernel.handle(&socket, message) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(e) => log::warn!("Failed to answer a message: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Accepts the connections of a ROUTER socket, and sends their messages down the channel with the connection to
+/// answer them on.
+fn forward(listener: TcpListener, requests: Sender<(Socket, Vec<Vec<u8>>)>) {
+    accept(listener, "ROUTER", move |socket| {
+        let requests = requests.clone();
+        thread::spawn(move || -> io::Result<()> {
+            let mut reader = socket.try_clone()?;
+            loop {
+                let frames = reader.recv()?;
+                if requests.send((socket.try_clone()?, frames)).is_err() {
+                    return Ok(());
+                }
+            }
+        });
+    });
+}
+
+/// Accepts the connections of the PUB socket, keeping them to publish to, and dropping what they send, which are
+/// only their subscriptions.
+fn subscribers(listener: TcpListener, iopub: Arc<Mutex<Vec<Socket>>>) {
+    accept(listener, "PUB", move |socket| {
+        if let Ok(mut reader) = socket.try_clone() {
+            thread::spawn(move || while reader.recv().is_ok() {});
+        }
+        iopub.lock().expect("a publisher panicked").push(socket);
+    });
+}
+
+/// Accepts the connections of the stdin socket, keeping the last one to ask for input on.
+fn prompts(listener: TcpListener, stdin: Arc<Mutex<Option<Socket>>>) {
+    accept(listener, "ROUTER", move |socket| *stdin.lock().expect("the kernel panicked") = Some(socket));
+}
+
+/// Accepts the connections of the heartbeat socket, and sends back everything they send.
+fn heartbeat(listener: TcpListener) {
+    accept(listener, "REP", |mut socket| {
+        thread::spawn(move || -> io::Result<()> {
+            loop {
+                let frames = socket.recv()?;
+                socket.send(&frames.iter().map(Vec::as_slice).collect::<Vec<_>>())?;
+            }
+        });
+    });
+}
+
+/// Accepts the connections to the listener in a thread, calling the function with every one once it is set up.
+fn accept(listener: TcpListener, socket_type: &'static str, mut connected: impl FnMut(Socket) + Send + 'static) {
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream.and_then(|stream| Socket::accept(stream, socket_type)) {
+                Ok(socket) => connected(socket),
+                Err(e) => log::warn!("Failed to accept a connection: {}", e),
+            }
+        }
+    });
+}
+
+/// A message of the protocol.
+struct Message {
+    /// Where the replies go.
+    ids: Vec<Vec<u8>>,
+    header: Value,
+    content: Value,
+}
+
+/// The signing key and the sockets shared by everything sending messages, and the request being answered.
+struct Session {
+    key: Vec<u8>,
+    id: String,
+    iopub: Arc<Mutex<Vec<Socket>>>,
+    stdin: Arc<Mutex<Option<Socket>>>,
+    /// Header of the request being answered, the parent of every message sent meanwhile.
+    parent: RefCell<Value>,
+    /// Whether the request allows asking for input.
+    allow_stdin: Cell<bool>,
+    /// Number of messages sent, for their ids.
+    sent: Cell<u64>,
+}
+
+impl Session {
+    fn new(key: Vec<u8>, iopub: Arc<Mutex<Vec<Socket>>>, stdin: Arc<Mutex<Option<Socket>>>) -> Session {
+        let since = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let id = format!("{:x}-{:x}", since.as_nanos(), std::process::id());
+        Session { key, id, iopub, stdin, parent: RefCell::new(json!({})), allow_stdin: Cell::new(false), sent: Cell::new(0) }
+    }
+
+    /// Parses the frames of a message, checking its signature.
+    fn parse(&self, frames: Vec<Vec<u8>>) -> Option<Message> {
+        let Some(delimiter) = frames.iter().position(|frame| frame == DELIMITER) else {
+            log::warn!("Dropping a message without delimiter");
+            return None;
+        };
+        let [signature, header, parent, metadata, content, ..] = &frames[delimiter + 1..] else {
+            log::warn!("Dropping a message with missing parts");
+            return None;
+        };
+        if !self.key.is_empty() && hmac::hmac_sha256(&self.key, &[header, parent, metadata, content]).as_bytes() != signature {
+            log::warn!("Dropping a message with an invalid signature");
+            return None;
+        }
+        match (serde_json::from_slice(header), serde_json::from_slice(content)) {
+            (Ok(header), Ok(content)) => Some(Message { ids: frames[..delimiter].to_vec(), header, content }),
+            _ => {
+                log::warn!("Dropping a message that is not valid JSON");
+                None
+            },
+        }
+    }
+
+    /// The frames of a message of the type, a child of the request being answered.
+    fn frames(&self, ids: &[Vec<u8>], msg_type: &str, content: &Value) -> Vec<Vec<u8>> {
+        self.sent.set(self.sent.get() + 1);
+        let header = json!({
+            "msg_id": format!("{}-{}", self.id, self.sent.get()),
+            "session": self.id,
+            "username": "kernel",
+            "date": now(),
+            "msg_type": msg_type,
+            "version": PROTOCOL_VERSION,
+        });
+        let parts = [header.to_string(), self.parent.borrow().to_string(), "{}".to_string(), content.to_string()];
+        let parts: Vec<&[u8]> = parts.iter().map(|part| part.as_bytes()).collect();
+        let signature = if self.key.is_empty() { String::new() } else { hmac::hmac_sha256(&self.key, &parts) };
+        let mut frames = ids.to_vec();
+        frames.push(DELIMITER.to_vec());
+        frames.push(signature.into_bytes());
+        frames.extend(parts.iter().map(|part| part.to_vec()));
+        frames
+    }
+
+    /// Sends a message on the socket.
+    fn send(&self, socket: &Socket, ids: &[Vec<u8>], msg_type: &str, content: Value) -> io::Result<()> {
+        let frames = self.frames(ids, msg_type, &content);
+        socket.send(&frames.iter().map(Vec::as_slice).collect::<Vec<_>>())
+    }
+
+    /// Sends a message to every subscriber, forgetting the ones that went away.
+    fn publish(&self, msg_type: &str, content: Value) {
+        let topic = format!("kernel.{}.{}", self.id, msg_type).into_bytes();
+        let frames = self.frames(&[topic], msg_type, &content);
+        let frames: Vec<&[u8]> = frames.iter().map(Vec::as_slice).collect();
+        self.iopub.lock().expect("a subscriber panicked").retain(|socket| socket.send(&frames).is_ok());
+    }
+
+    /// Asks the notebook for a line of input, if the request allows it. Returns `None` when there is no way to ask.
+    fn input(&self) -> io::Result<Option<String>> {
+        let stdin = self.stdin.lock().expect("a prompt panicked");
+        let Some(socket) = stdin.as_ref().filter(|_| self.allow_stdin.get()) else { return Ok(None) };
+        self.send(socket, &[], "input_request", json!({ "prompt": "", "password": false }))?;
+        let mut reader = socket.try_clone()?;
+        loop {
+            let Some(reply) = self.parse(reader.recv()?) else { continue };
+            if reply.header["msg_type"] == "input_reply" {
+                return Ok(Some(reply.content["value"].as_str().unwrap_or_default().to_string()));
+            }
+        }
+    }
+}
+
+/// The kernel state: the machine the cells run on, one after the other.
+struct Kernel<'a> {
+    session: Rc<Session>,
+    machine: Machine,
+    optimize: Optimize<'a>,
+    execution_count: u64,
+}
+
+impl Kernel<'_> {
+    /// Answers a request on the socket it came from. Returns `false` once asked to shut down.
+    fn handle(&mut self, socket: &Socket, message: Message) -> io::Result<bool> {
+        let msg_type = message.header["msg_type"].as_str().unwrap_or_default().to_string();
+        log::debug!("Received {}", msg_type);
+        *self.session.parent.borrow_mut() = message.header.clone();
+        self.session.publish("status", json!({ "execution_state": "busy" }));
+        let reply = match msg_type.as_str() {
+            "kernel_info_request" => Some(kernel_info()),
+            "execute_request" => Some(self.execute(&message.content)),
+            "is_complete_request" => {
+                let status = match Program::parse(message.content["code"].as_str().unwrap_or_default()) {
+                    Ok(_) => "complete",
+                    Err(BfError::UnmatchedOpen(_)) => "incomplete",
+                    Err(_) => "invalid",
+                };
+                Some(json!({ "status": status, "indent": "" }))
+            },
+            "complete_request" => {
+                let cursor = message.content["cursor_pos"].clone();
+                Some(json!({ "status": "ok", "matches": [], "cursor_start": cursor, "cursor_end": cursor, "metadata": {} }))
+            },
+            "inspect_request" => Some(json!({ "status": "ok", "found": false, "data": {}, "metadata": {} })),
+            "history_request" => Some(json!({ "status": "ok", "history": [] })),
+            "comm_info_request" => Some(json!({ "status": "ok", "comms": {} })),
+            "shutdown_request" => Some(json!({ "status": "ok", "restart": message.content["restart"] })),
+            _ => {
+                log::debug!("Ignoring {}", msg_type);
+                None
+            },
+        };
+        if let Some(content) = reply {
+            self.session.send(socket, &message.ids, &msg_type.replace("_request", "_reply"), content)?;
+        }
+        self.session.publish("status", json!({ "execution_state": "idle" }));
+        Ok(msg_type != "shutdown_request")
+    }
+
+    /// Runs the code of a cell, and returns the content of the reply.
+    fn execute(&mut self, content: &Value) -> Value {
+        let code = content["code"].as_str().unwrap_or_default();
+        if !content["silent"].as_bool().unwrap_or(false) {
+            self.execution_count += 1;
+        }
+        self.session.allow_stdin.set(content["allow_stdin"].as_bool().unwrap_or(false));
+        self.session.publish("execute_input", json!({ "code": code, "execution_count": self.execution_count }));
+        // A Ctrl-C from the notebook stops a single cell.
+        signal::INTERRUPTED.store(false, Ordering::Relaxed);
+        let result = Program::parse(code)
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|program| (self.optimize)(&program))
+            .and_then(|program| Ok(self.machine.run(&program)?));
+        match result {
+            Ok(()) => json!({ "status": "ok", "execution_count": self.execution_count, "user_expressions": {}, "payload": [] }),
+            Err(e) => {
+                let error = json!({ "ename": "BfError", "evalue": e.to_string(), "traceback": [e.to_string()] });
+                self.session.publish("error", error.clone());
+                let mut reply = json!({ "status": "error", "execution_count": self.execution_count });
+                reply.as_object_mut().expect("the reply is an object").extend(error.as_object().cloned().unwrap_or_default());
+                reply
+            },
+        }
+    }
+}
+
+/// The content of the reply to `kernel_info_request`.
+fn kernel_info() -> Value {
+    json!({
+        "status": "ok",
+        "protocol_version": PROTOCOL_VERSION,
+        "implementation": "brainfuck",
+        "implementation_version": env!("CARGO_PKG_VERSION"),
+        "language_info": {
+            "name": "brainfuck",
+            "version": "",
+            "mimetype": "text/x-brainfuck",
+            "file_extension": ".b",
+        },
+        "banner": "Brainfuck, on a machine that persists between the cells",
+        "help_links": [],
+    })
+}
+
+/// Output of the program, sent to the notebook as a stream.
+struct Stream {
+    session: Rc<Session>,
+    name: &'static str,
+    buffer: Vec<u8>,
+}
+
+impl Stream {
+    fn new(session: &Rc<Session>, name: &'static str) -> Stream {
+        Stream { session: Rc::clone(session), name, buffer: vec![] }
+    }
+
+    /// Sends the given number of bytes of the buffer.
+    fn send(&mut self, len: usize) {
+        let text = String::from_utf8_lossy(&self.buffer[..len]).into_owned();
+        self.buffer.drain(..len);
+        self.session.publish("stream", json!({ "name": self.name, "text": text }));
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= STREAM_BUFFER {
+            self.send(self.buffer.len());
+        } else if let Some(end) = self.buffer.iter().rposition(|&b| b == b'\n') {
+            self.send(end + 1);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.send(self.buffer.len());
+        }
+        Ok(())
+    }
+}
+
+/// Input of the program, asked to the notebook a line at a time.
+struct Input {
+    session: Rc<Session>,
+    buffer: VecDeque<u8>,
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.buffer.is_empty() {
+            match self.session.input()? {
+                Some(line) => self.buffer.extend(line.bytes().chain([b'\n'])),
+                None => return Ok(0),
+            }
+        }
+        self.buffer.read(buf)
+    }
+}
+
+/// Writes the kernel specification, so that Jupyter lists the kernel, and returns the directory it is in.
+pub fn install() -> Result<PathBuf, Box<dyn Error>> {
+    let dir = data_dir().ok_or("Can not find the Jupyter data directory, set JUPYTER_DATA_DIR")?.join("kernels").join("brainfuck");
+    fs::create_dir_all(&dir)?;
+    let spec = json!({
+        "argv": [env::current_exe()?, "jupyter-kernel", "-f", "{connection_file}"],
+        "display_name": "Brainfuck",
+        "language": "brainfuck",
+        "interrupt_mode": "signal",
+    });
+    fs::write(dir.join("kernel.json"), serde_json::to_string_pretty(&spec)?)?;
+    Ok(dir)
+}
+
+/// The directory of the Jupyter data of the user.
+fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("JUPYTER_DATA_DIR") {
+        return Some(dir.into());
+    }
+    if cfg!(windows) {
+        return env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("jupyter"));
+    }
+    let home = PathBuf::from(env::var_os("HOME")?);
+    if cfg!(target_os = "macos") {
+        Some(home.join("Library").join("Jupyter"))
+    } else {
+        Some(env::var_os("XDG_DATA_HOME").map_or_else(|| home.join(".local").join("share"), PathBuf::from).join("jupyter"))
+    }
+}
+
+/// The current time in ISO 8601, in UTC.
+fn now() -> String {
+    let since = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let (days, seconds) = ((since.as_secs() / 86_400) as i64, since.as_secs() % 86_400);
+    // The civil calendar from the days since the epoch, in eras of 400 years starting on March 1st.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let m = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * m + 2) / 5 + 1;
+    let month = if m < 10 { m + 3 } else { m - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+        since.subsec_micros()
+    )
+}
@@ -0,0 +1,125 @@
+// The few parts of ZeroMQ the Jupyter protocol needs: ZMTP 3.0 connections with the NULL mechanism.
+// MIT license.
+
+use std::io::{self, prelude::*};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+/// The frame is followed by more frames of the same message.
+const MORE: u8 = 0x01;
+/// The size of the frame takes 8 bytes instead of 1.
+const LONG: u8 = 0x02;
+/// The frame is a command, not part of a message.
+const COMMAND: u8 = 0x04;
+
+/// Frames larger than this are refused.
+const MAX_FRAME: u64 = 64 << 20;
+
+/// One side of a ZMTP connection, from a peer that connected to the kernel.
+pub struct Socket {
+    reader: TcpStream,
+    /// Shared by the clones, which can write from other threads.
+    writer: Arc<Mutex<TcpStream>>,
+}
+
+impl Socket {
+    /// Exchanges the greeting and the READY commands with a peer that just connected, announcing the socket type.
+    pub fn accept(stream: TcpStream, socket_type: &str) -> io::Result<Socket> {
+        let mut socket = Socket { reader: stream.try_clone()?, writer: Arc::new(Mutex::new(stream)) };
+        // Signature, version 3.0, the NULL mechanism padded to 20 bytes, not as server, and the filler.
+        let mut greeting = vec![0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0x7f, 3, 0];
+        greeting.extend_from_slice(b"NULL");
+        greeting.resize(64, 0);
+        socket.write(&greeting)?;
+        let mut peer = [0u8; 64];
+        socket.reader.read_exact(&mut peer)?;
+        if peer[0] != 0xff || peer[9] != 0x7f || peer[10] < 3 || &peer[12..16] != b"NULL" {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the peer does not speak ZMTP 3 with no security"));
+        }
+        let mut ready = b"\x05READY\x0bSocket-Type".to_vec();
+        ready.extend_from_slice(&(socket_type.len() as u32).to_be_bytes());
+        ready.extend_from_slice(socket_type.as_bytes());
+        socket.write(&frame(COMMAND, &ready))?;
+        let (flags, _) = socket.read_frame()?;
+        if flags & COMMAND == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the peer did not send its READY command"));
+        }
+        Ok(socket)
+    }
+
+    /// Another handle to the same connection.
+    pub fn try_clone(&self) -> io::Result<Socket> {
+        Ok(Socket { reader: self.reader.try_clone()?, writer: Arc::clone(&self.writer) })
+    }
+
+    /// Receives the frames of the next message, answering the heartbeats of the connection on the way.
+    pub fn recv(&mut self) -> io::Result<Vec<Vec<u8>>> {
+        let mut frames = vec![];
+        loop {
+            let (flags, body) = self.read_frame()?;
+            if flags & COMMAND != 0 {
+                // A PING has a TTL of 2 bytes before the context the PONG gives back.
+                if let Some(context) = body.strip_prefix(b"\x04PING").and_then(|rest| rest.get(2..)) {
+                    let mut pong = b"\x04PONG".to_vec();
+                    pong.extend_from_slice(context);
+                    self.write(&frame(COMMAND, &pong))?;
+                }
+                continue;
+            }
+            frames.push(body);
+            if flags & MORE == 0 {
+                return Ok(frames);
+            }
+        }
+    }
+
+    /// Sends a message made of the frames.
+    pub fn send(&self, frames: &[&[u8]]) -> io::Result<()> {
+        let mut data = vec![];
+        for (i, body) in frames.iter().enumerate() {
+            let flags = if i + 1 < frames.len() { MORE } else { 0 };
+            data.extend(frame(flags, body));
+        }
+        self.write(&data)
+    }
+
+    fn write(&self, data: &[u8]) -> io::Result<()> {
+        let mut writer = self.writer.lock().expect("a writer panicked");
+        writer.write_all(data)?;
+        writer.flush()
+    }
+
+    fn read_frame(&mut self) -> io::Result<(u8, Vec<u8>)> {
+        let mut flags = [0u8];
+        self.reader.read_exact(&mut flags)?;
+        let size = if flags[0] & LONG != 0 {
+            let mut size = [0u8; 8];
+            self.reader.read_exact(&mut size)?;
+            u64::from_be_bytes(size)
+        } else {
+            let mut size = [0u8];
+            self.reader.read_exact(&mut size)?;
+            size[0] as u64
+        };
+        if size > MAX_FRAME {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {} bytes is too large", size)));
+        }
+        let mut body = vec![0u8; size as usize];
+        self.reader.read_exact(&mut body)?;
+        Ok((flags[0], body))
+    }
+}
+
+/// Encodes a frame with the flags, choosing the size field.
+fn frame(flags: u8, body: &[u8]) -> Vec<u8> {
+    let mut data = vec![];
+    match u8::try_from(body.len()) {
+        Ok(size) => data.extend([flags, size]),
+        Err(_) => {
+            data.push(flags | LONG);
+            data.extend_from_slice(&(body.len() as u64).to_be_bytes());
+        },
+    }
+    data.extend_from_slice(body);
+    data
+}
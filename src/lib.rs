@@ -0,0 +1,30 @@
+// Very simple Brainfuck interpreter.
+// MIT license.
+
+pub mod analysis;
+pub mod asm;
+pub mod ast;
+pub mod bytecode;
+pub mod cfg;
+pub mod codegen;
+pub mod convert;
+pub mod decompile;
+pub mod equiv;
+pub mod error;
+pub mod explain;
+pub mod format;
+pub mod fuzz;
+pub mod generate;
+pub mod golf;
+pub mod machine;
+pub mod optimize;
+pub mod profile;
+pub mod program;
+pub mod stats;
+pub mod symexec;
+pub mod transpile;
+
+pub use error::BfError;
+pub use machine::{CellFormat, CellWidth, DebugFormat, Dispatch, Eof, Machine, MachineBuilder, Observer, Snapshot, Tape};
+pub use optimize::OptLevel;
+pub use program::{Dialect, FileOp, Instr, Op, Program, Span};
@@ -0,0 +1,84 @@
+// Diagnostics logger.
+// MIT license.
+
+use std::io::{self, IsTerminal, prelude::*};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap::ValueEnum;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// When to use colors in diagnostics.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Only if the standard error is a terminal.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Whether the level tags are colored.
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Minimal logger that writes every enabled record to the standard error.
+struct StderrLogger;
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let level = record.level().as_str().to_lowercase();
+            if COLOR.load(Ordering::Relaxed) {
+                let code = match record.level() {
+                    Level::Error => 31,
+                    Level::Warn => 33,
+                    Level::Info => 32,
+                    Level::Debug => 36,
+                    Level::Trace => 35,
+                };
+                eprintln!("\x1b[{}m[{}]\x1b[0m {}", code, level, record.args());
+            } else {
+                eprintln!("[{}] {}", level, record.args());
+            }
+        }
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+/// Installs the stderr logger with the level given by the command line flags.
+pub fn init(verbose: u8, quiet: bool, color: ColorChoice) {
+    let level = if quiet {
+        LevelFilter::Error
+    } else {
+        match verbose {
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    };
+    set_color(color);
+    if log::set_logger(&StderrLogger).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// Whether diagnostics use colors.
+pub fn colored() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+/// Changes when to use colors.
+pub fn set_color(color: ColorChoice) {
+    let enabled = match color {
+        ColorChoice::Auto => io::stderr().is_terminal(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+    COLOR.store(enabled, Ordering::Relaxed);
+}
@@ -0,0 +1,261 @@
+// A language server for editors, over the standard input and output.
+// MIT license.
+
+use std::collections::HashMap;
+use std::io::{self, prelude::*};
+
+use serde_json::{json, Value};
+
+use brainfuck::{BfError, Program};
+
+/// The error code of requests for methods the server does not have.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// A loop of a document.
+struct Loop {
+    /// Byte positions of the brackets.
+    open: usize,
+    close: usize,
+    /// Number of loops it is in, counting itself.
+    depth: usize,
+    /// How far every iteration moves the pointer, if it is always the same.
+    shift: Option<isize>,
+}
+
+/// A document open in the editor.
+struct Document {
+    text: String,
+    /// The loops, in the order of their closing brackets.
+    loops: Vec<Loop>,
+}
+
+impl Document {
+    fn new(text: String) -> Document {
+        let loops = loops(&text);
+        Document { text, loops }
+    }
+
+    /// The innermost loop containing the position.
+    fn loop_at(&self, pos: usize) -> Option<&Loop> {
+        self.loops.iter().filter(|l| l.open <= pos && pos <= l.close).max_by_key(|l| l.depth)
+    }
+
+    /// The loop of the bracket at the position, or else just before it, where the cursor is after typing it.
+    fn bracket_at(&self, pos: usize) -> Option<&Loop> {
+        let is_bracket = |l: &Loop, pos: usize| l.open == pos || l.close == pos;
+        self.loops
+            .iter()
+            .find(|l| is_bracket(l, pos))
+            .or_else(|| self.loops.iter().find(|l| pos > 0 && is_bracket(l, pos - 1)))
+    }
+
+    /// Converts a position of the protocol, a line and a column in UTF-16 code units, to a byte position.
+    fn offset(&self, position: &Value) -> Option<usize> {
+        let line = position["line"].as_u64()? as usize;
+        let character = position["character"].as_u64()? as usize;
+        let start = match line {
+            0 => 0,
+            _ => self.text.match_indices('\n').nth(line - 1)?.0 + 1,
+        };
+        let mut units = 0;
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= character || c == '\n' {
+                return Some(start + i);
+            }
+            units += c.len_utf16();
+        }
+        Some(self.text.len())
+    }
+
+    /// Converts a byte position to a position of the protocol.
+    fn position(&self, pos: usize) -> Value {
+        let before = &self.text[..pos];
+        let start = before.rfind('\n').map_or(0, |i| i + 1);
+        json!({ "line": before.matches('\n').count(), "character": before[start..].encode_utf16().count() })
+    }
+
+    /// The range of the single byte at the position.
+    fn range(&self, pos: usize) -> Value {
+        json!({ "start": self.position(pos), "end": self.position(pos + 1) })
+    }
+
+    /// The line of a byte position, starting at 0.
+    fn line(&self, pos: usize) -> usize {
+        self.text[..pos].matches('\n').count()
+    }
+}
+
+/// Finds the loops of the source code, skipping unmatched brackets, which are common while typing.
+fn loops(text: &str) -> Vec<Loop> {
+    let mut loops = vec![];
+    let mut stack: Vec<(usize, Option<isize>)> = vec![];
+    for (pos, b) in text.bytes().enumerate() {
+        match (b, stack.last_mut()) {
+            (b'>', Some((_, shift))) => *shift = shift.map(|s| s + 1),
+            (b'<', Some((_, shift))) => *shift = shift.map(|s| s - 1),
+            (b'[', _) => stack.push((pos, Some(0))),
+            (b']', Some(_)) => {
+                let (open, shift) = stack.pop().expect("a loop is open");
+                // The pointer is anywhere after a nested loop that moves it.
+                if let Some((_, parent)) = stack.last_mut().filter(|_| shift != Some(0)) {
+                    *parent = None;
+                }
+                loops.push(Loop { open, close: pos, depth: stack.len() + 1, shift });
+            },
+            _ => (),
+        }
+    }
+    loops
+}
+
+/// Describes a loop, for the hover.
+fn describe(l: &Loop) -> String {
+    let shift = match l.shift {
+        Some(0) => "Every iteration leaves the pointer where it started.".to_string(),
+        Some(n) => format!(
+            "Every iteration moves the pointer {} cell{} to the {}.",
+            n.unsigned_abs(),
+            if n.abs() == 1 { "" } else { "s" },
+            if n > 0 { "right" } else { "left" }
+        ),
+        None => "How far every iteration moves the pointer depends on the cells.".to_string(),
+    };
+    format!("Loop depth {}\n\n{}", l.depth, shift)
+}
+
+/// The server state: the open documents.
+struct Server<W: Write> {
+    output: W,
+    documents: HashMap<String, Document>,
+}
+
+impl<W: Write> Server<W> {
+    /// Answers a request, returning its result, or `None` for methods the server does not have.
+    fn request(&self, method: &str, params: &Value) -> Option<Value> {
+        let document = || self.documents.get(params["textDocument"]["uri"].as_str().unwrap_or_default());
+        let at = || document().and_then(|d| Some((d, d.offset(&params["position"])?)));
+        Some(match method {
+            "initialize" => json!({
+                "capabilities": {
+                    // Whole documents are sent on every change, they are small.
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "documentHighlightProvider": true,
+                    "foldingRangeProvider": true,
+                },
+                "serverInfo": { "name": "brainfuck", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => Value::Null,
+            "textDocument/documentHighlight" => match at().and_then(|(d, pos)| Some((d, d.bracket_at(pos)?))) {
+                Some((d, l)) => json!([{ "range": d.range(l.open), "kind": 1 }, { "range": d.range(l.close), "kind": 1 }]),
+                None => json!([]),
+            },
+            "textDocument/hover" => match at().and_then(|(d, pos)| Some((d, d.loop_at(pos)?))) {
+                Some((d, l)) => json!({
+                    "contents": { "kind": "markdown", "value": describe(l) },
+                    "range": { "start": d.position(l.open), "end": d.position(l.close + 1) },
+                }),
+                None => Value::Null,
+            },
+            "textDocument/foldingRange" => match document() {
+                Some(d) => d
+                    .loops
+                    .iter()
+                    .map(|l| (d.line(l.open), d.line(l.close)))
+                    .filter(|(start, end)| start < end)
+                    .map(|(start, end)| json!({ "startLine": start, "endLine": end }))
+                    .collect(),
+                None => json!([]),
+            },
+            _ => return None,
+        })
+    }
+
+    /// Handles a notification. Returns `false` when asked to exit.
+    fn notify(&mut self, method: &str, params: &Value) -> io::Result<bool> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        let text = match method {
+            "exit" => return Ok(false),
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            "textDocument/didChange" => params["contentChanges"].as_array().and_then(|c| c.last()?["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                None
+            },
+            _ => None,
+        };
+        if let Some(text) = text {
+            let document = Document::new(text.to_string());
+            self.publish_diagnostics(&uri, &document)?;
+            self.documents.insert(uri, document);
+        }
+        Ok(true)
+    }
+
+    /// Reports the unmatched brackets of the document.
+    fn publish_diagnostics(&mut self, uri: &str, document: &Document) -> io::Result<()> {
+        let diagnostics = match Program::parse(&document.text) {
+            Err(e @ (BfError::UnmatchedOpen(pos) | BfError::UnmatchedClose(pos))) => {
+                json!([{ "range": document.range(pos), "severity": 1, "source": "brainfuck", "message": e.to_string() }])
+            },
+            _ => json!([]),
+        };
+        let params = json!({ "uri": uri, "diagnostics": diagnostics });
+        self.send(json!({ "jsonrpc": "2.0", "method": "textDocument/publishDiagnostics", "params": params }))
+    }
+
+    fn send(&mut self, message: Value) -> io::Result<()> {
+        let body = message.to_string();
+        write!(self.output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        self.output.flush()
+    }
+}
+
+/// Reads the next message, or `None` at the end of the input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut len = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                len = value.trim().parse().ok();
+            }
+        }
+    }
+    let len = len.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "message without Content-Length"))?;
+    let mut body = vec![0; len];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// Runs the server on the input and the output until the editor makes it exit.
+pub fn serve(mut input: impl BufRead, output: impl Write) -> io::Result<()> {
+    let mut server = Server { output, documents: HashMap::new() };
+    while let Some(message) = read_message(&mut input)? {
+        // Responses to requests of the server have no method, and it makes none.
+        let Some(method) = message["method"].as_str() else { continue };
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            if !server.notify(method, params)? {
+                break;
+            }
+            continue;
+        };
+        let response = match server.request(method, params) {
+            Some(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            None => {
+                let error = json!({ "code": METHOD_NOT_FOUND, "message": format!("Unknown method {}", method) });
+                json!({ "jsonrpc": "2.0", "id": id, "error": error })
+            },
+        };
+        server.send(response)?;
+    }
+    Ok(())
+}
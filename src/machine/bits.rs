@@ -0,0 +1,233 @@
+// A machine with cells of one bit, for Boolfuck and Smallfuck.
+// MIT license.
+
+use std::io::{self, prelude::*};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::BfError;
+use crate::program::{Op, Program};
+
+use super::NEVER;
+
+/// Number of cells in a word of the tape.
+const WORD_BITS: i64 = 64;
+
+/// Cells of one bit, packed 64 to a word, growing in both directions as they are written to. The cells that were
+/// never written to are zero.
+#[derive(Debug, Clone, Default)]
+struct Bits {
+    /// The words of the cells from zero on.
+    right: Vec<u64>,
+    /// The words of the cells left of zero, from -1 on.
+    left: Vec<u64>,
+}
+
+impl Bits {
+    /// The words holding the cell, and the index of its word and of its bit there.
+    fn locate(cell: i64) -> (bool, usize, u32) {
+        let (left, n) = if cell < 0 { (true, -cell - 1) } else { (false, cell) };
+        (left, (n / WORD_BITS) as usize, (n % WORD_BITS) as u32)
+    }
+
+    fn get(&self, cell: i64) -> bool {
+        let (left, word, bit) = Bits::locate(cell);
+        let words = if left { &self.left } else { &self.right };
+        words.get(word).is_some_and(|w| w >> bit & 1 == 1)
+    }
+
+    fn set(&mut self, cell: i64, value: bool) {
+        let (left, word, bit) = Bits::locate(cell);
+        let words = if left { &mut self.left } else { &mut self.right };
+        if word >= words.len() {
+            if !value {
+                return;
+            }
+            words.resize(word + 1, 0);
+        }
+        words[word] = words[word] & !(1 << bit) | (value as u64) << bit;
+    }
+}
+
+/// A machine running Boolfuck, whose cells are bits: `+` flips the current one, `,` reads a bit of the input and `;`
+/// prints one, from the least significant bit of every byte. The tape is unbounded in both directions, and takes a
+/// bit per cell. With a bounded tape, it runs Smallfuck, whose programs end by leaving the tape.
+pub struct BitMachine {
+    tape: Bits,
+    ptr: i64,
+    /// Number of cells of a bounded tape.
+    len: Option<usize>,
+    /// The cells the pointer went to, from the leftmost to the rightmost one.
+    visited: (i64, i64),
+    max_steps: Option<u64>,
+    max_output: Option<u64>,
+    interrupt: &'static AtomicBool,
+    steps: u64,
+    at: usize,
+    /// The bits of the byte being read, and how many of them are left.
+    reading: (u8, u32),
+    /// The bits of the byte being printed, and how many of them there are.
+    writing: (u8, u32),
+    written: u64,
+}
+
+impl BitMachine {
+    /// A machine with every cell at zero.
+    pub fn new() -> BitMachine {
+        BitMachine {
+            tape: Bits::default(),
+            ptr: 0,
+            len: None,
+            visited: (0, 0),
+            max_steps: None,
+            max_output: None,
+            interrupt: &NEVER,
+            steps: 0,
+            at: 0,
+            reading: (0, 0),
+            writing: (0, 0),
+            written: 0,
+        }
+    }
+
+    /// A machine with a tape of the given number of cells, from zero, for Smallfuck. Moving the pointer off the tape
+    /// ends the program.
+    pub fn bounded(len: usize) -> BitMachine {
+        // All the cells are shown.
+        BitMachine { len: Some(len), visited: (0, len as i64 - 1), ..BitMachine::new() }
+    }
+
+    /// Sets the maximum number of instructions every run executes before failing, or no limit.
+    pub fn max_steps(mut self, max_steps: Option<u64>) -> BitMachine {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Sets the maximum number of bytes every run prints before failing, or no limit.
+    pub fn max_output(mut self, max_output: Option<u64>) -> BitMachine {
+        self.max_output = max_output;
+        self
+    }
+
+    /// Sets a flag that stops the program once set, like [`crate::MachineBuilder::interrupt`].
+    pub fn interrupt(mut self, flag: &'static AtomicBool) -> BitMachine {
+        self.interrupt = flag;
+        self
+    }
+
+    pub fn pointer(&self) -> i64 {
+        self.ptr
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Index of the instruction the current run is at, or the last run stopped at.
+    pub fn instruction(&self) -> usize {
+        self.at
+    }
+
+    /// The cells from the leftmost one the pointer went to, to the rightmost one, or those of the bounded tape.
+    pub fn cells(&self) -> Vec<bool> {
+        (self.visited.0..=self.visited.1).map(|cell| self.tape.get(cell)).collect()
+    }
+
+    /// Runs a program of the boolfuck or smallfuck dialect, as parsed: instructions from the optimizations of byte cells, like
+    /// the bytes printed ahead of time, are not supported. The bits printed last are completed with zeros to a
+    /// byte at the end.
+    pub fn run(&mut self, program: &Program, mut input: impl Read, mut output: impl Write) -> Result<(), BfError> {
+        self.steps = 0;
+        self.written = 0;
+        self.reading = (0, 0);
+        self.writing = (0, 0);
+        let mut i = 0;
+        while let Some(&op) = program.ops.get(i) {
+            self.at = i;
+            if self.max_steps == Some(self.steps) {
+                output.flush()?;
+                return Err(BfError::StepLimit(self.steps));
+            }
+            self.steps += 1;
+            let mut next = i + 1;
+            let bit = self.tape.get(self.ptr);
+            match op {
+                Op::Move(n) => {
+                    self.ptr += n as i64;
+                    if self.len.is_some_and(|len| !(0..len as i64).contains(&self.ptr)) {
+                        break;
+                    }
+                    self.visited = (self.visited.0.min(self.ptr), self.visited.1.max(self.ptr));
+                },
+                Op::Add(n) => self.tape.set(self.ptr, bit ^ (n & 1 == 1)),
+                Op::In => {
+                    let bit = self.read_bit(&mut input)?;
+                    self.tape.set(self.ptr, bit);
+                },
+                Op::Out => self.write_bit(bit, &mut output)?,
+                Op::Open(close) if !bit => next = close + 1,
+                Op::Close(open) if bit => {
+                    if self.interrupt.load(Ordering::Relaxed) {
+                        output.flush()?;
+                        return Err(BfError::Interrupted);
+                    }
+                    next = open + 1;
+                },
+                Op::Open(_) | Op::Close(_) => (),
+                op => return Err(BfError::InvalidBytecode(format!("'{}' can not run on cells of one bit", op))),
+            }
+            i = next;
+        }
+        if self.writing.1 > 0 {
+            self.flush_byte(&mut output)?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+
+    /// Reads the next bit of the input, or a zero at its end.
+    fn read_bit(&mut self, input: &mut impl Read) -> Result<bool, BfError> {
+        if self.reading.1 == 0 {
+            let mut byte = [0u8];
+            loop {
+                match input.read(&mut byte) {
+                    Ok(0) => return Ok(false),
+                    Ok(_) => break,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            self.reading = (byte[0], 8);
+        }
+        let (byte, left) = self.reading;
+        self.reading = (byte >> 1, left - 1);
+        Ok(byte & 1 == 1)
+    }
+
+    /// Adds a bit to the byte being printed, printing it once it is complete.
+    fn write_bit(&mut self, bit: bool, output: &mut impl Write) -> Result<(), BfError> {
+        let (byte, len) = self.writing;
+        self.writing = (byte | (bit as u8) << len, len + 1);
+        if self.writing.1 == 8 {
+            self.flush_byte(output)?;
+        }
+        Ok(())
+    }
+
+    /// Prints the byte being printed, checking the output limit.
+    fn flush_byte(&mut self, output: &mut impl Write) -> Result<(), BfError> {
+        if Some(self.written) == self.max_output {
+            output.flush()?;
+            return Err(BfError::OutputLimit(self.written));
+        }
+        self.written += 1;
+        output.write_all(&[self.writing.0])?;
+        self.writing = (0, 0);
+        Ok(())
+    }
+}
+
+impl Default for BitMachine {
+    fn default() -> BitMachine {
+        BitMachine::new()
+    }
+}
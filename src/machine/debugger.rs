@@ -0,0 +1,519 @@
+// The interactive debugger.
+// MIT license.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, prelude::*};
+use std::mem;
+use std::str::FromStr;
+
+use crate::error::BfError;
+use crate::program::Program;
+
+use super::{CellFormat, DebugFormat, Machine};
+
+/// Number of cells shown around the pointer at every stop.
+const STOP_CELLS: usize = 32;
+
+/// Number of changed cells listed after a step, at most.
+const CHANGED_CELLS: usize = 16;
+
+/// Number of cells shown by `x` without a length.
+const EXAMINE_CELLS: usize = 64;
+
+const HELP: &str = "\
+Commands:
+  (empty), s, step     run the next instruction, and list the cells it changed
+  c, continue          run up to the next breakpoint
+  b, break I [if C]    stop before the instruction at index I, if the condition C holds, like cell[3] == 10
+  b, break L:C [if C]  stop before the instruction from line L, column C of the source code, which can follow a
+                       file name, like program.b:12:7
+  w, watch N           stop when the value of the cell at position N changes
+  b, break             list the breakpoints and watchpoints
+  d, delete N          delete the breakpoint or watchpoint number N
+  dump                 print where the program is, the number of steps and all the cells in use
+  x [START] [LEN]      print LEN cells (64 by default) from START as a hexdump, around the pointer by default
+  eval CODE            run the Brainfuck code on the machine, from the pointer
+  eval --scratch CODE  run the code on a copy of the memory, which is left as it was
+  help                 print this list
+Conditions compare cell[N], cell (at the pointer) or ptr to a number, with ==, !=, <, <=, > or >=.
+";
+
+/// The state of the debugger of a machine.
+#[derive(Default)]
+pub(super) struct Debugger {
+    /// The breakpoints and watchpoints, by number. Deleted ones are left empty, to keep the numbers of the others.
+    breakpoints: Vec<Option<Breakpoint>>,
+    /// Whether the program runs up to the next breakpoint, instead of stopping after every instruction.
+    running: bool,
+    /// Commands to run before reading the standard input.
+    script: VecDeque<String>,
+    /// The memory when stepping, to show what the step changed at the next stop.
+    snapshot: Option<Vec<u32>>,
+}
+impl Debugger {
+    /// A debugger that runs the given commands first. Empty lines and comments, starting with `#`, are skipped.
+    pub(super) fn with_script(script: Vec<String>) -> Debugger {
+        let script = script.into_iter().filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#')).collect();
+        Debugger { script, ..Debugger::default() }
+    }
+
+    /// Returns the next command of the script, or else prints the prompt and reads one from the standard input.
+    /// Returns `None` at the end of the input.
+    fn command(&mut self) -> Result<Option<String>, BfError> {
+        if let Some(line) = self.script.pop_front() {
+            eprintln!("(debug) {}", line.trim());
+            return Ok(Some(line));
+        }
+        let mut stderr = io::stderr();
+        // The cursor should stay at the end of the prompt.
+        write!(stderr, "(debug) ")?;
+        stderr.flush()?;
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            eprintln!();
+            return Ok(None);
+        }
+        Ok(Some(line))
+    }
+}
+
+/// A way to stop the program.
+enum Breakpoint {
+    /// Before the instruction at the index, if the condition holds.
+    At { index: usize, condition: Option<Condition> },
+    /// When the cell at the position no longer has the value it had at the last stop.
+    Watch { cell: usize, value: u32 },
+}
+
+/// Where a breakpoint stops.
+enum Target {
+    /// The instruction at the index.
+    Index(usize),
+    /// The instruction from the line and column of the source code.
+    Location(usize, usize),
+}
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Target, String> {
+        let invalid = || format!("Invalid breakpoint '{}', expected an instruction index or [FILE:]LINE:COLUMN", s);
+        // The file name, as in the locations given by editors, is only there for convenience.
+        let parts: Vec<&str> = s.rsplitn(3, ':').collect();
+        match parts.as_slice() {
+            [index] => Ok(Target::Index(index.parse().map_err(|_| invalid())?)),
+            [column, line, ..] => Ok(Target::Location(line.parse().map_err(|_| invalid())?, column.parse().map_err(|_| invalid())?)),
+            [] => Err(invalid()),
+        }
+    }
+}
+
+/// What a condition of a breakpoint looks at.
+#[derive(Clone, Copy)]
+enum Operand {
+    /// The cell at the given position.
+    Cell(usize),
+    /// The cell at the pointer.
+    Current,
+    /// The position of the pointer.
+    Pointer,
+}
+
+/// A comparison of an operand to a number, like `cell[3] == 10`.
+struct Condition {
+    operand: Operand,
+    comparison: &'static str,
+    value: u64,
+}
+impl Condition {
+    fn holds(&self, machine: &Machine) -> bool {
+        let actual = match self.operand {
+            Operand::Cell(n) => machine.memory.get(n).map_or(0, |&c| c as u64),
+            Operand::Current => machine.memory[machine.ptr] as u64,
+            Operand::Pointer => machine.ptr as u64,
+        };
+        match self.comparison {
+            "==" => actual == self.value,
+            "!=" => actual != self.value,
+            "<" => actual < self.value,
+            "<=" => actual <= self.value,
+            ">" => actual > self.value,
+            ">=" => actual >= self.value,
+            _ => unreachable!("only valid comparisons are parsed"),
+        }
+    }
+}
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Condition, String> {
+        let invalid = || format!("Invalid condition '{}', expected something like cell[3] == 10", s.trim());
+        // The two-character comparisons first, so that `<=` is not taken for `<`.
+        let (at, comparison) = ["==", "!=", "<=", ">=", "<", ">"]
+            .iter()
+            .filter_map(|&c| s.find(c).map(|at| (at, c)))
+            .min_by_key(|&(at, c)| (at, usize::MAX - c.len()))
+            .ok_or_else(invalid)?;
+        let left: String = s[..at].split_whitespace().collect();
+        let operand = match left.as_str() {
+            "cell" => Operand::Current,
+            "ptr" => Operand::Pointer,
+            _ => {
+                let n = left.strip_prefix("cell[").and_then(|l| l.strip_suffix(']')).ok_or_else(invalid)?;
+                Operand::Cell(n.parse().map_err(|_| invalid())?)
+            },
+        };
+        let value = s[at + comparison.len()..].trim().parse().map_err(|_| invalid())?;
+        Ok(Condition { operand, comparison, value })
+    }
+}
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.operand {
+            Operand::Cell(n) => write!(f, "cell[{}]", n)?,
+            Operand::Current => write!(f, "cell")?,
+            Operand::Pointer => write!(f, "ptr")?,
+        }
+        write!(f, " {} {}", self.comparison, self.value)
+    }
+}
+
+/// A command typed at the prompt of the debugger.
+enum Command {
+    /// Run the next instruction.
+    Step,
+    /// Run up to the next breakpoint.
+    Continue,
+    /// Add a breakpoint.
+    Break(Target, Option<Condition>),
+    /// Add a watchpoint on the cell at the position.
+    Watch(usize),
+    /// List the breakpoints and watchpoints.
+    List,
+    /// Delete the breakpoint or watchpoint with the given number, starting at 1.
+    Delete(usize),
+    /// Print the whole state.
+    Dump,
+    /// Run code on the machine, or on a copy of its memory if `scratch` is set.
+    Eval { code: String, scratch: bool },
+    /// Print `len` cells from `start`, or around the pointer, as a hexdump.
+    Examine { start: Option<usize>, len: usize },
+    Help,
+}
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Command, String> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words.as_slice() {
+            [] | ["s" | "step"] => Ok(Command::Step),
+            ["c" | "continue"] => Ok(Command::Continue),
+            ["b" | "break"] => Ok(Command::List),
+            ["b" | "break", target, rest @ ..] => {
+                let target = target.parse()?;
+                let condition = match rest {
+                    [] => None,
+                    ["if", ..] => {
+                        let (_, condition) = s.split_once("if").expect("the condition follows an if");
+                        Some(condition.parse()?)
+                    },
+                    _ => return Err("Usage: break INDEX [if CONDITION]".to_string()),
+                };
+                Ok(Command::Break(target, condition))
+            },
+            ["w" | "watch", cell] => {
+                let cell = cell.strip_prefix("cell[").and_then(|c| c.strip_suffix(']')).unwrap_or(cell);
+                Ok(Command::Watch(cell.parse().map_err(|_| format!("Invalid cell position '{}'", cell))?))
+            },
+            ["dump"] => Ok(Command::Dump),
+            ["d" | "delete", n] => Ok(Command::Delete(n.parse().map_err(|_| format!("Invalid breakpoint number '{}'", n))?)),
+            ["x", args @ ..] if args.len() <= 2 => {
+                let numbers: Vec<usize> = args
+                    .iter()
+                    .map(|a| a.parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| "Usage: x [START] [LEN], with the position and the length as numbers".to_string())?;
+                Ok(Command::Examine { start: numbers.first().copied(), len: numbers.get(1).copied().unwrap_or(EXAMINE_CELLS) })
+            },
+            ["eval", "--scratch", ..] => {
+                let (_, code) = s.split_once("--scratch").expect("the code follows the option");
+                Ok(Command::Eval { code: code.trim().to_string(), scratch: true })
+            },
+            ["eval", ..] => {
+                let (_, code) = s.split_once("eval").expect("the code follows the command");
+                Ok(Command::Eval { code: code.trim().to_string(), scratch: false })
+            },
+            ["help" | "h"] => Ok(Command::Help),
+            _ => Err(format!("Unknown command '{}', enter help for the list", s.trim())),
+        }
+    }
+}
+
+impl Machine {
+    /// Stops before the next instruction, printing the state and reading commands until one resumes the program. At
+    /// the end of the commands, the program runs to its end.
+    pub(super) fn debug_stop(&mut self, program: &Program, current: usize, next: usize) -> Result<(), BfError> {
+        if self.debugger.running {
+            let Some(reason) = self.triggered(next) else { return Ok(()) };
+            self.debugger.running = false;
+            self.output.flush()?;
+            eprintln!("\n{}", reason);
+        } else {
+            self.output.flush()?;
+        }
+        // The program stops before the next instruction, for the reports.
+        self.at = next;
+        // Watchpoints compare with the values at the last stop.
+        for breakpoint in self.debugger.breakpoints.iter_mut().flatten() {
+            if let Breakpoint::Watch { cell, value } = breakpoint {
+                *value = self.memory.get(*cell).copied().unwrap_or_default();
+            }
+        }
+        eprintln!("\nCurrent: {}, next: {}", program.ops[current], program.ops[next]);
+        match self.debugger.snapshot.take() {
+            Some(before) => self.print_changes(&before),
+            None => self.print_state(),
+        }
+        loop {
+            let Some(line) = self.debugger.command()? else {
+                self.debug = false;
+                return Ok(());
+            };
+            match line.parse() {
+                Ok(Command::Step) => {
+                    self.debugger.snapshot = Some(self.memory.to_vec());
+                    return Ok(());
+                },
+                Ok(Command::Continue) => {
+                    self.debugger.running = true;
+                    return Ok(());
+                },
+                Ok(Command::Break(target, condition)) => {
+                    let index = match target {
+                        Target::Index(index) if index < program.len() => index,
+                        Target::Index(index) => {
+                            eprintln!("No instruction at index {}, the program has {}", index, program.len());
+                            continue;
+                        },
+                        Target::Location(line, column) => match program.instruction_at(line, column) {
+                            Some(index) => index,
+                            None => {
+                                eprintln!("No instruction at {}:{}, it may be a comment or optimized away", line, column);
+                                continue;
+                            },
+                        },
+                    };
+                    self.debugger.breakpoints.push(Some(Breakpoint::At { index, condition }));
+                    eprintln!("Breakpoint {} set at instruction {}", self.debugger.breakpoints.len(), describe(program, index));
+                },
+                Ok(Command::Watch(cell)) if cell >= self.memory.len() => {
+                    eprintln!("No cell at position {}, the memory has {}", cell, self.memory.len());
+                },
+                Ok(Command::Watch(cell)) => {
+                    self.debugger.breakpoints.push(Some(Breakpoint::Watch { cell, value: self.memory[cell] }));
+                    eprintln!("Watchpoint {} set on cell[{}]", self.debugger.breakpoints.len(), cell);
+                },
+                Ok(Command::List) => {
+                    for (n, breakpoint) in self.debugger.breakpoints.iter().enumerate() {
+                        let Some(breakpoint) = breakpoint else { continue };
+                        match breakpoint {
+                            Breakpoint::At { index, condition: Some(condition) } => {
+                                eprintln!("{}: instruction {} if {}", n + 1, describe(program, *index), condition);
+                            },
+                            Breakpoint::At { index, condition: None } => eprintln!("{}: instruction {}", n + 1, describe(program, *index)),
+                            Breakpoint::Watch { cell, .. } => eprintln!("{}: watch cell[{}]", n + 1, cell),
+                        }
+                    }
+                },
+                Ok(Command::Dump) => {
+                    let used = self.memory.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1);
+                    eprint!("{}", self.report(program));
+                    eprint!("{}", self.hexdump(0, used.max(1)));
+                },
+                Ok(Command::Delete(n)) => match self.debugger.breakpoints.get_mut(n.wrapping_sub(1)).and_then(Option::take) {
+                    Some(_) => eprintln!("Deleted {}", n),
+                    None => eprintln!("No breakpoint or watchpoint number {}", n),
+                },
+                Ok(Command::Examine { start, len }) => {
+                    let start = start.unwrap_or_else(|| self.centered(len));
+                    eprint!("{}", self.hexdump(start, len));
+                },
+                Ok(Command::Eval { code, scratch }) => {
+                    match self.eval(&code, scratch) {
+                        // After the output of the code, if any.
+                        Ok(()) => eprintln!(),
+                        Err(e) => eprintln!("\nEvaluation failed: {}", e),
+                    }
+                    self.print_state();
+                },
+                Ok(Command::Help) => eprint!("{}", HELP),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    }
+
+    /// Describes why the running program should stop before the instruction at the index, if it should.
+    fn triggered(&self, next: usize) -> Option<String> {
+        self.debugger.breakpoints.iter().enumerate().find_map(|(n, breakpoint)| match *breakpoint.as_ref()? {
+            Breakpoint::At { index, ref condition } if index == next && condition.as_ref().is_none_or(|c| c.holds(self)) => {
+                Some(format!("Breakpoint {} at instruction {}", n + 1, next))
+            },
+            Breakpoint::Watch { cell, value } if self.memory[cell] != value => {
+                let (old, new) = (self.cell_format.show(value), self.show(cell));
+                Some(format!("Watchpoint {}: cell[{}] changed from {} to {}", n + 1, cell, old, new))
+            },
+            _ => None,
+        })
+    }
+
+    /// Runs code in the middle of the program, on the same machine. With `scratch`, the memory and the pointer are
+    /// restored afterwards, but not what the code printed. The run starts like a fresh one, so everything else it
+    /// resets is restored, from the channel to the threads the program forked.
+    fn eval(&mut self, code: &str, scratch: bool) -> Result<(), BfError> {
+        let program = Program::parse(code)?;
+        let saved = (mem::take(&mut self.stack), self.steps, self.written, self.at, self.started);
+        let threads = (mem::take(&mut self.threads), self.read, self.ticks, self.slice_end, self.channel);
+        let memory = if scratch { Some((self.memory.to_vec(), self.ptr)) } else { None };
+        self.debug = false;
+        let result = self.run(&program);
+        self.debug = true;
+        (self.stack, self.steps, self.written, self.at, self.started) = saved;
+        let channel;
+        (self.threads, self.read, self.ticks, self.slice_end, channel) = threads;
+        if let Some((memory, ptr)) = memory {
+            self.memory.copy_from_slice(&memory);
+            self.ptr = ptr;
+        }
+        result.and(self.select(channel))
+    }
+
+    /// Prints the pointer and the cells around it.
+    fn print_state(&self) {
+        eprintln!("Ptr: {}, value: {}", self.ptr, self.show(self.ptr));
+        eprint!("{}", self.hexdump(self.centered(STOP_CELLS), STOP_CELLS));
+    }
+
+    /// Prints the state for the `#` instruction, in the chosen format.
+    pub(super) fn print_debug(&mut self) -> Result<(), BfError> {
+        let out = match self.debug_format {
+            DebugFormat::Cell => format!("Ptr: {}, value: {}\n", self.ptr, self.show(self.ptr)),
+            DebugFormat::Window(len) => format!(
+                "Ptr: {}, value: {}\n{}",
+                self.ptr,
+                self.show(self.ptr),
+                self.hexdump(self.centered(len), len)
+            ),
+            DebugFormat::Json => {
+                let used = self.memory.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1);
+                let memory: Vec<String> = self.memory[..used].iter().map(u32::to_string).collect();
+                format!(
+                    "{{\"instruction\":{},\"steps\":{},\"pointer\":{},\"value\":{},\"loop_depth\":{},\"memory\":[{}]}}\n",
+                    self.at,
+                    self.steps,
+                    self.ptr,
+                    self.memory[self.ptr],
+                    self.stack.len(),
+                    memory.join(",")
+                )
+            },
+        };
+        // The program output comes first, when both go to the same place.
+        self.output.flush()?;
+        self.debug_output.write_all(out.as_bytes())?;
+        self.debug_output.flush()?;
+        Ok(())
+    }
+
+    /// Prints the pointer and the cells that changed since the memory was the given one, with their old and new
+    /// values.
+    fn print_changes(&self, before: &[u32]) {
+        eprintln!("Ptr: {}, value: {}", self.ptr, self.show(self.ptr));
+        let mut changed = (0..self.memory.len()).filter(|&i| before[i] != self.memory[i]);
+        let shown: Vec<usize> = changed.by_ref().take(CHANGED_CELLS).collect();
+        if shown.is_empty() {
+            eprintln!("No cells changed");
+        }
+        for i in shown {
+            eprintln!("  cell[{}]: {} -> {}", i, self.cell_format.show(before[i]), self.show(i));
+        }
+        let more = changed.count();
+        if more > 0 {
+            eprintln!("  and {} more", more);
+        }
+    }
+
+    /// Number of cells per row of the hexdumps.
+    pub(super) fn row_len(&self) -> usize {
+        if self.mask == u8::MAX as u32 { 16 } else { 8 }
+    }
+
+    /// The first cell of a hexdump of `len` cells around the pointer, at the start of a row.
+    pub(super) fn centered(&self, len: usize) -> usize {
+        let start = self.ptr.saturating_sub(len / 2).min(self.memory.len().saturating_sub(len));
+        start - start % self.row_len()
+    }
+
+    /// Formats `len` cells from `start` in the classic hexdump layout: the position of the first cell of every row,
+    /// the values in hexadecimal and the characters they stand for. The cell at the pointer is in brackets.
+    pub(super) fn hexdump(&self, start: usize, len: usize) -> String {
+        let digits = self.mask.count_ones() as usize / 4;
+        let row_len = self.row_len();
+        let width = self.memory.len().to_string().len();
+        let end = start.saturating_add(len).min(self.memory.len());
+        let mut out = String::new();
+        for row in (start..end).step_by(row_len) {
+            let cells = &self.memory[row..(row + row_len).min(end)];
+            // The separators around every cell, with a wider one in the middle of the row.
+            let mut separators: Vec<Vec<char>> = (0..=cells.len())
+                .map(|i| if i == 0 || i == cells.len() || i == row_len / 2 { vec![' ', ' '] } else { vec![' '] })
+                .collect();
+            if (row..row + cells.len()).contains(&self.ptr) {
+                let i = self.ptr - row;
+                *separators[i].last_mut().expect("separators are not empty") = '[';
+                separators[i + 1][0] = ']';
+            }
+            out.push_str(&format!("{:>width$}", row, width = width));
+            for (i, &cell) in cells.iter().enumerate() {
+                out.extend(&separators[i]);
+                out.push_str(&self.hexdump_cell(cell, digits));
+            }
+            out.extend(&separators[cells.len()]);
+            // Short rows keep the characters aligned with the full ones.
+            let missing = row_len - cells.len();
+            let gap = usize::from(cells.len() <= row_len / 2 && missing > 0);
+            out.push_str(&" ".repeat(missing * (digits + 1) + gap));
+            let text: String = cells
+                .iter()
+                .map(|&c| match u8::try_from(c) {
+                    Ok(b) if b.is_ascii_graphic() || b == b' ' => b as char,
+                    _ => '.',
+                })
+                .collect();
+            out.push_str(&format!("|{}|\n", text));
+        }
+        out
+    }
+
+    /// Formats a cell of a hexdump in hexadecimal, or else as a character, right-aligned, when showing characters
+    /// and it fits.
+    fn hexdump_cell(&self, cell: u32, digits: usize) -> String {
+        if self.cell_format == CellFormat::Char {
+            let text = match char::from_u32(cell) {
+                Some(c) if c == ' ' || c.is_ascii_graphic() => Some(c.to_string()),
+                Some('\0') => Some("\\0".to_string()),
+                Some(c @ ('\t' | '\n' | '\r')) => Some(c.escape_default().to_string()),
+                _ => None,
+            };
+            if let Some(text) = text {
+                return format!("{:>digits$}", text, digits = digits);
+            }
+        }
+        format!("{:0digits$x}", cell, digits = digits)
+    }
+}
+
+/// Describes the instruction at the index, with its source location.
+fn describe(program: &Program, index: usize) -> String {
+    let (line, column) = program.location(program.spans[index].start);
+    format!("{} ({}) at {}:{}", index, program.ops[index], line, column)
+}
@@ -0,0 +1,91 @@
+// The files of the fs dialect.
+// MIT license.
+
+use std::fs::File;
+use std::io::{self, prelude::*};
+
+use crate::error::BfError;
+use crate::program::FileOp;
+
+use super::Machine;
+
+/// A file the program opened, buffered.
+pub(super) enum OpenFile {
+    Read(io::BufReader<File>),
+    Write(io::BufWriter<File>),
+}
+
+impl Machine {
+    /// Runs a file instruction, on the file whose descriptor is in the current cell. Descriptors start at one, so
+    /// that zero tells that a file could not be opened.
+    pub(super) fn file(&mut self, op: FileOp) -> Result<(), BfError> {
+        if self.files.is_none() {
+            return Err(BfError::FilesDenied);
+        }
+        let fd = self.memory[self.ptr];
+        match op {
+            FileOp::OpenRead | FileOp::OpenWrite => {
+                let start = self.ptr + 1;
+                let Some(len) = self.memory.get(start..).and_then(|name| name.iter().position(|&c| c == 0)) else {
+                    return Err(BfError::PointerOverflow(self.memory.len() as i64));
+                };
+                let name: Vec<u8> = self.memory[start..start + len].iter().map(|&c| c as u8).collect();
+                let name = String::from_utf8_lossy(&name).into_owned();
+                let opened = match op {
+                    FileOp::OpenRead => File::open(&name).map(|f| OpenFile::Read(io::BufReader::new(f))),
+                    _ => File::create(&name).map(|f| OpenFile::Write(io::BufWriter::new(f))),
+                };
+                let files = self.files.as_mut().expect("checked above");
+                let slot = files.iter().position(Option::is_none).unwrap_or(files.len());
+                self.memory[self.ptr] = match opened {
+                    // Descriptors that do not fit in the cell can not be used.
+                    Ok(file) if (slot as u32) < self.mask => {
+                        if slot == files.len() {
+                            files.push(None);
+                        }
+                        files[slot] = Some(file);
+                        slot as u32 + 1
+                    },
+                    Ok(_) => 0,
+                    Err(e) => {
+                        log::debug!("Can not open {}: {}", name, e);
+                        0
+                    },
+                };
+            },
+            FileOp::Close => {
+                match self.slot(fd).and_then(Option::take) {
+                    Some(OpenFile::Write(mut writer)) => writer.flush()?,
+                    Some(OpenFile::Read(_)) => (),
+                    None => return Err(BfError::BadFile(fd)),
+                }
+                self.memory[self.ptr] = 0;
+            },
+            FileOp::Read => {
+                let target = self.offset(1)?;
+                let Some(OpenFile::Read(reader)) = self.slot(fd).and_then(Option::as_mut) else {
+                    return Err(BfError::BadFile(fd));
+                };
+                let mut byte = [0u8];
+                let byte = match reader.read(&mut byte)? {
+                    0 => None,
+                    _ => Some(byte[0]),
+                };
+                self.store(target, byte);
+            },
+            FileOp::Write => {
+                let value = self.memory[self.offset(1)?] as u8;
+                let Some(OpenFile::Write(writer)) = self.slot(fd).and_then(Option::as_mut) else {
+                    return Err(BfError::BadFile(fd));
+                };
+                writer.write_all(&[value])?;
+            },
+        }
+        Ok(())
+    }
+
+    /// The slot of the file with the descriptor, if there is one, empty once the file is closed.
+    fn slot(&mut self, fd: u32) -> Option<&mut Option<OpenFile>> {
+        self.files.as_mut()?.get_mut((fd as usize).checked_sub(1)?)
+    }
+}
@@ -0,0 +1,138 @@
+// A machine with a fixed tape and no allocation, for embedded targets.
+// MIT license.
+
+use core::ops::ControlFlow;
+
+use super::Eof;
+use crate::error::BfError;
+
+/// A machine with a tape of `N` byte cells held in place, in an array, which allocates nothing: it runs the source
+/// code as it is, without parsing it to instructions first, and reads and writes through functions. It only needs
+/// `core`, apart from its error type, so it fits in a static on microcontrollers. It has no limits, extensions or
+/// debug mode.
+pub struct FixedMachine<const N: usize> {
+    tape: [u8; N],
+    ptr: usize,
+    eof: Eof,
+}
+
+impl<const N: usize> FixedMachine<N> {
+    /// A machine with every cell at zero, storing a zero when the input is exhausted.
+    pub const fn new() -> FixedMachine<N> {
+        const { assert!(N > 0, "the tape needs at least one cell") };
+        FixedMachine { tape: [0; N], ptr: 0, eof: Eof::Zero }
+    }
+
+    /// Sets what `,` stores when the input is exhausted.
+    pub const fn eof(mut self, eof: Eof) -> FixedMachine<N> {
+        self.eof = eof;
+        self
+    }
+
+    pub fn tape(&self) -> &[u8; N] {
+        &self.tape
+    }
+
+    pub fn ptr(&self) -> usize {
+        self.ptr
+    }
+
+    /// Sets every cell and the pointer back to zero.
+    pub fn reset(&mut self) {
+        self.tape = [0; N];
+        self.ptr = 0;
+    }
+
+    /// Runs the source code on the tape, as it was left by the previous runs. `input` gives the next byte, or
+    /// `None` at the end of the input, and `output` takes the printed bytes. Unmatched brackets are reported before
+    /// anything runs.
+    pub fn run(
+        &mut self,
+        source: &[u8],
+        mut input: impl FnMut() -> Option<u8>,
+        mut output: impl FnMut(u8),
+    ) -> Result<(), BfError> {
+        check(source)?;
+        let mut pc = 0;
+        while let Some(&b) = source.get(pc) {
+            match b {
+                b'>' if self.ptr + 1 == N => return Err(BfError::PointerOverflow(N as i64)),
+                b'>' => self.ptr += 1,
+                b'<' if self.ptr == 0 => return Err(BfError::PointerOverflow(-1)),
+                b'<' => self.ptr -= 1,
+                b'+' => self.tape[self.ptr] = self.tape[self.ptr].wrapping_add(1),
+                b'-' => self.tape[self.ptr] = self.tape[self.ptr].wrapping_sub(1),
+                b'.' => output(self.tape[self.ptr]),
+                b',' => match (input(), self.eof) {
+                    (Some(b), _) => self.tape[self.ptr] = b,
+                    (None, Eof::Zero) => self.tape[self.ptr] = 0,
+                    (None, Eof::Unchanged) => (),
+                    (None, Eof::Max) => self.tape[self.ptr] = u8::MAX,
+                },
+                b'[' if self.tape[self.ptr] == 0 => pc = matching(source, pc),
+                b']' if self.tape[self.ptr] != 0 => pc = matching(source, pc),
+                _ => (),
+            }
+            pc += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for FixedMachine<N> {
+    fn default() -> FixedMachine<N> {
+        FixedMachine::new()
+    }
+}
+
+/// Checks that the brackets match, without a stack: the first `]` that closes nothing is found going forward, and
+/// the innermost `[` left open going backward.
+fn check(source: &[u8]) -> Result<(), BfError> {
+    let depth = source.iter().enumerate().try_fold(0usize, |depth, (pos, &b)| match b {
+        b'[' => ControlFlow::Continue(depth + 1),
+        b']' if depth == 0 => ControlFlow::Break(pos),
+        b']' => ControlFlow::Continue(depth - 1),
+        _ => ControlFlow::Continue(depth),
+    });
+    match depth {
+        ControlFlow::Break(pos) => Err(BfError::UnmatchedClose(pos)),
+        ControlFlow::Continue(0) => Ok(()),
+        ControlFlow::Continue(_) => {
+            let mut closed = 0;
+            for (pos, &b) in source.iter().enumerate().rev() {
+                match b {
+                    b']' => closed += 1,
+                    b'[' if closed == 0 => return Err(BfError::UnmatchedOpen(pos)),
+                    b'[' => closed -= 1,
+                    _ => (),
+                }
+            }
+            unreachable!("an open loop was counted")
+        },
+    }
+}
+
+/// The position of the bracket matching the one at `pc`, found by counting the brackets between them.
+fn matching(source: &[u8], pc: usize) -> usize {
+    let mut depth = 0usize;
+    if source[pc] == b'[' {
+        for (pos, &b) in source.iter().enumerate().skip(pc) {
+            match b {
+                b'[' => depth += 1,
+                b']' if depth == 1 => return pos,
+                b']' => depth -= 1,
+                _ => (),
+            }
+        }
+    } else {
+        for (pos, &b) in source[..=pc].iter().enumerate().rev() {
+            match b {
+                b']' => depth += 1,
+                b'[' if depth == 1 => return pos,
+                b'[' => depth -= 1,
+                _ => (),
+            }
+        }
+    }
+    unreachable!("the brackets were checked")
+}
@@ -0,0 +1,57 @@
+// The threads of the Brainfork dialect, taking turns on the machine.
+// MIT license.
+
+use std::iter;
+use std::mem;
+
+use crate::error::BfError;
+
+use super::{Machine, Tape};
+
+/// Default number of instructions a thread runs before the next one takes its turn.
+pub const DEFAULT_TIME_SLICE: u64 = 1;
+
+/// A thread waiting for its turn, with its own tape and loops.
+pub(super) struct Thread {
+    memory: Tape,
+    ptr: usize,
+    stack: Vec<usize>,
+    /// Index of the instruction it runs next.
+    next: usize,
+}
+
+impl Machine {
+    /// Forks the running thread: the new one gets a copy of the tape, with the pointer one cell to the right and
+    /// that cell set to one, and waits for its turn after the others, at the given instruction. The current cell
+    /// is set to zero in the running thread.
+    pub(super) fn fork(&mut self, next: usize) -> Result<(), BfError> {
+        let ptr = self.offset(1)?;
+        let mut memory = Tape::from(self.memory.to_vec());
+        memory[ptr] = 1;
+        self.memory[self.ptr] = 0;
+        self.threads.push_back(Thread { memory, ptr, stack: self.stack.clone(), next });
+        Ok(())
+    }
+
+    /// Gives the machine to the next thread once the running one has run its time slice, or has reached the end of
+    /// the program, and returns the instruction to run next. Threads take turns in the order they were forked, so
+    /// that a program runs and prints the same way every time.
+    pub(super) fn schedule(&mut self, next: usize, end: usize) -> usize {
+        if next < end && self.steps < self.slice_end {
+            return next;
+        }
+        // Threads forked by the last instruction have nothing left to run.
+        let Some(thread) = iter::from_fn(|| self.threads.pop_front()).find(|t| t.next < end) else { return next };
+        self.slice_end = self.steps + self.time_slice;
+        let current = Thread {
+            memory: mem::replace(&mut self.memory, thread.memory),
+            ptr: mem::replace(&mut self.ptr, thread.ptr),
+            stack: mem::replace(&mut self.stack, thread.stack),
+            next,
+        };
+        if next < end {
+            self.threads.push_back(current);
+        }
+        thread.next
+    }
+}
@@ -0,0 +1,1185 @@
+// The Brainfuck machine.
+// MIT license.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, prelude::*};
+use std::mem;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::bytecode;
+use crate::error::BfError;
+use crate::program::{Op, Program};
+use crate::transpile::Settings;
+
+mod bits;
+mod debugger;
+mod files;
+mod fixed;
+mod fork;
+mod smbf;
+mod threaded;
+mod tape;
+mod tiered;
+
+use debugger::Debugger;
+use files::OpenFile;
+pub use bits::BitMachine;
+pub use fixed::FixedMachine;
+pub use fork::DEFAULT_TIME_SLICE;
+use fork::Thread;
+pub use tape::Tape;
+
+/// The flags of the machines without them.
+static NEVER: AtomicBool = AtomicBool::new(false);
+
+/// Default number of cells in the memory tape.
+pub const DEFAULT_MEM_SIZE: usize = 40_000;
+
+/// The width of a memory cell. Cells wrap around on overflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellWidth {
+    #[default]
+    U8,
+    U16,
+    U32,
+}
+impl CellWidth {
+    /// Bit mask with all the bits of a cell of this width set.
+    pub fn mask(self) -> u32 {
+        match self {
+            CellWidth::U8 => u8::MAX as u32,
+            CellWidth::U16 => u16::MAX as u32,
+            CellWidth::U32 => u32::MAX,
+        }
+    }
+}
+impl FromStr for CellWidth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CellWidth, String> {
+        match s {
+            "8" | "u8" => Ok(CellWidth::U8),
+            "16" | "u16" => Ok(CellWidth::U16),
+            "32" | "u32" => Ok(CellWidth::U32),
+            _ => Err(format!("invalid cell width '{}', expected 8, 16 or 32", s)),
+        }
+    }
+}
+impl fmt::Display for CellWidth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CellWidth::U8 => write!(f, "8"),
+            CellWidth::U16 => write!(f, "16"),
+            CellWidth::U32 => write!(f, "32"),
+        }
+    }
+}
+
+/// What `,` stores in the current cell when the input is exhausted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Eof {
+    /// Store a zero.
+    #[default]
+    Zero,
+    /// Leave the cell as it is.
+    Unchanged,
+    /// Store the maximum value of a cell, i.e. -1.
+    Max,
+}
+impl FromStr for Eof {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Eof, String> {
+        match s {
+            "zero" | "0" => Ok(Eof::Zero),
+            "unchanged" => Ok(Eof::Unchanged),
+            "max" | "-1" => Ok(Eof::Max),
+            _ => Err(format!("invalid EOF behavior '{}', expected zero, unchanged or max", s)),
+        }
+    }
+}
+impl fmt::Display for Eof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Eof::Zero => write!(f, "zero"),
+            Eof::Unchanged => write!(f, "unchanged"),
+            Eof::Max => write!(f, "max"),
+        }
+    }
+}
+
+/// How the machine finds the code of every instruction it runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dispatch {
+    /// Match on every instruction in a single loop.
+    Match,
+    /// Translate the program to a table of handlers first, and call through it. Faster.
+    #[default]
+    Threaded,
+}
+
+/// How the values of the cells are shown in the states, reports and dumps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellFormat {
+    /// In decimal.
+    #[default]
+    Dec,
+    /// In hexadecimal.
+    Hex,
+    /// As the character with that code.
+    Char,
+    /// In decimal, hexadecimal and as a character.
+    All,
+}
+impl CellFormat {
+    /// Formats the value of a cell.
+    pub fn show(self, value: u32) -> String {
+        match self {
+            CellFormat::Dec => value.to_string(),
+            CellFormat::Hex => format!("{:#x}", value),
+            CellFormat::Char => match char::from_u32(value) {
+                Some(c) => format!("{:?}", c),
+                None => value.to_string(),
+            },
+            CellFormat::All => match char::from_u32(value) {
+                Some(c) => format!("{} {:#x} {:?}", value, value, c),
+                None => format!("{} {:#x}", value, value),
+            },
+        }
+    }
+}
+impl FromStr for CellFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CellFormat, String> {
+        match s {
+            "dec" => Ok(CellFormat::Dec),
+            "hex" => Ok(CellFormat::Hex),
+            "char" => Ok(CellFormat::Char),
+            "all" => Ok(CellFormat::All),
+            _ => Err(format!("invalid cell format '{}', expected dec, hex, char or all", s)),
+        }
+    }
+}
+impl fmt::Display for CellFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CellFormat::Dec => write!(f, "dec"),
+            CellFormat::Hex => write!(f, "hex"),
+            CellFormat::Char => write!(f, "char"),
+            CellFormat::All => write!(f, "all"),
+        }
+    }
+}
+
+/// What the `#` instruction prints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugFormat {
+    /// The pointer and the value of the current cell.
+    Cell,
+    /// The pointer, and a hexdump of the given number of cells around it.
+    Window(usize),
+    /// The whole state as a JSON object on a single line: the instruction, the number of steps, the pointer, the
+    /// loop depth and the memory up to the last cell in use.
+    Json,
+}
+impl Default for DebugFormat {
+    fn default() -> DebugFormat {
+        DebugFormat::Window(DEBUG_CELLS)
+    }
+}
+impl FromStr for DebugFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<DebugFormat, String> {
+        let invalid = || format!("invalid debug format '{}', expected cell, window, window:<CELLS> or json", s);
+        match s.split_once(':') {
+            Some(("window", cells)) => cells.parse().ok().filter(|&n| n > 0).map(DebugFormat::Window).ok_or_else(invalid),
+            Some(_) => Err(invalid()),
+            None => match s {
+                "cell" => Ok(DebugFormat::Cell),
+                "window" => Ok(DebugFormat::Window(DEBUG_CELLS)),
+                "json" => Ok(DebugFormat::Json),
+                _ => Err(invalid()),
+            },
+        }
+    }
+}
+impl fmt::Display for DebugFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DebugFormat::Cell => write!(f, "cell"),
+            DebugFormat::Window(cells) => write!(f, "window:{}", cells),
+            DebugFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// The state of a run in the middle of a program, to resume it later, from [`Machine::snapshot`]. Encode it with
+/// [`crate::bytecode::encode_snapshot`] to save it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Hash of the instructions of the program, that the snapshot can only resume.
+    pub program: u64,
+    pub memory: Vec<u32>,
+    pub ptr: usize,
+    /// Index of the instruction to run next.
+    pub next: usize,
+    /// Indices of the opening brackets of the loops the run is in, from the outermost one.
+    pub stack: Vec<usize>,
+    pub steps: u64,
+    /// Bytes printed and read so far.
+    pub written: u64,
+    pub read: u64,
+}
+
+/// Hash of the instructions of a program, to tell whether a snapshot was taken while running it. It hashes their
+/// binary encoding, so that it stays the same when the interpreter is built again.
+fn fingerprint(program: &Program) -> u64 {
+    bytecode::hash(&bytecode::encode(program, &Settings::default()))
+}
+
+/// A function called while a program runs, with the machine and the program, able to stop the run with an error.
+pub type Observer = Box<dyn FnMut(&Machine, &Program) -> io::Result<()>>;
+
+/// Where `,` reads from and where `.` writes to on a channel.
+type Channel = (Box<dyn Read>, Box<dyn Write>);
+
+/// Builds machines. Get one with [`Machine::builder`].
+pub struct MachineBuilder {
+    mem_size: usize,
+    cell_width: CellWidth,
+    cell_format: CellFormat,
+    eof: Eof,
+    debug: bool,
+    debug_script: Vec<String>,
+    debug_format: DebugFormat,
+    debug_output: Box<dyn Write>,
+    dispatch: Dispatch,
+    tier_threshold: Option<u64>,
+    time_slice: u64,
+    fixed_time: bool,
+    allow_fs: bool,
+    max_steps: Option<u64>,
+    max_loop_depth: Option<usize>,
+    max_output: Option<u64>,
+    delay: Option<Duration>,
+    delay_every: u64,
+    observer: Option<(u64, Observer)>,
+    interrupt: &'static AtomicBool,
+    #[cfg(feature = "signals")]
+    dump: &'static AtomicBool,
+    progress: &'static AtomicBool,
+    resume: Option<Snapshot>,
+    tape: Option<Tape>,
+    preload: Option<(usize, Vec<u8>)>,
+    channels: Vec<Option<Channel>>,
+    peer: Option<Channel>,
+    input: Box<dyn Read>,
+    output: Box<dyn Write>,
+}
+impl MachineBuilder {
+    /// Sets the number of cells in the memory tape. The tape has at least one, so zero gives one.
+    pub fn mem_size(mut self, mem_size: usize) -> MachineBuilder {
+        self.mem_size = mem_size;
+        self
+    }
+
+    /// Sets the width of the memory cells.
+    pub fn cell_width(mut self, cell_width: CellWidth) -> MachineBuilder {
+        self.cell_width = cell_width;
+        self
+    }
+
+    /// Sets how the values of the cells are shown in the states, reports and dumps.
+    pub fn cell_format(mut self, cell_format: CellFormat) -> MachineBuilder {
+        self.cell_format = cell_format;
+        self
+    }
+
+    /// Sets the behavior of `,` at the end of the input.
+    pub fn eof(mut self, eof: Eof) -> MachineBuilder {
+        self.eof = eof;
+        self
+    }
+
+    /// Sets debug mode, which pauses after every instruction and prints the internal state.
+    pub fn debug(mut self, debug: bool) -> MachineBuilder {
+        self.debug = debug;
+        self
+    }
+
+    /// Sets commands for the debugger to run at the first stops, as if they were typed, before reading the standard
+    /// input.
+    pub fn debug_script(mut self, commands: Vec<String>) -> MachineBuilder {
+        self.debug_script = commands;
+        self
+    }
+
+    /// Sets what the `#` instruction prints.
+    pub fn debug_format(mut self, format: DebugFormat) -> MachineBuilder {
+        self.debug_format = format;
+        self
+    }
+
+    /// Sets where the `#` instruction prints to. Defaults to the standard error.
+    pub fn debug_output<W: Write + 'static>(mut self, writer: W) -> MachineBuilder {
+        self.debug_output = Box::new(writer);
+        self
+    }
+
+    /// Sets how instructions are dispatched. Debug mode always matches on every instruction.
+    pub fn dispatch(mut self, dispatch: Dispatch) -> MachineBuilder {
+        self.dispatch = dispatch;
+        self
+    }
+
+    /// Compiles the loops on the fly once they ran the given number of iterations, or never. Compiled loops are
+    /// optimized with every pass first, and run as closures instead of through the table of handlers, which speeds
+    /// up long programs without slowing down the start of short ones. Only for the threaded dispatch.
+    pub fn tier_threshold(mut self, iterations: Option<u64>) -> MachineBuilder {
+        self.tier_threshold = iterations.map(|n| n.max(1));
+        self
+    }
+
+    /// Sets the number of instructions every thread of a program that forks runs before the next one takes its turn.
+    /// Defaults to [`DEFAULT_TIME_SLICE`].
+    pub fn time_slice(mut self, instructions: u64) -> MachineBuilder {
+        self.time_slice = instructions.max(1);
+        self
+    }
+
+    /// Makes `@` store the number of times it ran before in the run instead of the time, so that the program does
+    /// the same thing on every run.
+    pub fn fixed_time(mut self, fixed: bool) -> MachineBuilder {
+        self.fixed_time = fixed;
+        self
+    }
+
+    /// Lets the file instructions of the fs dialect open files, which they can not by default.
+    pub fn allow_fs(mut self, allow: bool) -> MachineBuilder {
+        self.allow_fs = allow;
+        self
+    }
+
+    /// Sets the maximum number of instructions every run executes before failing, or no limit. Limited runs always
+    /// match on every instruction.
+    pub fn max_steps(mut self, max_steps: Option<u64>) -> MachineBuilder {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Sets the maximum number of loops running inside each other, or no limit. Deeper programs fail when entering
+    /// the loop past the limit.
+    pub fn max_loop_depth(mut self, max_loop_depth: Option<usize>) -> MachineBuilder {
+        self.max_loop_depth = max_loop_depth;
+        self
+    }
+
+    /// Sets the maximum number of bytes every run prints, or no limit. Programs printing more fail when printing the
+    /// byte past the limit.
+    pub fn max_output(mut self, max_output: Option<u64>) -> MachineBuilder {
+        self.max_output = max_output;
+        self
+    }
+
+    /// Sets a pause after every instruction, or every few of them with [`MachineBuilder::delay_every`], to slow the
+    /// program down for demonstrations. Delayed runs always match on every instruction.
+    pub fn delay(mut self, delay: Option<Duration>) -> MachineBuilder {
+        self.delay = delay;
+        self
+    }
+
+    /// Sets the number of instructions between pauses. Defaults to 1.
+    pub fn delay_every(mut self, instructions: u64) -> MachineBuilder {
+        self.delay_every = instructions.max(1);
+        self
+    }
+
+    /// Sets a function to call after every given number of instructions, like to draw the state of the machine.
+    /// Its errors stop the run. Observed runs always match on every instruction.
+    pub fn observe<F>(mut self, every: u64, observer: F) -> MachineBuilder
+    where
+        F: FnMut(&Machine, &Program) -> io::Result<()> + 'static,
+    {
+        self.observer = Some((every.max(1), Box::new(observer)));
+        self
+    }
+
+    /// Sets a flag that stops the running program with [`BfError::Interrupted`] when it becomes true, like from a
+    /// signal handler. It is checked on every jump back to the start of a loop and when reading the input fails
+    /// because of an interruption.
+    pub fn interrupt(mut self, flag: &'static AtomicBool) -> MachineBuilder {
+        self.interrupt = flag;
+        self
+    }
+
+    /// Sets a flag that makes the running program print its state to the standard error when it becomes true, like
+    /// from a signal handler, and keep going. The flag is cleared after every dump. It is checked on every jump back
+    /// to the start of a loop.
+    #[cfg(feature = "signals")]
+    pub fn dump_on(mut self, flag: &'static AtomicBool) -> MachineBuilder {
+        self.dump = flag;
+        self
+    }
+
+    /// Sets a flag that makes the running program print its progress to the standard error when it becomes true,
+    /// like from a timer, and keep going: the time since the start of the run, the number of steps, the number of
+    /// instructions per second and the current instruction. The flag is cleared after every line. It is checked on
+    /// every jump back to the start of a loop.
+    pub fn progress(mut self, flag: &'static AtomicBool) -> MachineBuilder {
+        self.progress = flag;
+        self
+    }
+
+    /// Makes the first run of the machine resume the run the snapshot was taken from, instead of starting the
+    /// program. The input must be the same as in that run: the bytes read before the snapshot are skipped.
+    pub fn resume(mut self, snapshot: Snapshot) -> MachineBuilder {
+        self.resume = Some(snapshot);
+        self
+    }
+
+    /// Keeps the cells in the tape, like one mapped from a file, instead of a new one with the number of cells of
+    /// [`MachineBuilder::mem_size`]. Cells too wide for the cell width are cut to it.
+    pub fn tape(mut self, tape: Tape) -> MachineBuilder {
+        self.tape = Some(tape);
+        self
+    }
+
+    /// Sets the cells from the given one on to the bytes, once the tape is created, like a binary file for the
+    /// program to work on. The bytes past the end of the tape are left out.
+    pub fn preload(mut self, offset: usize, bytes: Vec<u8>) -> MachineBuilder {
+        self.preload = Some((offset, bytes));
+        self
+    }
+
+    /// Sets where `,` reads from and where `.` writes to once the program selects the given channel, with the `~`
+    /// instruction of the channels dialect. Channel 0 is the one of [`MachineBuilder::io`], where every run starts.
+    pub fn channel<R: Read + 'static, W: Write + 'static>(mut self, n: usize, reader: R, writer: W) -> MachineBuilder {
+        if n == 0 {
+            return self.io(reader, writer);
+        }
+        if self.channels.len() <= n {
+            self.channels.resize_with(n + 1, || None);
+        }
+        self.channels[n] = Some((Box::new(reader), Box::new(writer)));
+        self
+    }
+
+    /// Connects the machine to a peer, another machine usually running on another thread, for the `!` and `?`
+    /// instructions of the pair dialect: `!` writes to the writer, and `?` reads from the reader.
+    pub fn peer<R: Read + 'static, W: Write + 'static>(mut self, reader: R, writer: W) -> MachineBuilder {
+        self.peer = Some((Box::new(reader), Box::new(writer)));
+        self
+    }
+
+    /// Sets where `,` reads from and where `.` writes to. Defaults to the standard input and output.
+    pub fn io<R: Read + 'static, W: Write + 'static>(mut self, reader: R, writer: W) -> MachineBuilder {
+        self.input = Box::new(reader);
+        self.output = Box::new(writer);
+        self
+    }
+
+    /// Creates the machine.
+    pub fn build(mut self) -> Machine {
+        let mask = self.cell_width.mask();
+        // A tape kept from a wider machine can have cells that do not fit.
+        for cell in self.tape.iter_mut().flat_map(|t| t.iter_mut()).filter(|c| **c & !mask != 0) {
+            *cell &= mask;
+        }
+        let mut memory = self.tape.unwrap_or_else(|| Tape::zeroed(self.mem_size.max(1)));
+        if let Some((offset, bytes)) = self.preload {
+            let cells = memory.iter_mut().skip(offset);
+            cells.zip(bytes).for_each(|(cell, byte)| *cell = byte as u32);
+        }
+        Machine {
+            debug: self.debug,
+            debug_format: self.debug_format,
+            debug_output: self.debug_output,
+            dispatch: self.dispatch,
+            tier_threshold: self.tier_threshold,
+            time_slice: self.time_slice,
+            fixed_time: self.fixed_time,
+            files: Some(vec![]).filter(|_| self.allow_fs),
+            max_steps: self.max_steps,
+            max_loop_depth: self.max_loop_depth.unwrap_or(usize::MAX),
+            max_output: self.max_output.unwrap_or(u64::MAX),
+            delay: self.delay,
+            delay_every: self.delay_every,
+            observer: self.observer,
+            interrupt: self.interrupt,
+            #[cfg(feature = "signals")]
+            dump: self.dump,
+            progress: self.progress,
+            memory,
+            mask,
+            cell_format: self.cell_format,
+            eof: self.eof,
+            ptr: 0,
+            stack: vec![],
+            steps: 0,
+            written: 0,
+            read: 0,
+            at: 0,
+            next: None,
+            resume: self.resume,
+            started: Instant::now(),
+            debugger: Debugger::with_script(self.debug_script),
+            channels: self.channels,
+            channel: 0,
+            peer: self.peer,
+            threads: VecDeque::new(),
+            slice_end: 0,
+            ticks: 0,
+            input: self.input,
+            output: self.output,
+        }
+    }
+}
+impl Default for MachineBuilder {
+    fn default() -> MachineBuilder {
+        MachineBuilder {
+            mem_size: DEFAULT_MEM_SIZE,
+            cell_width: CellWidth::default(),
+            cell_format: CellFormat::default(),
+            eof: Eof::default(),
+            debug: false,
+            debug_script: vec![],
+            debug_format: DebugFormat::default(),
+            debug_output: Box::new(io::stderr()),
+            dispatch: Dispatch::default(),
+            tier_threshold: None,
+            time_slice: DEFAULT_TIME_SLICE,
+            fixed_time: false,
+            allow_fs: false,
+            max_steps: None,
+            max_loop_depth: None,
+            max_output: None,
+            delay: None,
+            delay_every: 1,
+            observer: None,
+            interrupt: &NEVER,
+            #[cfg(feature = "signals")]
+            dump: &NEVER,
+            progress: &NEVER,
+            resume: None,
+            tape: None,
+            preload: None,
+            channels: vec![],
+            peer: None,
+            input: Box::new(io::stdin()),
+            output: Box::new(io::stdout()),
+        }
+    }
+}
+
+/// The machine definition.
+pub struct Machine {
+    /// Debug mode.
+    debug: bool,
+    /// What `#` prints.
+    debug_format: DebugFormat,
+    /// Where `#` prints to.
+    debug_output: Box<dyn Write>,
+    /// How instructions are dispatched.
+    dispatch: Dispatch,
+    /// Number of iterations after which loops are compiled, with the threaded dispatch.
+    tier_threshold: Option<u64>,
+    /// Number of instructions every thread runs in its turn.
+    time_slice: u64,
+    /// Whether `@` counts instead of reading the time.
+    fixed_time: bool,
+    /// Maximum number of instructions per run.
+    max_steps: Option<u64>,
+    /// Maximum number of loops in the stack.
+    max_loop_depth: usize,
+    /// Maximum number of bytes printed per run.
+    max_output: u64,
+    /// Pause between instructions.
+    delay: Option<Duration>,
+    /// Number of instructions between pauses.
+    delay_every: u64,
+    /// Function called every few instructions, and how many.
+    observer: Option<(u64, Observer)>,
+    /// Flag stopping the program when set.
+    interrupt: &'static AtomicBool,
+    /// Flag asking for a dump of the state when set.
+    #[cfg(feature = "signals")]
+    dump: &'static AtomicBool,
+    /// Flag asking for a progress line when set.
+    progress: &'static AtomicBool,
+    /// Memory data.
+    memory: Tape,
+    /// Mask applied to cells after every modification, from the cell width.
+    mask: u32,
+    /// How cell values are shown.
+    cell_format: CellFormat,
+    /// End of input behavior.
+    eof: Eof,
+    /// Memory pointer.
+    ptr: usize,
+    /// Stack.
+    stack: Vec<usize>,
+    /// Number of instructions executed by the current or last run.
+    steps: u64,
+    /// Number of bytes printed by the current or last run.
+    written: u64,
+    /// Number of bytes read by the current or last run.
+    read: u64,
+    /// Index of the instruction the last run stopped at.
+    at: usize,
+    /// Index of the instruction to run next, while the observer is called.
+    next: Option<usize>,
+    /// State to resume on the next run.
+    resume: Option<Snapshot>,
+    /// When the current or last run started.
+    started: Instant,
+    /// State of the debugger, in debug mode.
+    debugger: Debugger,
+    /// The channels other than the selected one, which has its streams in `input` and `output`.
+    channels: Vec<Option<Channel>>,
+    /// Number of the selected channel.
+    channel: usize,
+    /// Where the bytes of the peer machine come from, and where those for it go.
+    peer: Option<Channel>,
+    /// The threads waiting for their turn, in a program that forks. The running one has its state in the machine.
+    threads: VecDeque<Thread>,
+    /// Step count at which the turn of the running thread ends.
+    slice_end: u64,
+    /// Number of times `@` ran in the current or last run.
+    ticks: u64,
+    /// The files the program opened, at their descriptor minus one, if it may open files.
+    files: Option<Vec<Option<OpenFile>>>,
+    /// Input stream for `,`.
+    input: Box<dyn Read>,
+    /// Output stream for `.`.
+    output: Box<dyn Write>,
+}
+impl Machine {
+    /// Creates a new machine with the given memory size, using the standard input and output.
+    pub fn new(mem_size: usize, debug: bool) -> Machine {
+        Machine::builder().mem_size(mem_size).debug(debug).build()
+    }
+
+    /// Returns a builder to configure a new machine.
+    pub fn builder() -> MachineBuilder {
+        MachineBuilder::default()
+    }
+
+    /// Parses and interprets the given string on this machine.
+    pub fn interpret(&mut self, program: &str) -> Result<(), BfError> {
+        let program = Program::parse(program)?;
+        self.run(&program)
+    }
+
+    /// The memory tape.
+    pub fn memory(&self) -> &[u32] {
+        &self.memory
+    }
+
+    /// Position of the memory pointer.
+    pub fn pointer(&self) -> usize {
+        self.ptr
+    }
+
+    /// Number of instructions executed by the current or last run.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Index of the instruction the current run is at, or the last run stopped at.
+    pub fn instruction(&self) -> usize {
+        self.at
+    }
+
+    /// A hexdump of the given number of rows of cells around the pointer, with the cell at the pointer in brackets.
+    pub fn hexdump_rows(&self, rows: usize) -> String {
+        let len = rows * self.row_len();
+        self.hexdump(self.centered(len), len)
+    }
+
+    /// Describes where the last run of the program stopped: the instruction, the number of steps, the pointer and
+    /// the cells around it.
+    pub fn report(&self, program: &Program) -> String {
+        let mut out = match program.spans.get(self.at) {
+            Some(span) if !program.is_empty() => {
+                let (line, column) = program.location(span.start);
+                format!("Instruction: {} ({}) at {}:{}, after {} steps\n", self.at, program.ops[self.at], line, column, self.steps)
+            },
+            _ => format!("Instruction: {}, after {} steps\n", self.at, self.steps),
+        };
+        out.push_str(&format!("Pointer: {}, value: {}, loop depth: {}\n", self.ptr, self.show(self.ptr), self.stack.len()));
+        let start = self.ptr.saturating_sub(REPORT_CELLS);
+        let end = (self.ptr + REPORT_CELLS + 1).min(self.memory.len());
+        let cells: Vec<String> = (start..end)
+            .map(|i| if i == self.ptr { format!("[{}]", self.show(i)) } else { self.show(i) })
+            .collect();
+        let used = self.memory.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1);
+        // Values in all the formats have spaces already.
+        let separator = if self.cell_format == CellFormat::All { ", " } else { " " };
+        out.push_str(&format!("Tape {}..{}: {} ({} cells in use)\n", start, end, cells.join(separator), used));
+        out
+    }
+
+    /// Runs the given program on this machine.
+    pub fn run(&mut self, program: &Program) -> Result<(), BfError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("execute", ops = program.len()).entered();
+        // Spans of the loops currently being executed.
+        #[cfg(feature = "tracing-loops")]
+        let mut loop_spans: Vec<tracing::span::EnteredSpan> = vec![];
+
+        self.started = Instant::now();
+        let resume = self.resume.take();
+        // Instruction pointer, points to current instruction.
+        let mut i: usize = match &resume {
+            Some(snapshot) => self.restore(program, snapshot)?,
+            None => {
+                self.select(0)?;
+                self.stack.clear();
+                self.steps = 0;
+                self.written = 0;
+                self.read = 0;
+                self.at = 0;
+                self.threads.clear();
+                self.ticks = 0;
+                self.slice_end = self.time_slice;
+                0
+            },
+        };
+        // Limited, delayed and observed runs count every instruction, and loop spans need the loop that matches on
+        // every instruction as well. So do resumed runs, which start in the middle of the program, and programs that
+        // fork, whose threads take turns in it.
+        let counted = self.max_steps.is_some() || self.delay.is_some() || self.observer.is_some() || resume.is_some();
+        let counted = counted || program.ops.contains(&Op::Fork);
+        if self.dispatch == Dispatch::Threaded && !self.debug && !counted && !cfg!(feature = "tracing-loops") {
+            return self.run_threaded(program);
+        }
+
+        let prog = &program.ops;
+
+        while i < prog.len() {
+            self.at = i;
+            if self.max_steps == Some(self.steps) {
+                self.output.flush()?;
+                return Err(BfError::StepLimit(self.steps));
+            }
+            self.steps += 1;
+            let mut next: usize = i + 1;
+            match prog[i] {
+
+                // Move pointer.
+                Op::Move(n) => self.ptr = self.offset(n)?,
+
+                // Modify memory.
+                Op::Add(n) => self.memory[self.ptr] = self.memory[self.ptr].wrapping_add(n as u32) & self.mask,
+                Op::AddAt { offset, value } => self.add_at(offset, value)?,
+
+                // Print contents of memory.
+                Op::Out => {
+                    let contents: u8 = self.memory[self.ptr] as u8;
+                    self.write(contents)?;
+                },
+                Op::OutByte(b) => self.write(b)?,
+
+                // Read from input.
+                Op::In => self.input()?,
+
+                // Conditionals.
+                Op::Open(close) => {
+                    if self.memory[self.ptr] == 0 {
+                        // Go to command after matching ']'.
+                        next = close + 1;
+                    } else {
+                        self.enter(i, program)?;
+                        #[cfg(feature = "tracing-loops")]
+                        loop_spans.push(tracing::trace_span!("loop", start = i, end = close).entered());
+                    }
+                },
+                Op::Close(open) => {
+                    if self.memory[self.ptr] != 0 {
+                        if self.attention() {
+                            self.notify(program);
+                        }
+                        self.check_interrupt()?;
+                        // Go to command after matching '['.
+                        next = open + 1;
+                    } else {
+                        self.stack.pop();
+                        #[cfg(feature = "tracing-loops")]
+                        loop_spans.pop();
+                    }
+                },
+
+                // Debug command.
+                Op::Debug => self.print_debug()?,
+                Op::Channel => self.select(self.memory[self.ptr] as usize)?,
+                Op::Send => self.send()?,
+                Op::Receive => self.receive()?,
+                Op::Fork => self.fork(next)?,
+                Op::Clock => self.clock(),
+                Op::File(op) => self.file(op)?,
+
+                // Optimized loops.
+                Op::Clear => self.memory[self.ptr] = 0,
+                Op::MulAdd { offset, factor } => self.mul_add(offset, factor)?,
+                Op::Fill { offset, len, value } => self.fill(offset, len, value)?,
+                Op::Scan(n) => self.scan(n)?,
+            }
+
+            if self.debug && next < prog.len() {
+                self.debug_stop(program, i, next)?;
+            }
+            if let Some(delay) = self.delay {
+                if self.steps.is_multiple_of(self.delay_every) {
+                    // What the program printed so far is part of the show.
+                    self.output.flush()?;
+                    thread::sleep(delay);
+                    self.check_interrupt()?;
+                }
+            }
+            if self.observer.as_ref().is_some_and(|&(every, _)| self.steps.is_multiple_of(every)) {
+                self.next = Some(next);
+                let result = self.call_observer(program);
+                self.next = None;
+                result?;
+            }
+
+            i = next;
+            if !self.threads.is_empty() {
+                i = self.schedule(i, prog.len());
+            }
+
+        }
+        self.output.flush()?;
+        Ok(())
+    }
+
+    /// Calls the observer, which can not be borrowed from the machine it looks at.
+    fn call_observer(&mut self, program: &Program) -> Result<(), BfError> {
+        let Some((every, mut observer)) = self.observer.take() else { return Ok(()) };
+        let result = observer(self, program);
+        self.observer = Some((every, observer));
+        Ok(result?)
+    }
+
+    /// The state of the current run, to resume it later with [`MachineBuilder::resume`]. Taken from the observer, the
+    /// run resumes after the instruction just run. Taken after a run that stopped at an instruction, like when
+    /// interrupted or at the step limit, it resumes at that instruction.
+    pub fn snapshot(&self, program: &Program) -> Snapshot {
+        Snapshot {
+            program: fingerprint(program),
+            memory: self.memory.to_vec(),
+            ptr: self.ptr,
+            next: self.next.unwrap_or(self.at),
+            stack: self.stack.clone(),
+            steps: self.steps,
+            written: self.written,
+            read: self.read,
+        }
+    }
+
+    /// Sets the state of the snapshot, skipping the input it read, and returns the instruction to resume at.
+    fn restore(&mut self, program: &Program, snapshot: &Snapshot) -> Result<usize, BfError> {
+        let invalid = |reason: &str| Err(BfError::InvalidBytecode(reason.to_string()));
+        if snapshot.program != fingerprint(program) {
+            return invalid("the snapshot was taken while running another program");
+        }
+        if snapshot.memory.len() != self.memory.len() || snapshot.memory.iter().any(|&c| c & !self.mask != 0) {
+            return invalid("the snapshot was taken on a machine with other cells");
+        }
+        let opens = |&open: &usize| matches!(program.ops.get(open), Some(Op::Open(_)));
+        if snapshot.ptr >= self.memory.len() || snapshot.next > program.len() || !snapshot.stack.iter().all(opens) {
+            return invalid("the snapshot is not a state of the program");
+        }
+        let skipped = io::copy(&mut (&mut self.input).take(snapshot.read), &mut io::sink())?;
+        if skipped != snapshot.read {
+            return invalid("the input is shorter than when the snapshot was taken");
+        }
+        self.memory.copy_from_slice(&snapshot.memory);
+        self.ptr = snapshot.ptr;
+        self.stack.clone_from(&snapshot.stack);
+        self.steps = snapshot.steps;
+        self.written = snapshot.written;
+        self.read = snapshot.read;
+        self.at = snapshot.next;
+        Ok(snapshot.next)
+    }
+
+    /// Fails if the interrupt flag is set, flushing the output first.
+    #[inline(always)]
+    fn check_interrupt(&mut self) -> Result<(), BfError> {
+        if self.interrupt.load(Ordering::Relaxed) {
+            return self.interrupted();
+        }
+        Ok(())
+    }
+
+    /// The slow path of [`Machine::check_interrupt`], kept apart so that the check stays small.
+    #[cold]
+    #[inline(never)]
+    fn interrupted(&mut self) -> Result<(), BfError> {
+        self.output.flush()?;
+        Err(BfError::Interrupted)
+    }
+
+    /// Whether the state or the progress should be printed.
+    #[inline(always)]
+    fn attention(&self) -> bool {
+        #[cfg(feature = "signals")]
+        if self.dump.load(Ordering::Relaxed) {
+            return true;
+        }
+        self.progress.load(Ordering::Relaxed)
+    }
+
+    /// Prints the state or the progress, whichever was asked for, and clears the requests.
+    #[cold]
+    #[inline(never)]
+    fn notify(&self, program: &Program) {
+        #[cfg(feature = "signals")]
+        if self.dump.swap(false, Ordering::Relaxed) {
+            eprint!("{}", self.dump(program));
+        }
+        if self.progress.swap(false, Ordering::Relaxed) {
+            let elapsed = self.started.elapsed().as_secs_f64();
+            let (line, column) = program.location(program.spans[self.at].start);
+            eprintln!(
+                "[progress] {:.1} s, {} steps, {:.1}M instructions/s, at {}:{}",
+                elapsed,
+                self.steps,
+                self.steps as f64 / elapsed / 1e6,
+                line,
+                column
+            );
+        }
+    }
+
+    /// The state of the running program on a single line: the instruction, the pointer, the number of steps and the
+    /// innermost loop.
+    #[cfg(feature = "signals")]
+    fn dump(&self, program: &Program) -> String {
+        let describe = |i: usize| {
+            let (line, column) = program.location(program.spans[i].start);
+            format!("{} ({}) at {}:{}", i, program.ops[i], line, column)
+        };
+        let innermost = match self.stack.last() {
+            Some(&open) => format!("{} loops deep, innermost from {}", self.stack.len(), describe(open)),
+            None => "not in a loop".to_string(),
+        };
+        format!(
+            "[state] instruction {}, pointer {} (value {}), {} steps, {}\n",
+            describe(self.at),
+            self.ptr,
+            self.show(self.ptr),
+            self.steps,
+            innermost
+        )
+    }
+
+    /// Formats the value of the cell at the position.
+    fn show(&self, cell: usize) -> String {
+        self.cell_format.show(self.memory[cell])
+    }
+
+    /// Pushes the loop opening at the given index to the stack, checking the depth limit.
+    fn enter(&mut self, open: usize, program: &Program) -> Result<(), BfError> {
+        if self.stack.len() == self.max_loop_depth {
+            return Err(BfError::LoopTooDeep(self.max_loop_depth, program.spans[open].start));
+        }
+        self.stack.push(open);
+        Ok(())
+    }
+
+    /// Returns the memory position at the given offset from the pointer, checking that it is in the tape.
+    fn offset(&self, n: isize) -> Result<usize, BfError> {
+        match self.ptr.checked_add_signed(n).filter(|&p| p < self.memory.len()) {
+            Some(p) => Ok(p),
+            None => Err(BfError::PointerOverflow(self.ptr as i64 + n as i64)),
+        }
+    }
+
+    /// Prints a byte, checking the output limit.
+    fn write(&mut self, byte: u8) -> Result<(), BfError> {
+        if self.written == self.max_output {
+            self.output.flush()?;
+            return Err(BfError::OutputLimit(self.max_output));
+        }
+        self.written += 1;
+        self.output.write_all(&[byte])?;
+        Ok(())
+    }
+
+    /// Adds the value to the cell at the given offset from the pointer.
+    fn add_at(&mut self, offset: isize, value: i32) -> Result<(), BfError> {
+        let target = self.offset(offset)?;
+        self.memory[target] = self.memory[target].wrapping_add(value as u32) & self.mask;
+        Ok(())
+    }
+
+    /// Makes `.` and `,` use the given channel, flushing the output of the previous one.
+    fn select(&mut self, n: usize) -> Result<(), BfError> {
+        if n == self.channel {
+            return Ok(());
+        }
+        let Some((input, output)) = self.channels.get_mut(n).and_then(Option::take) else {
+            return Err(BfError::UnknownChannel(n as u32));
+        };
+        self.output.flush()?;
+        let previous = (mem::replace(&mut self.input, input), mem::replace(&mut self.output, output));
+        self.channels[self.channel] = Some(previous);
+        self.channel = n;
+        Ok(())
+    }
+
+    /// Reads a byte from the input into the current cell.
+    fn input(&mut self) -> Result<(), BfError> {
+        // Prompts usually come without a newline, make sure they are visible.
+        self.output.flush()?;
+        let c = self.read_char()?;
+        self.store(self.ptr, c);
+        Ok(())
+    }
+
+    /// Stores the byte read in the cell, or what the end of the input stores.
+    fn store(&mut self, cell: usize, byte: Option<u8>) {
+        match (byte, self.eof) {
+            (Some(c), _) => self.memory[cell] = c as u32,
+            (None, Eof::Zero) => self.memory[cell] = 0,
+            (None, Eof::Unchanged) => (),
+            (None, Eof::Max) => self.memory[cell] = self.mask,
+        }
+    }
+
+    /// Sends the current cell to the peer, after what this machine printed so far.
+    fn send(&mut self) -> Result<(), BfError> {
+        self.output.flush()?;
+        let Some((_, writer)) = &mut self.peer else { return Err(BfError::NoPeer) };
+        writer.write_all(&[self.memory[self.ptr] as u8])?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Waits for a byte from the peer and stores it in the current cell. Once the peer is gone, it stores what the end
+    /// of the input does.
+    fn receive(&mut self) -> Result<(), BfError> {
+        self.output.flush()?;
+        let mut byte = [0u8];
+        loop {
+            let Some((reader, _)) = &mut self.peer else { return Err(BfError::NoPeer) };
+            match reader.read(&mut byte) {
+                Ok(0) => break self.store(self.ptr, None),
+                Ok(_) => break self.store(self.ptr, Some(byte[0])),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => self.check_interrupt()?,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores the milliseconds since the run started in the current cell, or with a fixed time, the number of times
+    /// the clock was read before.
+    fn clock(&mut self) {
+        let time = if self.fixed_time { self.ticks } else { self.started.elapsed().as_millis() as u64 };
+        self.ticks += 1;
+        self.memory[self.ptr] = time as u32 & self.mask;
+    }
+
+    /// Adds the current cell times the factor to the cell at the given offset.
+    fn mul_add(&mut self, offset: isize, factor: i32) -> Result<(), BfError> {
+        let value = self.memory[self.ptr];
+        // The original loop does not run, and does not touch the target, if the cell is zero.
+        if value != 0 {
+            let target = self.offset(offset)?;
+            self.memory[target] = self.memory[target].wrapping_add(value.wrapping_mul(factor as u32)) & self.mask;
+        }
+        Ok(())
+    }
+
+    /// Sets the `len` cells starting at the given offset to the value.
+    fn fill(&mut self, offset: isize, len: usize, value: i32) -> Result<(), BfError> {
+        let start = self.offset(offset)?;
+        let end = self.offset(offset + len as isize - 1)? + 1;
+        self.memory[start..end].fill(value as u32 & self.mask);
+        Ok(())
+    }
+
+    /// Moves the pointer by the given step until it reaches a zero cell. Steps of one cell search the tape a chunk at
+    /// a time.
+    fn scan(&mut self, step: isize) -> Result<(), BfError> {
+        match step {
+            1 => match find_zero(&self.memory[self.ptr..]) {
+                Some(n) => self.ptr += n,
+                None => return Err(BfError::PointerOverflow(self.memory.len() as i64)),
+            },
+            -1 => match rfind_zero(&self.memory[..=self.ptr]) {
+                Some(n) => self.ptr = n,
+                None => return Err(BfError::PointerOverflow(-1)),
+            },
+            _ => while self.memory[self.ptr] != 0 {
+                self.ptr = self.offset(step)?;
+            },
+        }
+        Ok(())
+    }
+
+
+    /// Reads a character from the input and returns it as a byte, or `None` at the end of the input.
+    fn read_char(&mut self) -> Result<Option<u8>, BfError> {
+        let mut byte = [0u8];
+        loop {
+            match self.input.read(&mut byte) {
+                Ok(0) => {
+                    log::debug!("End of input reached while reading character");
+                    return Ok(None);
+                },
+                Ok(_) => {
+                    self.read += 1;
+                    return Ok(Some(byte[0]));
+                },
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => self.check_interrupt()?,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Number of cells `#` shows by default.
+const DEBUG_CELLS: usize = 32;
+
+/// Number of cells shown on each side of the pointer in reports.
+const REPORT_CELLS: usize = 8;
+
+/// Number of cells checked at once when searching the tape. Checking a whole chunk has no early exit, so the compiler
+/// can use SIMD instructions for it.
+const CHUNK: usize = 32;
+
+/// Returns the position of the first zero cell.
+fn find_zero(cells: &[u32]) -> Option<usize> {
+    let mut chunks = cells.chunks_exact(CHUNK);
+    let mut start = 0;
+    for chunk in &mut chunks {
+        if chunk.iter().fold(false, |zero, &c| zero | (c == 0)) {
+            return chunk.iter().position(|&c| c == 0).map(|n| start + n);
+        }
+        start += CHUNK;
+    }
+    chunks.remainder().iter().position(|&c| c == 0).map(|n| start + n)
+}
+
+/// Returns the position of the last zero cell.
+fn rfind_zero(cells: &[u32]) -> Option<usize> {
+    let mut chunks = cells.rchunks_exact(CHUNK);
+    let mut end = cells.len();
+    for chunk in &mut chunks {
+        end -= CHUNK;
+        if chunk.iter().fold(false, |zero, &c| zero | (c == 0)) {
+            return chunk.iter().rposition(|&c| c == 0).map(|n| end + n);
+        }
+    }
+    chunks.remainder().iter().rposition(|&c| c == 0)
+}
@@ -0,0 +1,87 @@
+// Self-modifying Brainfuck, running from the tape.
+// MIT license.
+
+use std::time::Instant;
+
+use crate::error::BfError;
+
+use super::{Machine, Tape};
+
+impl Machine {
+    /// Runs self-modifying Brainfuck: the source code goes on the tape, before the cells of the machine, and runs
+    /// from there, so that the program changes its own code by going left of the cell the pointer starts at, the
+    /// first one after the code. The run ends when it reaches that cell. The cells are numbered from the start of
+    /// the code, and the instruction the run is at is a position in it.
+    pub fn run_self_modifying(&mut self, source: &[u8]) -> Result<(), BfError> {
+        self.started = Instant::now();
+        self.select(0)?;
+        let origin = source.len();
+        let mut cells: Vec<u32> = source.iter().map(|&b| b as u32 & self.mask).collect();
+        cells.extend_from_slice(&self.memory);
+        self.memory = Tape::from(cells);
+        self.ptr = origin;
+        self.stack.clear();
+        self.steps = 0;
+        self.written = 0;
+        self.read = 0;
+        let mut pc = 0;
+        while pc < origin {
+            self.at = pc;
+            let byte = self.memory[pc] as u8;
+            if !b"><+-.,[]#".contains(&byte) {
+                pc += 1;
+                continue;
+            }
+            if self.max_steps == Some(self.steps) {
+                self.output.flush()?;
+                return Err(BfError::StepLimit(self.steps));
+            }
+            self.steps += 1;
+            let mut next = pc + 1;
+            match byte {
+                b'>' => self.ptr = self.offset(1)?,
+                b'<' => self.ptr = self.offset(-1)?,
+                b'+' => self.memory[self.ptr] = self.memory[self.ptr].wrapping_add(1) & self.mask,
+                b'-' => self.memory[self.ptr] = self.memory[self.ptr].wrapping_sub(1) & self.mask,
+                b'.' => self.write(self.memory[self.ptr] as u8)?,
+                b',' => self.input()?,
+                b'[' if self.memory[self.ptr] == 0 => next = self.matching(pc, origin)? + 1,
+                b']' if self.memory[self.ptr] != 0 => {
+                    self.check_interrupt()?;
+                    next = self.matching(pc, origin)? + 1;
+                },
+                b'#' => self.print_debug()?,
+                _ => (),
+            }
+            pc = next;
+        }
+        self.output.flush()?;
+        Ok(())
+    }
+
+    /// The position of the bracket matching the one at `pc`, in the code as it is now, which ends at `end`.
+    fn matching(&self, pc: usize, end: usize) -> Result<usize, BfError> {
+        let mut depth = 0usize;
+        if self.memory[pc] == b'[' as u32 {
+            for pos in pc..end {
+                match self.memory[pos] as u8 {
+                    b'[' => depth += 1,
+                    b']' if depth == 1 => return Ok(pos),
+                    b']' => depth -= 1,
+                    _ => (),
+                }
+            }
+            Err(BfError::UnmatchedOpen(pc))
+        } else {
+            for pos in (0..=pc).rev() {
+                match self.memory[pos] as u8 {
+                    b']' => depth += 1,
+                    b'[' if depth == 1 => return Ok(pos),
+                    b'[' => depth -= 1,
+                    _ => (),
+                }
+            }
+            Err(BfError::UnmatchedClose(pc))
+        }
+    }
+}
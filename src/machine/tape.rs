@@ -0,0 +1,134 @@
+// The memory tape, in memory or mapped from a file.
+// MIT license.
+
+use std::fs;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+
+/// Bytes a cell takes in a tape file.
+const CELL_BYTES: u64 = 4;
+
+/// The cells of a machine. A tape mapped from a file keeps its cells there, as 32-bit integers in the byte order of
+/// the computer, so that they survive the run and only the pages in use take memory.
+pub struct Tape(Storage);
+
+enum Storage {
+    Heap(Vec<u32>),
+    #[cfg(unix)]
+    Mapped(Mapping),
+}
+
+impl Tape {
+    /// A tape of the given number of cells at zero, in memory.
+    pub fn zeroed(cells: usize) -> Tape {
+        Tape(Storage::Heap(vec![0; cells]))
+    }
+
+    /// Maps the file as a tape of the given number of cells, creating it, or growing or shrinking it to that size.
+    /// The cells a new or grown file gets start at zero. Only on Unix.
+    pub fn map(path: &Path, cells: usize) -> io::Result<Tape> {
+        if cells == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the tape needs at least one cell"));
+        }
+        #[cfg(unix)]
+        return Ok(Tape(Storage::Mapped(Mapping::new(path, cells)?)));
+        #[cfg(not(unix))]
+        {
+            let _ = path;
+            Err(io::Error::new(io::ErrorKind::Unsupported, "tape files are only supported on Unix"))
+        }
+    }
+
+    /// Number of cells in the tape file, or `None` if it is missing or empty.
+    pub fn cells_in(path: &Path) -> Option<usize> {
+        let len = fs::metadata(path).ok()?.len();
+        Some((len / CELL_BYTES) as usize).filter(|&n| n > 0)
+    }
+}
+
+impl From<Vec<u32>> for Tape {
+    /// A tape of these cells, in memory.
+    fn from(cells: Vec<u32>) -> Tape {
+        Tape(Storage::Heap(cells))
+    }
+}
+
+impl Deref for Tape {
+    type Target = [u32];
+
+    fn deref(&self) -> &[u32] {
+        match &self.0 {
+            Storage::Heap(cells) => cells,
+            #[cfg(unix)]
+            Storage::Mapped(mapping) => mapping.cells(),
+        }
+    }
+}
+
+impl DerefMut for Tape {
+    fn deref_mut(&mut self) -> &mut [u32] {
+        match &mut self.0 {
+            Storage::Heap(cells) => cells,
+            #[cfg(unix)]
+            Storage::Mapped(mapping) => mapping.cells_mut(),
+        }
+    }
+}
+
+/// A file mapped to memory, shared with the file so that writes reach it.
+#[cfg(unix)]
+struct Mapping {
+    ptr: *mut u32,
+    cells: usize,
+}
+
+#[cfg(unix)]
+impl Mapping {
+    fn new(path: &Path, cells: usize) -> io::Result<Mapping> {
+        use std::os::unix::io::AsRawFd;
+
+        let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "the tape is too large to map");
+        let bytes = (cells as u64).checked_mul(CELL_BYTES).filter(|&b| b <= isize::MAX as u64).ok_or_else(too_large)?;
+        let file = fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        if file.metadata()?.len() != bytes {
+            file.set_len(bytes)?;
+        }
+        // The file can be closed once mapped, the mapping keeps it open.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                bytes as usize,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mapping { ptr: ptr.cast(), cells })
+    }
+
+    fn cells(&self) -> &[u32] {
+        // The mapping is page aligned and lives as long as self.
+        unsafe { std::slice::from_raw_parts(self.ptr, self.cells) }
+    }
+
+    fn cells_mut(&mut self) -> &mut [u32] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.cells) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mapping {
+    /// Writes the cells back to the file before unmapping it, so that they are on disk when the machine goes away.
+    fn drop(&mut self) {
+        let len = self.cells * CELL_BYTES as usize;
+        unsafe {
+            libc::msync(self.ptr.cast(), len, libc::MS_SYNC);
+            libc::munmap(self.ptr.cast(), len);
+        }
+    }
+}
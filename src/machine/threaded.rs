@@ -0,0 +1,313 @@
+// Threaded dispatch of instructions.
+// MIT license.
+
+use std::collections::HashMap;
+use std::mem;
+
+use crate::error::BfError;
+use crate::program::{Op, Program};
+
+use super::tiered::{self, Code};
+use super::Machine;
+
+/// Runs one instruction, at the given index, and returns the index of the next one. On errors, it stores the error
+/// and returns `STOP`.
+type Handler = fn(&mut Threaded, &Op, usize) -> usize;
+
+/// Index returned by the handlers when their instruction failed.
+const STOP: usize = usize::MAX;
+
+/// A machine running a program through a table of handlers.
+struct Threaded<'a> {
+    machine: &'a mut Machine,
+    program: &'a Program,
+    error: Option<BfError>,
+    /// Where to continue after stopping to print the state or the progress.
+    resume: Option<usize>,
+    /// Whether the code stopped at `#`, rather than for a dump or the progress.
+    debug: bool,
+    /// Number of iterations after which loops are compiled, with the tiered dispatch.
+    threshold: u64,
+    /// Number of iterations every loop ran, at the index of its closing bracket.
+    iterations: Vec<u64>,
+    /// The compiled loops, at the index of their opening bracket.
+    compiled: HashMap<usize, Code<'a>>,
+    /// The loop to compile, when the code stopped for that.
+    tier_up: Option<usize>,
+    /// The compiled loop to run, when the code stopped for that.
+    entering: Option<usize>,
+    /// The loop whose handler is to be replaced by its compiled code.
+    patch: Option<usize>,
+}
+
+impl Threaded<'_> {
+    /// Prints the state or the progress, compiles a loop or runs a compiled one if the code stopped for that, and
+    /// returns where to continue. These stop the code like errors, to keep the step count out of the handlers.
+    fn resume(&mut self, steps: &mut u64) -> Option<usize> {
+        let next = self.resume.take()?;
+        self.machine.steps = *steps;
+        if let Some(open) = self.tier_up.take() {
+            self.compiled.insert(open, tiered::compile(self.machine, self.program, open));
+            self.patch = Some(open);
+            // The closing bracket that went past the threshold runs again, as the test of the compiled loop.
+            *steps -= 1;
+            return Some(next);
+        }
+        if let Some(open) = self.entering.take() {
+            // The compiled loop counts its opening bracket itself.
+            self.machine.steps -= 1;
+            let result = self.compiled[&open](self.machine);
+            *steps = self.machine.steps;
+            return match result {
+                Ok(()) => Some(next),
+                Err(e) => {
+                    // Instructions of compiled code are not those of the program, errors are at the loop.
+                    self.machine.at = open;
+                    self.error = Some(e);
+                    None
+                },
+            };
+        }
+        if mem::take(&mut self.debug) {
+            if let Err(e) = self.machine.print_debug() {
+                self.error = Some(e);
+                return None;
+            }
+        } else {
+            self.machine.notify(self.program);
+        }
+        Some(next)
+    }
+
+    /// Returns the next index if the result of the instruction at the given index is fine, and keeps the error and
+    /// where it happened otherwise.
+    fn check(&mut self, result: Result<(), BfError>, i: usize, next: usize) -> usize {
+        match result {
+            Ok(()) => next,
+            Err(e) => {
+                self.machine.at = i;
+                self.error = Some(e);
+                STOP
+            },
+        }
+    }
+}
+
+impl Machine {
+    /// Runs the program by calling the handler of every instruction through a table, instead of matching on them.
+    pub(super) fn run_threaded(&mut self, program: &Program) -> Result<(), BfError> {
+        let threshold = self.tier_threshold;
+        let handler = |op| match (op, threshold) {
+            (Op::Close(_), Some(_)) => run_close_tiered,
+            _ => handler(op),
+        };
+        let mut code: Vec<(Handler, Op)> = program.ops.iter().map(|&op| (handler(op), op)).collect();
+        let mut threaded = Threaded {
+            machine: self,
+            program,
+            error: None,
+            resume: None,
+            debug: false,
+            threshold: threshold.unwrap_or(u64::MAX),
+            iterations: if threshold.is_some() { vec![0; program.len()] } else { vec![] },
+            compiled: HashMap::new(),
+            tier_up: None,
+            entering: None,
+            patch: None,
+        };
+        let mut i: usize = 0;
+        let mut steps = 0;
+        loop {
+            while let Some((handler, op)) = code.get(i) {
+                steps += 1;
+                i = handler(&mut threaded, op, i);
+            }
+            match threaded.resume(&mut steps) {
+                Some(next) => i = next,
+                None => break,
+            }
+            if let Some(open) = threaded.patch.take() {
+                code[open].0 = run_compiled;
+            }
+        }
+        // Stopping at an error jumps out of the code.
+        let error = threaded.error.take();
+        drop(threaded);
+        self.steps = steps;
+        self.output.flush()?;
+        error.map_or(Ok(()), Err)
+    }
+}
+
+/// Returns the handler of the instruction.
+fn handler(op: Op) -> Handler {
+    match op {
+        Op::Move(_) => run_move,
+        Op::Add(_) => run_add,
+        Op::AddAt { .. } => run_add_at,
+        Op::Out => run_out,
+        Op::OutByte(_) => run_out_byte,
+        Op::In => run_in,
+        Op::Open(_) => run_open,
+        Op::Close(_) => run_close,
+        Op::Debug => run_debug,
+        Op::Channel => run_channel,
+        Op::Send => run_send,
+        Op::Receive => run_receive,
+        Op::Clock => run_clock,
+        Op::File(_) => run_file,
+        Op::Fork => unreachable!("programs that fork run in the loop that matches"),
+        Op::Clear => run_clear,
+        Op::MulAdd { .. } => run_mul_add,
+        Op::Fill { .. } => run_fill,
+        Op::Scan(_) => run_scan,
+    }
+}
+
+// Every handler is only called with the instruction it was chosen for.
+
+fn run_move(t: &mut Threaded, op: &Op, i: usize) -> usize {
+    let &Op::Move(n) = op else { unreachable!() };
+    let result = t.machine.offset(n).map(|p| t.machine.ptr = p);
+    t.check(result, i, i + 1)
+}
+
+fn run_add(t: &mut Threaded, op: &Op, i: usize) -> usize {
+    let &Op::Add(n) = op else { unreachable!() };
+    let m = &mut *t.machine;
+    m.memory[m.ptr] = m.memory[m.ptr].wrapping_add(n as u32) & m.mask;
+    i + 1
+}
+
+fn run_add_at(t: &mut Threaded, op: &Op, i: usize) -> usize {
+    let &Op::AddAt { offset, value } = op else { unreachable!() };
+    let result = t.machine.add_at(offset, value);
+    t.check(result, i, i + 1)
+}
+
+fn run_out(t: &mut Threaded, _: &Op, i: usize) -> usize {
+    let m = &mut *t.machine;
+    let contents = m.memory[m.ptr] as u8;
+    let result = m.write(contents);
+    t.check(result, i, i + 1)
+}
+
+fn run_out_byte(t: &mut Threaded, op: &Op, i: usize) -> usize {
+    let &Op::OutByte(b) = op else { unreachable!() };
+    let result = t.machine.write(b);
+    t.check(result, i, i + 1)
+}
+
+fn run_in(t: &mut Threaded, _: &Op, i: usize) -> usize {
+    let result = t.machine.input();
+    t.check(result, i, i + 1)
+}
+
+fn run_open(t: &mut Threaded, op: &Op, i: usize) -> usize {
+    let &Op::Open(close) = op else { unreachable!() };
+    let m = &mut *t.machine;
+    if m.memory[m.ptr] == 0 {
+        return close + 1;
+    }
+    let result = m.enter(i, t.program);
+    t.check(result, i, i + 1)
+}
+
+fn run_close(t: &mut Threaded, op: &Op, i: usize) -> usize {
+    let &Op::Close(open) = op else { unreachable!() };
+    let m = &mut *t.machine;
+    if m.memory[m.ptr] != 0 {
+        if m.attention() {
+            m.at = i;
+            t.resume = Some(open + 1);
+            return STOP;
+        }
+        let result = m.check_interrupt();
+        return t.check(result, i, open + 1);
+    }
+    m.stack.pop();
+    i + 1
+}
+
+/// The closing bracket of the tiered dispatch, which counts the iterations and has the loop compiled once they go
+/// past the threshold.
+fn run_close_tiered(t: &mut Threaded, op: &Op, i: usize) -> usize {
+    let &Op::Close(open) = op else { unreachable!() };
+    if t.machine.memory[t.machine.ptr] != 0 {
+        t.iterations[i] += 1;
+        if t.iterations[i] == t.threshold {
+            // The compiled loop enters again.
+            t.machine.stack.pop();
+            t.tier_up = Some(open);
+            t.resume = Some(open);
+            return STOP;
+        }
+    }
+    run_close(t, op, i)
+}
+
+/// Runs the compiled code of the loop opening here.
+fn run_compiled(t: &mut Threaded, op: &Op, i: usize) -> usize {
+    let &Op::Open(close) = op else { unreachable!() };
+    t.entering = Some(i);
+    t.resume = Some(close + 1);
+    STOP
+}
+
+fn run_debug(t: &mut Threaded, _: &Op, i: usize) -> usize {
+    t.machine.at = i;
+    t.debug = true;
+    t.resume = Some(i + 1);
+    STOP
+}
+
+fn run_channel(t: &mut Threaded, _: &Op, i: usize) -> usize {
+    let m = &mut *t.machine;
+    let result = m.select(m.memory[m.ptr] as usize);
+    t.check(result, i, i + 1)
+}
+
+fn run_send(t: &mut Threaded, _: &Op, i: usize) -> usize {
+    let result = t.machine.send();
+    t.check(result, i, i + 1)
+}
+
+fn run_receive(t: &mut Threaded, _: &Op, i: usize) -> usize {
+    let result = t.machine.receive();
+    t.check(result, i, i + 1)
+}
+
+fn run_clock(t: &mut Threaded, _: &Op, i: usize) -> usize {
+    t.machine.clock();
+    i + 1
+}
+
+fn run_file(t: &mut Threaded, op: &Op, i: usize) -> usize {
+    let &Op::File(file_op) = op else { unreachable!() };
+    let result = t.machine.file(file_op);
+    t.check(result, i, i + 1)
+}
+
+fn run_clear(t: &mut Threaded, _: &Op, i: usize) -> usize {
+    let m = &mut *t.machine;
+    m.memory[m.ptr] = 0;
+    i + 1
+}
+
+fn run_mul_add(t: &mut Threaded, op: &Op, i: usize) -> usize {
+    let &Op::MulAdd { offset, factor } = op else { unreachable!() };
+    let result = t.machine.mul_add(offset, factor);
+    t.check(result, i, i + 1)
+}
+
+fn run_fill(t: &mut Threaded, op: &Op, i: usize) -> usize {
+    let &Op::Fill { offset, len, value } = op else { unreachable!() };
+    let result = t.machine.fill(offset, len, value);
+    t.check(result, i, i + 1)
+}
+
+fn run_scan(t: &mut Threaded, op: &Op, i: usize) -> usize {
+    let &Op::Scan(n) = op else { unreachable!() };
+    let result = t.machine.scan(n);
+    t.check(result, i, i + 1)
+}
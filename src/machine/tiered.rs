@@ -0,0 +1,156 @@
+// Compilation of hot loops to closures, for the tiered dispatch.
+// MIT license.
+
+use std::collections::HashMap;
+
+use crate::error::BfError;
+use crate::optimize::{self, Target, PASSES};
+use crate::program::{Instr, Op, Program};
+
+use super::{CellWidth, Machine};
+
+/// Compiled code, running on the machine. Every instruction adds to the step count of the machine.
+pub(super) type Code<'a> = Box<dyn Fn(&mut Machine) -> Result<(), BfError> + 'a>;
+
+/// Compiles the loop opening at the given index, after optimizing it with every pass but `const-fold`, which only
+/// works from the start of a program.
+pub(super) fn compile<'a>(machine: &Machine, program: &'a Program, open: usize) -> Code<'a> {
+    let Op::Open(close) = program.ops[open] else { unreachable!("only loops are compiled") };
+    let cell_width = match machine.mask {
+        0xff => CellWidth::U8,
+        0xffff => CellWidth::U16,
+        _ => CellWidth::U32,
+    };
+    // The cells are not known when entering the loop.
+    let target = Target { cell_width, mem_size: machine.memory.len(), fresh: false };
+    let instrs: Vec<Instr> = (open..=close).map(|i| Instr { op: program.ops[i], span: program.spans[i] }).collect();
+    let instrs = PASSES
+        .iter()
+        .filter(|&&name| name != "const-fold")
+        .map(|name| optimize::pass(name).expect("built-in passes exist"))
+        .fold(instrs, |instrs, pass| pass.run(instrs, &target));
+
+    // The loops on the stack of the machine are the indices of their opening brackets in the program, found back
+    // from their source positions, which the passes keep.
+    let indices: HashMap<usize, usize> =
+        (open..=close).filter(|&i| matches!(program.ops[i], Op::Open(_))).map(|i| (program.spans[i].start, i)).collect();
+    let mut stack: Vec<(usize, Vec<Code<'a>>)> = vec![(open, vec![])];
+    for instr in instrs {
+        match instr.op {
+            Op::Open(_) => stack.push((indices.get(&instr.span.start).copied().unwrap_or(open), vec![])),
+            Op::Close(_) => {
+                let (index, body) = stack.pop().expect("balanced brackets");
+                stack.last_mut().expect("balanced brackets").1.push(compile_loop(program, index, body));
+            },
+            op => stack.last_mut().expect("balanced brackets").1.push(compile_op(op, open)),
+        }
+    }
+    let (_, code) = stack.pop().expect("balanced brackets");
+    Box::new(move |m| code.iter().try_for_each(|c| c(m)))
+}
+
+/// Compiles a loop, from the index of its opening bracket in the program and its compiled body.
+fn compile_loop<'a>(program: &'a Program, open: usize, body: Vec<Code<'a>>) -> Code<'a> {
+    Box::new(move |m| {
+        m.steps += 1;
+        if m.memory[m.ptr] == 0 {
+            return Ok(());
+        }
+        m.enter(open, program)?;
+        loop {
+            for code in &body {
+                code(m)?;
+            }
+            m.steps += 1;
+            if m.memory[m.ptr] == 0 {
+                break;
+            }
+            if m.attention() {
+                m.at = open;
+                m.notify(program);
+            }
+            m.check_interrupt()?;
+        }
+        m.stack.pop();
+        Ok(())
+    })
+}
+
+/// Compiles an instruction other than a bracket, of the loop opening at the given index.
+fn compile_op<'a>(op: Op, open: usize) -> Code<'a> {
+    match op {
+        Op::Move(n) => Box::new(move |m| {
+            m.steps += 1;
+            m.ptr = m.offset(n)?;
+            Ok(())
+        }),
+        Op::Add(n) => Box::new(move |m| {
+            m.steps += 1;
+            m.memory[m.ptr] = m.memory[m.ptr].wrapping_add(n as u32) & m.mask;
+            Ok(())
+        }),
+        Op::AddAt { offset, value } => Box::new(move |m| {
+            m.steps += 1;
+            m.add_at(offset, value)
+        }),
+        Op::Out => Box::new(|m| {
+            m.steps += 1;
+            let contents = m.memory[m.ptr] as u8;
+            m.write(contents)
+        }),
+        Op::OutByte(b) => Box::new(move |m| {
+            m.steps += 1;
+            m.write(b)
+        }),
+        Op::In => Box::new(|m| {
+            m.steps += 1;
+            m.input()
+        }),
+        // The instructions of compiled code are not those of the program, `#` shows the loop.
+        Op::Debug => Box::new(move |m| {
+            m.steps += 1;
+            m.at = open;
+            m.print_debug()
+        }),
+        Op::Channel => Box::new(|m| {
+            m.steps += 1;
+            m.select(m.memory[m.ptr] as usize)
+        }),
+        Op::Send => Box::new(|m| {
+            m.steps += 1;
+            m.send()
+        }),
+        Op::Receive => Box::new(|m| {
+            m.steps += 1;
+            m.receive()
+        }),
+        Op::Clock => Box::new(|m| {
+            m.steps += 1;
+            m.clock();
+            Ok(())
+        }),
+        Op::File(file_op) => Box::new(move |m| {
+            m.steps += 1;
+            m.file(file_op)
+        }),
+        Op::Fork => unreachable!("programs that fork run in the loop that matches"),
+        Op::Clear => Box::new(|m| {
+            m.steps += 1;
+            m.memory[m.ptr] = 0;
+            Ok(())
+        }),
+        Op::MulAdd { offset, factor } => Box::new(move |m| {
+            m.steps += 1;
+            m.mul_add(offset, factor)
+        }),
+        Op::Fill { offset, len, value } => Box::new(move |m| {
+            m.steps += 1;
+            m.fill(offset, len, value)
+        }),
+        Op::Scan(n) => Box::new(move |m| {
+            m.steps += 1;
+            m.scan(n)
+        }),
+        Op::Open(_) | Op::Close(_) => unreachable!("brackets are compiled as loops"),
+    }
+}
@@ -0,0 +1,146 @@
+// The minimal command line interface, which only runs programs, for builds without the `cli` feature.
+// MIT license.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, prelude::*};
+use std::process;
+use std::str::FromStr;
+
+use brainfuck::{BfError, CellWidth, Eof, Machine, OptLevel, Program};
+use brainfuck::machine::DEFAULT_MEM_SIZE;
+use brainfuck::optimize::optimize;
+
+use crate::{console, signal};
+
+const USAGE: &str = "\
+Usage: brainfuck [OPTIONS] [FILE]
+
+Runs the program in FILE, or the one given with -e, or else the one read from the standard input.
+
+Options:
+  -e <CODE>             Run this code
+  -O <LEVEL>            Optimization level, from 0 to 3 [default: 2]
+      --mem-size <N>    Number of cells in the memory tape [default: 40000]
+      --cell-width <W>  Width of the memory cells, in bits (8, 16 or 32) [default: 8]
+      --eof <EOF>       What ',' stores at the end of the input (zero, unchanged or max) [default: zero]
+      --max-steps <N>   Stop the program after this many instructions
+  -h, --help            Print help
+  -V, --version         Print version";
+
+/// Exit status of invalid arguments, like that of the full interface.
+const USAGE_STATUS: i32 = 2;
+
+/// What the arguments ask for.
+#[derive(Default)]
+struct Args {
+    eval: Option<String>,
+    input: Option<String>,
+    opt_level: OptLevel,
+    mem_size: Option<usize>,
+    cell_width: Option<CellWidth>,
+    eof: Option<Eof>,
+    max_steps: Option<u64>,
+}
+
+pub fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("[error] {}\n\n{}", e, USAGE);
+            process::exit(USAGE_STATUS);
+        },
+    };
+    if let Err(e) = run(&args) {
+        let _ = io::stdout().flush();
+        if let Some(BfError::Io(io_error)) = e.downcast_ref::<BfError>() {
+            if io_error.kind() == io::ErrorKind::BrokenPipe {
+                return;
+            }
+        }
+        eprintln!("[error] {}", e);
+        if let Some(BfError::Interrupted) = e.downcast_ref::<BfError>() {
+            process::exit(signal::INTERRUPTED_STATUS);
+        }
+        process::exit(1);
+    }
+}
+
+/// Parses the arguments. Returns `None` when they only ask for the help or the version, which are printed.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args::default();
+    while let Some(arg) = args.next() {
+        // Values go after the flag, or after its `=`, or right after `-O`.
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ if arg.starts_with("-O") && arg.len() > 2 => ("-O".to_string(), Some(arg[2..].to_string())),
+            _ => (arg, None),
+        };
+        let mut value = || inline.clone().or_else(|| args.next()).ok_or_else(|| format!("{} needs a value", flag));
+        match flag.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(None);
+            },
+            "-V" | "--version" => {
+                println!("brainfuck {}", env!("CARGO_PKG_VERSION"));
+                return Ok(None);
+            },
+            "-e" | "--eval" => parsed.eval = Some(value()?),
+            "-O" => parsed.opt_level = parse(&value()?)?,
+            "--mem-size" => parsed.mem_size = Some(parse(&value()?)?),
+            "--cell-width" => parsed.cell_width = Some(parse(&value()?)?),
+            "--eof" => parsed.eof = Some(parse(&value()?)?),
+            "--max-steps" => parsed.max_steps = Some(parse(&value()?)?),
+            "-" => parsed.input = Some(flag),
+            _ if flag.starts_with('-') => return Err(format!("unexpected argument '{}'", flag)),
+            _ if parsed.input.is_some() => return Err(format!("unexpected argument '{}'", flag)),
+            _ => parsed.input = Some(flag),
+        }
+    }
+    if parsed.eval.is_some() && parsed.input.is_some() {
+        return Err("the code of -e and a program file can not be given together".to_string());
+    }
+    Ok(Some(parsed))
+}
+
+fn parse<T: FromStr>(value: &str) -> Result<T, String>
+where
+    T::Err: ToString,
+{
+    value.parse().map_err(|e: T::Err| format!("invalid value '{}': {}", value, e.to_string()))
+}
+
+/// Reads, optimizes and runs the program. Ctrl-C stops it like in the full interface.
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let source = match (&args.eval, args.input.as_deref()) {
+        (Some(code), _) => code.clone(),
+        (None, Some(path)) if path != "-" => {
+            fs::read_to_string(path).map_err(|e| format!("Can not read {}: {}", path, e))?
+        },
+        (None, _) => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source)?;
+            source
+        },
+    };
+    let program = optimize(&Program::parse(&source)?, args.opt_level);
+    let mut machine = Machine::builder()
+        .mem_size(args.mem_size.unwrap_or(DEFAULT_MEM_SIZE))
+        .cell_width(args.cell_width.unwrap_or_default())
+        .eof(args.eof.unwrap_or_default())
+        .max_steps(args.max_steps)
+        .io(console::stdin(), console::stdout())
+        .interrupt(&signal::INTERRUPTED)
+        .build();
+    signal::install();
+    let result = machine.run(&program);
+    if let Err(BfError::Interrupted) = result {
+        eprint!("{}", machine.report(&program));
+    }
+    result?;
+    io::stdout().flush()?;
+    Ok(())
+}
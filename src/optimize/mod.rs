@@ -0,0 +1,288 @@
+// Optimization of parsed programs.
+// MIT license.
+
+pub mod passes;
+mod report;
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::machine::{CellWidth, DEFAULT_MEM_SIZE};
+use crate::profile::Profile;
+use crate::program::{Instr, Op, Program};
+
+pub use report::PassReport;
+
+/// How much to optimize a program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum OptLevel {
+    /// No optimizations, every instruction is executed as written.
+    O0,
+    /// Fuse runs of moves and additions.
+    O1,
+    /// Also replace clear, copy and scan loops with dedicated instructions, add at offsets from the pointer instead
+    /// of moving it, fill runs of cells at once, and remove dead code.
+    #[default]
+    O2,
+    /// Also reorder the moves and additions between loops to minimize pointer movement, and run the start of the
+    /// program ahead of time up to its first input.
+    O3,
+}
+impl FromStr for OptLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<OptLevel, String> {
+        match s {
+            "0" => Ok(OptLevel::O0),
+            "1" => Ok(OptLevel::O1),
+            "2" => Ok(OptLevel::O2),
+            "3" => Ok(OptLevel::O3),
+            _ => Err(format!("invalid optimization level '{}', expected 0, 1, 2 or 3", s)),
+        }
+    }
+}
+impl fmt::Display for OptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", *self as u8)
+    }
+}
+
+/// What the passes can assume about the machine the program will run on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    /// Width of the memory cells.
+    pub cell_width: CellWidth,
+    /// Number of cells in the memory tape.
+    pub mem_size: usize,
+    /// Whether the program runs on a fresh machine, with the pointer at zero and all cells at zero. This is not the
+    /// case when many programs run one after the other on the same machine.
+    pub fresh: bool,
+}
+impl Default for Target {
+    fn default() -> Target {
+        Target { cell_width: CellWidth::default(), mem_size: DEFAULT_MEM_SIZE, fresh: true }
+    }
+}
+
+/// An optimization pass, which transforms the instructions of a program into equivalent ones.
+///
+/// The instructions given to a pass, and the ones it returns, have balanced brackets but their jump targets are
+/// not meaningful. They are resolved once all passes have run. Passes should keep the source spans meaningful,
+/// joining the spans of the instructions they merge.
+pub trait Pass {
+    /// Name of the pass, as given to `--passes`.
+    fn name(&self) -> &'static str;
+
+    /// Runs the pass, for a program running on the given target.
+    fn run(&self, instrs: Vec<Instr>, target: &Target) -> Vec<Instr>;
+}
+
+/// Names of all the built-in passes, in their default order.
+pub const PASSES: &[&str] = &["fuse", "reorder", "clear-loop", "copy-loop", "scan", "offset", "fill", "dce", "const-fold"];
+
+/// Returns the built-in pass with the given name.
+pub fn pass(name: &str) -> Option<Box<dyn Pass>> {
+    match name {
+        "fuse" => Some(Box::new(passes::Fuse)),
+        "reorder" => Some(Box::new(passes::Reorder)),
+        "clear-loop" => Some(Box::new(passes::ClearLoop)),
+        "copy-loop" => Some(Box::new(passes::CopyLoop)),
+        "scan" => Some(Box::new(passes::Scan)),
+        "offset" => Some(Box::new(passes::OffsetFuse)),
+        "fill" => Some(Box::new(passes::BulkFill)),
+        "dce" => Some(Box::new(passes::DeadCode)),
+        "const-fold" => Some(Box::new(passes::ConstFold)),
+        _ => None,
+    }
+}
+
+/// What one pass did.
+#[derive(Debug, Clone)]
+pub struct PassStats {
+    /// Name of the pass.
+    pub name: &'static str,
+    /// Number of instructions before the pass.
+    pub before: usize,
+    /// Number of instructions after the pass.
+    pub after: usize,
+    /// Time taken by the pass.
+    pub time: Duration,
+}
+impl fmt::Display for PassStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<12} {:>8} -> {:<8} {:?}", self.name, self.before, self.after, self.time)
+    }
+}
+
+/// Function called with what a pass did, and the instructions before and after it.
+type EachPass<'a> = dyn FnMut(&PassStats, &[Instr], &[Instr]) + 'a;
+
+/// A sequence of passes.
+#[derive(Default)]
+pub struct Pipeline {
+    passes: Vec<Box<dyn Pass>>,
+    target: Target,
+    /// Source positions of the loops found hot by a profile.
+    hot: Vec<usize>,
+}
+impl Pipeline {
+    /// Creates an empty pipeline.
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// Creates the pipeline of the given optimization level.
+    pub fn for_level(level: OptLevel) -> Pipeline {
+        let names: &[&str] = match level {
+            OptLevel::O0 => &[],
+            OptLevel::O1 => &["fuse"],
+            OptLevel::O2 => &["fuse", "clear-loop", "copy-loop", "scan", "offset", "fill", "dce"],
+            OptLevel::O3 => PASSES,
+        };
+        Pipeline::from_names(names.iter().copied()).expect("built-in passes exist")
+    }
+
+    /// Creates a pipeline with the built-in passes with the given names, in order.
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Result<Pipeline, String> {
+        let mut pipeline = Pipeline::new();
+        for name in names {
+            let pass = pass(name).ok_or_else(|| format!("unknown pass '{}', expected one of: {}", name, PASSES.join(", ")))?;
+            pipeline.passes.push(pass);
+        }
+        Ok(pipeline)
+    }
+
+    /// Sets the target the program will run on. By default, a fresh machine with the default settings.
+    pub fn target(mut self, target: Target) -> Pipeline {
+        self.target = target;
+        self
+    }
+
+    /// Optimizes the loops the profile found hot with every pass but `const-fold`, whatever the passes of the
+    /// pipeline, before running them. Those passes include the ones of level 3, which are not worth their time
+    /// on the rest of the program.
+    pub fn profile(mut self, profile: &Profile) -> Pipeline {
+        self.hot = profile.hot_loops();
+        self
+    }
+
+    /// Adds a pass at the end of the pipeline.
+    pub fn push(&mut self, pass: Box<dyn Pass>) {
+        self.passes.push(pass);
+    }
+
+    /// Names of the passes in the pipeline.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.passes.iter().map(|p| p.name()).collect()
+    }
+
+    /// Runs all the passes on the program, returning the optimized program and what every pass did.
+    pub fn run(&self, program: &Program) -> (Program, Vec<PassStats>) {
+        self.run_with(program, None)
+    }
+
+    /// Runs all the passes like `run`, reporting in detail what every pass did instead, with the number of
+    /// instructions run estimated from the profile, if there is one.
+    pub fn report(&self, program: &Program, profile: Option<&Profile>) -> (Program, Vec<PassReport>) {
+        let mut reports = vec![];
+        let mut each = |stats: &PassStats, before: &[Instr], after: &[Instr]| {
+            reports.push(PassReport::new(stats.clone(), before, after, profile));
+        };
+        let (optimized, _) = self.run_with(program, Some(&mut each));
+        (optimized, reports)
+    }
+
+    /// Runs all the passes, calling `each` with the instructions before and after every pass, if given.
+    fn run_with(&self, program: &Program, mut each: Option<&mut EachPass>) -> (Program, Vec<PassStats>) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("optimize", passes = self.passes.len()).entered();
+
+        let mut instrs = program.instrs();
+        let mut stats = Vec::with_capacity(self.passes.len() + 1);
+        // The instructions before the pass, only kept to report on them.
+        let mut input = vec![];
+        if !self.hot.is_empty() {
+            let before = instrs.len();
+            if each.is_some() {
+                input.clone_from(&instrs);
+            }
+            let start = Instant::now();
+            instrs = self.optimize_hot(instrs);
+            stats.push(PassStats { name: "hot-loops", before, after: instrs.len(), time: start.elapsed() });
+            if let Some(each) = each.as_mut() {
+                each(stats.last().expect("just pushed"), &input, &instrs);
+            }
+        }
+        for pass in &self.passes {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!("pass", name = pass.name()).entered();
+
+            let before = instrs.len();
+            if each.is_some() {
+                input.clone_from(&instrs);
+            }
+            let start = Instant::now();
+            instrs = pass.run(instrs, &self.target);
+            stats.push(PassStats { name: pass.name(), before, after: instrs.len(), time: start.elapsed() });
+            if let Some(each) = each.as_mut() {
+                each(stats.last().expect("just pushed"), &input, &instrs);
+            }
+        }
+        (program.with_instrs(instrs), stats)
+    }
+}
+
+impl Pipeline {
+    /// Runs every pass but `const-fold` on the bodies of the hot loops.
+    fn optimize_hot(&self, instrs: Vec<Instr>) -> Vec<Instr> {
+        // The cells are not known when entering a loop.
+        let target = Target { fresh: false, ..self.target };
+        let passes: Vec<Box<dyn Pass>> =
+            PASSES.iter().filter(|&&name| name != "const-fold").map(|name| pass(name).expect("built-in passes exist")).collect();
+        let jumps = passes::match_brackets(&instrs);
+        let mut out = Vec::with_capacity(instrs.len());
+        let mut i = 0;
+        while i < instrs.len() {
+            let instr = instrs[i];
+            if !matches!(instr.op, Op::Open(_)) || !self.hot.contains(&instr.span.start) {
+                out.push(instr);
+                i += 1;
+                continue;
+            }
+            let close = jumps[i];
+            let body = passes.iter().fold(instrs[i + 1..close].to_vec(), |body, pass| pass.run(body, &target));
+            out.push(instr);
+            out.extend(body);
+            out.push(instrs[close]);
+            i = close + 1;
+        }
+        out
+    }
+}
+
+/// Optimizes the program at the given level.
+pub fn optimize(program: &Program, level: OptLevel) -> Program {
+    Pipeline::for_level(level).run(program).0
+}
+
+/// Default maximum number of instructions executed by `precompute`.
+pub const PRECOMPUTE_STEPS: usize = 100_000_000;
+
+/// Runs a whole program that never reads its input ahead of time, returning everything it prints. Returns `None`
+/// if the program reads its input or uses `#`, if it goes out of the memory tape, or if it does not end within the
+/// given number of instructions.
+pub fn precompute(program: &Program, target: &Target, max_steps: usize) -> Option<Vec<u8>> {
+    if program.ops.iter().any(|op| matches!(op, Op::In | Op::Debug | Op::Channel | Op::Send | Op::Receive | Op::Fork | Op::Clock | Op::File(_))) {
+        return None;
+    }
+    let instrs = program.instrs();
+    let jumps = passes::match_brackets(&instrs);
+    let mut folder = passes::Folder::new(target, max_steps, usize::MAX);
+    if folder.run(&instrs, &jumps, None) < instrs.len() {
+        return None;
+    }
+    Some(folder.output)
+}
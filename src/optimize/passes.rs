@@ -0,0 +1,612 @@
+// Optimization passes.
+// MIT license.
+
+use std::collections::BTreeMap;
+
+use crate::program::{Instr, Op, Span};
+
+use super::{Pass, Target};
+
+/// Merges consecutive moves and consecutive additions, and removes the ones that cancel out.
+pub struct Fuse;
+impl Pass for Fuse {
+    fn name(&self) -> &'static str {
+        "fuse"
+    }
+
+    fn run(&self, instrs: Vec<Instr>, _target: &Target) -> Vec<Instr> {
+        let mut out: Vec<Instr> = Vec::with_capacity(instrs.len());
+        for instr in instrs {
+            match (out.last_mut(), instr.op) {
+                (Some(Instr { op: Op::Move(a), span }), Op::Move(b)) => {
+                    *a += b;
+                    *span = span.join(instr.span);
+                },
+                (Some(Instr { op: Op::Add(a), span }), Op::Add(b)) => {
+                    *a = a.wrapping_add(b);
+                    *span = span.join(instr.span);
+                },
+                _ => out.push(instr),
+            }
+            if let Some(Op::Move(0)) | Some(Op::Add(0)) = out.last().map(|i| i.op) {
+                out.pop();
+            }
+        }
+        out
+    }
+}
+
+/// Rewrites every run of moves and additions so that each cell is visited once, sweeping the touched cells in the
+/// direction that needs the least pointer movement.
+pub struct Reorder;
+impl Pass for Reorder {
+    fn name(&self) -> &'static str {
+        "reorder"
+    }
+
+    fn run(&self, instrs: Vec<Instr>, _target: &Target) -> Vec<Instr> {
+        let mut out: Vec<Instr> = Vec::with_capacity(instrs.len());
+        let mut i = 0;
+        while i < instrs.len() {
+            let end = instrs[i..].iter().position(|instr| !matches!(instr.op, Op::Move(_) | Op::Add(_))).map_or(instrs.len(), |n| i + n);
+            if end == i {
+                out.push(instrs[i]);
+                i += 1;
+                continue;
+            }
+            let run = &instrs[i..end];
+            let (deltas, last) = net_effect(run);
+            let reordered = sweep(&deltas, last);
+            if reordered.len() <= run.len() {
+                // The new instructions can not be traced back to individual ones, they all come from the run.
+                let span = span_of(run);
+                out.extend(reordered.into_iter().map(|op| Instr { op, span }));
+            } else {
+                out.extend_from_slice(run);
+            }
+            i = end;
+        }
+        out
+    }
+}
+
+/// Replaces loops like `[-]` with `Clear`. The addition must be odd for the loop to always terminate.
+pub struct ClearLoop;
+impl Pass for ClearLoop {
+    fn name(&self) -> &'static str {
+        "clear-loop"
+    }
+
+    fn run(&self, instrs: Vec<Instr>, _target: &Target) -> Vec<Instr> {
+        rewrite_loops(instrs, |body| match body {
+            [Instr { op: Op::Add(n), .. }] if n % 2 != 0 => Some(vec![Op::Clear]),
+            _ => None,
+        })
+    }
+}
+
+/// Replaces loops like `[->+>++<<]`, which add multiples of the current cell to other cells, with `MulAdd`s and a
+/// `Clear`.
+pub struct CopyLoop;
+impl Pass for CopyLoop {
+    fn name(&self) -> &'static str {
+        "copy-loop"
+    }
+
+    fn run(&self, instrs: Vec<Instr>, _target: &Target) -> Vec<Instr> {
+        rewrite_loops(instrs, |body| {
+            if body.is_empty() || !body.iter().all(|instr| matches!(instr.op, Op::Move(_) | Op::Add(_))) {
+                return None;
+            }
+            let (mut deltas, last) = net_effect(body);
+            if last != 0 {
+                return None;
+            }
+            // The loop runs as many times as the current cell says if it decrements it, and as many times as its
+            // negation if it increments it.
+            let sign = match deltas.remove(&0) {
+                Some(-1) => 1,
+                Some(1) => -1,
+                _ => return None,
+            };
+            let mut out: Vec<Op> = deltas.into_iter().map(|(offset, d)| Op::MulAdd { offset, factor: d.wrapping_mul(sign) }).collect();
+            out.push(Op::Clear);
+            Some(out)
+        })
+    }
+}
+
+/// Replaces loops like `[>]` or `[<<]` with `Scan`.
+pub struct Scan;
+impl Pass for Scan {
+    fn name(&self) -> &'static str {
+        "scan"
+    }
+
+    fn run(&self, instrs: Vec<Instr>, _target: &Target) -> Vec<Instr> {
+        rewrite_loops(instrs, |body| match body {
+            [Instr { op: Op::Move(n), .. }] => Some(vec![Op::Scan(*n)]),
+            _ => None,
+        })
+    }
+}
+
+/// Replaces every run of moves and additions with additions at offsets from the pointer, followed by a single move.
+pub struct OffsetFuse;
+impl Pass for OffsetFuse {
+    fn name(&self) -> &'static str {
+        "offset"
+    }
+
+    fn run(&self, instrs: Vec<Instr>, _target: &Target) -> Vec<Instr> {
+        let mut out: Vec<Instr> = Vec::with_capacity(instrs.len());
+        let mut i = 0;
+        while i < instrs.len() {
+            let end = instrs[i..].iter().position(|instr| !matches!(instr.op, Op::Move(_) | Op::Add(_))).map_or(instrs.len(), |n| i + n);
+            if end == i {
+                out.push(instrs[i]);
+                i += 1;
+                continue;
+            }
+            let run = &instrs[i..end];
+            let (deltas, last) = net_effect(run);
+            let span = span_of(run);
+            out.extend(deltas.into_iter().map(|(offset, value)| Instr {
+                op: if offset == 0 { Op::Add(value) } else { Op::AddAt { offset, value } },
+                span,
+            }));
+            if last != 0 {
+                out.push(Instr { op: Op::Move(last), span });
+            }
+            i = end;
+        }
+        out
+    }
+}
+
+/// Replaces runs that clear or set consecutive cells to the same value, like `[-]>[-]>[-]` or `[-]++>[-]++`, with a
+/// `Fill`.
+pub struct BulkFill;
+impl Pass for BulkFill {
+    fn name(&self) -> &'static str {
+        "fill"
+    }
+
+    fn run(&self, instrs: Vec<Instr>, _target: &Target) -> Vec<Instr> {
+        let mut out: Vec<Instr> = Vec::with_capacity(instrs.len());
+        let mut i = 0;
+        while i < instrs.len() {
+            let (consumed, ops) = match fill_run(&instrs[i..]) {
+                Some((consumed, ops)) => {
+                    let span = span_of(&instrs[i..i + consumed]);
+                    (consumed, ops.into_iter().map(|op| Instr { op, span }).collect())
+                },
+                None => (1, vec![instrs[i]]),
+            };
+            for instr in ops {
+                // The move after a fill can be merged with the next one.
+                match (out.last_mut(), instr.op) {
+                    (Some(Instr { op: Op::Move(a), span }), Op::Move(b)) => {
+                        *a += b;
+                        *span = span.join(instr.span);
+                    },
+                    _ => out.push(instr),
+                }
+                if let Some(Op::Move(0)) = out.last().map(|i| i.op) {
+                    out.pop();
+                }
+            }
+            i += consumed;
+        }
+        out
+    }
+}
+
+/// Finds a run of at least two cells set to the same value at the start of the instructions, each of them with a
+/// `Clear` and an optional `Add`, separated by moves of one cell in the same direction. Returns the number of
+/// instructions of the run and its replacement.
+fn fill_run(instrs: &[Instr]) -> Option<(usize, Vec<Op>)> {
+    // Parses one cell: the number of instructions and the value it ends up with.
+    let cell = |at: usize| -> Option<(usize, i32)> {
+        match (instrs.get(at).map(|i| i.op), instrs.get(at + 1).map(|i| i.op)) {
+            (Some(Op::Clear), Some(Op::Add(n))) => Some((2, n)),
+            (Some(Op::Clear), _) => Some((1, 0)),
+            _ => None,
+        }
+    };
+    let (mut end, value) = cell(0)?;
+    let step = match instrs.get(end).map(|i| i.op) {
+        Some(Op::Move(n)) if n == 1 || n == -1 => n,
+        _ => return None,
+    };
+    let mut len = 1;
+    while let Some(Op::Move(n)) = instrs.get(end).map(|i| i.op) {
+        match cell(end + 1) {
+            Some((size, v)) if n == step && v == value => {
+                end += 1 + size;
+                len += 1;
+            },
+            _ => break,
+        }
+    }
+    if len < 2 {
+        return None;
+    }
+    // The pointer ends at the last cell.
+    let last = step * (len as isize - 1);
+    Some((end, vec![Op::Fill { offset: last.min(0), len, value }, Op::Move(last)]))
+}
+
+/// Removes instructions that can not have any effect: loops at cells known to be zero, additions to cells that are
+/// cleared before being read, and everything after a top level loop known to never end.
+pub struct DeadCode;
+impl Pass for DeadCode {
+    fn name(&self) -> &'static str {
+        "dce"
+    }
+
+    fn run(&self, instrs: Vec<Instr>, target: &Target) -> Vec<Instr> {
+        let instrs = remove_dead_stores(instrs);
+        let jumps = match_brackets(&instrs);
+        let mask = target.cell_width.mask();
+        let mut out: Vec<Instr> = Vec::with_capacity(instrs.len());
+        // The value of the current cell, if known. The memory of a fresh machine is all zeros.
+        let mut value: Option<u32> = if target.fresh { Some(0) } else { None };
+        let mut depth = 0;
+        let mut i = 0;
+        while i < instrs.len() {
+            let instr = instrs[i];
+            match instr.op {
+                Op::Open(_) if value == Some(0) => {
+                    i = jumps[i] + 1;
+                    continue;
+                },
+                Op::Open(_) if depth == 0 && value.is_some() && never_ends(&instrs[i + 1..jumps[i]], mask) => {
+                    out.extend_from_slice(&instrs[i..=jumps[i]]);
+                    break;
+                },
+                Op::Open(_) => {
+                    depth += 1;
+                    value = None;
+                },
+                Op::Close(_) => {
+                    depth -= 1;
+                    value = Some(0);
+                },
+                Op::Clear if value == Some(0) => {
+                    i += 1;
+                    continue;
+                },
+                Op::Clear | Op::Scan(_) => value = Some(0),
+                Op::Add(n) => value = value.map(|v| v.wrapping_add(n as u32) & mask),
+                Op::Move(_) | Op::In | Op::Debug | Op::Receive | Op::Fork | Op::Clock | Op::File(_) => value = None,
+                Op::Channel | Op::Send => (),
+                Op::Fill { offset, len, value: v } if (offset..offset + len as isize).contains(&0) => {
+                    value = Some(v as u32 & mask)
+                },
+                Op::Out | Op::OutByte(_) | Op::MulAdd { .. } | Op::AddAt { .. } | Op::Fill { .. } => (),
+            }
+            out.push(instr);
+            i += 1;
+        }
+        out
+    }
+}
+
+/// Removes the additions and clears of cells that are cleared again before being read, in runs of instructions
+/// without loops.
+fn remove_dead_stores(instrs: Vec<Instr>) -> Vec<Instr> {
+    let mut keep = vec![true; instrs.len()];
+    // The writes to every cell, relative to the start of the run, that have not been read yet.
+    let mut pending: BTreeMap<isize, Vec<usize>> = BTreeMap::new();
+    let mut offset: isize = 0;
+    for (i, instr) in instrs.iter().enumerate() {
+        match instr.op {
+            Op::Move(n) => offset += n,
+            Op::Add(_) => pending.entry(offset).or_default().push(i),
+            Op::AddAt { offset: o, .. } => pending.entry(offset + o).or_default().push(i),
+            Op::Clear => {
+                for j in pending.insert(offset, vec![i]).unwrap_or_default() {
+                    keep[j] = false;
+                }
+            },
+            Op::Out | Op::In | Op::Channel | Op::Send | Op::Receive | Op::Clock => {
+                pending.remove(&offset);
+            },
+            Op::MulAdd { offset: o, .. } => {
+                pending.remove(&offset);
+                pending.remove(&(offset + o));
+            },
+            Op::OutByte(_) => (),
+            Op::Open(_) | Op::Close(_) | Op::Scan(_) | Op::Debug | Op::Fork | Op::File(_) | Op::Fill { .. } => {
+                pending.clear();
+                offset = 0;
+            },
+        }
+    }
+    instrs.into_iter().zip(keep).filter(|(_, k)| *k).map(|(instr, _)| instr).collect()
+}
+
+/// Whether a loop with the given body never ends once entered: it has no inner loops, it only moves, adds and
+/// prints, it leaves the pointer where it was and it does not change the current cell.
+fn never_ends(body: &[Instr], mask: u32) -> bool {
+    let mut offset: isize = 0;
+    let mut delta: u32 = 0;
+    for instr in body {
+        match instr.op {
+            Op::Move(n) => offset += n,
+            Op::Add(n) if offset == 0 => delta = delta.wrapping_add(n as u32),
+            Op::AddAt { offset: o, value } if offset + o == 0 => delta = delta.wrapping_add(value as u32),
+            Op::Add(_) | Op::AddAt { .. } | Op::Out | Op::OutByte(_) => (),
+            _ => return false,
+        }
+    }
+    offset == 0 && delta & mask == 0
+}
+
+/// The span covering all the given instructions, which can not be empty.
+fn span_of(instrs: &[Instr]) -> Span {
+    instrs.iter().map(|i| i.span).reduce(Span::join).expect("no instructions")
+}
+
+/// Computes the net addition to every cell and the final pointer offset of a run of moves and additions.
+fn net_effect(instrs: &[Instr]) -> (BTreeMap<isize, i32>, isize) {
+    let mut deltas: BTreeMap<isize, i32> = BTreeMap::new();
+    let mut offset: isize = 0;
+    for instr in instrs {
+        match instr.op {
+            Op::Move(n) => offset += n,
+            Op::Add(n) => {
+                let d = deltas.entry(offset).or_insert(0);
+                *d = d.wrapping_add(n);
+            },
+            _ => unreachable!("only moves and additions have a net effect"),
+        }
+    }
+    deltas.retain(|_, d| *d != 0);
+    (deltas, offset)
+}
+
+/// Generates the moves and additions to apply the given deltas and end at the given offset.
+fn sweep(deltas: &BTreeMap<isize, i32>, last: isize) -> Vec<Op> {
+    let (min, max) = match (deltas.keys().next(), deltas.keys().next_back()) {
+        (Some(&min), Some(&max)) => (min, max),
+        _ => return if last == 0 { vec![] } else { vec![Op::Move(last)] },
+    };
+    let ascending = min.abs() + (max - min) + (last - max).abs();
+    let descending = max.abs() + (max - min) + (last - min).abs();
+
+    let mut out = vec![];
+    let mut offset: isize = 0;
+    let mut visit = |(&o, &d): (&isize, &i32)| {
+        if o != offset {
+            out.push(Op::Move(o - offset));
+            offset = o;
+        }
+        out.push(Op::Add(d));
+    };
+    if ascending <= descending {
+        deltas.iter().for_each(&mut visit);
+    } else {
+        deltas.iter().rev().for_each(&mut visit);
+    }
+    if last != offset {
+        out.push(Op::Move(last - offset));
+    }
+    out
+}
+
+/// Replaces every innermost loop for which `rewrite` returns a replacement. The replacement instructions get the
+/// span of the whole loop.
+fn rewrite_loops(instrs: Vec<Instr>, rewrite: impl Fn(&[Instr]) -> Option<Vec<Op>>) -> Vec<Instr> {
+    let mut out: Vec<Instr> = Vec::with_capacity(instrs.len());
+    let mut i = 0;
+    while i < instrs.len() {
+        if let Op::Open(_) = instrs[i].op {
+            // Only innermost loops, the body can not contain other loops.
+            let close = instrs[i + 1..].iter().position(|instr| matches!(instr.op, Op::Open(_) | Op::Close(_))).map(|n| i + 1 + n);
+            if let Some(close) = close.filter(|&c| matches!(instrs[c].op, Op::Close(_))) {
+                if let Some(replacement) = rewrite(&instrs[i + 1..close]) {
+                    let span = instrs[i].span.join(instrs[close].span);
+                    out.extend(replacement.into_iter().map(|op| Instr { op, span }));
+                    i = close + 1;
+                    continue;
+                }
+            }
+        }
+        out.push(instrs[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Maximum number of instructions executed ahead of time by `ConstFold`.
+const FOLD_STEPS: usize = 1 << 20;
+/// Maximum number of output bytes produced ahead of time by `ConstFold`.
+const FOLD_OUTPUT: usize = 1 << 16;
+
+/// Runs the start of the program ahead of time, until its first input or debug instruction, or until it takes too
+/// long, and replaces it with the output it produced and direct initializations of the cells it modified. Only
+/// programs that run on a fresh machine can be folded.
+pub struct ConstFold;
+impl Pass for ConstFold {
+    fn name(&self) -> &'static str {
+        "const-fold"
+    }
+
+    fn run(&self, instrs: Vec<Instr>, target: &Target) -> Vec<Instr> {
+        if !target.fresh || instrs.is_empty() {
+            return instrs;
+        }
+        let jumps = match_brackets(&instrs);
+        // Find how far the program can run, then run it again to stop exactly there, between two top level
+        // instructions.
+        let end = Folder::new(target, FOLD_STEPS, FOLD_OUTPUT).run(&instrs, &jumps, None);
+        if end == 0 {
+            return instrs;
+        }
+        let mut folder = Folder::new(target, FOLD_STEPS, FOLD_OUTPUT);
+        folder.run(&instrs, &jumps, Some(end));
+
+        let mut ops: Vec<Op> = folder.output.iter().map(|&b| Op::OutByte(b)).collect();
+        let deltas: BTreeMap<isize, i32> = folder.tape.iter().enumerate()
+            .filter(|(_, &v)| v != 0)
+            .map(|(i, &v)| (i as isize, v as i32))
+            .collect();
+        ops.extend(sweep(&deltas, folder.ptr as isize));
+
+        let span = span_of(&instrs[..end]);
+        let mut out: Vec<Instr> = ops.into_iter().map(|op| Instr { op, span }).collect();
+        out.extend_from_slice(&instrs[end..]);
+        out
+    }
+}
+
+/// Executes programs ahead of time, for `ConstFold` and `precompute`.
+pub(crate) struct Folder {
+    mask: u32,
+    mem_size: usize,
+    max_steps: usize,
+    max_output: usize,
+    /// The cells touched so far, the rest are zero.
+    tape: Vec<u32>,
+    ptr: usize,
+    pub(crate) output: Vec<u8>,
+}
+impl Folder {
+    /// Creates a folder that gives up after the given number of instructions or output bytes.
+    pub(crate) fn new(target: &Target, max_steps: usize, max_output: usize) -> Folder {
+        Folder {
+            mask: target.cell_width.mask(),
+            mem_size: target.mem_size,
+            max_steps,
+            max_output,
+            tape: vec![],
+            ptr: 0,
+            output: vec![],
+        }
+    }
+
+    /// Returns the cell at the given offset from the pointer, if it is in the tape.
+    fn cell(&mut self, offset: isize) -> Option<&mut u32> {
+        let pos = self.ptr.checked_add_signed(offset).filter(|&p| p < self.mem_size)?;
+        if pos >= self.tape.len() {
+            self.tape.resize(pos + 1, 0);
+        }
+        Some(&mut self.tape[pos])
+    }
+
+    /// Moves the pointer, if the target is in the tape.
+    fn step(&mut self, offset: isize) -> bool {
+        match self.ptr.checked_add_signed(offset).filter(|&p| p < self.mem_size) {
+            Some(p) => {
+                self.ptr = p;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Runs the instructions until something that can not be done ahead of time is found, or until the top level
+    /// instruction at `stop` is reached. Returns the index of the last top level instruction reached.
+    pub(crate) fn run(&mut self, instrs: &[Instr], jumps: &[usize], stop: Option<usize>) -> usize {
+        let mut i = 0;
+        let mut depth = 0;
+        let mut boundary = 0;
+        let mut steps = 0;
+        while i < instrs.len() {
+            if depth == 0 {
+                boundary = i;
+                if stop == Some(i) {
+                    return i;
+                }
+            }
+            steps += 1;
+            if steps > self.max_steps || self.output.len() > self.max_output {
+                return boundary;
+            }
+            let mut next = i + 1;
+            let mask = self.mask;
+            match instrs[i].op {
+                Op::Move(n) => if !self.step(n) {
+                    return boundary;
+                },
+                Op::Add(n) => match self.cell(0) {
+                    Some(c) => *c = c.wrapping_add(n as u32) & mask,
+                    None => return boundary,
+                },
+                Op::AddAt { offset, value } => match self.cell(offset) {
+                    Some(c) => *c = c.wrapping_add(value as u32) & mask,
+                    None => return boundary,
+                },
+                Op::Out => match self.cell(0) {
+                    Some(&mut c) => self.output.push(c as u8),
+                    None => return boundary,
+                },
+                Op::OutByte(b) => self.output.push(b),
+                Op::In | Op::Debug | Op::Channel | Op::Send | Op::Receive | Op::Fork | Op::Clock | Op::File(_) => return boundary,
+                Op::Open(_) => match self.cell(0) {
+                    Some(0) => next = jumps[i] + 1,
+                    Some(_) => depth += 1,
+                    None => return boundary,
+                },
+                Op::Close(_) => match self.cell(0) {
+                    Some(0) => depth -= 1,
+                    Some(_) => next = jumps[i] + 1,
+                    None => return boundary,
+                },
+                Op::Clear => match self.cell(0) {
+                    Some(c) => *c = 0,
+                    None => return boundary,
+                },
+                Op::MulAdd { offset, factor } => {
+                    let value = match self.cell(0) {
+                        Some(&mut v) => v,
+                        None => return boundary,
+                    };
+                    if value != 0 {
+                        match self.cell(offset) {
+                            Some(c) => *c = c.wrapping_add(value.wrapping_mul(factor as u32)) & mask,
+                            None => return boundary,
+                        }
+                    }
+                },
+                Op::Fill { offset, len, value } => for o in offset..offset + len as isize {
+                    match self.cell(o) {
+                        Some(c) => *c = value as u32 & mask,
+                        None => return boundary,
+                    }
+                },
+                Op::Scan(n) => loop {
+                    match self.cell(0) {
+                        Some(0) => break,
+                        Some(_) => if !self.step(n) {
+                            return boundary;
+                        },
+                        None => return boundary,
+                    }
+                },
+            }
+            i = next;
+        }
+        instrs.len()
+    }
+}
+
+/// Returns, for every bracket, the index of its matching bracket.
+pub(crate) fn match_brackets(instrs: &[Instr]) -> Vec<usize> {
+    let mut jumps = vec![0; instrs.len()];
+    let mut open: Vec<usize> = vec![];
+    for (i, instr) in instrs.iter().enumerate() {
+        match instr.op {
+            Op::Open(_) => open.push(i),
+            Op::Close(_) => {
+                let o = open.pop().expect("unbalanced brackets");
+                jumps[o] = i;
+                jumps[i] = o;
+            },
+            _ => (),
+        }
+    }
+    jumps
+}
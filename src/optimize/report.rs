@@ -0,0 +1,60 @@
+// Detailed reports of what the optimization passes did.
+// MIT license.
+
+use std::fmt;
+
+use super::PassStats;
+use crate::profile::Profile;
+use crate::program::Instr;
+
+/// What one pass did, with the instructions it removed and those it fused with others.
+#[derive(Debug, Clone)]
+pub struct PassReport {
+    pub stats: PassStats,
+    /// Instructions removed with nothing in their place, like dead code.
+    pub eliminated: usize,
+    /// Instructions that disappeared by being fused with others into fewer ones, like runs of `+`.
+    pub fused: usize,
+    /// Estimated number of instructions run before and after the pass, from a profile.
+    pub steps: Option<(u64, u64)>,
+}
+
+impl PassReport {
+    /// Compares the instructions before and after the pass. An instruction is still there, on its own or fused
+    /// with others, if one of the instructions after the pass comes from source code including it.
+    pub fn new(stats: PassStats, before: &[Instr], after: &[Instr], profile: Option<&Profile>) -> PassReport {
+        let mut spans: Vec<_> = after.iter().map(|i| i.span).collect();
+        spans.sort_by_key(|s| s.start);
+        // The furthest end of the spans starting up to every one of them, since reordered spans overlap.
+        let ends: Vec<usize> = spans
+            .iter()
+            .scan(0, |end, span| {
+                *end = span.end.max(*end);
+                Some(*end)
+            })
+            .collect();
+        let kept = before
+            .iter()
+            .filter(|i| {
+                let n = spans.partition_point(|s| s.start <= i.span.start);
+                n > 0 && ends[n - 1] > i.span.start
+            })
+            .count();
+        PassReport {
+            stats,
+            eliminated: before.len() - kept,
+            fused: kept.saturating_sub(after.len()),
+            steps: profile.map(|p| (p.estimate(before), p.estimate(after))),
+        }
+    }
+}
+
+impl fmt::Display for PassReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<12} {:>8} -> {:<8} {:>10} {:>8}", self.stats.name, self.stats.before, self.stats.after, self.eliminated, self.fused)?;
+        if let Some((before, after)) = self.steps {
+            write!(f, " {:>14} -> {}", before, after)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+// Tests of the optimization passes, running programs with and without them.
+// MIT license.
+
+use crate::equiv::{self, Ending};
+use crate::program::{Op, Program};
+use crate::transpile::Settings;
+
+use super::{OptLevel, Pipeline, PASSES};
+
+/// Instructions the programs may run, for the ones that never end.
+const MAX_STEPS: u64 = 100_000;
+
+/// Optimizes the program with the given passes, checks that it prints the same and leaves the same tape as without
+/// them, and returns the optimized program and how it ended.
+fn check(source: &str, passes: &[&str]) -> (Program, Ending) {
+    let program = Program::parse(source).unwrap();
+    let optimized = Pipeline::from_names(passes.iter().copied()).unwrap().run(&program).0;
+    let settings = Settings::default();
+    let expected = equiv::run(&Pipeline::for_level(OptLevel::O0).run(&program).0, &settings, b"", MAX_STEPS);
+    let outcome = equiv::run(&optimized, &settings, b"", MAX_STEPS);
+    assert_eq!(outcome.output, expected.output, "output of {:?} with {:?}", source, passes);
+    assert_eq!(outcome.tape, expected.tape, "tape of {:?} with {:?}", source, passes);
+    assert_eq!(outcome.ending, expected.ending, "ending of {:?} with {:?}", source, passes);
+    (optimized, outcome.ending)
+}
+
+#[test]
+fn dce_removes_loops_at_zero_cells() {
+    // The first loop starts on a fresh tape, the last one right after a loop left its cell at zero.
+    let (optimized, _) = check("[+.>]++[>+<-][-.]>.", &["dce"]);
+    assert_eq!(optimized.ops.iter().filter(|op| matches!(op, Op::Open(_))).count(), 1);
+}
+
+#[test]
+fn dce_removes_code_after_infinite_loops() {
+    let (optimized, ending) = check("++++++++[>++++++++<-]>+.[-]+[]<+.>.", &["fuse", "dce"]);
+    assert_eq!(ending, Ending::TimedOut);
+    assert!(matches!(optimized.ops.last(), Some(Op::Close(_))));
+    assert_eq!(optimized.ops.iter().filter(|op| matches!(op, Op::Out)).count(), 1);
+}
+
+#[test]
+fn dce_keeps_loops_that_end() {
+    let (optimized, ending) = check("+[-].>++[-<+>]<.", &["fuse", "dce"]);
+    assert_eq!(ending, Ending::Finished);
+    assert_eq!(optimized.ops.iter().filter(|op| matches!(op, Op::Out)).count(), 2);
+    // A loop that moves the pointer ends on the first zero cell.
+    let (_, ending) = check("+.[>]++.", &["dce"]);
+    assert_eq!(ending, Ending::Finished);
+}
+
+#[test]
+fn dce_removes_stores_cleared_before_being_read() {
+    let (optimized, _) = check("+++>+++++<[-]>[-]+.<++.", &["fuse", "clear-loop", "dce"]);
+    assert!(!optimized.ops.contains(&Op::Add(3)));
+    assert!(!optimized.ops.contains(&Op::Add(5)));
+    assert!(optimized.ops.contains(&Op::Add(2)));
+}
+
+#[test]
+fn dce_keeps_stores_read_before_being_cleared() {
+    let (optimized, _) = check("+++.[-]>+++++[<+>-]<[-]+.", &["fuse", "clear-loop", "dce"]);
+    assert!(optimized.ops.contains(&Op::Add(3)));
+    assert!(optimized.ops.contains(&Op::Add(5)));
+}
+
+#[test]
+fn offset_fuses_runs_of_moves_and_additions() {
+    let (optimized, _) = check(">>+++<-<++>>>+<.<.<.", &["offset"]);
+    let moves = optimized.ops.iter().take_while(|op| !matches!(op, Op::Out)).filter(|op| matches!(op, Op::Move(_))).count();
+    assert_eq!(moves, 1);
+    assert!(optimized.ops.contains(&Op::AddAt { offset: 2, value: 3 }));
+    assert!(optimized.ops.contains(&Op::AddAt { offset: 3, value: 1 }));
+}
+
+#[test]
+fn offset_keeps_loops_and_output() {
+    check("++++[>++>+++<<-]>.>.<<+++[>>-<+<-]>.>.", &["offset"]);
+    check("++++[>++>+++<<-]>.>.<<+++[>>-<+<-]>.>.", &["fuse", "clear-loop", "copy-loop", "offset"]);
+}
+
+#[test]
+fn fill_replaces_runs_of_cleared_cells() {
+    let (optimized, _) = check("+++>+>+++++>++<<<[-]>[-]>[-].>.", &["fuse", "clear-loop", "fill"]);
+    assert!(optimized.ops.contains(&Op::Fill { offset: 0, len: 3, value: 0 }));
+}
+
+#[test]
+fn fill_replaces_runs_of_set_cells_to_the_left() {
+    let (optimized, _) = check("+++>+>+++++>++[-]++<[-]++<[-]++<.>.>.>.", &["fuse", "clear-loop", "fill"]);
+    assert!(optimized.ops.contains(&Op::Fill { offset: -2, len: 3, value: 2 }));
+}
+
+#[test]
+fn fill_keeps_runs_of_different_values() {
+    let (optimized, _) = check("+>+>+<<[-]+>[-]++>[-]+.<.<.", &["fuse", "clear-loop", "fill"]);
+    assert!(!optimized.ops.iter().any(|op| matches!(op, Op::Fill { .. })));
+}
+
+#[test]
+fn every_pass_keeps_what_programs_do() {
+    let program = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.";
+    for &pass in PASSES {
+        check(program, &[pass]);
+    }
+    check(program, PASSES);
+}
@@ -0,0 +1,86 @@
+// Execution profiles, to optimize the parts of programs where the time goes.
+// MIT license.
+
+use serde::{Deserialize, Serialize};
+
+use crate::program::{Instr, Op, Program};
+
+/// Share of the instructions run that makes a loop hot, in percent.
+pub const HOT_SHARE: f64 = 1.0;
+
+/// What a loop did during a profiled run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoopProfile {
+    /// Source position of the opening bracket, which does not change with the optimization level.
+    pub position: usize,
+    /// Times the loop was entered.
+    pub entries: u64,
+    /// Iterations run, over all the entries.
+    pub iterations: u64,
+    /// Instructions run in the loop, those of the nested loops included.
+    pub steps: u64,
+}
+
+/// What the loops of a program did during a run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    /// Instructions run in total.
+    pub steps: u64,
+    /// The loops of the program, in the order of their opening brackets.
+    pub loops: Vec<LoopProfile>,
+}
+
+impl Profile {
+    /// Builds the profile of a run of the program from the number of times every instruction ran.
+    pub fn from_counts(program: &Program, counts: &[u64]) -> Profile {
+        let loops = program
+            .ops
+            .iter()
+            .enumerate()
+            .filter_map(|(i, op)| match *op {
+                Op::Open(close) => Some(LoopProfile {
+                    position: program.spans[i].start,
+                    entries: counts[i],
+                    iterations: counts[close],
+                    steps: counts[i..=close].iter().sum(),
+                }),
+                _ => None,
+            })
+            .collect();
+        Profile { steps: counts.iter().sum(), loops }
+    }
+
+    /// Source positions of the opening brackets of the loops that ran at least `HOT_SHARE` percent of the
+    /// instructions.
+    pub fn hot_loops(&self) -> Vec<usize> {
+        let min = self.steps as f64 * HOT_SHARE / 100.0;
+        self.loops.iter().filter(|l| l.steps > 0 && l.steps as f64 >= min).map(|l| l.position).collect()
+    }
+
+    /// Estimates how many instructions run, from the iterations of their loops. Instructions in a loop run once per
+    /// iteration of the innermost one, and the others once. Loops the profile does not know, like those replaced by
+    /// the optimization level it was recorded at, count one iteration per entry: profiles recorded with `-O0` give
+    /// the closest estimates.
+    pub fn estimate(&self, instrs: &[Instr]) -> u64 {
+        let find = |pos: usize| self.loops.binary_search_by_key(&pos, |l| l.position).ok().map(|i| self.loops[i]);
+        // Iterations of the loops the instruction is in, from the outermost one.
+        let mut iterations = vec![1u64];
+        let mut steps = 0u64;
+        for instr in instrs {
+            let current = *iterations.last().expect("the program itself is at the bottom");
+            match instr.op {
+                Op::Open(_) => {
+                    let entries = find(instr.span.start).map_or(current, |l| l.entries);
+                    steps = steps.saturating_add(entries);
+                    iterations.push(find(instr.span.start).map_or(entries, |l| l.iterations));
+                },
+                Op::Close(_) if iterations.len() > 1 => {
+                    steps = steps.saturating_add(current);
+                    iterations.pop();
+                },
+                _ => steps = steps.saturating_add(current),
+            }
+        }
+        steps
+    }
+}
@@ -0,0 +1,468 @@
+// Parsing of Brainfuck source code into instructions.
+// MIT license.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::analysis::{self, LoopInfo};
+use crate::error::BfError;
+
+/// Largest count of the extended syntax, like `+*65536`.
+pub const MAX_REPEAT: usize = 1 << 16;
+
+/// A single instruction of a parsed program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    /// Move the memory pointer by the given amount.
+    Move(isize),
+    /// Add the given amount to the current cell, wrapping around.
+    Add(i32),
+    /// Print the current cell.
+    Out,
+    /// Read a byte into the current cell.
+    In,
+    /// Jump past the matching `Close` (at the given index) if the current cell is zero.
+    Open(usize),
+    /// Jump back past the matching `Open` (at the given index) if the current cell is not zero.
+    Close(usize),
+    /// Print the internal state (the `#` extension).
+    Debug,
+    /// Make `.` and `,` use the channel numbered by the current cell (the `~` instruction of the channels dialect).
+    Channel,
+    /// Send the current cell to the peer machine (the `!` instruction of the pair dialect).
+    Send,
+    /// Receive a byte from the peer machine into the current cell, waiting for it (the `?` instruction of the pair
+    /// dialect).
+    Receive,
+    /// Fork the current thread (the `Y` instruction of the Brainfork dialect): the cell is set to zero in this
+    /// thread, and the new one runs on a copy of the tape, with the pointer one cell to the right, set to one.
+    Fork,
+    /// Store the milliseconds since the run started in the current cell, wrapping around (the `@` instruction of the
+    /// clock dialect).
+    Clock,
+    /// Open, close, read or write the file whose descriptor is in the current cell (the instructions of the fs dialect).
+    File(FileOp),
+    /// Set the current cell to zero, from a loop like `[-]`.
+    Clear,
+    /// Add the current cell times the factor to the cell at the offset, from loops like `[->++<]`. The loop is
+    /// replaced by one of these per target cell, followed by a `Clear`.
+    MulAdd { offset: isize, factor: i32 },
+    /// Move the pointer by the given step until it reaches a zero cell, from loops like `[>]`.
+    Scan(isize),
+    /// Print the given byte, computed ahead of time.
+    OutByte(u8),
+    /// Add the value to the cell at the offset from the pointer, without moving it, from runs like `>>+++<<`.
+    AddAt { offset: isize, value: i32 },
+    /// Set the `len` cells starting at the offset from the pointer to the value, from runs like `[-]>[-]>[-]`.
+    Fill { offset: isize, len: usize, value: i32 },
+}
+
+/// What a file instruction of the fs dialect does. The descriptor of the file is in the current cell, and the data in
+/// the cell to its right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileOp {
+    /// Open the file named by the cells to the right of the pointer, up to a zero cell, for reading (`(`), and store
+    /// its descriptor in the current cell, or zero if it can not be opened.
+    OpenRead,
+    /// Open the file the same way for writing, creating it or emptying it (`{`).
+    OpenWrite,
+    /// Close the file, and set the current cell to zero (`)`).
+    Close,
+    /// Read a byte of the file into the cell to the right (`;`).
+    Read,
+    /// Write the cell to the right to the file (`:`).
+    Write,
+}
+impl FileOp {
+    /// The character of the instruction.
+    pub fn symbol(self) -> char {
+        match self {
+            FileOp::OpenRead => '(',
+            FileOp::OpenWrite => '{',
+            FileOp::Close => ')',
+            FileOp::Read => ';',
+            FileOp::Write => ':',
+        }
+    }
+
+    /// The name of the operation in listings.
+    pub fn name(self) -> &'static str {
+        match self {
+            FileOp::OpenRead => "open-read",
+            FileOp::OpenWrite => "open-write",
+            FileOp::Close => "close",
+            FileOp::Read => "read",
+            FileOp::Write => "write",
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Op::Move(n) => write!(f, "{}", if n < 0 { "<" } else { ">" }.repeat(n.unsigned_abs())),
+            Op::Add(n) => write!(f, "{}", if n < 0 { "-" } else { "+" }.repeat(n.unsigned_abs() as usize)),
+            Op::Out => write!(f, "."),
+            Op::In => write!(f, ","),
+            Op::Open(_) => write!(f, "["),
+            Op::Close(_) => write!(f, "]"),
+            Op::Debug => write!(f, "#"),
+            Op::Channel => write!(f, "~"),
+            Op::Send => write!(f, "!"),
+            Op::Receive => write!(f, "?"),
+            Op::Fork => write!(f, "Y"),
+            Op::Clock => write!(f, "@"),
+            Op::File(op) => write!(f, "{}", op.symbol()),
+            Op::Clear => write!(f, "[-]"),
+            Op::MulAdd { offset, factor } => write!(f, "mul({}, {})", offset, factor),
+            Op::Scan(n) => write!(f, "[{}]", Op::Move(n)),
+            Op::OutByte(b) => write!(f, "out({})", b),
+            Op::AddAt { offset, value } => write!(f, "add({}, {})", offset, value),
+            Op::Fill { offset, len, value } => write!(f, "fill({}, {}, {})", offset, len, value),
+        }
+    }
+}
+
+/// The instructions source code has on top of those of Brainfuck and `#`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// Nothing else.
+    #[default]
+    Standard,
+    /// `~` makes `.` and `,` use the channel numbered by the current cell, one of several streams of the machine.
+    Channels,
+    /// `!` sends the current cell to the peer machine running next to this one, and `?` waits for a byte from it.
+    /// Experimental.
+    Pair,
+    /// `Y` forks the current thread, the threads taking turns on the machine.
+    Brainfork,
+    /// `@` stores the time in the current cell.
+    Clock,
+    /// `(` and `{` open files, `;` and `:` read and write them, and `)` closes them, if the machine may touch files.
+    Fs,
+    /// Self-modifying Brainfuck, which runs from the tape with [`crate::Machine::run_self_modifying`]. Parsed, it is
+    /// the code before it changes, as standard Brainfuck.
+    Smbf,
+    /// Boolfuck, whose cells are bits, run by [`crate::machine::BitMachine`]: `+` flips the current cell, `,` reads
+    /// a bit and `;` prints one, and `-`, `.` and `#` are comments.
+    Boolfuck,
+    /// Smallfuck, whose cells are bits, on a tape of `--mem-size` cells, run by [`crate::machine::BitMachine`]: `*`
+    /// flips the current cell, there is no input or output, and leaving the tape ends the program.
+    Smallfuck,
+}
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Dialect, String> {
+        match s {
+            "standard" => Ok(Dialect::Standard),
+            "channels" => Ok(Dialect::Channels),
+            "pair" => Ok(Dialect::Pair),
+            "brainfork" => Ok(Dialect::Brainfork),
+            "clock" => Ok(Dialect::Clock),
+            "fs" => Ok(Dialect::Fs),
+            "smbf" => Ok(Dialect::Smbf),
+            "boolfuck" => Ok(Dialect::Boolfuck),
+            "smallfuck" => Ok(Dialect::Smallfuck),
+            _ => Err(format!(
+                "invalid dialect '{}', expected standard, channels, pair, brainfork, clock, fs, smbf, boolfuck or \
+                smallfuck",
+                s
+            )),
+        }
+    }
+}
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Dialect::Standard => write!(f, "standard"),
+            Dialect::Channels => write!(f, "channels"),
+            Dialect::Pair => write!(f, "pair"),
+            Dialect::Brainfork => write!(f, "brainfork"),
+            Dialect::Clock => write!(f, "clock"),
+            Dialect::Fs => write!(f, "fs"),
+            Dialect::Smbf => write!(f, "smbf"),
+            Dialect::Boolfuck => write!(f, "boolfuck"),
+            Dialect::Smallfuck => write!(f, "smallfuck"),
+        }
+    }
+}
+
+/// A range of byte positions in the source code, end excluded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+impl Span {
+    /// The span of the single byte at the given position.
+    pub fn at(pos: usize) -> Span {
+        Span { start: pos, end: pos + 1 }
+    }
+
+    /// The smallest span covering both spans.
+    pub fn join(self, other: Span) -> Span {
+        Span { start: self.start.min(other.start), end: self.end.max(other.end) }
+    }
+}
+
+/// An instruction with the span of the source code it comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instr {
+    pub op: Op,
+    pub span: Span,
+}
+
+/// Splits source code at the first `!` that is not between double quotes, the convention of many archives to store a
+/// program and its input in a single file. Returns the code and the input after the `!`, which is empty without one.
+pub fn split_input(source: &str) -> (&str, &str) {
+    let mut quoted = false;
+    for (pos, c) in source.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '!' if !quoted => return (&source[..pos], &source[pos + 1..]),
+            _ => (),
+        }
+    }
+    (source, "")
+}
+
+/// A parsed program, with all bracket pairs resolved.
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    /// The instructions.
+    pub ops: Vec<Op>,
+    /// The source span of every instruction.
+    pub spans: Vec<Span>,
+    /// Position where each line of the source code starts.
+    pub(crate) lines: Vec<usize>,
+}
+
+impl Program {
+    /// Parses the given source code. Characters that are not instructions are comments and are skipped.
+    pub fn parse(source: &str) -> Result<Program, BfError> {
+        Ok(Program::parse_with(source, Dialect::Standard, false, false)?.0)
+    }
+
+    /// Parses the given source code in the extended syntax, where a command other than a bracket followed by `*`
+    /// and a count is repeated that many times: `+*65` is the same as 65 `+`.
+    pub fn parse_extended(source: &str) -> Result<Program, BfError> {
+        Ok(Program::parse_with(source, Dialect::Standard, true, false)?.0)
+    }
+
+    /// Parses the given source code with the instructions of the dialect, in the extended syntax or not.
+    pub fn parse_dialect(source: &str, dialect: Dialect, extended: bool) -> Result<Program, BfError> {
+        Ok(Program::parse_with(source, dialect, extended, false)?.0)
+    }
+
+    /// Parses the given source code, in the extended syntax or not, without failing on unmatched brackets, like
+    /// permissive interpreters: a `]` without its `[` is skipped, and the missing `]` are added at the end. Returns
+    /// the program and the unmatched brackets, as the errors the other functions would fail with. Only fails on a
+    /// count of the extended syntax over [`MAX_REPEAT`].
+    pub fn parse_lenient(source: &str, extended: bool) -> Result<(Program, Vec<BfError>), BfError> {
+        Program::parse_with(source, Dialect::Standard, extended, true)
+    }
+
+    fn parse_with(source: &str, dialect: Dialect, extended: bool, lenient: bool) -> Result<(Program, Vec<BfError>), BfError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("parse", len = source.len()).entered();
+
+        let mut ops: Vec<Op> = Vec::with_capacity(source.len());
+        let mut spans: Vec<Span> = Vec::with_capacity(source.len());
+        let mut lines: Vec<usize> = vec![0];
+        // Indices of the open brackets, and their source positions.
+        let mut open: Vec<(usize, usize)> = vec![];
+        let mut unmatched = vec![];
+
+        let bytes = source.as_bytes();
+        let mut next = 0;
+        while let Some(&c) = bytes.get(next) {
+            let pos = next;
+            next += 1;
+            let op = match c as char {
+                '-' | '.' | '#' if dialect == Dialect::Boolfuck => continue,
+                ';' if dialect == Dialect::Boolfuck => Op::Out,
+                '+' | '-' | '.' | ',' | '#' if dialect == Dialect::Smallfuck => continue,
+                '*' if dialect == Dialect::Smallfuck => Op::Add(1),
+                '>' => Op::Move(1),
+                '<' => Op::Move(-1),
+                '+' => Op::Add(1),
+                '-' => Op::Add(-1),
+                '.' => Op::Out,
+                ',' => Op::In,
+                '[' => {
+                    open.push((ops.len(), pos));
+                    // Patched when the matching bracket is found.
+                    Op::Open(0)
+                },
+                ']' => {
+                    let Some((o, _)) = open.pop() else {
+                        if !lenient {
+                            return Err(BfError::UnmatchedClose(pos));
+                        }
+                        unmatched.push(BfError::UnmatchedClose(pos));
+                        continue;
+                    };
+                    ops[o] = Op::Open(ops.len());
+                    Op::Close(o)
+                },
+                '#' => Op::Debug,
+                '~' if dialect == Dialect::Channels => Op::Channel,
+                '!' if dialect == Dialect::Pair => Op::Send,
+                '?' if dialect == Dialect::Pair => Op::Receive,
+                'Y' if dialect == Dialect::Brainfork => Op::Fork,
+                '@' if dialect == Dialect::Clock => Op::Clock,
+                '(' if dialect == Dialect::Fs => Op::File(FileOp::OpenRead),
+                '{' if dialect == Dialect::Fs => Op::File(FileOp::OpenWrite),
+                ')' if dialect == Dialect::Fs => Op::File(FileOp::Close),
+                ';' if dialect == Dialect::Fs => Op::File(FileOp::Read),
+                ':' if dialect == Dialect::Fs => Op::File(FileOp::Write),
+                '\n' => {
+                    lines.push(pos + 1);
+                    continue;
+                },
+                _ => continue,
+            };
+            if extended && !matches!(op, Op::Open(_) | Op::Close(_)) && bytes.get(next) == Some(&b'*') {
+                let digits = bytes[next + 1..].iter().take_while(|b| b.is_ascii_digit()).count();
+                // Without a count, the star is just a comment.
+                if digits > 0 {
+                    let span = Span { start: pos, end: next + 1 + digits };
+                    let count = source[next + 1..span.end].parse::<usize>().ok().filter(|&n| n <= MAX_REPEAT);
+                    let count = count.ok_or(BfError::RepeatTooLarge(span))?;
+                    next = span.end;
+                    // Moves and additions repeated are a single one, the others are repeated as written.
+                    match op {
+                        _ if count == 0 => (),
+                        Op::Add(n) => ops.push(Op::Add(n * count as i32)),
+                        Op::Move(n) => ops.push(Op::Move(n * count as isize)),
+                        _ => ops.extend(std::iter::repeat_n(op, count)),
+                    }
+                    spans.extend(std::iter::repeat_n(span, ops.len() - spans.len()));
+                    continue;
+                }
+            }
+            ops.push(op);
+            spans.push(Span::at(pos));
+        }
+
+        if let Some(&(_, pos)) = open.last().filter(|_| !lenient) {
+            return Err(BfError::UnmatchedOpen(pos));
+        }
+        // The added brackets come from the end of the source code, where nothing is.
+        unmatched.extend(open.iter().map(|&(_, pos)| BfError::UnmatchedOpen(pos)));
+        while let Some((o, _)) = open.pop() {
+            ops[o] = Op::Open(ops.len());
+            ops.push(Op::Close(o));
+            spans.push(Span { start: source.len(), end: source.len() });
+        }
+        Ok((Program { ops, spans, lines }, unmatched))
+    }
+
+    /// Creates a program from instructions that do not come from source code. Their brackets must be resolved.
+    pub fn from_ops(ops: Vec<Op>) -> Program {
+        Program { spans: vec![Span::default(); ops.len()], ops, lines: vec![] }
+    }
+
+    /// The instructions, with their spans.
+    pub fn instrs(&self) -> Vec<Instr> {
+        self.ops.iter().zip(&self.spans).map(|(&op, &span)| Instr { op, span }).collect()
+    }
+
+    /// Creates a program from the given instructions of this program, transformed, resolving the targets of the
+    /// brackets again. The brackets must be balanced.
+    pub fn with_instrs(&self, instrs: Vec<Instr>) -> Program {
+        let mut ops: Vec<Op> = instrs.iter().map(|i| i.op).collect();
+        let spans: Vec<Span> = instrs.iter().map(|i| i.span).collect();
+        let mut open: Vec<usize> = vec![];
+        for i in 0..ops.len() {
+            match ops[i] {
+                Op::Open(_) => open.push(i),
+                Op::Close(_) => {
+                    let o = open.pop().expect("unbalanced brackets");
+                    ops[o] = Op::Open(i);
+                    ops[i] = Op::Close(o);
+                },
+                _ => (),
+            }
+        }
+        Program { ops, spans, lines: self.lines.clone() }
+    }
+
+    /// Returns the line and column, starting at 1, of the given source position.
+    pub fn location(&self, pos: usize) -> (usize, usize) {
+        if self.lines.is_empty() {
+            return (1, pos + 1);
+        }
+        let line = self.lines.partition_point(|&start| start <= pos);
+        (line, pos - self.lines[line - 1] + 1)
+    }
+
+    /// Returns the index of the first instruction coming from the source code at the given line and column,
+    /// starting at 1.
+    pub fn instruction_at(&self, line: usize, column: usize) -> Option<usize> {
+        let start = if line == 1 { 0 } else { *self.lines.get(line.checked_sub(1)?)? };
+        let pos = start + column.checked_sub(1)?;
+        // Columns past the end of the line are not on the next one.
+        if self.lines.get(line).is_some_and(|&next| pos >= next) {
+            return None;
+        }
+        self.spans.iter().position(|span| span.start <= pos && pos < span.end)
+    }
+
+    /// Dumps the instructions, one per line, with their index, jump targets, source locations and the classification
+    /// of the loops.
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        let width = self.len().to_string().len();
+        let mut loops: Vec<Option<LoopInfo>> = vec![None; self.len()];
+        for info in analysis::loops(self) {
+            loops[info.open] = Some(info);
+        }
+        for (i, (op, span)) in self.ops.iter().zip(&self.spans).enumerate() {
+            let text = match *op {
+                Op::Move(n) => format!("move {}", n),
+                Op::Add(n) => format!("add {}", n),
+                Op::Out => "out".to_string(),
+                Op::In => "in".to_string(),
+                Op::Open(target) => format!("open -> {}", target),
+                Op::Close(target) => format!("close -> {}", target),
+                Op::Debug => "debug".to_string(),
+                Op::Channel => "channel".to_string(),
+                Op::Send => "send".to_string(),
+                Op::Receive => "receive".to_string(),
+                Op::Fork => "fork".to_string(),
+                Op::Clock => "clock".to_string(),
+                Op::File(op) => format!("file {}", op.name()),
+                Op::Clear => "clear".to_string(),
+                Op::MulAdd { offset, factor } => format!("mul-add [{}] * {}", offset, factor),
+                Op::Scan(n) => format!("scan {}", n),
+                Op::OutByte(b) => format!("out-byte {} {:?}", b, b as char),
+                Op::AddAt { offset, value } => format!("add-at [{}] {}", offset, value),
+                Op::Fill { offset, len, value } => format!("fill [{}..{}] {}", offset, offset + len as isize, value),
+            };
+            let (line, col) = self.location(span.start);
+            let (end_line, end_col) = self.location(span.end.max(span.start + 1) - 1);
+            let location = if (line, col) == (end_line, end_col) {
+                format!("{}:{}", line, col)
+            } else {
+                format!("{}:{}-{}:{}", line, col, end_line, end_col)
+            };
+            match loops[i] {
+                Some(info) => out.push_str(&format!("{:>width$}  {:<24} {:<16} {}\n", i, text, location, info, width = width)),
+                None => out.push_str(&format!("{:>width$}  {:<24} {}\n", i, text, location, width = width)),
+            }
+        }
+        out
+    }
+
+    /// Number of instructions in the program.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Whether the program has no instructions at all.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
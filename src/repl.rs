@@ -0,0 +1,202 @@
+// The interactive interpreter, with a panel showing the cells around the pointer, and its sessions.
+// MIT license.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, prelude::*};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use brainfuck::{Machine, MachineBuilder, OptLevel, Program};
+use brainfuck::optimize::optimize;
+
+use crate::cli::ReplArgs;
+use crate::console;
+use crate::logger;
+use crate::tee::{Capture, Tee};
+
+/// What the commands of the REPL do, which start with a `:`, not a Brainfuck instruction.
+const HELP: &str = "\
+:save-session FILE  Save the lines entered so far, what they printed and the state after them, to replay them
+:help               Show this help";
+
+/// A line of a session, with what it printed and the state it left the machine in.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    line: String,
+    /// What the line printed, with the bytes that are not UTF-8 replaced.
+    output: String,
+    /// Why the line failed, if it did.
+    error: Option<String>,
+    pointer: usize,
+    /// Value of the cell at the pointer.
+    value: u32,
+    /// Instructions the line ran.
+    steps: u64,
+    /// Cells up to the last one that is not zero.
+    cells_in_use: usize,
+}
+
+/// The machine of the session, what it printed, and the lines entered so far.
+struct Session {
+    machine: Machine,
+    output: Capture,
+    entries: Vec<Entry>,
+    /// Where every line is written as soon as it runs, with --transcript.
+    transcript: Option<fs::File>,
+}
+
+impl Session {
+    /// Runs the line on the machine, and records it.
+    fn eval(&mut self, line: &str) -> Result<&Entry, Box<dyn Error>> {
+        let program = Program::parse(line);
+        let ran = program.is_ok();
+        let result = program.and_then(|p| self.machine.run(&optimize(&p, OptLevel::default())));
+        // An invalid line should not end the session.
+        if let Err(e) = &result {
+            log::error!("{}", e);
+        }
+        let memory = self.machine.memory();
+        let entry = Entry {
+            line: line.to_string(),
+            output: String::from_utf8_lossy(&self.output.take()).into_owned(),
+            error: result.err().map(|e| e.to_string()),
+            pointer: self.machine.pointer(),
+            value: memory[self.machine.pointer()],
+            steps: if ran { self.machine.steps() } else { 0 },
+            cells_in_use: memory.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1),
+        };
+        if let Some(file) = &mut self.transcript {
+            writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        }
+        self.entries.push(entry);
+        Ok(self.entries.last().expect("just pushed"))
+    }
+
+    /// Writes the session so far to the file, a line of JSON per line entered.
+    fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut text = String::new();
+        for entry in &self.entries {
+            text.push_str(&serde_json::to_string(entry)?);
+            text.push('\n');
+        }
+        fs::write(path, text).map_err(|e| format!("Can not write {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    /// Handles a command, reporting what went wrong without ending the session.
+    fn command(&self, command: &str) {
+        let result = match command.split_once(char::is_whitespace).map(|(c, arg)| (c, arg.trim())) {
+            Some(("save-session", path)) if !path.is_empty() => self.save(Path::new(path)).map(|()| {
+                let lines = self.entries.len();
+                eprintln!("Saved {} line{} to {}", lines, if lines == 1 { "" } else { "s" }, path);
+            }),
+            _ if command == "help" => {
+                eprintln!("{}", HELP);
+                Ok(())
+            },
+            _ if command == "save-session" => Err("Give the file to save the session to, as :save-session FILE".into()),
+            _ => Err(format!("Unknown command ':{}', see :help", command).into()),
+        };
+        if let Err(e) = result {
+            log::error!("{}", e);
+        }
+    }
+}
+
+/// Interprets every line of the standard input on the same machine, with a prompt, after the lines of the session to
+/// replay. The panel is printed to the standard error after every line, apart from what the program prints.
+pub fn run(args: &ReplArgs, builder: MachineBuilder) -> Result<(), Box<dyn Error>> {
+    let output = Capture::default();
+    let machine = builder.io(console::stdin(), Tee(console::stdout(), output.clone())).build();
+    let transcript = match &args.transcript {
+        Some(path) => Some(fs::File::create(path).map_err(|e| format!("Can not create {}: {}", path.display(), e))?),
+        None => None,
+    };
+    let mut session = Session { machine, output, entries: vec![], transcript };
+    if let Some(path) = &args.replay {
+        replay(&mut session, path, args)?;
+    }
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    loop {
+        write!(stdout, "> ")?;
+        stdout.flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            writeln!(io::stdout())?;
+            return Ok(());
+        }
+        let line = line.trim_end_matches(['\n', '\r']);
+        if let Some(command) = line.trim_start().strip_prefix(':') {
+            session.command(command.trim());
+            continue;
+        }
+        session.eval(line)?;
+        writeln!(io::stdout())?;
+        if !args.no_panel {
+            eprint!("{}", panel(&session.machine, args.panel_cells as usize));
+        }
+    }
+}
+
+/// Runs the lines of a saved session again, showing them after the prompt, and warns about the lines that print
+/// something else than they did.
+fn replay(session: &mut Session, path: &Path, args: &ReplArgs) -> Result<(), Box<dyn Error>> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Can not read {}: {}", path.display(), e))?;
+    for (n, json) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let saved: Entry = serde_json::from_str(json).map_err(|e| format!("Invalid session {}:{}: {}", path.display(), n + 1, e))?;
+        writeln!(io::stdout(), "> {}", saved.line)?;
+        let entry = session.eval(&saved.line)?;
+        writeln!(io::stdout())?;
+        if entry.output != saved.output {
+            log::warn!("Line {} printed {:?}, instead of {:?} when it was saved", n + 1, entry.output, saved.output);
+        }
+        if !args.no_panel {
+            eprint!("{}", panel(&session.machine, args.panel_cells as usize));
+        }
+    }
+    Ok(())
+}
+
+/// The cells around the pointer as a table, with a column per cell: its index, and its value in decimal, in
+/// hexadecimal and as a character. The pointer is marked under its cell, which is also highlighted with colors.
+pub fn panel(machine: &Machine, cells: usize) -> String {
+    let memory = machine.memory();
+    let ptr = machine.pointer();
+    let start = ptr.saturating_sub(cells / 2).min(memory.len().saturating_sub(cells));
+    let end = (start + cells).min(memory.len());
+    let columns: Vec<[String; 4]> =
+        (start..end).map(|i| [i.to_string(), memory[i].to_string(), format!("{:02x}", memory[i]), show(memory[i])]).collect();
+    let width = columns.iter().flatten().map(|s| s.chars().count()).max().unwrap_or(0).max(3) + 2;
+    let highlight = logger::colored();
+    let mut out = String::new();
+    for (row, label) in ["cell", "dec", "hex", "char"].iter().enumerate() {
+        let mut line = format!("{:<5}", label);
+        for (i, column) in columns.iter().enumerate() {
+            let cell = format!("{:>width$}", column[row], width = width);
+            if highlight && start + i == ptr {
+                line.push_str(&format!("\x1b[7m{}\x1b[0m", cell));
+            } else {
+                line.push_str(&cell);
+            }
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out.push_str(&format!("{:<5}{:>width$}\n", "", "^", width = (ptr - start + 1) * width));
+    out
+}
+
+/// A cell as a character: printable ones as they are, common control characters as escapes, and nothing for the
+/// others.
+fn show(value: u32) -> String {
+    match char::from_u32(value) {
+        Some('\n') => "\\n".to_string(),
+        Some('\t') => "\\t".to_string(),
+        Some('\r') => "\\r".to_string(),
+        Some(c) if !c.is_control() && c != ' ' => c.to_string(),
+        _ => String::new(),
+    }
+}
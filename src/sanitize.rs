@@ -0,0 +1,142 @@
+// Sanitization of the program output, to protect terminals from untrusted programs.
+// MIT license.
+
+use std::io::{self, prelude::*};
+
+use clap::ValueEnum;
+
+/// What to do with the bytes that could control the terminal.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SanitizeMode {
+    /// Show them as escapes, like `\x1b`.
+    #[default]
+    Escape,
+    /// Remove them, with the whole escape sequences they start.
+    Strip,
+}
+
+/// Where the output is in an escape sequence, when stripping them.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Text,
+    /// After an escape.
+    Escape,
+    /// In a control sequence, like the `\x1b[31m` of colors, until its final byte.
+    Csi,
+    /// In an operating system command, like the `\x1b]0;title\x07` of window titles, until a bell or an escape.
+    Osc,
+}
+
+/// Writes the output with its control bytes other than line breaks and tabs, its escape sequences and its invalid
+/// UTF-8 escaped or removed. Valid UTF-8 is kept, except for the C1 control characters.
+pub struct Sanitize<W: Write> {
+    inner: W,
+    mode: SanitizeMode,
+    state: State,
+    /// The start of a UTF-8 character, until the rest of it is written.
+    pending: Vec<u8>,
+}
+
+impl<W: Write> Sanitize<W> {
+    pub fn new(inner: W, mode: SanitizeMode) -> Sanitize<W> {
+        Sanitize { inner, mode, state: State::Text, pending: vec![] }
+    }
+
+    fn reject(&self, b: u8, out: &mut Vec<u8>) {
+        if self.mode == SanitizeMode::Escape {
+            write!(out, "\\x{:02x}", b).expect("writing to a vector does not fail");
+        }
+    }
+
+    /// Rejects the start of a character that did not go on.
+    fn reject_pending(&mut self, out: &mut Vec<u8>) {
+        for b in std::mem::take(&mut self.pending) {
+            self.reject(b, out);
+        }
+    }
+
+    fn byte(&mut self, b: u8, out: &mut Vec<u8>) {
+        match self.state {
+            State::Text => (),
+            State::Escape => {
+                self.state = match b {
+                    b'[' => State::Csi,
+                    b']' => State::Osc,
+                    _ => State::Text,
+                };
+                return;
+            },
+            // Parameters, then the final byte. Anything else ends the sequence and is written.
+            State::Csi if (0x20..0x40).contains(&b) => return,
+            State::Csi if (0x40..0x7f).contains(&b) => {
+                self.state = State::Text;
+                return;
+            },
+            State::Csi => self.state = State::Text,
+            // Titles do not span lines, so a sequence that never ends does not hide the rest of the output.
+            State::Osc if b == b'\n' => self.state = State::Text,
+            State::Osc => {
+                match b {
+                    0x07 => self.state = State::Text,
+                    0x1b => self.state = State::Escape,
+                    _ => (),
+                }
+                return;
+            },
+        }
+        if !self.pending.is_empty() {
+            if (0x80..0xc0).contains(&b) {
+                self.pending.push(b);
+                if self.pending.len() == utf8_len(self.pending[0]) {
+                    let pending = std::mem::take(&mut self.pending);
+                    match std::str::from_utf8(&pending).ok().and_then(|s| s.chars().next()) {
+                        Some(c) if !c.is_control() => out.extend_from_slice(&pending),
+                        _ => pending.iter().for_each(|&b| self.reject(b, out)),
+                    }
+                }
+                return;
+            }
+            self.reject_pending(out);
+        }
+        match b {
+            b'\n' | b'\t' | 0x20..0x7f => out.push(b),
+            0x1b if self.mode == SanitizeMode::Strip => self.state = State::Escape,
+            0xc2..0xf5 => self.pending.push(b),
+            _ => self.reject(b, out),
+        }
+    }
+}
+
+/// The length of the UTF-8 character starting with the byte.
+fn utf8_len(first: u8) -> usize {
+    match first {
+        0xc2..0xe0 => 2,
+        0xe0..0xf0 => 3,
+        _ => 4,
+    }
+}
+
+impl<W: Write> Write for Sanitize<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut out = Vec::with_capacity(buf.len());
+        for &b in buf {
+            self.byte(b, &mut out);
+        }
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for Sanitize<W> {
+    fn drop(&mut self) {
+        // The output ended in the middle of a character.
+        let mut out = vec![];
+        self.reject_pending(&mut out);
+        let _ = self.inner.write_all(&out);
+        let _ = self.inner.flush();
+    }
+}
@@ -0,0 +1,367 @@
+// A web server running programs, with a playground and an API.
+// MIT license.
+
+use std::io::{self, prelude::*, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TrySendError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use brainfuck::{BfError, Machine, OptLevel, Program};
+use brainfuck::optimize::Pipeline;
+use brainfuck::transpile::Settings;
+
+use crate::tee::Capture;
+
+/// The page of the playground.
+const PLAYGROUND: &str = include_str!("playground.html");
+
+/// Maximum number of bytes a run prints, unless given.
+pub const DEFAULT_MAX_OUTPUT: u64 = 1 << 20;
+
+/// Maximum size of a request body.
+const MAX_BODY: usize = 1 << 20;
+
+/// Number of connections waiting for a worker, at most. Further ones are turned away until the workers catch up.
+const QUEUE: usize = 64;
+
+/// How long clients have to send their request, and to read the response.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the runs are checked against their time limit.
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What the server serves, and the limits of every run.
+pub struct Options {
+    /// Serve the playground page.
+    pub playground: bool,
+    /// Serve the API.
+    pub api: bool,
+    /// Number of requests handled at the same time.
+    pub workers: usize,
+    pub limits: Limits,
+}
+
+/// The machine and the limits of a run.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub settings: Settings,
+    /// Maximum number of instructions.
+    pub max_steps: u64,
+    /// Maximum number of bytes printed.
+    pub max_output: u64,
+    /// Maximum number of loops running inside each other.
+    pub max_loop_depth: Option<usize>,
+    /// Maximum time, after which the run is stopped.
+    pub timeout: Duration,
+}
+
+/// Serves requests on the address until the process is stopped, handing the connections to a pool of workers.
+pub fn serve(address: &str, options: &Options) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    log::info!("Listening on http://{} with {} workers", listener.local_addr()?, options.workers);
+    let (sender, receiver) = mpsc::sync_channel(QUEUE);
+    let receiver = Mutex::new(receiver);
+    let workers: Vec<Worker> = (0..options.workers.max(1)).map(|_| Worker::new()).collect();
+    thread::scope(|scope| {
+        for worker in &workers {
+            scope.spawn(|| worker.work(&receiver, options));
+        }
+        scope.spawn(|| watchdog(&workers));
+        for stream in listener.incoming() {
+            match stream.map(|stream| sender.try_send(stream)) {
+                Ok(Ok(())) => (),
+                Ok(Err(TrySendError::Full(stream))) => {
+                    log::warn!("All the workers are busy, turning a connection away");
+                    if let Err(e) = write_response(stream, &Response::error("503 Service Unavailable", "the server is busy")) {
+                        log::warn!("Failed to answer a connection: {}", e);
+                    }
+                },
+                Ok(Err(TrySendError::Disconnected(_))) => break,
+                Err(e) => log::warn!("Failed to accept a connection: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// A thread handling one request at a time, with the flag stopping its runs and when to set it.
+struct Worker {
+    interrupt: &'static AtomicBool,
+    deadline: Mutex<Option<Instant>>,
+}
+impl Worker {
+    fn new() -> Worker {
+        // Machines take a static flag. There is one per worker, for as long as the server runs.
+        Worker { interrupt: Box::leak(Box::new(AtomicBool::new(false))), deadline: Mutex::new(None) }
+    }
+
+    /// Handles the connections from the queue, until it is closed.
+    fn work(&self, queue: &Mutex<Receiver<TcpStream>>, options: &Options) {
+        loop {
+            let Ok(stream) = queue.lock().expect("a worker panicked").recv() else { return };
+            if let Err(e) = handle(stream, options, self) {
+                log::warn!("Failed to handle a request: {}", e);
+            }
+        }
+    }
+
+    /// Runs the program on the machine, stopping it once it runs for longer than the timeout.
+    fn run(&self, machine: &mut Machine, program: &Program, timeout: Duration) -> Result<(), BfError> {
+        self.interrupt.store(false, Ordering::Relaxed);
+        *self.deadline.lock().expect("the watchdog panicked") = Some(Instant::now() + timeout);
+        let result = machine.run(program);
+        *self.deadline.lock().expect("the watchdog panicked") = None;
+        result
+    }
+}
+
+/// Stops the runs of the workers once they reach their deadline.
+fn watchdog(workers: &[Worker]) {
+    loop {
+        thread::sleep(WATCHDOG_INTERVAL);
+        let now = Instant::now();
+        for worker in workers {
+            if worker.deadline.lock().expect("a worker panicked").is_some_and(|deadline| now >= deadline) {
+                worker.interrupt.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// A parsed HTTP request.
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+/// A response, with its status line, content type and body.
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+impl Response {
+    fn json(status: &'static str, value: Value) -> Response {
+        Response { status, content_type: "application/json", body: value.to_string().into_bytes() }
+    }
+
+    fn error(status: &'static str, message: &str) -> Response {
+        Response::json(status, json!({ "error": message }))
+    }
+}
+
+/// Reads a request from the connection, answers it and closes the connection.
+fn handle(stream: TcpStream, options: &Options, worker: &Worker) -> io::Result<()> {
+    // Slow clients must not keep the worker.
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let response = match read_request(&mut reader)? {
+        Ok(request) => {
+            log::info!("{} {}", request.method, request.path);
+            route(&request, options, worker)
+        },
+        Err(response) => response,
+    };
+    write_response(stream, &response)
+}
+
+/// Reads the request line, the headers and the body. Malformed requests get the response to give instead.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Result<Request, Response>> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut words = line.split_whitespace();
+    let (Some(method), Some(path)) = (words.next(), words.next()) else {
+        return Ok(Err(Response::error("400 Bad Request", "malformed request line")));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+    let mut length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                match value.trim().parse() {
+                    Ok(n) => length = n,
+                    Err(_) => return Ok(Err(Response::error("400 Bad Request", "invalid content length"))),
+                }
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Ok(Err(Response::error("413 Payload Too Large", "the request is too large")));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Ok(Request { method, path, body }))
+}
+
+fn write_response(mut stream: TcpStream, response: &Response) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+/// Answers a request.
+fn route(request: &Request, options: &Options, worker: &Worker) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") if options.playground => {
+            Response { status: "200 OK", content_type: "text/html; charset=utf-8", body: PLAYGROUND.as_bytes().to_vec() }
+        },
+        ("POST", "/playground/run") if options.playground => match serde_json::from_slice(&request.body) {
+            Ok(run) => Response::json("200 OK", playground_run(&run, &options.limits, worker)),
+            Err(e) => Response::error("400 Bad Request", &e.to_string()),
+        },
+        ("POST", "/run") if options.api => match serde_json::from_slice(&request.body) {
+            Ok(run) => Response::json("200 OK", api_run(&run, &options.limits, worker)),
+            Err(e) => Response::error("400 Bad Request", &e.to_string()),
+        },
+        _ => Response::error("404 Not Found", "not found"),
+    }
+}
+
+/// A run from the playground.
+#[derive(Deserialize)]
+struct PlaygroundRun {
+    program: String,
+    #[serde(default)]
+    input: String,
+    /// Stop after this many instructions, to step through the program.
+    steps: Option<u64>,
+}
+
+/// Runs the program from the start, for the given number of steps or to its end, and describes the machine after it.
+fn playground_run(run: &PlaygroundRun, limits: &Limits, worker: &Worker) -> Value {
+    let program = match Program::parse(&run.program) {
+        Ok(program) => program,
+        Err(e) => return json!({ "status": "error", "error": e.to_string() }),
+    };
+    let max_steps = run.steps.map_or(limits.max_steps, |steps| steps.min(limits.max_steps));
+    let limits = Limits { max_steps, ..*limits };
+    let (machine, result, output) = execute(&program, run.input.as_bytes(), &limits, worker);
+    let (status, error) = match result {
+        Ok(()) => ("finished", None),
+        Err(BfError::StepLimit(_)) if run.steps.is_some_and(|steps| steps <= max_steps) => ("paused", None),
+        Err(e) => ("error", Some(describe(&e, &limits))),
+    };
+    // The tape up to the last cell in use, or the pointer.
+    let memory = machine.memory();
+    let used = memory.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1).max(machine.pointer() + 1);
+    let position = (status != "finished").then(|| program.spans.get(machine.instruction()).map(|span| span.start)).flatten();
+    json!({
+        "status": status,
+        "error": error,
+        "output": String::from_utf8_lossy(&output),
+        "steps": machine.steps(),
+        "pointer": machine.pointer(),
+        "memory": &memory[..used],
+        "position": position,
+    })
+}
+
+/// A run from the API.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiRun {
+    program: String,
+    #[serde(default)]
+    input: String,
+    #[serde(default)]
+    limits: RequestLimits,
+}
+
+/// Limits asked for by a request, which can only be lower than the ones of the server.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct RequestLimits {
+    max_steps: Option<u64>,
+    max_output: Option<u64>,
+    mem_size: Option<usize>,
+    max_loop_depth: Option<usize>,
+    /// Time limit, in milliseconds.
+    timeout_ms: Option<u64>,
+}
+impl RequestLimits {
+    /// The limits of the server, lowered to the ones of the request.
+    fn within(&self, limits: &Limits) -> Limits {
+        let lower = |asked: Option<u64>, limit: u64| asked.map_or(limit, |n| n.min(limit));
+        let max_loop_depth = match (self.max_loop_depth, limits.max_loop_depth) {
+            (Some(asked), Some(limit)) => Some(asked.min(limit)),
+            (asked, limit) => asked.or(limit),
+        };
+        let mem_size = self.mem_size.map_or(limits.settings.mem_size, |n| n.clamp(1, limits.settings.mem_size));
+        Limits {
+            settings: Settings { mem_size, ..limits.settings },
+            max_steps: lower(self.max_steps, limits.max_steps),
+            max_output: lower(self.max_output, limits.max_output),
+            max_loop_depth,
+            timeout: self.timeout_ms.map_or(limits.timeout, |ms| Duration::from_millis(ms).min(limits.timeout)),
+        }
+    }
+}
+
+/// Runs the program to its end, optimized, and reports how it went: `finished`, `step_limit`, `output_limit`,
+/// `time_limit` or `error`, with the error message.
+fn api_run(run: &ApiRun, limits: &Limits, worker: &Worker) -> Value {
+    let limits = run.limits.within(limits);
+    let program = match Program::parse(&run.program) {
+        Ok(program) => Pipeline::for_level(OptLevel::default()).target(limits.settings.target()).run(&program).0,
+        Err(e) => return json!({ "status": "error", "error": e.to_string(), "output": "", "steps": 0 }),
+    };
+    let (machine, result, output) = execute(&program, run.input.as_bytes(), &limits, worker);
+    let status = match &result {
+        Ok(()) => "finished",
+        Err(BfError::StepLimit(_)) => "step_limit",
+        Err(BfError::OutputLimit(_)) => "output_limit",
+        Err(BfError::Interrupted) => "time_limit",
+        Err(_) => "error",
+    };
+    json!({
+        "status": status,
+        "error": result.err().map(|e| describe(&e, &limits)),
+        "output": String::from_utf8_lossy(&output),
+        "steps": machine.steps(),
+    })
+}
+
+/// Runs the program on a fresh machine with the limits and the input, on the worker, and returns the machine, how the
+/// run ended and what the program printed.
+fn execute(program: &Program, input: &[u8], limits: &Limits, worker: &Worker) -> (Machine, Result<(), BfError>, Vec<u8>) {
+    let output = Capture::default();
+    let mut machine = Machine::builder()
+        .mem_size(limits.settings.mem_size)
+        .cell_width(limits.settings.cell_width)
+        .eof(limits.settings.eof)
+        .max_steps(Some(limits.max_steps))
+        .max_output(Some(limits.max_output))
+        .max_loop_depth(limits.max_loop_depth)
+        .interrupt(worker.interrupt)
+        .io(io::Cursor::new(input.to_vec()), output.clone())
+        .build();
+    let result = worker.run(&mut machine, program, limits.timeout);
+    let printed = output.contents().clone();
+    (machine, result, printed)
+}
+
+/// The message of an error of a run. Runs are only interrupted when they reach their time limit.
+fn describe(error: &BfError, limits: &Limits) -> String {
+    match error {
+        BfError::Interrupted => format!("Time limit reached, the program ran for more than {} ms", limits.timeout.as_millis()),
+        e => e.to_string(),
+    }
+}
@@ -0,0 +1,57 @@
+// Handling of signals while running programs.
+// MIT license.
+
+use std::sync::atomic::AtomicBool;
+#[cfg(unix)]
+use std::sync::atomic::Ordering;
+
+/// Set when the user presses Ctrl-C, to stop the running program.
+pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Set on SIGUSR1, to print the state of the running program.
+#[cfg(feature = "signals")]
+pub static DUMP: AtomicBool = AtomicBool::new(false);
+
+/// Exit status of the interpreter when the program was interrupted, the usual one for SIGINT.
+pub const INTERRUPTED_STATUS: i32 = 130;
+
+/// Installs the SIGINT handler, which sets [`INTERRUPTED`]. A second Ctrl-C exits at once, in case the program is
+/// stuck where the flag is not checked. With the `signals` feature, also installs the SIGUSR1 handler, which sets
+/// `DUMP`. Only on Unix, elsewhere Ctrl-C still ends the process right away.
+pub fn install() {
+    #[cfg(unix)]
+    {
+        // No SA_RESTART, so that a program waiting for its input is interrupted as well.
+        handle(libc::SIGINT, "SIGINT", on_sigint, 0);
+        // Dumping the state must not disturb a program waiting for its input.
+        #[cfg(feature = "signals")]
+        handle(libc::SIGUSR1, "SIGUSR1", on_sigusr1, libc::SA_RESTART);
+    }
+}
+
+#[cfg(unix)]
+fn handle(signal: libc::c_int, name: &str, handler: extern "C" fn(libc::c_int), flags: libc::c_int) {
+    // SAFETY: the handlers only touch atomics and call `_exit`, which are all async-signal-safe.
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handler as libc::sighandler_t;
+        action.sa_flags = flags;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(signal, &action, std::ptr::null_mut()) != 0 {
+            log::debug!("Can not install the {} handler: {}", name, std::io::Error::last_os_error());
+        }
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_sigint(_: libc::c_int) {
+    if INTERRUPTED.swap(true, Ordering::Relaxed) {
+        // SAFETY: `_exit` is async-signal-safe.
+        unsafe { libc::_exit(INTERRUPTED_STATUS) };
+    }
+}
+
+#[cfg(all(unix, feature = "signals"))]
+extern "C" fn on_sigusr1(_: libc::c_int) {
+    DUMP.store(true, Ordering::Relaxed);
+}
@@ -0,0 +1,181 @@
+// Program statistics.
+// MIT license.
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::analysis;
+use crate::program::{Op, Program};
+
+/// Static statistics about a program.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    /// Total number of instructions.
+    pub instructions: usize,
+    /// Pointer moves, `>` and `<`.
+    pub moves: usize,
+    /// Cell modifications, `+` and `-`.
+    pub adds: usize,
+    /// Outputs, `.`.
+    pub outputs: usize,
+    /// Inputs, `,`.
+    pub inputs: usize,
+    /// Number of loops.
+    pub loops: usize,
+    /// Loops that leave the pointer where it started on every iteration.
+    pub balanced_loops: usize,
+    /// Loops that can be replaced by straight code, see `LoopInfo::is_simple`.
+    pub simple_loops: usize,
+    /// Deepest loop nesting level.
+    pub max_depth: usize,
+    /// Sum of the nesting levels of all the loops.
+    pub total_depth: usize,
+    /// Loops replaced by the optimizer with dedicated instructions.
+    pub optimized_loops: usize,
+}
+
+impl Stats {
+    /// Computes the statistics of the given program.
+    pub fn of(program: &Program) -> Stats {
+        let mut stats = Stats { instructions: program.len(), ..Stats::default() };
+        let mut depth: usize = 0;
+        for op in &program.ops {
+            match op {
+                Op::Move(n) => stats.moves += n.unsigned_abs(),
+                Op::Add(n) | Op::AddAt { value: n, .. } => stats.adds += n.unsigned_abs() as usize,
+                Op::Out | Op::OutByte(_) => stats.outputs += 1,
+                Op::In => stats.inputs += 1,
+                Op::Open(_) => {
+                    stats.loops += 1;
+                    depth += 1;
+                    stats.max_depth = stats.max_depth.max(depth);
+                    stats.total_depth += depth;
+                },
+                Op::Close(_) => depth -= 1,
+                Op::Debug | Op::Channel | Op::Send | Op::Receive | Op::Fork | Op::Clock | Op::File(_) | Op::MulAdd { .. } => (),
+                Op::Clear | Op::Scan(_) => stats.optimized_loops += 1,
+                Op::Fill { len, .. } => stats.optimized_loops += len,
+            }
+        }
+        for info in analysis::loops(program) {
+            stats.balanced_loops += info.balanced as usize;
+            stats.simple_loops += info.is_simple() as usize;
+        }
+        stats
+    }
+
+    /// Loops that can leave the pointer somewhere else than where their iterations started.
+    pub fn unbalanced_loops(&self) -> usize {
+        self.loops - self.balanced_loops
+    }
+
+    /// Average loop nesting level, zero without loops.
+    pub fn average_depth(&self) -> f64 {
+        self.total_depth as f64 / self.loops.max(1) as f64
+    }
+
+    /// Number of inputs and outputs per pointer move and cell modification, zero without them.
+    pub fn io_ratio(&self) -> f64 {
+        (self.inputs + self.outputs) as f64 / (self.moves + self.adds).max(1) as f64
+    }
+
+    /// A rough estimate of how hard the program is to follow. It grows with the size of the program, with how deep
+    /// its loops nest and with the share of loops that move the pointer around. The score has no unit, it is only
+    /// meant to compare programs: hello world scores 48.
+    pub fn difficulty(&self) -> f64 {
+        let size = 10.0 * (1.0 + self.instructions as f64).log10();
+        let nesting = 3.0 * self.max_depth as f64 + 5.0 * self.average_depth();
+        let unbalanced = 20.0 * self.unbalanced_loops() as f64 / self.loops.max(1) as f64;
+        ((size + nesting + unbalanced) * 10.0).round() / 10.0
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Instructions: {}", self.instructions)?;
+        writeln!(f, "Moves:        {}", self.moves)?;
+        writeln!(f, "Adds:         {}", self.adds)?;
+        writeln!(f, "Outputs:      {}", self.outputs)?;
+        writeln!(f, "Inputs:       {}", self.inputs)?;
+        writeln!(f, "Loops:        {}", self.loops)?;
+        writeln!(f, "Balanced:     {}", self.balanced_loops)?;
+        writeln!(f, "Unbalanced:   {}", self.unbalanced_loops())?;
+        writeln!(f, "Simple:       {}", self.simple_loops)?;
+        writeln!(f, "Max depth:    {}", self.max_depth)?;
+        writeln!(f, "Avg depth:    {:.2}", self.average_depth())?;
+        writeln!(f, "I/O ratio:    {:.3}", self.io_ratio())?;
+        writeln!(f, "Optimized:    {}", self.optimized_loops)?;
+        write!(f, "Difficulty:   {:.1}", self.difficulty())
+    }
+}
+
+/// A loop of a program, with its place in the loop tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopStats {
+    /// Indices of the brackets.
+    pub open: usize,
+    pub close: usize,
+    /// Number of loops it is in, counting itself.
+    pub depth: usize,
+    /// Number of instructions between the brackets, those of the nested loops included.
+    pub instructions: usize,
+}
+
+/// The loops of the program, in the order of their opening brackets, so that every loop comes right before the
+/// loops nested in it.
+pub fn loop_tree(program: &Program) -> Vec<LoopStats> {
+    let mut depth = 0;
+    let mut loops = vec![];
+    for (i, op) in program.ops.iter().enumerate() {
+        match *op {
+            Op::Open(close) => {
+                depth += 1;
+                loops.push(LoopStats { open: i, close, depth, instructions: close - i - 1 });
+            },
+            Op::Close(_) => depth -= 1,
+            _ => (),
+        }
+    }
+    loops
+}
+
+/// Prints the loop tree, with the location and size of every loop, indented by depth. With the number of times
+/// every instruction ran, from a profiled run, also prints how many times every loop was entered, how many
+/// iterations it ran and how much of the run was spent in it.
+pub fn loop_report(program: &Program, counts: Option<&[u64]>) -> String {
+    let loops = loop_tree(program);
+    if loops.is_empty() {
+        return "No loops\n".to_string();
+    }
+    let total: u64 = counts.map_or(0, |c| c.iter().sum());
+    let mut out = String::new();
+    for l in loops {
+        let (line, column) = program.location(program.spans[l.open].start);
+        out.push_str(&format!(
+            "{}{}:{} depth {}, {}",
+            "  ".repeat(l.depth - 1),
+            line,
+            column,
+            l.depth,
+            plural(l.instructions as u64, "instruction")
+        ));
+        if let Some(counts) = counts {
+            let steps: u64 = counts[l.open..=l.close].iter().sum();
+            out.push_str(&format!(
+                ", entered {}, {}, {} ({:.1}%)",
+                plural(counts[l.open], "time"),
+                plural(counts[l.close], "iteration"),
+                plural(steps, "step"),
+                100.0 * steps as f64 / total.max(1) as f64
+            ));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The count and the noun, in plural unless the count is one.
+fn plural(count: u64, noun: &str) -> String {
+    format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+}
@@ -0,0 +1,608 @@
+// Symbolic execution of programs, with the input bytes as variables.
+// MIT license.
+
+use std::collections::BTreeMap;
+
+use crate::machine::Eof;
+use crate::program::{Op, Program};
+use crate::transpile::Settings;
+
+/// Maximum number of assignments tried by the solver before giving up on a set of constraints.
+const MAX_TRIES: usize = 100_000;
+
+/// The value of a cell, as an affine function of the input bytes, modulo the cell size.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Expr {
+    constant: u64,
+    /// Coefficient of every input byte, by index. Never zero.
+    terms: BTreeMap<usize, u64>,
+}
+
+impl Expr {
+    fn constant(value: u64) -> Expr {
+        Expr { constant: value, terms: BTreeMap::new() }
+    }
+
+    fn var(index: usize) -> Expr {
+        Expr { constant: 0, terms: BTreeMap::from([(index, 1)]) }
+    }
+
+    fn as_constant(&self) -> Option<u64> {
+        self.terms.is_empty().then_some(self.constant)
+    }
+
+    /// Adds the other expression times the factor, modulo `mask + 1`.
+    fn add(&mut self, other: &Expr, factor: u64, mask: u64) {
+        self.constant = self.constant.wrapping_add(other.constant.wrapping_mul(factor)) & mask;
+        for (&var, &coeff) in &other.terms {
+            let sum = self.terms.get(&var).copied().unwrap_or(0).wrapping_add(coeff.wrapping_mul(factor)) & mask;
+            if sum == 0 {
+                self.terms.remove(&var);
+            } else {
+                self.terms.insert(var, sum);
+            }
+        }
+    }
+
+    /// Replaces the variable with the expression.
+    fn substitute(&mut self, var: usize, value: &Expr, mask: u64) {
+        if let Some(coeff) = self.terms.remove(&var) {
+            self.add(value, coeff, mask);
+        }
+    }
+
+    /// The value with the given variables, or `None` if one of its variables is not given.
+    fn eval(&self, values: &BTreeMap<usize, u64>, mask: u64) -> Option<u64> {
+        let mut sum = self.constant;
+        for (var, &coeff) in &self.terms {
+            sum = sum.wrapping_add(values.get(var)?.wrapping_mul(coeff));
+        }
+        Some(sum & mask)
+    }
+}
+
+/// A condition on the path: the expression is zero, or it is not.
+#[derive(Debug, Clone)]
+struct Constraint {
+    expr: Expr,
+    zero: bool,
+}
+
+/// The inverse of an odd number, modulo 2^64.
+fn inverse(a: u64) -> u64 {
+    // Newton's iteration doubles the number of correct bits every time.
+    let mut x = a;
+    for _ in 0..6 {
+        x = x.wrapping_mul(2u64.wrapping_sub(a.wrapping_mul(x)));
+    }
+    x
+}
+
+/// What the solver found out about a set of constraints.
+enum Solution {
+    /// An input satisfying them, one byte per variable.
+    Sat(Vec<u8>),
+    /// No input satisfies them.
+    Unsat,
+    /// The solver gave up.
+    Unknown,
+}
+
+/// Finds input bytes for the variables `0..vars` satisfying the constraints, modulo `mask + 1`. Equalities with a
+/// variable of odd coefficient are solved for it, and the remaining variables are searched.
+fn solve(constraints: &[Constraint], vars: usize, mask: u64) -> Solution {
+    let mut solved: Vec<(usize, Expr)> = vec![];
+    let mut rest: Vec<Constraint> = vec![];
+    for constraint in constraints {
+        let mut expr = constraint.expr.clone();
+        for (var, value) in &solved {
+            expr.substitute(*var, value, mask);
+        }
+        let pivot = expr.terms.iter().find(|&(_, &c)| c % 2 == 1).map(|(&v, &c)| (v, c));
+        match (constraint.zero, pivot) {
+            (zero, None) if expr.terms.is_empty() => {
+                if (expr.constant == 0) != zero {
+                    return Solution::Unsat;
+                }
+            },
+            (true, Some((var, coeff))) => {
+                // coeff * var + rest = 0, so var = -rest / coeff.
+                expr.terms.remove(&var);
+                let mut value = Expr::default();
+                value.add(&expr, inverse(coeff).wrapping_neg(), mask);
+                for (_, other) in &mut solved {
+                    other.substitute(var, &value, mask);
+                }
+                for other in &mut rest {
+                    other.expr.substitute(var, &value, mask);
+                }
+                solved.push((var, value));
+            },
+            _ => rest.push(Constraint { expr, zero: constraint.zero }),
+        }
+    }
+    let free: Vec<usize> = (0..vars).filter(|v| !solved.iter().any(|(s, _)| s == v)).collect();
+    let mut values: BTreeMap<usize, u64> = BTreeMap::new();
+    let mut tries = 0;
+    match search(&free, &rest, &solved, &mut values, mask, &mut tries) {
+        Some(true) => {
+            let input = (0..vars).map(|v| values.get(&v).copied().unwrap_or(0) as u8).collect();
+            Solution::Sat(input)
+        },
+        Some(false) => Solution::Unsat,
+        None => Solution::Unknown,
+    }
+}
+
+/// Assigns the free variables one at a time, checking every constraint once all its variables are known. Returns
+/// `None` if it tried too many assignments.
+fn search(
+    free: &[usize],
+    rest: &[Constraint],
+    solved: &[(usize, Expr)],
+    values: &mut BTreeMap<usize, u64>,
+    mask: u64,
+    tries: &mut usize,
+) -> Option<bool> {
+    let consistent = |values: &BTreeMap<usize, u64>| {
+        rest.iter().all(|c| c.expr.eval(values, mask).is_none_or(|v| (v == 0) == c.zero))
+    };
+    let Some((&var, free)) = free.split_first() else {
+        // The solved variables are input bytes too.
+        let mut all = values.clone();
+        for (var, value) in solved {
+            match value.eval(values, mask) {
+                Some(v) if v <= u8::MAX as u64 => all.insert(*var, v),
+                _ => return Some(false),
+            };
+        }
+        *values = all;
+        return Some(consistent(values));
+    };
+    for value in 0..=u8::MAX as u64 {
+        *tries += 1;
+        if *tries > MAX_TRIES {
+            return None;
+        }
+        values.insert(var, value);
+        if consistent(values) && search(free, rest, solved, values, mask, tries)? {
+            return Some(true);
+        }
+    }
+    values.remove(&var);
+    Some(false)
+}
+
+/// What to look for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    /// An input reaching the instruction at the given index.
+    Reach(usize),
+    /// An input printing the given byte.
+    Output(u8),
+}
+
+/// Bounds of the exploration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of branches on input-dependent cells per path.
+    pub max_depth: usize,
+    /// Maximum number of instructions per path.
+    pub max_steps: u64,
+}
+
+/// An input reaching the goal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found {
+    pub input: Vec<u8>,
+    /// Number of instructions executed to reach the goal.
+    pub steps: u64,
+}
+
+/// The result of an exploration.
+#[derive(Debug, Clone, Default)]
+pub struct Exploration {
+    /// The first input found that reaches the goal.
+    pub found: Option<Found>,
+    /// Number of paths explored.
+    pub paths: usize,
+    /// Number of paths abandoned at the depth or step limit.
+    pub cut: usize,
+}
+
+/// A path being explored.
+#[derive(Debug, Clone, Default)]
+struct State {
+    /// Index of the next instruction.
+    pc: usize,
+    ptr: usize,
+    /// The cells that are not zero.
+    tape: BTreeMap<usize, Expr>,
+    constraints: Vec<Constraint>,
+    /// Number of bytes read, which are also the variables.
+    reads: usize,
+    steps: u64,
+    depth: usize,
+}
+
+/// Runs program paths, with symbolic or concrete input.
+struct Engine<'a> {
+    program: &'a Program,
+    settings: &'a Settings,
+    goal: Goal,
+    limits: Limits,
+    mask: u64,
+    /// The input, for concrete runs.
+    input: Option<&'a [u8]>,
+}
+
+/// What happened to a path after one instruction.
+enum Step {
+    Continue(State),
+    Fork(State, State),
+    /// The path ended, or left the tape.
+    End,
+    /// The path reached the goal.
+    Goal,
+}
+
+impl Engine<'_> {
+    fn cell(&self, state: &State, at: usize) -> Expr {
+        state.tape.get(&at).cloned().unwrap_or_default()
+    }
+
+    fn set(&self, state: &mut State, at: usize, expr: Expr) {
+        if expr.as_constant() == Some(0) {
+            state.tape.remove(&at);
+        } else {
+            state.tape.insert(at, expr);
+        }
+    }
+
+    fn offset(&self, state: &State, n: isize) -> Option<usize> {
+        state.ptr.checked_add_signed(n).filter(|&p| p < self.settings.mem_size)
+    }
+
+    /// Whether the constraints, with the extra one, can be satisfied. Undecided ones are assumed to be.
+    fn feasible(&self, state: &State, extra: Constraint) -> bool {
+        let mut constraints = state.constraints.clone();
+        constraints.push(extra);
+        !matches!(solve(&constraints, state.reads, self.mask), Solution::Unsat)
+    }
+
+    /// Goes on with the path depending on whether the expression is zero, forking if it depends on the input.
+    fn branch(&self, mut state: State, expr: Expr, if_zero: usize, if_not: impl FnOnce(&mut State)) -> Step {
+        if let Some(value) = expr.as_constant() {
+            if value == 0 {
+                state.pc = if_zero;
+            } else {
+                if_not(&mut state);
+            }
+            return Step::Continue(state);
+        }
+        let zero = Constraint { expr: expr.clone(), zero: true };
+        let not_zero = Constraint { expr, zero: false };
+        match (self.feasible(&state, zero.clone()), self.feasible(&state, not_zero.clone())) {
+            (true, true) => {
+                let mut other = state.clone();
+                state.depth += 1;
+                other.depth += 1;
+                state.pc = if_zero;
+                state.constraints.push(zero);
+                if_not(&mut other);
+                other.constraints.push(not_zero);
+                Step::Fork(state, other)
+            },
+            (true, false) => {
+                state.pc = if_zero;
+                Step::Continue(state)
+            },
+            (false, true) => {
+                if_not(&mut state);
+                Step::Continue(state)
+            },
+            (false, false) => Step::End,
+        }
+    }
+
+    /// Whether some input following the path makes the expression equal to the byte.
+    fn can_print(&self, state: &State, expr: &Expr, byte: u8) -> bool {
+        match expr.as_constant() {
+            Some(value) => value as u8 == byte,
+            None => {
+                let mut target = expr.clone();
+                target.add(&Expr::constant(byte as u64), self.mask, self.mask);
+                self.feasible(state, Constraint { expr: target, zero: true })
+            },
+        }
+    }
+
+    fn step(&self, mut state: State) -> Step {
+        let Some(&op) = self.program.ops.get(state.pc) else {
+            return Step::End;
+        };
+        if self.goal == Goal::Reach(state.pc) {
+            return Step::Goal;
+        }
+        state.steps += 1;
+        let next = state.pc + 1;
+        match op {
+            Op::Move(n) => match self.offset(&state, n) {
+                Some(p) => state.ptr = p,
+                None => return Step::End,
+            },
+            Op::Add(n) => {
+                let mut expr = self.cell(&state, state.ptr);
+                expr.add(&Expr::constant(n as u32 as u64), 1, self.mask);
+                let at = state.ptr;
+                self.set(&mut state, at, expr);
+            },
+            Op::AddAt { offset, value } => {
+                let Some(at) = self.offset(&state, offset) else { return Step::End };
+                let mut expr = self.cell(&state, at);
+                expr.add(&Expr::constant(value as u32 as u64), 1, self.mask);
+                self.set(&mut state, at, expr);
+            },
+            Op::MulAdd { offset, factor } => {
+                let source = self.cell(&state, state.ptr);
+                if source.as_constant() != Some(0) {
+                    let Some(at) = self.offset(&state, offset) else { return Step::End };
+                    let mut expr = self.cell(&state, at);
+                    expr.add(&source, factor as u32 as u64, self.mask);
+                    self.set(&mut state, at, expr);
+                }
+            },
+            Op::Clear => {
+                let at = state.ptr;
+                self.set(&mut state, at, Expr::default());
+            },
+            Op::Fill { offset, len, value } => {
+                let (Some(start), Some(_)) = (self.offset(&state, offset), self.offset(&state, offset + len as isize - 1)) else {
+                    return Step::End;
+                };
+                for at in start..start + len {
+                    self.set(&mut state, at, Expr::constant(value as u32 as u64 & self.mask));
+                }
+            },
+            Op::In => {
+                let expr = match self.input {
+                    None => Expr::var(state.reads),
+                    Some(input) => match (input.get(state.reads), self.settings.eof) {
+                        (Some(&b), _) => Expr::constant(b as u64),
+                        (None, Eof::Zero) => Expr::default(),
+                        (None, Eof::Unchanged) => self.cell(&state, state.ptr),
+                        (None, Eof::Max) => Expr::constant(self.mask),
+                    },
+                };
+                state.reads += 1;
+                let at = state.ptr;
+                self.set(&mut state, at, expr);
+            },
+            Op::Out => {
+                if let Goal::Output(byte) = self.goal {
+                    if self.can_print(&state, &self.cell(&state, state.ptr), byte) {
+                        return Step::Goal;
+                    }
+                }
+            },
+            Op::OutByte(b) => {
+                if self.goal == Goal::Output(b) {
+                    return Step::Goal;
+                }
+            },
+            // The channels are all one stream here.
+            Op::Debug | Op::Channel | Op::Send => (),
+            // What the peer sends, the time and the files are not known, and the threads are not followed, the path ends there.
+            Op::Receive | Op::Fork | Op::Clock | Op::File(_) => return Step::End,
+            Op::Open(close) => {
+                let expr = self.cell(&state, state.ptr);
+                return self.branch(state, expr, close + 1, |s| s.pc = next);
+            },
+            Op::Close(open) => {
+                let expr = self.cell(&state, state.ptr);
+                return self.branch(state, expr, next, |s| s.pc = open + 1);
+            },
+            Op::Scan(n) => {
+                let expr = self.cell(&state, state.ptr);
+                let Some(moved) = self.offset(&state, n) else {
+                    // Leaving the tape ends the path, so the only way on is to stop here.
+                    if expr.as_constant().is_some_and(|v| v != 0) {
+                        return Step::End;
+                    }
+                    return self.branch(state, expr, next, |_| ());
+                };
+                // The instruction runs again after moving.
+                return self.branch(state, expr, next, |s| s.ptr = moved);
+            },
+        }
+        state.pc = next;
+        Step::Continue(state)
+    }
+
+    /// Follows the paths from the start of the program, depth first, until one reaches the goal with an input
+    /// that `check` accepts. Returns it, with the number of paths that ended and that were cut.
+    fn explore(&self, mut check: impl FnMut(&State) -> Option<Found>) -> (Option<Found>, usize, usize) {
+        let mut stack = vec![State::default()];
+        let (mut paths, mut cut) = (0, 0);
+        while let Some(state) = stack.pop() {
+            if state.depth > self.limits.max_depth || state.steps > self.limits.max_steps {
+                cut += 1;
+                continue;
+            }
+            match self.step(state.clone()) {
+                Step::Continue(next) => stack.push(next),
+                Step::Fork(zero, not_zero) => {
+                    stack.push(zero);
+                    stack.push(not_zero);
+                },
+                Step::End => paths += 1,
+                Step::Goal => {
+                    paths += 1;
+                    if let Some(found) = check(&state) {
+                        return (Some(found), paths, cut);
+                    }
+                },
+            }
+        }
+        (None, paths, cut)
+    }
+
+    /// Finds an input following the path to the goal, and checks it with a concrete run.
+    fn solve_path(&self, state: &State) -> Option<Found> {
+        let mut constraints = state.constraints.clone();
+        if let (Goal::Output(byte), Some(Op::Out)) = (self.goal, self.program.ops.get(state.pc)) {
+            let mut target = self.cell(state, state.ptr);
+            target.add(&Expr::constant(byte as u64), self.mask, self.mask);
+            constraints.push(Constraint { expr: target, zero: true });
+        }
+        let Solution::Sat(input) = solve(&constraints, state.reads, self.mask) else {
+            return None;
+        };
+        // The concrete run has a single path.
+        let limits = Limits { max_depth: 0, ..self.limits };
+        let concrete = Engine { input: Some(&input), limits, ..*self };
+        match concrete.explore(|s| Some(Found { input: input.clone(), steps: s.steps })) {
+            (Some(found), _, _) => Some(found),
+            _ => None,
+        }
+    }
+}
+
+/// Explores the paths of the program, with every byte read as a variable, looking for an input reaching the goal.
+/// The programs are assumed to never reach the end of their input. Inputs found are checked by running the program
+/// on them.
+pub fn explore(program: &Program, settings: &Settings, goal: Goal, limits: Limits) -> Exploration {
+    let mask = settings.cell_width.mask() as u64;
+    let engine = Engine { program, settings, goal, limits, mask, input: None };
+    let (found, paths, cut) = engine.explore(|state| engine.solve_path(state));
+    Exploration { found, paths, cut }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equiv;
+    use crate::machine::CellWidth;
+
+    const MASK_8: u64 = u8::MAX as u64;
+    const MASK_16: u64 = u16::MAX as u64;
+
+    const LIMITS: Limits = Limits { max_depth: 16, max_steps: 10_000 };
+
+    /// The constraint that `constant + sum of coeff * var` is zero, or is not.
+    fn constraint(constant: u64, terms: &[(usize, u64)], zero: bool) -> Constraint {
+        Constraint { expr: Expr { constant, terms: terms.iter().copied().collect() }, zero }
+    }
+
+    /// Minus the value, modulo `mask + 1`.
+    fn neg(value: u64, mask: u64) -> u64 {
+        value.wrapping_neg() & mask
+    }
+
+    /// Checks that the input satisfies the constraints.
+    fn satisfies(input: &[u8], constraints: &[Constraint], mask: u64) -> bool {
+        let values: BTreeMap<usize, u64> = input.iter().enumerate().map(|(i, &b)| (i, b as u64)).collect();
+        constraints.iter().all(|c| (c.expr.eval(&values, mask) == Some(0)) == c.zero)
+    }
+
+    fn sat(constraints: &[Constraint], vars: usize, mask: u64) -> Vec<u8> {
+        match solve(constraints, vars, mask) {
+            Solution::Sat(input) => {
+                assert!(satisfies(&input, constraints, mask), "{:?} for {:?}", input, constraints);
+                input
+            },
+            Solution::Unsat => panic!("no input found for {:?}", constraints),
+            Solution::Unknown => panic!("the solver gave up on {:?}", constraints),
+        }
+    }
+
+    fn unsat(constraints: &[Constraint], vars: usize, mask: u64) {
+        assert!(matches!(solve(constraints, vars, mask), Solution::Unsat), "{:?} is satisfiable", constraints);
+    }
+
+    #[test]
+    fn inverse_of_odd_numbers() {
+        for a in [1, 3, 5, 7, 255, 65_535, 0x1234_5679, u64::MAX] {
+            assert_eq!(a.wrapping_mul(inverse(a)), 1, "inverse of {}", a);
+        }
+    }
+
+    #[test]
+    fn solves_equalities_at_8_bits() {
+        // 3 * x + 5 = 0 mod 256.
+        sat(&[constraint(5, &[(0, 3)], true)], 1, MASK_8);
+        // x + 2 * y = 10, y != 0, and x is not 2.
+        let constraints = [
+            constraint(neg(10, MASK_8), &[(0, 1), (1, 2)], true),
+            constraint(0, &[(1, 1)], false),
+            constraint(neg(2, MASK_8), &[(0, 1)], false),
+        ];
+        let input = sat(&constraints, 2, MASK_8);
+        assert_ne!(input[1], 0);
+        // Even coefficients are searched: 2 * x = 6 mod 256.
+        sat(&[constraint(neg(6, MASK_8), &[(0, 2)], true)], 1, MASK_8);
+    }
+
+    #[test]
+    fn refuses_impossible_constraints_at_8_bits() {
+        // 2 * x + 1 is odd, never zero.
+        unsat(&[constraint(1, &[(0, 2)], true)], 1, MASK_8);
+        // x = 4 and x != 4.
+        unsat(&[constraint(neg(4, MASK_8), &[(0, 1)], true), constraint(neg(4, MASK_8), &[(0, 1)], false)], 1, MASK_8);
+        // A constant that is not zero.
+        unsat(&[constraint(1, &[], true)], 0, MASK_8);
+    }
+
+    #[test]
+    fn solves_equalities_at_16_bits() {
+        // x + 2 * y = 510 needs y of at least 128, for x to be a byte.
+        let input = sat(&[constraint(neg(510, MASK_16), &[(0, 1), (1, 2)], true)], 2, MASK_16);
+        assert!(input[1] >= 128);
+        // 2 * x = 400 mod 65536.
+        sat(&[constraint(neg(400, MASK_16), &[(0, 2)], true)], 1, MASK_16);
+    }
+
+    #[test]
+    fn refuses_impossible_constraints_at_16_bits() {
+        // x = 300 does not fit in a byte, it would at 8 bits.
+        unsat(&[constraint(neg(300, MASK_16), &[(0, 1)], true)], 1, MASK_16);
+        sat(&[constraint(neg(300, MASK_8), &[(0, 1)], true)], 1, MASK_8);
+        // x + y = 600 with two bytes.
+        unsat(&[constraint(neg(600, MASK_16), &[(0, 1), (1, 1)], true)], 2, MASK_16);
+    }
+
+    #[test]
+    fn finds_inputs_printing_a_byte() {
+        let program = Program::parse(",+++.,>,[-<->]<.").unwrap();
+        let settings = Settings::default();
+        let found = explore(&program, &settings, Goal::Output(b'A'), LIMITS).found.expect("an input printing A");
+        let outcome = equiv::run(&program, &settings, &found.input, LIMITS.max_steps);
+        assert_eq!(outcome.output.first(), Some(&b'A'));
+        // The second byte printed is a difference of two bytes read.
+        let found = explore(&program, &settings, Goal::Output(200), LIMITS).found.expect("an input printing 200");
+        let outcome = equiv::run(&program, &settings, &found.input, LIMITS.max_steps);
+        assert!(outcome.output.contains(&200));
+    }
+
+    #[test]
+    fn finds_inputs_reaching_an_instruction() {
+        // The '.' is reached only when the byte read is 3.
+        let program = Program::parse(",>+<---[>-<[-]]>[.[-]]").unwrap();
+        let index = program.ops.iter().position(|op| *op == Op::Out).unwrap();
+        for cell_width in [CellWidth::U8, CellWidth::U16] {
+            let settings = Settings { cell_width, ..Settings::default() };
+            let found = explore(&program, &settings, Goal::Reach(index), LIMITS).found.expect("an input reaching '.'");
+            assert_eq!(found.input, [3]);
+            let outcome = equiv::run(&program, &settings, &found.input, LIMITS.max_steps);
+            assert_eq!(outcome.output, [1]);
+        }
+    }
+
+    #[test]
+    fn finds_nothing_for_unreachable_goals() {
+        let program = Program::parse(",[-]>[.]").unwrap();
+        let index = program.ops.iter().position(|op| *op == Op::Out).unwrap();
+        let exploration = explore(&program, &Settings::default(), Goal::Reach(index), LIMITS);
+        assert_eq!(exploration.found, None);
+    }
+}
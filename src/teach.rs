@@ -0,0 +1,66 @@
+// Narration of runs in plain English, for teaching.
+// MIT license.
+
+use std::io::{self, prelude::*};
+use std::thread;
+use std::time::Duration;
+
+use brainfuck::{Machine, Op, Program};
+
+/// Pause after every instruction, unless --delay gives another one.
+pub const DELAY: u64 = 500;
+
+/// A value, with the character it is if it is a printable one.
+fn value(v: u32) -> String {
+    match char::from_u32(v).filter(|c| c.is_ascii_graphic()) {
+        Some(c) => format!("{} ('{}')", v, c),
+        None => v.to_string(),
+    }
+}
+
+/// What the instruction the machine just ran did, in a sentence, from the state it left the machine in.
+fn explain(machine: &Machine, program: &Program) -> String {
+    let memory = machine.memory();
+    let ptr = machine.pointer();
+    let cell = |at: usize| format!("cell {} = {}", at, value(memory[at]));
+    let at = |offset: isize| ptr.wrapping_add_signed(offset);
+    match program.ops[machine.instruction()] {
+        Op::Move(n) => {
+            let side = if n < 0 { "left" } else { "right" };
+            match n.unsigned_abs() {
+                1 => format!("move the pointer {} to cell {}", side, ptr),
+                n => format!("move the pointer {} cells {}, to cell {}", n, side, ptr),
+            }
+        },
+        Op::Add(n) if n < 0 => format!("subtract {} from cell {}, which is now {}", n.unsigned_abs(), ptr, value(memory[ptr])),
+        Op::Add(n) => format!("add {} to cell {}, which is now {}", n, ptr, value(memory[ptr])),
+        Op::AddAt { offset, value: n } => format!("add {} to cell {}, which is now {}", n, at(offset), value(memory[at(offset)])),
+        Op::Out => format!("print {}", cell(ptr)),
+        Op::OutByte(b) => format!("print {}", value(b as u32)),
+        Op::In => format!("read a byte into cell {}: {}", ptr, value(memory[ptr])),
+        Op::Open(_) if memory[ptr] == 0 => format!("skip the loop, because {}", cell(ptr)),
+        Op::Open(_) => format!("enter the loop, because {}", cell(ptr)),
+        Op::Close(_) if memory[ptr] == 0 => format!("the loop ends, because {}", cell(ptr)),
+        Op::Close(_) => format!("the loop continues, because {}", cell(ptr)),
+        Op::Clear => format!("clear cell {}", ptr),
+        Op::MulAdd { offset, factor } => {
+            format!("add {} times cell {} to cell {}, which is now {}", factor, ptr, at(offset), value(memory[at(offset)]))
+        },
+        Op::Fill { offset, len, value: v } => format!("set cells {} to {} to {}", at(offset), at(offset) + len - 1, v),
+        Op::Scan(n) => format!("look for a zero cell, {} cells at a time, and find cell {}", n, ptr),
+        Op::Debug => "show the memory, in debug mode".to_string(),
+        op => format!("run '{}' with cell {}", op, ptr),
+    }
+}
+
+/// Prints what the instruction the machine just ran did to the standard error, with where it is in the source code,
+/// after what the program printed, and pauses.
+pub fn narrate(machine: &Machine, program: &Program, pause: Duration) -> io::Result<()> {
+    io::stdout().flush()?;
+    let i = machine.instruction();
+    let (line, column) = program.location(program.spans[i].start);
+    let location = format!("{}:{}", line, column);
+    writeln!(io::stderr(), "{:<8} {:<4} {}", location, program.ops[i].to_string(), explain(machine, program))?;
+    thread::sleep(pause);
+    Ok(())
+}
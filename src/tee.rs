@@ -0,0 +1,49 @@
+// Copying of the program output.
+// MIT license.
+
+use std::cell::{Ref, RefCell};
+use std::io::{self, prelude::*};
+use std::rc::Rc;
+
+/// Writes everything to both writers.
+pub struct Tee<A, B>(pub A, pub B);
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.0.write(buf)?;
+        self.1.write_all(&buf[..n])?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()?;
+        self.1.flush()
+    }
+}
+
+/// Keeps everything written to it, readable by its clones while one is given away as a writer.
+#[derive(Clone, Default)]
+pub struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl Capture {
+    /// Everything written so far.
+    pub fn contents(&self) -> Ref<'_, Vec<u8>> {
+        self.0.borrow()
+    }
+
+    /// Takes everything written so far, leaving nothing.
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.0.borrow_mut())
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+// Go backend.
+// MIT license.
+
+use crate::machine::{CellWidth, Eof};
+use crate::program::Op;
+
+use super::{index, CodegenBackend, Settings};
+
+/// Generates a Go program. Unsigned integers wrap around in Go, so cells of the right width behave like the
+/// interpreter ones.
+pub struct Go;
+
+impl Go {
+    fn cell(settings: &Settings) -> &'static str {
+        match settings.cell_width {
+            CellWidth::U8 => "uint8",
+            CellWidth::U16 => "uint16",
+            CellWidth::U32 => "uint32",
+        }
+    }
+}
+
+impl CodegenBackend for Go {
+    fn prologue(&self, settings: &Settings) -> String {
+        let mut out = String::new();
+        out.push_str("// Generated by the brainfuck interpreter.\n\n");
+        out.push_str("package main\n\n");
+        out.push_str("import (\n\t\"bufio\"\n\t\"fmt\"\n\t\"os\"\n)\n\n");
+        out.push_str("func main() {\n");
+        out.push_str(&format!("\tmem := make([]{}, {})\n", Go::cell(settings), settings.mem_size));
+        out.push_str("\tp := 0\n");
+        out.push_str("\tin := bufio.NewReader(os.Stdin)\n");
+        out.push_str("\tout := bufio.NewWriter(os.Stdout)\n");
+        out.push_str("\t// Checks that the position is in the tape.\n");
+        out.push_str("\tat := func(i int) int {\n");
+        out.push_str("\t\tif i < 0 || i >= len(mem) {\n");
+        out.push_str("\t\t\tout.Flush()\n");
+        out.push_str("\t\t\tfmt.Fprintf(os.Stderr, \"Memory overflow (pointer=%d)\\n\", i)\n");
+        out.push_str("\t\t\tos.Exit(1)\n");
+        out.push_str("\t\t}\n");
+        out.push_str("\t\treturn i\n");
+        out.push_str("\t}\n");
+        out.push_str("\t// Returns the next input byte, and false at the end of the input.\n");
+        out.push_str("\tread := func() (byte, bool) {\n");
+        out.push_str("\t\tout.Flush()\n");
+        out.push_str("\t\tb, err := in.ReadByte()\n");
+        out.push_str("\t\treturn b, err == nil\n");
+        out.push_str("\t}\n");
+        // Go rejects unused variables, and small programs may not need all of them.
+        out.push_str("\t_, _, _ = p, at, read\n");
+        out
+    }
+
+    fn epilogue(&self, _settings: &Settings) -> String {
+        "\tout.Flush()\n}\n".to_string()
+    }
+
+    fn instruction(&self, op: Op, settings: &Settings) -> Vec<String> {
+        let mask = settings.cell_width.mask();
+        let line = match op {
+            Op::Move(n) => format!("p = at({})", index(n)),
+            Op::Add(n) => format!("mem[p] += {}", n as u32 & mask),
+            Op::AddAt { offset, value } => format!("mem[at({})] += {}", index(offset), value as u32 & mask),
+            Op::Out => "out.WriteByte(byte(mem[p]))".to_string(),
+            Op::OutByte(b) => format!("out.WriteByte({})", b),
+            Op::In => {
+                let mut lines = vec!["if b, ok := read(); ok {".to_string(), format!("\tmem[p] = {}(b)", Go::cell(settings))];
+                match settings.eof {
+                    Eof::Zero => lines.extend(["} else {".to_string(), "\tmem[p] = 0".to_string()]),
+                    Eof::Unchanged => (),
+                    Eof::Max => lines.extend(["} else {".to_string(), format!("\tmem[p] = ^{}(0)", Go::cell(settings))]),
+                }
+                lines.push("}".to_string());
+                return lines;
+            },
+            Op::Open(_) => "for mem[p] != 0 {".to_string(),
+            Op::Close(_) => "}".to_string(),
+            Op::Debug => "fmt.Fprintf(os.Stderr, \"Ptr: %d, value: %d\\n\", p, mem[p])".to_string(),
+            Op::Channel => "// Channels are not translated, the output stays on the standard streams".to_string(),
+            Op::Send | Op::Receive => "// Messages to the peer machine are not translated".to_string(),
+            Op::Fork => "// Threads are not translated, the program runs as the first one".to_string(),
+            Op::Clock => "// The clock is not translated, the cell is left as it is".to_string(),
+            Op::File(_) => "// Files are not translated, the cells are left as they are".to_string(),
+            Op::Clear => "mem[p] = 0".to_string(),
+            Op::MulAdd { offset, factor } => {
+                format!("if mem[p] != 0 {{ mem[at({})] += mem[p] * {} }}", index(offset), factor as u32 & mask)
+            },
+            Op::Fill { offset, len, value } => format!(
+                "for i := at({}); i <= at({}); i++ {{ mem[i] = {} }}",
+                index(offset),
+                index(offset + len as isize - 1),
+                value as u32 & mask
+            ),
+            Op::Scan(n) => format!("for mem[p] != 0 {{ p = at({}) }}", index(n)),
+        };
+        vec![line]
+    }
+
+    fn literal(&self, output: &[u8]) -> String {
+        let bytes: Vec<String> = output.iter().map(|b| b.to_string()).collect();
+        let mut out = String::new();
+        out.push_str("// Generated by the brainfuck interpreter.\n\n");
+        out.push_str("package main\n\n");
+        out.push_str("import \"os\"\n\n");
+        out.push_str("func main() {\n");
+        out.push_str(&format!("\tos.Stdout.Write([]byte{{{}}})\n", bytes.join(", ")));
+        out.push_str("}\n");
+        out
+    }
+
+    fn indent(&self) -> &'static str {
+        "\t"
+    }
+}
@@ -0,0 +1,111 @@
+// Java backend.
+// MIT license.
+
+use crate::machine::Eof;
+use crate::program::Op;
+
+use super::{index, CodegenBackend, Settings};
+
+/// Generates a Java program, in a class named `Main`. Cells are `int`s, masked after every change to wrap around
+/// like in the interpreter.
+///
+/// The program is a single method, and Java limits methods to 64KB of bytecode, so very large programs may not
+/// compile. Optimizing them first helps.
+pub struct Java;
+
+impl CodegenBackend for Java {
+    fn prologue(&self, settings: &Settings) -> String {
+        let mut out = String::new();
+        out.push_str("// Generated by the brainfuck interpreter.\n\n");
+        out.push_str("import java.io.BufferedOutputStream;\n");
+        out.push_str("import java.io.IOException;\n");
+        out.push_str("import java.util.Arrays;\n\n");
+        out.push_str("public class Main {\n");
+        out.push_str(&format!("    static final int MEM_SIZE = {};\n", settings.mem_size));
+        out.push_str(&format!("    static final int MASK = {:#x};\n", settings.cell_width.mask()));
+        out.push_str("    static final BufferedOutputStream out = new BufferedOutputStream(System.out, 8192);\n\n");
+        out.push_str("    /** Checks that the position is in the tape. */\n");
+        out.push_str("    static int at(int i) throws IOException {\n");
+        out.push_str("        if (i < 0 || i >= MEM_SIZE) {\n");
+        out.push_str("            out.flush();\n");
+        out.push_str("            System.err.println(\"Memory overflow (pointer=\" + i + \")\");\n");
+        out.push_str("            System.exit(1);\n");
+        out.push_str("        }\n");
+        out.push_str("        return i;\n");
+        out.push_str("    }\n\n");
+        out.push_str("    /** Returns the next input byte, or -1 at the end of the input. */\n");
+        out.push_str("    static int read() throws IOException {\n");
+        out.push_str("        out.flush();\n");
+        out.push_str("        return System.in.read();\n");
+        out.push_str("    }\n\n");
+        out.push_str("    public static void main(String[] args) throws IOException {\n");
+        out.push_str("        int[] mem = new int[MEM_SIZE];\n");
+        out.push_str("        int p = 0;\n");
+        out
+    }
+
+    fn epilogue(&self, _settings: &Settings) -> String {
+        "        out.flush();\n    }\n}\n".to_string()
+    }
+
+    fn instruction(&self, op: Op, settings: &Settings) -> Vec<String> {
+        let line = match op {
+            Op::Move(n) => format!("p = at({});", index(n)),
+            Op::Add(n) => format!("mem[p] = (mem[p] + {}) & MASK;", n),
+            Op::AddAt { offset, value } => {
+                format!("{{ int i = at({}); mem[i] = (mem[i] + {}) & MASK; }}", index(offset), value)
+            },
+            Op::Out => "out.write(mem[p]);".to_string(),
+            Op::OutByte(b) => format!("out.write({});", b),
+            Op::In => {
+                let eof = match settings.eof {
+                    Eof::Zero => " else { mem[p] = 0; }",
+                    Eof::Unchanged => "",
+                    Eof::Max => " else { mem[p] = MASK; }",
+                };
+                format!("{{ int b = read(); if (b >= 0) {{ mem[p] = b; }}{} }}", eof)
+            },
+            Op::Open(_) => "while (mem[p] != 0) {".to_string(),
+            Op::Close(_) => "}".to_string(),
+            Op::Debug => {
+                "System.err.println(\"Ptr: \" + p + \", value: \" + Integer.toUnsignedString(mem[p]));".to_string()
+            },
+            Op::Channel => "// Channels are not translated, the output stays on the standard streams".to_string(),
+            Op::Send | Op::Receive => "// Messages to the peer machine are not translated".to_string(),
+            Op::Fork => "// Threads are not translated, the program runs as the first one".to_string(),
+            Op::Clock => "// The clock is not translated, the cell is left as it is".to_string(),
+            Op::File(_) => "// Files are not translated, the cells are left as they are".to_string(),
+            Op::Clear => "mem[p] = 0;".to_string(),
+            Op::MulAdd { offset, factor } => format!(
+                "if (mem[p] != 0) {{ int i = at({}); mem[i] = (mem[i] + mem[p] * {}) & MASK; }}",
+                index(offset),
+                factor
+            ),
+            Op::Fill { offset, len, value } => format!(
+                "Arrays.fill(mem, at({}), at({}) + 1, {} & MASK);",
+                index(offset),
+                index(offset + len as isize - 1),
+                value
+            ),
+            Op::Scan(n) => format!("while (mem[p] != 0) {{ p = at({}); }}", index(n)),
+        };
+        vec![line]
+    }
+
+    fn literal(&self, output: &[u8]) -> String {
+        let bytes: Vec<String> = output.iter().map(|&b| (b as i8).to_string()).collect();
+        let mut out = String::new();
+        out.push_str("// Generated by the brainfuck interpreter.\n\n");
+        out.push_str("public class Main {\n");
+        out.push_str("    public static void main(String[] args) {\n");
+        out.push_str(&format!("        System.out.write(new byte[] {{{}}}, 0, {});\n", bytes.join(", "), output.len()));
+        out.push_str("        System.out.flush();\n");
+        out.push_str("    }\n");
+        out.push_str("}\n");
+        out
+    }
+
+    fn base_depth(&self) -> usize {
+        2
+    }
+}
@@ -0,0 +1,115 @@
+// JavaScript backend.
+// MIT license.
+
+use crate::machine::{CellWidth, Eof};
+use crate::program::Op;
+
+use super::{index, CodegenBackend, Settings};
+
+/// Generates a Node script. The tape is a typed array, which wraps around on its own.
+pub struct Js;
+
+impl CodegenBackend for Js {
+    fn prologue(&self, settings: &Settings) -> String {
+        let array = match settings.cell_width {
+            CellWidth::U8 => "Uint8Array",
+            CellWidth::U16 => "Uint16Array",
+            CellWidth::U32 => "Uint32Array",
+        };
+        let mut out = String::new();
+        out.push_str("// Generated by the brainfuck interpreter.\n\n");
+        out.push_str("\"use strict\";\n\n");
+        out.push_str("const fs = require(\"fs\");\n\n");
+        out.push_str(&format!("const mem = new {}({});\n", array, settings.mem_size));
+        out.push_str("let p = 0;\n");
+        out.push_str("const output = [];\n\n");
+        out.push_str("function flush() {\n");
+        out.push_str("    if (output.length > 0) {\n");
+        out.push_str("        fs.writeSync(process.stdout.fd, Buffer.from(output));\n");
+        out.push_str("        output.length = 0;\n");
+        out.push_str("    }\n");
+        out.push_str("}\n\n");
+        out.push_str("function put(b) {\n");
+        out.push_str("    output.push(b & 0xff);\n");
+        out.push_str("    if (output.length >= 8192) {\n");
+        out.push_str("        flush();\n");
+        out.push_str("    }\n");
+        out.push_str("}\n\n");
+        out.push_str("// Returns the next input byte, or -1 at the end of the input.\n");
+        out.push_str("function get() {\n");
+        out.push_str("    flush();\n");
+        out.push_str("    const b = Buffer.alloc(1);\n");
+        out.push_str("    try {\n");
+        out.push_str("        return fs.readSync(process.stdin.fd, b, 0, 1, null) === 1 ? b[0] : -1;\n");
+        out.push_str("    } catch (e) {\n");
+        out.push_str("        if (e.code === \"EOF\") {\n");
+        out.push_str("            return -1;\n");
+        out.push_str("        }\n");
+        out.push_str("        throw e;\n");
+        out.push_str("    }\n");
+        out.push_str("}\n\n");
+        out.push_str("// Checks that the position is in the tape.\n");
+        out.push_str("function at(i) {\n");
+        out.push_str("    if (i < 0 || i >= mem.length) {\n");
+        out.push_str("        flush();\n");
+        out.push_str("        console.error(`Memory overflow (pointer=${i})`);\n");
+        out.push_str("        process.exit(1);\n");
+        out.push_str("    }\n");
+        out.push_str("    return i;\n");
+        out.push_str("}\n\n");
+        out
+    }
+
+    fn epilogue(&self, _settings: &Settings) -> String {
+        "flush();\n".to_string()
+    }
+
+    fn instruction(&self, op: Op, settings: &Settings) -> Vec<String> {
+        let line = match op {
+            Op::Move(n) => format!("p = at({});", index(n)),
+            Op::Add(n) => format!("mem[p] += {};", n),
+            Op::AddAt { offset, value } => format!("mem[at({})] += {};", index(offset), value),
+            Op::Out => "put(mem[p]);".to_string(),
+            Op::OutByte(b) => format!("put({});", b),
+            Op::In => {
+                let eof = match settings.eof {
+                    Eof::Zero => " else { mem[p] = 0; }",
+                    Eof::Unchanged => "",
+                    Eof::Max => " else { mem[p] = -1; }",
+                };
+                format!("{{ const b = get(); if (b >= 0) {{ mem[p] = b; }}{} }}", eof)
+            },
+            Op::Open(_) => "while (mem[p] !== 0) {".to_string(),
+            Op::Close(_) => "}".to_string(),
+            Op::Debug => "console.error(`Ptr: ${p}, value: ${mem[p]}`);".to_string(),
+            Op::Channel => "// Channels are not translated, the output stays on the standard streams".to_string(),
+            Op::Send | Op::Receive => "// Messages to the peer machine are not translated".to_string(),
+            Op::Fork => "// Threads are not translated, the program runs as the first one".to_string(),
+            Op::Clock => "// The clock is not translated, the cell is left as it is".to_string(),
+            Op::File(_) => "// Files are not translated, the cells are left as they are".to_string(),
+            Op::Clear => "mem[p] = 0;".to_string(),
+            Op::MulAdd { offset, factor } => {
+                // Math.imul keeps the product exact modulo 2^32, like the cells.
+                format!("if (mem[p] !== 0) {{ mem[at({})] += Math.imul(mem[p], {}); }}", index(offset), factor)
+            },
+            Op::Fill { offset, len, value } => {
+                format!("mem.fill({}, at({}), at({}) + 1);", value, index(offset), index(offset + len as isize - 1))
+            },
+            Op::Scan(n) => format!("while (mem[p] !== 0) {{ p = at({}); }}", index(n)),
+        };
+        vec![line]
+    }
+
+    fn literal(&self, output: &[u8]) -> String {
+        let bytes: Vec<String> = output.iter().map(|b| b.to_string()).collect();
+        let mut out = String::new();
+        out.push_str("// Generated by the brainfuck interpreter.\n\n");
+        out.push_str("\"use strict\";\n\n");
+        out.push_str(&format!("process.stdout.write(Buffer.from([{}]));\n", bytes.join(", ")));
+        out
+    }
+
+    fn base_depth(&self) -> usize {
+        0
+    }
+}
@@ -0,0 +1,174 @@
+// Translation of programs to other languages.
+// MIT license.
+
+mod go;
+mod java;
+mod js;
+mod python;
+mod rust;
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::machine::{CellWidth, Eof, DEFAULT_MEM_SIZE};
+use crate::optimize::Target;
+use crate::program::{Op, Program};
+
+pub use self::go::Go;
+pub use self::java::Java;
+pub use self::js::Js;
+pub use self::python::Python;
+pub use self::rust::{cargo_manifest, Rust};
+
+/// Machine semantics the generated code must reproduce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    /// Number of cells in the memory tape.
+    pub mem_size: usize,
+    /// Width of the memory cells.
+    pub cell_width: CellWidth,
+    /// Behavior of `,` at the end of the input.
+    pub eof: Eof,
+}
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings { mem_size: DEFAULT_MEM_SIZE, cell_width: CellWidth::default(), eof: Eof::default() }
+    }
+}
+
+impl Settings {
+    /// The optimization target for a fresh machine with these settings.
+    pub fn target(&self) -> Target {
+        Target { cell_width: self.cell_width, mem_size: self.mem_size, fresh: true }
+    }
+}
+
+/// Generates code in one language. The instructions are walked by [`generate`], which also takes care of the
+/// indentation of loops, so backends only have to produce the code of every instruction.
+pub trait CodegenBackend {
+    /// Code before the first instruction.
+    fn prologue(&self, settings: &Settings) -> String;
+
+    /// Code after the last instruction.
+    fn epilogue(&self, settings: &Settings) -> String;
+
+    /// Lines of code of one instruction. The lines after an `Open` are indented one more level, up to its `Close`.
+    fn instruction(&self, op: Op, settings: &Settings) -> Vec<String>;
+
+    /// A whole program that just prints the given output, for programs computed ahead of time.
+    fn literal(&self, output: &[u8]) -> String;
+
+    /// Indentation level of the top level instructions.
+    fn base_depth(&self) -> usize {
+        1
+    }
+
+    /// One level of indentation.
+    fn indent(&self) -> &'static str {
+        "    "
+    }
+
+    /// Line to put in loops without instructions, for languages that do not allow empty blocks.
+    fn empty_loop(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// Translates the program with the given backend.
+pub fn generate(backend: &dyn CodegenBackend, program: &Program, settings: &Settings) -> String {
+    let mut out = backend.prologue(settings);
+    let mut depth = backend.base_depth();
+    let mut previous: Option<Op> = None;
+    for &op in &program.ops {
+        if let Op::Close(_) = op {
+            if let (Some(Op::Open(_)), Some(line)) = (previous, backend.empty_loop()) {
+                out.push_str(&backend.indent().repeat(depth));
+                out.push_str(line);
+                out.push('\n');
+            }
+            depth -= 1;
+        }
+        for line in backend.instruction(op, settings) {
+            out.push_str(&backend.indent().repeat(depth));
+            out.push_str(&line);
+            out.push('\n');
+        }
+        if let Op::Open(_) = op {
+            depth += 1;
+        }
+        previous = Some(op);
+    }
+    out.push_str(&backend.epilogue(settings));
+    out
+}
+
+/// A language programs can be translated to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    Rust,
+    /// JavaScript, for Node.
+    Js,
+    /// Python 3.
+    Python,
+    Go,
+    Java,
+}
+impl Language {
+    /// The backend generating code in this language.
+    pub fn backend(self) -> &'static dyn CodegenBackend {
+        match self {
+            Language::Rust => &Rust,
+            Language::Js => &Js,
+            Language::Python => &Python,
+            Language::Go => &Go,
+            Language::Java => &Java,
+        }
+    }
+}
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Language, String> {
+        match s {
+            "rust" | "rs" => Ok(Language::Rust),
+            "js" | "javascript" => Ok(Language::Js),
+            "python" | "py" => Ok(Language::Python),
+            "go" => Ok(Language::Go),
+            "java" => Ok(Language::Java),
+            _ => Err(format!("invalid target '{}', expected rust, js, python, go or java", s)),
+        }
+    }
+}
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Language::Rust => write!(f, "rust"),
+            Language::Js => write!(f, "js"),
+            Language::Python => write!(f, "python"),
+            Language::Go => write!(f, "go"),
+            Language::Java => write!(f, "java"),
+        }
+    }
+}
+
+/// Translates the program to the given language.
+pub fn translate(program: &Program, settings: &Settings, language: Language) -> String {
+    generate(language.backend(), program, settings)
+}
+
+/// Generates a program in the given language that just prints the given output, for programs computed ahead of
+/// time.
+pub fn literal(output: &[u8], language: Language) -> String {
+    language.backend().literal(output)
+}
+
+/// Translates the program to the source code of a Rust program with a `main` function.
+pub fn to_rust(program: &Program, settings: &Settings) -> String {
+    generate(&Rust, program, settings)
+}
+
+/// The index of the cell at the given offset from the pointer, in the generated code.
+fn index(offset: isize) -> String {
+    if offset < 0 { format!("p - {}", offset.unsigned_abs()) } else { format!("p + {}", offset) }
+}
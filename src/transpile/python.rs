@@ -0,0 +1,101 @@
+// Python backend.
+// MIT license.
+
+use crate::machine::Eof;
+use crate::program::Op;
+
+use super::{index, CodegenBackend, Settings};
+
+/// Generates a Python 3 script. Python integers do not overflow, so cells are masked after every change to wrap
+/// around like in the interpreter.
+pub struct Python;
+
+impl CodegenBackend for Python {
+    fn prologue(&self, settings: &Settings) -> String {
+        let mut out = String::new();
+        out.push_str("# Generated by the brainfuck interpreter.\n\n");
+        out.push_str("import sys\n\n");
+        out.push_str(&format!("MEM_SIZE = {}\n", settings.mem_size));
+        out.push_str(&format!("MASK = {:#x}\n\n", settings.cell_width.mask()));
+        out.push_str("output = sys.stdout.buffer\n\n\n");
+        out.push_str("def at(i):\n");
+        out.push_str("    \"\"\"Checks that the position is in the tape.\"\"\"\n");
+        out.push_str("    if i < 0 or i >= MEM_SIZE:\n");
+        out.push_str("        output.flush()\n");
+        out.push_str("        sys.exit(f\"Memory overflow (pointer={i})\")\n");
+        out.push_str("    return i\n\n\n");
+        out.push_str("def read():\n");
+        out.push_str("    \"\"\"Returns the next input byte, or None at the end of the input.\"\"\"\n");
+        out.push_str("    output.flush()\n");
+        out.push_str("    b = sys.stdin.buffer.read(1)\n");
+        out.push_str("    return b[0] if b else None\n\n\n");
+        out.push_str("def main():\n");
+        out.push_str("    mem = [0] * MEM_SIZE\n");
+        out.push_str("    p = 0\n");
+        out
+    }
+
+    fn epilogue(&self, _settings: &Settings) -> String {
+        let mut out = String::new();
+        out.push_str("    output.flush()\n\n\n");
+        out.push_str("if __name__ == \"__main__\":\n");
+        out.push_str("    main()\n");
+        out
+    }
+
+    fn instruction(&self, op: Op, settings: &Settings) -> Vec<String> {
+        match op {
+            Op::Move(n) => vec![format!("p = at({})", index(n))],
+            Op::Add(n) => vec![format!("mem[p] = (mem[p] + {}) & MASK", n)],
+            Op::AddAt { offset, value } => {
+                vec![format!("i = at({})", index(offset)), format!("mem[i] = (mem[i] + {}) & MASK", value)]
+            },
+            Op::Out => vec!["output.write(bytes((mem[p] & 0xff,)))".to_string()],
+            Op::OutByte(b) => vec![format!("output.write(bytes(({},)))", b)],
+            Op::In => {
+                let mut lines = vec!["b = read()".to_string(), "if b is not None:".to_string(), "    mem[p] = b".to_string()];
+                match settings.eof {
+                    Eof::Zero => lines.extend(["else:".to_string(), "    mem[p] = 0".to_string()]),
+                    Eof::Unchanged => (),
+                    Eof::Max => lines.extend(["else:".to_string(), "    mem[p] = MASK".to_string()]),
+                }
+                lines
+            },
+            Op::Open(_) => vec!["while mem[p]:".to_string()],
+            // The block ends with the indentation.
+            Op::Close(_) => vec![],
+            Op::Debug => vec!["print(f\"Ptr: {p}, value: {mem[p]}\", file=sys.stderr)".to_string()],
+            Op::Channel => vec!["# Channels are not translated, the output stays on the standard streams".to_string()],
+            Op::Send | Op::Receive => vec!["# Messages to the peer machine are not translated".to_string()],
+            Op::Fork => vec!["# Threads are not translated, the program runs as the first one".to_string()],
+            Op::Clock => vec!["# The clock is not translated, the cell is left as it is".to_string()],
+            Op::File(_) => vec!["# Files are not translated, the cells are left as they are".to_string()],
+            Op::Clear => vec!["mem[p] = 0".to_string()],
+            Op::MulAdd { offset, factor } => vec![
+                "if mem[p]:".to_string(),
+                format!("    i = at({})", index(offset)),
+                format!("    mem[i] = (mem[i] + mem[p] * {}) & MASK", factor),
+            ],
+            Op::Fill { offset, len, value } => vec![format!(
+                "mem[at({}):at({}) + 1] = [{}] * {}",
+                index(offset),
+                index(offset + len as isize - 1),
+                value as u32 & settings.cell_width.mask(),
+                len
+            )],
+            Op::Scan(n) => vec!["while mem[p]:".to_string(), format!("    p = at({})", index(n))],
+        }
+    }
+
+    fn literal(&self, output: &[u8]) -> String {
+        let mut out = String::new();
+        out.push_str("# Generated by the brainfuck interpreter.\n\n");
+        out.push_str("import sys\n\n");
+        out.push_str(&format!("sys.stdout.buffer.write(b\"{}\")\n", output.escape_ascii()));
+        out
+    }
+
+    fn empty_loop(&self) -> Option<&'static str> {
+        Some("pass")
+    }
+}
@@ -0,0 +1,106 @@
+// Rust backend.
+// MIT license.
+
+use crate::machine::{CellWidth, Eof};
+use crate::program::Op;
+
+use super::{index, CodegenBackend, Settings};
+
+/// Generates a Rust program with a `main` function.
+pub struct Rust;
+
+impl Rust {
+    fn cell(settings: &Settings) -> &'static str {
+        match settings.cell_width {
+            CellWidth::U8 => "u8",
+            CellWidth::U16 => "u16",
+            CellWidth::U32 => "u32",
+        }
+    }
+}
+
+impl CodegenBackend for Rust {
+    fn prologue(&self, settings: &Settings) -> String {
+        let mut out = String::new();
+        out.push_str("// Generated by the brainfuck interpreter.\n\n");
+        out.push_str("#![allow(unused)]\n\n");
+        out.push_str("use std::io::{self, Read, Write};\n\n");
+        out.push_str("fn main() {\n");
+        out.push_str(&format!("    let mut mem: Vec<{}> = vec![0; {}];\n", Rust::cell(settings), settings.mem_size));
+        out.push_str("    let mut p: usize = 0;\n");
+        out.push_str("    let mut input = io::stdin().lock();\n");
+        out.push_str("    let mut output = io::BufWriter::new(io::stdout().lock());\n");
+        out
+    }
+
+    fn epilogue(&self, _settings: &Settings) -> String {
+        "    output.flush().unwrap();\n}\n".to_string()
+    }
+
+    fn instruction(&self, op: Op, settings: &Settings) -> Vec<String> {
+        let mask = settings.cell_width.mask();
+        let line = match op {
+            Op::Move(n) if n < 0 => format!("p -= {};", n.unsigned_abs()),
+            Op::Move(n) => format!("p += {};", n),
+            Op::Add(n) if n < 0 => format!("mem[p] = mem[p].wrapping_sub({});", n.unsigned_abs() & mask),
+            Op::Add(n) => format!("mem[p] = mem[p].wrapping_add({});", n as u32 & mask),
+            Op::Out => "output.write_all(&[mem[p] as u8]).unwrap();".to_string(),
+            Op::OutByte(b) => format!("output.write_all(&[{}]).unwrap();", b),
+            Op::In => {
+                let eof = match settings.eof {
+                    Eof::Zero => "mem[p] = 0;",
+                    Eof::Unchanged => "",
+                    Eof::Max => "mem[p] = !0;",
+                };
+                format!("output.flush().unwrap(); let mut b = [0u8]; if input.read(&mut b).unwrap() == 1 {{ mem[p] = b[0] as {}; }} else {{ {} }}", Rust::cell(settings), eof)
+            },
+            Op::Open(_) => "while mem[p] != 0 {".to_string(),
+            Op::Close(_) => "}".to_string(),
+            Op::Debug => "eprintln!(\"Ptr: {}, value: {}\", p, mem[p]);".to_string(),
+            Op::Channel => "// Channels are not translated, the output stays on the standard streams".to_string(),
+            Op::Send | Op::Receive => "// Messages to the peer machine are not translated".to_string(),
+            Op::Fork => "// Threads are not translated, the program runs as the first one".to_string(),
+            Op::Clock => "// The clock is not translated, the cell is left as it is".to_string(),
+            Op::File(_) => "// Files are not translated, the cells are left as they are".to_string(),
+            Op::Clear => "mem[p] = 0;".to_string(),
+            Op::AddAt { offset, value } => {
+                let target = index(offset);
+                format!("mem[{0}] = mem[{0}].wrapping_add({1});", target, value as u32 & mask)
+            },
+            Op::MulAdd { offset, factor } => {
+                let target = index(offset);
+                format!("if mem[p] != 0 {{ mem[{0}] = mem[{0}].wrapping_add(mem[p].wrapping_mul({1})); }}", target, factor as u32 & mask)
+            },
+            Op::Fill { offset, len, value } => {
+                format!("mem[{}..{} + {}].fill({});", index(offset), index(offset), len, value as u32 & mask)
+            },
+            Op::Scan(n) if n < 0 => format!("while mem[p] != 0 {{ p -= {}; }}", n.unsigned_abs()),
+            Op::Scan(n) => format!("while mem[p] != 0 {{ p += {}; }}", n),
+        };
+        vec![line]
+    }
+
+    fn literal(&self, output: &[u8]) -> String {
+        let mut out = String::new();
+        out.push_str("// Generated by the brainfuck interpreter.\n\n");
+        out.push_str("use std::io::{self, Write};\n\n");
+        out.push_str("fn main() {\n");
+        out.push_str(&format!("    io::stdout().write_all(b\"{}\").unwrap();\n", output.escape_ascii()));
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Generates the manifest of a cargo project with the given package name, for the generated code in `src/main.rs`.
+pub fn cargo_manifest(name: &str) -> String {
+    let mut out = String::new();
+    out.push_str("# Generated by the brainfuck interpreter.\n\n");
+    out.push_str("[package]\n");
+    out.push_str(&format!("name = \"{}\"\n", name));
+    out.push_str("version = \"0.1.0\"\n");
+    out.push_str("edition = \"2021\"\n\n");
+    out.push_str("[dependencies]\n\n");
+    out.push_str("[profile.release]\n");
+    out.push_str("lto = true\n");
+    out
+}
@@ -0,0 +1,162 @@
+// The interactive tutorial, with lessons checked on the machine the programs typed in leave.
+// MIT license.
+
+use std::error::Error;
+use std::io::{self, prelude::*};
+
+use brainfuck::{Machine, Op, Program};
+use brainfuck::optimize::{optimize, OptLevel};
+
+use crate::cli::TutorialArgs;
+use crate::repl::panel;
+use crate::tee::Capture;
+
+/// Instructions an answer may run, so that loops that never end do not end the tutorial.
+const MAX_STEPS: u64 = 1_000_000;
+
+/// Cells the panel shows after a wrong answer.
+const PANEL_CELLS: usize = 9;
+
+/// What the commands of the tutorial do, which start with a `:`.
+const HELP: &str = "\
+:hint  Show a hint for this lesson
+:skip  Go to the next lesson
+:help  Show this help";
+
+/// A lesson: what to do, the cells the machine starts with, and how to check it was done.
+struct Lesson {
+    title: &'static str,
+    text: &'static str,
+    start: &'static [u8],
+    hint: &'static str,
+    /// Checks the program, the machine it ran on and what it printed, and says what is wrong.
+    check: fn(&Program, &Machine, &[u8]) -> Result<(), String>,
+}
+
+/// The value of a cell, for the checks.
+fn cell(machine: &Machine, i: usize) -> u32 {
+    machine.memory()[i]
+}
+
+const LESSONS: &[Lesson] = &[
+    Lesson {
+        title: "Moving",
+        text: "The machine has a tape of cells, all at zero, and a pointer at the first one, cell 0. '>' moves the \
+            pointer one cell to the right, and '<' one to the left.\nMove the pointer to cell 3.",
+        start: &[],
+        hint: "Three '>' in a row move the pointer three cells to the right.",
+        check: |_, machine, _| match machine.pointer() {
+            3 => Ok(()),
+            ptr => Err(format!("The pointer is at cell {}, not at cell 3.", ptr)),
+        },
+    },
+    Lesson {
+        title: "Adding",
+        text: "'+' adds one to the cell at the pointer, and '-' subtracts one.\nSet cell 0 to 5.",
+        start: &[],
+        hint: "Type '+' five times.",
+        check: |_, machine, _| match cell(machine, 0) {
+            5 => Ok(()),
+            n => Err(format!("Cell 0 is {}, not 5.", n)),
+        },
+    },
+    Lesson {
+        title: "Looping",
+        text: "'[' skips to the matching ']' if the cell at the pointer is zero, and ']' goes back to the matching \
+            '[' unless it is zero. The code between them runs again and again until the cell is zero.\nCell 0 \
+            starts at 10 this time. Clear it with a loop, in less than 10 instructions.",
+        start: &[10],
+        hint: "'[-]' subtracts one from the cell until it is zero, whatever it starts at.",
+        check: |program, machine, _| {
+            if !program.ops.iter().any(|op| matches!(op, Op::Open(_))) {
+                return Err("There is no loop, use '[' and ']'.".to_string());
+            }
+            match cell(machine, 0) {
+                0 if program.len() < 10 => Ok(()),
+                0 => Err(format!("Cell 0 is clear, but the program has {} instructions.", program.len())),
+                n => Err(format!("Cell 0 is {}, not 0.", n)),
+            }
+        },
+    },
+    Lesson {
+        title: "Copying",
+        text: "A loop running once for every unit of a cell can add it to others: '[->+<]' moves cell 0 to cell 1, \
+            leaving cell 0 at zero. To copy a cell, move it to two cells, then move one of them back.\nCell 0 \
+            starts at 7. Copy it to cell 1, so that both are 7. Cell 2 can help, but should end at zero.",
+        start: &[7],
+        hint: "'[->+>+<<]' moves cell 0 to cells 1 and 2, then '>>[-<<+>>]' moves cell 2 back to cell 0.",
+        check: |_, machine, _| match (cell(machine, 0), cell(machine, 1), cell(machine, 2)) {
+            (7, 7, 0) => Ok(()),
+            (7, 7, n) => Err(format!("Cells 0 and 1 are 7, but cell 2 is {}, not 0.", n)),
+            (a, b, _) => Err(format!("Cell 0 is {} and cell 1 is {}, both should be 7.", a, b)),
+        },
+    },
+    Lesson {
+        title: "Printing",
+        text: "'.' prints the cell at the pointer, as the character with that code: 65 is 'A'. Loops make large \
+            numbers short to write, by adding a few units to a cell many times.\nPrint the letter A.",
+        start: &[],
+        hint: "'++++++++[>++++++++<-]' adds 8 to cell 1 eight times, which makes 64. Then '>+.' prints 65.",
+        check: |_, _, output| match output {
+            b"A" => Ok(()),
+            b"" => Err("The program printed nothing, use '.'.".to_string()),
+            output => Err(format!("The program printed \"{}\", not \"A\".", output.escape_ascii())),
+        },
+    },
+];
+
+/// Runs the answer on a fresh machine with the cells the lesson starts with, and checks it.
+fn attempt(lesson: &Lesson, line: &str) -> Result<(), Box<dyn Error>> {
+    let program = Program::parse(line)?;
+    let output = Capture::default();
+    let mut machine = Machine::builder()
+        .preload(0, lesson.start.to_vec())
+        .max_steps(Some(MAX_STEPS))
+        .io(io::empty(), output.clone())
+        .build();
+    // Higher levels take the tape to start at zero, which the lessons starting with values in cells do not.
+    let result = machine.run(&optimize(&program, OptLevel::O1));
+    let printed = output.take();
+    if let Err(e) = result {
+        return Err(format!("{}\n{}", e, panel(&machine, PANEL_CELLS).trim_end()).into());
+    }
+    (lesson.check)(&program, &machine, &printed).map_err(|e| format!("{}\n{}", e, panel(&machine, PANEL_CELLS).trim_end()).into())
+}
+
+/// Walks through the lessons from the given one, reading an answer per line of the standard input until it is
+/// right. Typing nothing shows the lesson again.
+pub fn run(args: &TutorialArgs) -> Result<(), Box<dyn Error>> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let first = (args.lesson as usize).clamp(1, LESSONS.len());
+    for (n, lesson) in LESSONS.iter().enumerate().skip(first - 1) {
+        let show = |stdout: &mut io::Stdout| writeln!(stdout, "\nLesson {} of {}: {}\n\n{}\n", n + 1, LESSONS.len(), lesson.title, lesson.text);
+        show(&mut stdout)?;
+        loop {
+            write!(stdout, "> ")?;
+            stdout.flush()?;
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line)? == 0 {
+                writeln!(stdout)?;
+                return Ok(());
+            }
+            let line = line.trim();
+            match line {
+                "" => show(&mut stdout)?,
+                ":hint" => writeln!(stdout, "{}", lesson.hint)?,
+                ":skip" => break,
+                ":help" => writeln!(stdout, "{}", HELP)?,
+                _ if line.starts_with(':') => writeln!(stdout, "Unknown command '{}', see :help", line)?,
+                _ => match attempt(lesson, line) {
+                    Ok(()) => {
+                        writeln!(stdout, "Well done!")?;
+                        break;
+                    },
+                    Err(e) => writeln!(stdout, "Not yet. {}\nTry again, or type :hint.", e)?,
+                },
+            }
+        }
+    }
+    writeln!(stdout, "\nThat was the last lesson. Try your programs in the REPL, with brainfuck repl.")?;
+    Ok(())
+}